@@ -0,0 +1,242 @@
+use byteorder::{ByteOrder, LittleEndian};
+use futures_core::Stream;
+use std::cmp;
+use std::future::Future;
+use std::io::{Error, ErrorKind, Result};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use document::Document;
+use document_deserializer::{document_length, invalid_data, parse_document};
+use document_serializer::DocumentSerializer;
+
+/// Read a single document from an async reader, reading exactly the number
+/// of bytes given by its length prefix.
+///
+/// # Parameters
+/// - `reader` - The reader to read the document from.
+///
+/// # Returns
+/// The `ReadDocument` future resolving to the `Result` with the `Document`.
+pub fn read_document_async<R>(reader: &mut R) -> ReadDocument<'_, R> where R: AsyncRead + Unpin + ?Sized {
+    ReadDocument { reader, state: ReadState::new() }
+}
+
+/// Write a single document to an async writer. The writer is not flushed.
+///
+/// # Parameters
+/// - `writer` - The writer to write the document to.
+/// - `document` - The `Document` to write.
+///
+/// # Returns
+/// The `WriteDocument` future resolving to the `Result`.
+pub fn write_document_async<'a, W>(writer: &'a mut W, document: &Document) -> WriteDocument<'a, W>
+        where W: AsyncWrite + Unpin + ?Sized {
+    let mut bytes = Vec::new();
+    let error = DocumentSerializer::new(&mut bytes).serialize(document).err();
+    WriteDocument { writer, bytes, position: 0, error }
+}
+
+/// The future returned by `read_document_async`.
+pub struct ReadDocument<'a, R: ?Sized + 'a> {
+    reader: &'a mut R,
+    state: ReadState
+}
+
+impl<'a, R> Future for ReadDocument<'a, R> where R: AsyncRead + Unpin + ?Sized {
+    type Output = Result<Document>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Document>> {
+        let this = self.get_mut();
+        match this.state.poll_document(cx, Pin::new(&mut *this.reader), None) {
+            Poll::Ready(Ok(Some(document))) => Poll::Ready(Ok(document)),
+            Poll::Ready(Ok(None)) => Poll::Ready(Err(Error::new(ErrorKind::UnexpectedEof, "no document to read"))),
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+            Poll::Pending => Poll::Pending
+        }
+    }
+}
+
+/// The future returned by `write_document_async`.
+pub struct WriteDocument<'a, W: ?Sized + 'a> {
+    writer: &'a mut W,
+    bytes: Vec<u8>,
+    position: usize,
+    error: Option<Error>
+}
+
+impl<'a, W> Future for WriteDocument<'a, W> where W: AsyncWrite + Unpin + ?Sized {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Err(error));
+        }
+        while this.position < this.bytes.len() {
+            match Pin::new(&mut *this.writer).poll_write(cx, &this.bytes[this.position..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(Error::new(ErrorKind::WriteZero, "failed to write the whole document")));
+                },
+                Poll::Ready(Ok(n)) => this.position += n,
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The `AsyncDocumentReader` object that reads a stream of concatenated BSON
+/// documents from an async reader, yielding each document in turn.
+///
+/// Each document is read by first reading its length prefix and then exactly
+/// the rest of its bytes. The stream ends cleanly when the reader ends before
+/// a document starts. Any error, including a truncated document, ends the
+/// stream after it is returned.
+pub struct AsyncDocumentReader<R> {
+    reader: R,
+    state: ReadState,
+    max_document_size: Option<usize>,
+    done: bool
+}
+
+/// Implementation for the `AsyncDocumentReader` object.
+impl<R> AsyncDocumentReader<R> where R: AsyncRead + Unpin {
+
+    /// Create the new `AsyncDocumentReader` object.
+    ///
+    /// # Parameters
+    /// - `reader` - The reader to read documents from.
+    ///
+    /// # Returns
+    /// The new `AsyncDocumentReader` object.
+    pub fn new(reader: R) -> AsyncDocumentReader<R> {
+        AsyncDocumentReader {
+            reader,
+            state: ReadState::new(),
+            max_document_size: None,
+            done: false
+        }
+    }
+
+    /// Set the largest document size in bytes that will be read. A length
+    /// prefix larger than this is returned as an error instead of allocating
+    /// a buffer for it.
+    ///
+    /// # Parameters
+    /// - `size` - The maximum document size.
+    ///
+    /// # Returns
+    /// The `AsyncDocumentReader` object.
+    pub fn max_document_size(mut self, size: usize) -> AsyncDocumentReader<R> {
+        self.max_document_size = Some(size);
+        self
+    }
+
+    /// Get a reference to the underlying reader.
+    ///
+    /// # Returns
+    /// The reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Unwrap the underlying reader. Bytes of a partially read document are
+    /// lost.
+    ///
+    /// # Returns
+    /// The reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> Stream for AsyncDocumentReader<R> where R: AsyncRead + Unpin {
+    type Item = Result<Document>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Document>>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let result = match this.state.poll_document(cx, Pin::new(&mut this.reader), this.max_document_size) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending
+        };
+        match result {
+            Ok(Some(document)) => Poll::Ready(Some(Ok(document))),
+            Ok(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            },
+            Err(error) => {
+                this.done = true;
+                Poll::Ready(Some(Err(error)))
+            }
+        }
+    }
+}
+
+// The most bytes the buffer grows by for each read, so that an untrusted
+// length prefix doesn't reserve memory before the document arrives.
+const READ_CHUNK_SIZE: usize = 8192;
+
+// The progress through reading a single document, kept between polls.
+struct ReadState {
+    buffer: Vec<u8>,
+    length: usize
+}
+
+impl ReadState {
+
+    fn new() -> ReadState {
+        ReadState { buffer: Vec::new(), length: 4 }
+    }
+
+    /// Poll for the next document, resetting the state once it is complete.
+    ///
+    /// # Returns
+    /// `None` if the reader ended before the document started.
+    fn poll_document<R>(&mut self, cx: &mut Context<'_>, mut reader: Pin<&mut R>, max_document_size: Option<usize>)
+            -> Poll<Result<Option<Document>>> where R: AsyncRead + ?Sized {
+        loop {
+            if self.length == 4 && self.buffer.len() == 4 {
+                let length = document_length(LittleEndian::read_i32(&self.buffer))?;
+                if let Some(max) = max_document_size {
+                    if length > max {
+                        return Poll::Ready(Err(invalid_data(format!(
+                            "document length {} exceeds the maximum of {}", length, max
+                        ))));
+                    }
+                }
+                self.length = length;
+            }
+            if self.buffer.len() == self.length {
+                let result = parse_document(&self.buffer);
+                self.buffer.clear();
+                self.length = 4;
+                return Poll::Ready(result.map(Some));
+            }
+            let filled = self.buffer.len();
+            self.buffer.resize(cmp::min(self.length, filled + READ_CHUNK_SIZE), 0);
+            let mut read_buf = ReadBuf::new(&mut self.buffer[filled..]);
+            let poll = reader.as_mut().poll_read(cx, &mut read_buf);
+            let n = read_buf.filled().len();
+            self.buffer.truncate(filled + n);
+            match poll {
+                Poll::Ready(Ok(())) => {
+                    if n == 0 {
+                        if filled == 0 {
+                            return Poll::Ready(Ok(None));
+                        }
+                        return Poll::Ready(Err(Error::new(ErrorKind::UnexpectedEof, "truncated document")));
+                    }
+                },
+                Poll::Ready(Err(ref error)) if error.kind() == ErrorKind::Interrupted => {},
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
 /// The BSON enum.
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::num::TryFromIntError;
 use document::Document;
 
 /// The enum for all valid BSON types.
@@ -10,6 +13,7 @@ pub enum Bson {
     Array(Vec<Bson>), // 0x04
     Binary(u8, Vec<u8>), // 0x05
     Undefined, // 0x06
+    ObjectId([u8; 12]), // 0x07
     Boolean(bool), // 0x08
     DateTime(i64), //0x09
     Null, // 0x0A
@@ -20,10 +24,76 @@ pub enum Bson {
     Int32(i32), // 0x10
     Timestamp(u64), // 0x11
     Int64(i64), // 0x12
+    Decimal128([u8; 16]), // 0x13
     MinKey, // 0xFF
     MaxKey // 0x7F
 }
 
+/// The implementation for `Bson`.
+impl Bson {
+
+    /// Get the BSON element type byte for the value.
+    ///
+    /// # Returns
+    /// The element type.
+    pub fn element_type(&self) -> u8 {
+        match *self {
+            Bson::Double(_) => 0x01,
+            Bson::String(_) => 0x02,
+            Bson::Document(_) => 0x03,
+            Bson::Array(_) => 0x04,
+            Bson::Binary(_, _) => 0x05,
+            Bson::Undefined => 0x06,
+            Bson::ObjectId(_) => 0x07,
+            Bson::Boolean(_) => 0x08,
+            Bson::DateTime(_) => 0x09,
+            Bson::Null => 0x0A,
+            Bson::RegExp(_, _) => 0x0B,
+            Bson::DbPointer(_, _) => 0x0C,
+            Bson::Code(_, ref scope) if scope.is_empty() => 0x0D,
+            Bson::Symbol(_) => 0x0E,
+            Bson::Code(_, _) => 0x0F,
+            Bson::Int32(_) => 0x10,
+            Bson::Timestamp(_) => 0x11,
+            Bson::Int64(_) => 0x12,
+            Bson::Decimal128(_) => 0x13,
+            Bson::MinKey => 0xFF,
+            Bson::MaxKey => 0x7F
+        }
+    }
+
+    /// Get the name the server uses for the type of the value, as accepted by
+    /// the `$type` query operator.
+    ///
+    /// # Returns
+    /// The type name.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Bson::Double(_) => "double",
+            Bson::String(_) => "string",
+            Bson::Document(_) => "object",
+            Bson::Array(_) => "array",
+            Bson::Binary(_, _) => "binData",
+            Bson::Undefined => "undefined",
+            Bson::ObjectId(_) => "objectId",
+            Bson::Boolean(_) => "bool",
+            Bson::DateTime(_) => "date",
+            Bson::Null => "null",
+            Bson::RegExp(_, _) => "regex",
+            Bson::DbPointer(_, _) => "dbPointer",
+            Bson::Code(_, ref scope) if scope.is_empty() => "javascript",
+            Bson::Symbol(_) => "symbol",
+            Bson::Code(_, _) => "javascriptWithScope",
+            Bson::Int32(_) => "int",
+            Bson::Timestamp(_) => "timestamp",
+            Bson::Int64(_) => "long",
+            Bson::Decimal128(_) => "decimal",
+            Bson::MinKey => "minKey",
+            Bson::MaxKey => "maxKey"
+        }
+    }
+}
+
 /// The from implementation for converting a `f64` to a `Bson::Double`.
 impl From<f64> for Bson {
 
@@ -144,6 +214,209 @@ impl From<i64> for Bson {
     }
 }
 
+/// The from implementation for converting a `f32` to a `Bson::Double`.
+impl From<f32> for Bson {
+
+    /// Convert from a `f32` to a `Bson::Double`.
+    ///
+    /// # Parameters
+    /// - `value` - The `f32` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Double`.
+    fn from(value: f32) -> Bson {
+        Bson::Double(value as f64)
+    }
+}
+
+/// The from implementation for converting a `String` to a `Bson::String`.
+impl From<String> for Bson {
+
+    /// Convert from a `String` to a `Bson::String`.
+    ///
+    /// # Parameters
+    /// - `value` - The `String` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::String`.
+    fn from(value: String) -> Bson {
+        Bson::String(value)
+    }
+}
+
+/// The from implementation for converting a `&String` to a `Bson::String`.
+impl From<&String> for Bson {
+
+    /// Convert from a `&String` to a `Bson::String`.
+    ///
+    /// # Parameters
+    /// - `value` - The `&String` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::String`.
+    fn from(value: &String) -> Bson {
+        Bson::String(value.clone())
+    }
+}
+
+/// The from implementation for converting a `u8` to a `Bson::Int32`.
+impl From<u8> for Bson {
+
+    /// Convert from a `u8` to a `Bson::Int32`.
+    ///
+    /// # Parameters
+    /// - `value` - The `u8` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Int32`.
+    fn from(value: u8) -> Bson {
+        Bson::Int32(value as i32)
+    }
+}
+
+/// The from implementation for converting a `u16` to a `Bson::Int32`.
+impl From<u16> for Bson {
+
+    /// Convert from a `u16` to a `Bson::Int32`.
+    ///
+    /// # Parameters
+    /// - `value` - The `u16` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Int32`.
+    fn from(value: u16) -> Bson {
+        Bson::Int32(value as i32)
+    }
+}
+
+/// The from implementation for converting a `u32` to a `Bson::Int64`. A `u32`
+/// does not always fit in an `Int32`, so the wider type is always used.
+impl From<u32> for Bson {
+
+    /// Convert from a `u32` to a `Bson::Int64`.
+    ///
+    /// # Parameters
+    /// - `value` - The `u32` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Int64`.
+    fn from(value: u32) -> Bson {
+        Bson::Int64(value as i64)
+    }
+}
+
+/// The try from implementation for converting a `usize` to a `Bson::Int64`.
+///
+/// There is no equivalent for `u64` since `From<u64>` already produces a
+/// `Bson::Timestamp`.
+impl TryFrom<usize> for Bson {
+    type Error = TryFromIntError;
+
+    /// Convert from a `usize` to a `Bson::Int64`.
+    ///
+    /// # Parameters
+    /// - `value` - The `usize` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Int64` or an error if the value does not fit in an `i64`.
+    fn try_from(value: usize) -> Result<Bson, TryFromIntError> {
+        i64::try_from(value).map(Bson::Int64)
+    }
+}
+
+/// The from implementation for converting a `Document` to a `Bson::Document`.
+impl From<Document> for Bson {
+
+    /// Convert from a `Document` to a `Bson::Document`.
+    ///
+    /// # Parameters
+    /// - `value` - The `Document` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Document`.
+    fn from(value: Document) -> Bson {
+        Bson::Document(value)
+    }
+}
+
+/// The from implementation for converting a `Vec` to a `Bson::Array`.
+impl<T> From<Vec<T>> for Bson where T: Into<Bson> {
+
+    /// Convert from a `Vec` to a `Bson::Array`.
+    ///
+    /// # Parameters
+    /// - `value` - The `Vec` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Array`.
+    fn from(value: Vec<T>) -> Bson {
+        Bson::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// The from implementation for converting a slice to a `Bson::Array`.
+impl<T> From<&[T]> for Bson where T: Clone + Into<Bson> {
+
+    /// Convert from a slice to a `Bson::Array`.
+    ///
+    /// # Parameters
+    /// - `value` - The slice to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Array`.
+    fn from(value: &[T]) -> Bson {
+        Bson::Array(value.iter().cloned().map(Into::into).collect())
+    }
+}
+
+/// The from implementation for converting an `Option` to a `Bson` value.
+impl<T> From<Option<T>> for Bson where T: Into<Bson> {
+
+    /// Convert from an `Option` to a `Bson` value.
+    ///
+    /// # Parameters
+    /// - `value` - The `Option` to convert from.
+    ///
+    /// # Returns
+    /// The converted value, or `Bson::Null` when `None`.
+    fn from(value: Option<T>) -> Bson {
+        match value {
+            Some(value) => value.into(),
+            None => Bson::Null
+        }
+    }
+}
+
+/// The from implementation for converting a `HashMap` to a `Bson::Document`.
+impl<T> From<HashMap<String, T>> for Bson where T: Into<Bson> {
+
+    /// Convert from a `HashMap` to a `Bson::Document`.
+    ///
+    /// # Parameters
+    /// - `value` - The `HashMap` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Document`.
+    fn from(value: HashMap<String, T>) -> Bson {
+        Bson::Document(Document::from(value))
+    }
+}
+
+/// The from implementation for converting a `BTreeMap` to a `Bson::Document`.
+impl<T> From<BTreeMap<String, T>> for Bson where T: Into<Bson> {
+
+    /// Convert from a `BTreeMap` to a `Bson::Document`.
+    ///
+    /// # Parameters
+    /// - `value` - The `BTreeMap` to convert from.
+    ///
+    /// # Returns
+    /// The `Bson::Document`.
+    fn from(value: BTreeMap<String, T>) -> Bson {
+        Bson::Document(Document::from(value))
+    }
+}
+
 /// Converts expressions in the macro to normal `Bson` variants.
 #[macro_export]
 macro_rules! bson {
@@ -225,6 +498,12 @@ macro_rules! bson_undefined {
     () => ($crate::Bson::Undefined);
 }
 
+/// Convenience for defining BSON object ids.
+#[macro_export]
+macro_rules! bson_objectid {
+    ($id:expr) => ($crate::Bson::ObjectId($id));
+}
+
 /// Convenience for defining BSON null objects.
 #[macro_export]
 macro_rules! bson_null {
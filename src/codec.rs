@@ -0,0 +1,196 @@
+use byteorder::{ByteOrder, LittleEndian};
+use bytes::{BufMut, BytesMut};
+use std::io::{Error, ErrorKind, Result};
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder};
+use document::Document;
+use document_deserializer::{document_length, invalid_data, parse_document};
+use document_serializer::DocumentSerializer;
+use raw_document::RawDocumentBuf;
+
+/// The largest frame accepted by a `BsonCodec` when no maximum has been set,
+/// matching the server's 16MiB document limit.
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 16 * 1024 * 1024;
+
+/// The `BsonCodec` object that frames BSON documents over a byte stream,
+/// using each document's own length prefix as the frame length.
+///
+/// Decoding yields a `Document` from a codec created with `new`, or a
+/// `RawDocumentBuf` from one created with `raw`. Both encode either kind.
+/// Partial frames are buffered until complete. A frame whose contents are
+/// malformed is consumed and returned as an `InvalidData` error, so decoding
+/// can continue with the next frame. A length prefix that is invalid or over
+/// the maximum frame length leaves the stream position unknown, and is
+/// returned as an error on every following decode.
+#[derive(Clone, Debug)]
+pub struct BsonCodec<T = Document> {
+    max_frame_length: usize,
+    item: PhantomData<T>
+}
+
+/// Implementation for the `BsonCodec` object decoding documents.
+impl BsonCodec<Document> {
+
+    /// Create the new `BsonCodec` object decoding frames into `Document`s.
+    ///
+    /// # Returns
+    /// The new `BsonCodec` object.
+    pub fn new() -> BsonCodec<Document> {
+        BsonCodec::with_default_max()
+    }
+}
+
+impl Default for BsonCodec<Document> {
+    fn default() -> BsonCodec<Document> {
+        BsonCodec::new()
+    }
+}
+
+/// Implementation for the `BsonCodec` object decoding raw documents.
+impl BsonCodec<RawDocumentBuf> {
+
+    /// Create the new `BsonCodec` object decoding frames into
+    /// `RawDocumentBuf`s without parsing their elements.
+    ///
+    /// # Returns
+    /// The new `BsonCodec` object.
+    pub fn raw() -> BsonCodec<RawDocumentBuf> {
+        BsonCodec::with_default_max()
+    }
+}
+
+/// Implementation for the `BsonCodec` object.
+impl<T> BsonCodec<T> {
+
+    /// Set the largest frame length in bytes that will be decoded or encoded.
+    ///
+    /// # Parameters
+    /// - `length` - The maximum frame length.
+    ///
+    /// # Returns
+    /// The `BsonCodec` object.
+    pub fn max_frame_length(mut self, length: usize) -> BsonCodec<T> {
+        self.max_frame_length = length;
+        self
+    }
+
+    /// Get the largest frame length in bytes that will be decoded or encoded.
+    ///
+    /// # Returns
+    /// The maximum frame length.
+    pub fn get_max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    fn with_default_max() -> BsonCodec<T> {
+        BsonCodec { max_frame_length: DEFAULT_MAX_FRAME_LENGTH, item: PhantomData }
+    }
+
+    /// Split the next complete frame off the buffer.
+    ///
+    /// # Parameters
+    /// - `src` - The buffered bytes.
+    ///
+    /// # Returns
+    /// The frame, or `None` if more bytes are needed.
+    fn decode_frame(&self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        if src.len() < 4 {
+            return Ok(None);
+        }
+        let length = document_length(LittleEndian::read_i32(src))?;
+        if length > self.max_frame_length {
+            return Err(invalid_data(format!(
+                "frame length {} exceeds the maximum of {}", length, self.max_frame_length
+            )));
+        }
+        if src.len() < length {
+            src.reserve(length - src.len());
+            return Ok(None);
+        }
+        Ok(Some(src.split_to(length)))
+    }
+
+    /// Append the bytes written by `write` to the buffer as a frame, removing
+    /// them again if they exceed the maximum frame length.
+    ///
+    /// # Parameters
+    /// - `dst` - The buffer to write to.
+    /// - `write` - The function writing the frame.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    fn encode_frame<F>(&self, dst: &mut BytesMut, write: F) -> Result<()> where F: FnOnce(&mut BytesMut) -> Result<()> {
+        let start = dst.len();
+        let result = write(dst);
+        let length = dst.len() - start;
+        if result.is_err() || length > self.max_frame_length {
+            dst.truncate(start);
+        }
+        result?;
+        if length > self.max_frame_length {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "frame length {} exceeds the maximum of {}", length, self.max_frame_length
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for BsonCodec<Document> {
+    type Item = Document;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Document>> {
+        match self.decode_frame(src)? {
+            Some(frame) => parse_document(&frame).map(Some),
+            None => Ok(None)
+        }
+    }
+}
+
+impl Decoder for BsonCodec<RawDocumentBuf> {
+    type Item = RawDocumentBuf;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<RawDocumentBuf>> {
+        match self.decode_frame(src)? {
+            Some(frame) => RawDocumentBuf::new(frame.to_vec()).map(Some),
+            None => Ok(None)
+        }
+    }
+}
+
+impl<'a, T> Encoder<&'a Document> for BsonCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, document: &'a Document, dst: &mut BytesMut) -> Result<()> {
+        self.encode_frame(dst, |dst| DocumentSerializer::new(&mut BufMut::writer(dst)).serialize(document))
+    }
+}
+
+impl<T> Encoder<Document> for BsonCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, document: Document, dst: &mut BytesMut) -> Result<()> {
+        self.encode(&document, dst)
+    }
+}
+
+impl<'a, T> Encoder<&'a RawDocumentBuf> for BsonCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, document: &'a RawDocumentBuf, dst: &mut BytesMut) -> Result<()> {
+        self.encode_frame(dst, |dst| {
+            dst.extend_from_slice(document.as_bytes());
+            Ok(())
+        })
+    }
+}
+
+impl<T> Encoder<RawDocumentBuf> for BsonCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, document: RawDocumentBuf, dst: &mut BytesMut) -> Result<()> {
+        self.encode(&document, dst)
+    }
+}
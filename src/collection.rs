@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+use linked_hash_map::LinkedHashMap;
+use bson::Bson;
+use document::Document;
+use filter::{whole_number, Filter};
+use hashable::HashableBson;
+use object_id::new_object_id;
+use projection::Projection;
+use query_error::QueryError;
+use sort_spec::SortSpec;
+use update::Update;
+
+/// The `Collection` object, an in-memory stand-in for a MongoDB collection.
+///
+/// Documents are kept in insertion order and indexed by `_id`, which is
+/// generated as an ObjectId for documents inserted without one. Filters,
+/// updates, projections and sorts have the server's semantics, and writes
+/// that would break the uniqueness of `_id` or of a unique secondary index
+/// fail with the server's duplicate key error, leaving the collection
+/// unchanged.
+#[derive(Clone, Debug)]
+pub struct Collection {
+    name: String,
+    documents: LinkedHashMap<HashableBson, Document>,
+    indexes: Vec<Index>
+}
+
+/// The options for `Collection::find`.
+#[derive(Clone, Debug, Default)]
+pub struct FindOptions {
+    projection: Option<Document>,
+    sort: Option<Document>,
+    skip: usize,
+    limit: Option<usize>
+}
+
+/// The options for `Collection::update_one` and `Collection::update_many`.
+#[derive(Clone, Debug, Default)]
+pub struct UpdateOptions {
+    upsert: bool,
+    array_filters: Vec<Document>
+}
+
+/// The outcome of an update.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateResult {
+    /// The number of documents matched by the filter.
+    pub matched_count: usize,
+    /// The number of matched documents that were changed.
+    pub modified_count: usize,
+    /// The `_id` of the document inserted by an upsert.
+    pub upserted_id: Option<Bson>
+}
+
+// A secondary index, mapping each key to the `_id`s of the documents with it.
+#[derive(Clone, Debug)]
+struct Index {
+    name: String,
+    keys: Document,
+    unique: bool,
+    entries: HashMap<Vec<HashableBson>, Vec<HashableBson>>
+}
+
+/// Implementation for the `Collection` object.
+impl Collection {
+
+    /// Create a new empty `Collection`.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the collection, used in error messages.
+    ///
+    /// # Returns
+    /// The new `Collection`.
+    pub fn new(name: &str) -> Collection {
+        Collection { name: name.to_string(), documents: LinkedHashMap::new(), indexes: Vec::new() }
+    }
+
+    /// Get the name of the collection.
+    ///
+    /// # Returns
+    /// The name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of documents in the collection.
+    ///
+    /// # Returns
+    /// The number of documents.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Determine if the collection has no documents.
+    ///
+    /// # Returns
+    /// True if the collection is empty.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Insert a document, adding an ObjectId `_id` as its first field if it
+    /// has none.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` to insert.
+    ///
+    /// # Returns
+    /// The `Result` with the `_id` of the inserted document.
+    pub fn insert_one(&mut self, document: Document) -> Result<Bson, QueryError> {
+        let document = with_id(document)?;
+        let id = document.get("_id").cloned().expect("an _id");
+        self.check_unique(&document, None)?;
+        self.add(document);
+        Ok(id)
+    }
+
+    /// Insert documents in order, stopping at the first that fails.
+    ///
+    /// # Parameters
+    /// - `documents` - The `Document`s to insert.
+    ///
+    /// # Returns
+    /// The `Result` with the `_id`s of the inserted documents. The documents
+    /// before one that fails remain inserted.
+    pub fn insert_many<I>(&mut self, documents: I) -> Result<Vec<Bson>, QueryError> where I: IntoIterator<Item = Document> {
+        documents.into_iter().map(|document| self.insert_one(document)).collect()
+    }
+
+    /// Find the documents matching a filter.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    /// - `options` - The `FindOptions`.
+    ///
+    /// # Returns
+    /// The `Result` with the matching `Document`s, sorted, skipped, limited
+    /// and projected by the options.
+    pub fn find(&self, filter: &Document, options: &FindOptions) -> Result<Vec<Document>, QueryError> {
+        let query = Filter::parse(filter)?;
+        let sort = match options.sort {
+            Some(ref sort) => Some(SortSpec::parse(sort)?),
+            None => None
+        };
+        let projection = match options.projection {
+            Some(ref projection) => Some(Projection::parse(projection)?.query(query.clone())),
+            None => None
+        };
+        let mut found = self.matching(filter, &query);
+        if let Some(sort) = sort {
+            found.sort_by(|left, right| sort.compare(left, right));
+        }
+        let limit = options.limit.unwrap_or(usize::MAX);
+        found.into_iter().skip(options.skip).take(limit).map(|document| match projection {
+            Some(ref projection) => projection.apply(document),
+            None => Ok(document.clone())
+        }).collect()
+    }
+
+    /// Find the first document matching a filter.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the first matching `Document`, if any.
+    pub fn find_one(&self, filter: &Document) -> Result<Option<Document>, QueryError> {
+        let options = FindOptions::new().limit(1);
+        Ok(self.find(filter, &options)?.into_iter().next())
+    }
+
+    /// Count the documents matching a filter.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the number of matching documents.
+    pub fn count(&self, filter: &Document) -> Result<usize, QueryError> {
+        let query = Filter::parse(filter)?;
+        Ok(self.matching(filter, &query).len())
+    }
+
+    /// Update the first document matching a filter.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    /// - `update` - The update `Document` of operators, or a replacement.
+    /// - `options` - The `UpdateOptions`.
+    ///
+    /// # Returns
+    /// The `Result` with the `UpdateResult`.
+    pub fn update_one(&mut self, filter: &Document, update: &Document, options: &UpdateOptions) -> Result<UpdateResult, QueryError> {
+        self.update(filter, update, options, false)
+    }
+
+    /// Update every document matching a filter. Documents updated before one
+    /// that fails remain updated.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    /// - `update` - The update `Document` of operators.
+    /// - `options` - The `UpdateOptions`.
+    ///
+    /// # Returns
+    /// The `Result` with the `UpdateResult`.
+    pub fn update_many(&mut self, filter: &Document, update: &Document, options: &UpdateOptions) -> Result<UpdateResult, QueryError> {
+        self.update(filter, update, options, true)
+    }
+
+    /// Delete the first document matching a filter.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the number of documents deleted.
+    pub fn delete_one(&mut self, filter: &Document) -> Result<usize, QueryError> {
+        self.delete(filter, false)
+    }
+
+    /// Delete every document matching a filter.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the number of documents deleted.
+    pub fn delete_many(&mut self, filter: &Document) -> Result<usize, QueryError> {
+        self.delete(filter, true)
+    }
+
+    /// Create a secondary index on one or more fields, such as
+    /// `{ "a": 1, "b.c": -1 }`. Array fields add a key for each element.
+    ///
+    /// # Parameters
+    /// - `keys` - The index key `Document`.
+    /// - `unique` - Whether the index rejects documents with duplicate keys.
+    ///
+    /// # Returns
+    /// The `Result` with the name of the index, or a `QueryError` if the
+    /// existing documents have duplicate keys for a unique index.
+    pub fn create_index(&mut self, keys: &Document, unique: bool) -> Result<String, QueryError> {
+        if keys.is_empty() {
+            return Err(QueryError::new("Index keys cannot be an empty field."));
+        }
+        let mut name = String::new();
+        for (field, direction) in keys.iter() {
+            match whole_number(direction) {
+                Some(direction) if direction != 0 => {
+                    if !name.is_empty() {
+                        name.push('_');
+                    }
+                    name.push_str(&format!("{}_{}", field, direction));
+                },
+                _ => return Err(QueryError::new(format!(
+                    "Values in the index key pattern can only be non-zero numbers, found: {}", direction
+                )))
+            }
+        }
+        if let Some(existing) = self.indexes.iter().find(|index| index.name == name) {
+            if existing.unique != unique {
+                return Err(QueryError::new(format!(
+                    "An existing index has the same name as the requested index. Requested index: {}, existing index: {}",
+                    name, existing.name
+                )));
+            }
+            return Ok(name);
+        }
+        let mut index = Index { name: name.clone(), keys: keys.clone(), unique, entries: HashMap::new() };
+        for (id, document) in self.documents.iter() {
+            if let Some(key) = index.conflict(document, Some(id))? {
+                return Err(self.duplicate_key(&index.name, &key));
+            }
+            index.add(id, document);
+        }
+        self.indexes.push(index);
+        Ok(name)
+    }
+
+    /// Drop a secondary index.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the index.
+    ///
+    /// # Returns
+    /// The `Result`, with a `QueryError` if there is no such index.
+    pub fn drop_index(&mut self, name: &str) -> Result<(), QueryError> {
+        match self.indexes.iter().position(|index| index.name == name) {
+            Some(position) => {
+                self.indexes.remove(position);
+                Ok(())
+            },
+            None => Err(QueryError::new(format!("index not found with name [{}]", name)))
+        }
+    }
+
+    /// Get the names of the indexes, starting with the `_id` index.
+    ///
+    /// # Returns
+    /// The index names.
+    pub fn index_names(&self) -> Vec<String> {
+        let mut names = vec!["_id_".to_string()];
+        names.extend(self.indexes.iter().map(|index| index.name.clone()));
+        names
+    }
+
+    /// Find the matching documents, looking up a plain `_id` equality filter
+    /// directly.
+    fn matching<'a>(&'a self, filter: &Document, query: &Filter) -> Vec<&'a Document> {
+        if filter.len() == 1 {
+            match filter.get("_id") {
+                Some(Bson::Document(_)) | Some(Bson::Array(_)) | Some(Bson::RegExp(..)) | None => {},
+                Some(id) => {
+                    return self.documents.get(&HashableBson::numeric(id.clone())).into_iter().collect();
+                }
+            }
+        }
+        self.documents.values().filter(|document| query.matches(document)).collect()
+    }
+
+    fn update(&mut self, filter: &Document, update: &Document, options: &UpdateOptions, multi: bool)
+            -> Result<UpdateResult, QueryError> {
+        let query = Filter::parse(filter)?;
+        let mut parsed = Update::parse(update)?.query(query.clone());
+        if !options.array_filters.is_empty() {
+            parsed = parsed.array_filters(&options.array_filters)?;
+        }
+        if multi && update.iter().next().is_some_and(|(key, _)| !key.starts_with('$')) {
+            return Err(QueryError::new("multi update is not supported for replacement-style update"));
+        }
+        let ids: Vec<HashableBson> = self.documents.iter()
+            .filter(|&(_, document)| query.matches(document))
+            .map(|(id, _)| id.clone())
+            .take(if multi { usize::MAX } else { 1 })
+            .collect();
+        let mut result = UpdateResult { matched_count: ids.len(), modified_count: 0, upserted_id: None };
+        if ids.is_empty() && options.upsert {
+            let mut document = upsert_seed(filter);
+            parsed.apply_insert(&mut document)?;
+            result.upserted_id = Some(self.insert_one(document)?);
+            return Ok(result);
+        }
+        for id in ids {
+            let original = self.documents.get(&id).cloned().expect("a matched document");
+            let mut updated = original.clone();
+            parsed.apply(&mut updated)?;
+            if updated != original {
+                self.check_unique(&updated, Some(&id))?;
+                self.remove(&id);
+                self.replace(id, updated);
+                result.modified_count += 1;
+            }
+        }
+        Ok(result)
+    }
+
+    fn delete(&mut self, filter: &Document, multi: bool) -> Result<usize, QueryError> {
+        let query = Filter::parse(filter)?;
+        let ids: Vec<HashableBson> = self.documents.iter()
+            .filter(|&(_, document)| query.matches(document))
+            .map(|(id, _)| id.clone())
+            .take(if multi { usize::MAX } else { 1 })
+            .collect();
+        for id in &ids {
+            self.remove(id);
+            self.documents.remove(id);
+        }
+        Ok(ids.len())
+    }
+
+    fn check_unique(&self, document: &Document, existing: Option<&HashableBson>) -> Result<(), QueryError> {
+        let id = HashableBson::numeric(document.get("_id").cloned().unwrap_or(Bson::Null));
+        if existing.is_none() && self.documents.contains_key(&id) {
+            return Err(self.duplicate_key("_id_", &[(String::from("_id"), id.into_inner())]));
+        }
+        for index in &self.indexes {
+            if let Some(key) = index.conflict(document, existing)? {
+                return Err(self.duplicate_key(&index.name, &key));
+            }
+        }
+        Ok(())
+    }
+
+    fn duplicate_key(&self, index: &str, key: &[(String, Bson)]) -> QueryError {
+        let mut fields = Document::new();
+        for (field, value) in key {
+            fields.insert(field.clone(), value.clone());
+        }
+        QueryError::new(format!(
+            "E11000 duplicate key error collection: {} index: {} dup key: {}", self.name, index, fields
+        ))
+    }
+
+    fn add(&mut self, document: Document) {
+        let id = HashableBson::numeric(document.get("_id").cloned().unwrap_or(Bson::Null));
+        for index in &mut self.indexes {
+            index.add(&id, &document);
+        }
+        self.documents.insert(id, document);
+    }
+
+    // Replace a document in place, keeping its position in the collection.
+    fn replace(&mut self, id: HashableBson, document: Document) {
+        for index in &mut self.indexes {
+            index.add(&id, &document);
+        }
+        if let Some(existing) = self.documents.get_mut(&id) {
+            *existing = document;
+        }
+    }
+
+    // Remove a document from the secondary indexes.
+    fn remove(&mut self, id: &HashableBson) {
+        if let Some(document) = self.documents.get(id) {
+            for index in &mut self.indexes {
+                index.remove(id, document);
+            }
+        }
+    }
+}
+
+/// Implementation for the `FindOptions` object.
+impl FindOptions {
+
+    /// Create new `FindOptions` that return every matching document whole,
+    /// in insertion order.
+    ///
+    /// # Returns
+    /// The new `FindOptions`.
+    pub fn new() -> FindOptions {
+        FindOptions::default()
+    }
+
+    /// Set the projection applied to each document.
+    ///
+    /// # Parameters
+    /// - `projection` - The projection specification `Document`.
+    ///
+    /// # Returns
+    /// The `FindOptions`.
+    pub fn projection(mut self, projection: Document) -> FindOptions {
+        self.projection = Some(projection);
+        self
+    }
+
+    /// Set the sort order.
+    ///
+    /// # Parameters
+    /// - `sort` - The sort specification `Document`.
+    ///
+    /// # Returns
+    /// The `FindOptions`.
+    pub fn sort(mut self, sort: Document) -> FindOptions {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Set the number of documents to skip.
+    ///
+    /// # Parameters
+    /// - `skip` - The number of documents.
+    ///
+    /// # Returns
+    /// The `FindOptions`.
+    pub fn skip(mut self, skip: usize) -> FindOptions {
+        self.skip = skip;
+        self
+    }
+
+    /// Set the maximum number of documents to return.
+    ///
+    /// # Parameters
+    /// - `limit` - The number of documents.
+    ///
+    /// # Returns
+    /// The `FindOptions`.
+    pub fn limit(mut self, limit: usize) -> FindOptions {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// Implementation for the `UpdateOptions` object.
+impl UpdateOptions {
+
+    /// Create new `UpdateOptions` that only update existing documents.
+    ///
+    /// # Returns
+    /// The new `UpdateOptions`.
+    pub fn new() -> UpdateOptions {
+        UpdateOptions::default()
+    }
+
+    /// Set whether to insert a document built from the filter and the update
+    /// when no document matches.
+    ///
+    /// # Parameters
+    /// - `upsert` - Whether to upsert.
+    ///
+    /// # Returns
+    /// The `UpdateOptions`.
+    pub fn upsert(mut self, upsert: bool) -> UpdateOptions {
+        self.upsert = upsert;
+        self
+    }
+
+    /// Set the array filters for `$[<identifier>]` paths in the update.
+    ///
+    /// # Parameters
+    /// - `array_filters` - The array filter `Document`s.
+    ///
+    /// # Returns
+    /// The `UpdateOptions`.
+    pub fn array_filters(mut self, array_filters: Vec<Document>) -> UpdateOptions {
+        self.array_filters = array_filters;
+        self
+    }
+}
+
+impl Index {
+
+    /// Get the keys of a document, one for each element of an array field
+    /// as in a multikey index, with missing fields indexed as null. Only one
+    /// of the key fields may reach an array.
+    fn keys(&self, document: &Document) -> Result<Vec<Vec<HashableBson>>, QueryError> {
+        let mut keys: Vec<Vec<HashableBson>> = vec![Vec::new()];
+        let mut multikey: Option<&str> = None;
+        for (field, _) in self.keys.iter() {
+            let (values, through_array) = index_values(document, field);
+            if through_array {
+                if let Some(other) = multikey {
+                    return Err(QueryError::new(format!("cannot index parallel arrays [{}] [{}]", field, other)));
+                }
+                multikey = Some(field);
+            }
+            keys = keys.into_iter().flat_map(|key| values.iter().map(move |value| {
+                let mut key = key.clone();
+                key.push(HashableBson::numeric(value.clone()));
+                key
+            })).collect();
+        }
+        let mut unique: Vec<Vec<HashableBson>> = Vec::with_capacity(keys.len());
+        for key in keys {
+            if !unique.contains(&key) {
+                unique.push(key);
+            }
+        }
+        Ok(unique)
+    }
+
+    /// Find a key of a document that a unique index already holds for another
+    /// document, checking that the document can be indexed at all.
+    fn conflict(&self, document: &Document, id: Option<&HashableBson>) -> Result<Option<Vec<(String, Bson)>>, QueryError> {
+        let keys = self.keys(document)?;
+        if !self.unique {
+            return Ok(None);
+        }
+        Ok(keys.into_iter().find(|key| match self.entries.get(key) {
+            Some(ids) => ids.iter().any(|existing| Some(existing) != id),
+            None => false
+        }).map(|key| {
+            self.keys.iter().map(|(field, _)| field.clone()).zip(key.into_iter().map(HashableBson::into_inner)).collect()
+        }))
+    }
+
+    // Documents are checked with `conflict` before they are added, so their
+    // keys can be built.
+    fn add(&mut self, id: &HashableBson, document: &Document) {
+        for key in self.keys(document).unwrap_or_default() {
+            self.entries.entry(key).or_default().push(id.clone());
+        }
+    }
+
+    fn remove(&mut self, id: &HashableBson, document: &Document) {
+        for key in self.keys(document).unwrap_or_default() {
+            if let Some(ids) = self.entries.get_mut(&key) {
+                ids.retain(|existing| existing != id);
+                if ids.is_empty() {
+                    self.entries.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Add an ObjectId `_id` as the first field of a document without one.
+fn with_id(document: Document) -> Result<Document, QueryError> {
+    match document.get("_id") {
+        Some(Bson::Array(_)) => Err(QueryError::new("The '_id' value cannot be of type array")),
+        Some(Bson::RegExp(..)) => Err(QueryError::new("can't use a regex for _id")),
+        Some(_) => Ok(document),
+        None => {
+            let mut identified = Document::new();
+            identified.insert("_id".to_string(), Bson::ObjectId(new_object_id()));
+            for (key, value) in document {
+                identified.insert(key, value);
+            }
+            Ok(identified)
+        }
+    }
+}
+
+/// Build the document an upsert starts from, out of the equality conditions
+/// on the top level of the filter.
+fn upsert_seed(filter: &Document) -> Document {
+    let mut seed = Document::new();
+    for (key, value) in filter.iter() {
+        if key.starts_with('$') || key.contains('.') {
+            continue;
+        }
+        match *value {
+            Bson::Document(ref operators) if operators.iter().next().is_some_and(|(name, _)| name.starts_with('$')) => {
+                if let Some(value) = operators.get("$eq") {
+                    seed.insert(key.clone(), value.clone());
+                }
+            },
+            Bson::RegExp(..) => {},
+            ref value => {
+                seed.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    seed
+}
+
+// The values a dotted path indexes, going through arrays of subdocuments as
+// a multikey index does, and whether an array was reached on the way.
+fn index_values(document: &Document, path: &str) -> (Vec<Bson>, bool) {
+    let parts: Vec<&str> = path.split('.').collect();
+    let mut values = Vec::new();
+    let mut through_array = false;
+    collect_index_values(document.get(parts[0]), &parts[1..], &mut values, &mut through_array);
+    if values.is_empty() {
+        values.push(Bson::Null);
+    }
+    (values, through_array)
+}
+
+fn collect_index_values(value: Option<&Bson>, rest: &[&str], values: &mut Vec<Bson>, through_array: &mut bool) {
+    match (value, rest.split_first()) {
+        (Some(Bson::Array(elements)), None) => {
+            *through_array = true;
+            if elements.is_empty() {
+                values.push(Bson::Undefined);
+            } else {
+                values.extend(elements.iter().cloned());
+            }
+        },
+        (Some(value), None) => values.push(value.clone()),
+        (Some(Bson::Document(document)), Some((part, rest))) => {
+            collect_index_values(document.get(part), rest, values, through_array);
+        },
+        (Some(Bson::Array(elements)), Some((part, rest))) => {
+            *through_array = true;
+            for element in elements {
+                if let Bson::Document(document) = element {
+                    collect_index_values(document.get(part), rest, values, through_array);
+                }
+            }
+        },
+        _ => values.push(Bson::Null)
+    }
+}
@@ -0,0 +1,1127 @@
+use bson::Bson;
+use document::Document;
+use filter::whole_number;
+
+/// A builder for a server command document.
+///
+/// Each command document starts with the command name and its own fields,
+/// followed by the fields shared by every command, in the order `$db`,
+/// `lsid`, `txnNumber`, `writeConcern` and `readConcern`, each included only
+/// when set.
+pub trait Command: Sized {
+
+    /// Get the fields shared by every command.
+    ///
+    /// # Returns
+    /// The `CommandOptions`.
+    fn options(&self) -> &CommandOptions;
+
+    /// Get the fields shared by every command to change them.
+    ///
+    /// # Returns
+    /// The `CommandOptions`.
+    fn options_mut(&mut self) -> &mut CommandOptions;
+
+    /// Get the command name and the command's own fields.
+    ///
+    /// # Returns
+    /// The fields `Document`, starting with the command name.
+    fn fields(&self) -> Document;
+
+    /// Set the logical session the command runs in.
+    ///
+    /// # Parameters
+    /// - `lsid` - The session id `Document`, such as `{ id: <UUID> }`.
+    ///
+    /// # Returns
+    /// The command.
+    fn session(mut self, lsid: Document) -> Self {
+        self.options_mut().session = Some(lsid);
+        self
+    }
+
+    /// Set the transaction number of a retryable write or transaction.
+    ///
+    /// # Parameters
+    /// - `txn_number` - The transaction number.
+    ///
+    /// # Returns
+    /// The command.
+    fn txn_number(mut self, txn_number: i64) -> Self {
+        self.options_mut().txn_number = Some(txn_number);
+        self
+    }
+
+    /// Set the write concern.
+    ///
+    /// # Parameters
+    /// - `write_concern` - The `WriteConcern`.
+    ///
+    /// # Returns
+    /// The command.
+    fn write_concern(mut self, write_concern: WriteConcern) -> Self {
+        self.options_mut().write_concern = Some(write_concern);
+        self
+    }
+
+    /// Set the read concern.
+    ///
+    /// # Parameters
+    /// - `read_concern` - The `ReadConcern`.
+    ///
+    /// # Returns
+    /// The command.
+    fn read_concern(mut self, read_concern: ReadConcern) -> Self {
+        self.options_mut().read_concern = Some(read_concern);
+        self
+    }
+
+    /// Build the command document.
+    ///
+    /// # Returns
+    /// The command `Document`.
+    fn to_document(&self) -> Document {
+        let mut document = self.fields();
+        let options = self.options();
+        document.insert("$db".to_string(), Bson::String(options.database.clone()));
+        if let Some(ref lsid) = options.session {
+            document.insert("lsid".to_string(), Bson::Document(lsid.clone()));
+        }
+        if let Some(txn_number) = options.txn_number {
+            document.insert("txnNumber".to_string(), Bson::Int64(txn_number));
+        }
+        if let Some(ref write_concern) = options.write_concern {
+            document.insert("writeConcern".to_string(), Bson::Document(write_concern.to_document()));
+        }
+        if let Some(ref read_concern) = options.read_concern {
+            document.insert("readConcern".to_string(), Bson::Document(read_concern.to_document()));
+        }
+        document
+    }
+}
+
+/// The fields shared by every command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandOptions {
+    database: String,
+    session: Option<Document>,
+    txn_number: Option<i64>,
+    write_concern: Option<WriteConcern>,
+    read_concern: Option<ReadConcern>
+}
+
+/// The write concern of a write command, such as `{ w: "majority" }`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WriteConcern {
+    w: Option<Bson>,
+    journal: Option<bool>,
+    timeout: Option<i64>
+}
+
+/// The read concern of a read command, such as `{ level: "majority" }`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadConcern {
+    level: String
+}
+
+/// The builder for a `find` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FindCommand {
+    options: CommandOptions,
+    collection: String,
+    filter: Option<Document>,
+    sort: Option<Document>,
+    projection: Option<Document>,
+    skip: Option<i64>,
+    limit: Option<i64>,
+    batch_size: Option<i32>,
+    single_batch: Option<bool>
+}
+
+/// The builder for an `insert` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InsertCommand {
+    options: CommandOptions,
+    collection: String,
+    documents: Vec<Document>,
+    ordered: Option<bool>
+}
+
+/// The builder for an `update` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateCommand {
+    options: CommandOptions,
+    collection: String,
+    updates: Vec<UpdateStatement>,
+    ordered: Option<bool>
+}
+
+/// A statement of an `update` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateStatement {
+    filter: Document,
+    update: Document,
+    upsert: Option<bool>,
+    multi: Option<bool>,
+    array_filters: Option<Vec<Document>>
+}
+
+/// The builder for a `delete` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeleteCommand {
+    options: CommandOptions,
+    collection: String,
+    deletes: Vec<(Document, i32)>,
+    ordered: Option<bool>
+}
+
+/// The builder for an `aggregate` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregateCommand {
+    options: CommandOptions,
+    collection: Option<String>,
+    pipeline: Vec<Document>,
+    batch_size: Option<i32>,
+    allow_disk_use: Option<bool>
+}
+
+/// The builder for a `getMore` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GetMoreCommand {
+    options: CommandOptions,
+    collection: String,
+    cursor_id: i64,
+    batch_size: Option<i32>,
+    max_time_ms: Option<i64>
+}
+
+/// The builder for a `createIndexes` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateIndexesCommand {
+    options: CommandOptions,
+    collection: String,
+    indexes: Vec<IndexModel>
+}
+
+/// An index of a `createIndexes` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexModel {
+    keys: Document,
+    name: Option<String>,
+    unique: Option<bool>
+}
+
+/// The builder for a `findAndModify` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FindAndModifyCommand {
+    options: CommandOptions,
+    collection: String,
+    query: Document,
+    update: Option<Document>,
+    sort: Option<Document>,
+    projection: Option<Document>,
+    return_new: Option<bool>,
+    upsert: Option<bool>,
+    array_filters: Option<Vec<Document>>
+}
+
+/// Implementation for the `CommandOptions` object.
+impl CommandOptions {
+
+    /// Create new `CommandOptions` for a command on a database.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    ///
+    /// # Returns
+    /// The new `CommandOptions`.
+    pub fn new(database: &str) -> CommandOptions {
+        CommandOptions { database: database.to_string(), session: None, txn_number: None, write_concern: None, read_concern: None }
+    }
+}
+
+/// Implementation for the `WriteConcern` object.
+impl WriteConcern {
+
+    /// Create a new `WriteConcern` that leaves every field to the server's
+    /// default.
+    ///
+    /// # Returns
+    /// The new `WriteConcern`.
+    pub fn new() -> WriteConcern {
+        WriteConcern::default()
+    }
+
+    /// Create a new `WriteConcern` acknowledged by a majority of nodes.
+    ///
+    /// # Returns
+    /// The new `WriteConcern`.
+    pub fn majority() -> WriteConcern {
+        WriteConcern { w: Some(Bson::String("majority".to_string())), ..WriteConcern::default() }
+    }
+
+    /// Create a new `WriteConcern` acknowledged by a number of nodes, where
+    /// 0 requests no acknowledgement.
+    ///
+    /// # Parameters
+    /// - `nodes` - The number of nodes.
+    ///
+    /// # Returns
+    /// The new `WriteConcern`.
+    pub fn nodes(nodes: i32) -> WriteConcern {
+        WriteConcern { w: Some(Bson::Int32(nodes)), ..WriteConcern::default() }
+    }
+
+    /// Set whether the write must be written to the journal.
+    ///
+    /// # Parameters
+    /// - `journal` - Whether to wait for the journal.
+    ///
+    /// # Returns
+    /// The `WriteConcern`.
+    pub fn journal(mut self, journal: bool) -> WriteConcern {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Set how long to wait for the acknowledgement.
+    ///
+    /// # Parameters
+    /// - `millis` - The time limit in milliseconds.
+    ///
+    /// # Returns
+    /// The `WriteConcern`.
+    pub fn timeout(mut self, millis: i64) -> WriteConcern {
+        self.timeout = Some(millis);
+        self
+    }
+
+    /// Get the write concern document.
+    ///
+    /// # Returns
+    /// The `Document` with the `w`, `j` and `wtimeout` fields that are set.
+    pub fn to_document(&self) -> Document {
+        let mut document = Document::new();
+        if let Some(ref w) = self.w {
+            document.insert("w".to_string(), w.clone());
+        }
+        if let Some(journal) = self.journal {
+            document.insert("j".to_string(), Bson::Boolean(journal));
+        }
+        if let Some(timeout) = self.timeout {
+            document.insert("wtimeout".to_string(), Bson::Int64(timeout));
+        }
+        document
+    }
+}
+
+/// Implementation for the `ReadConcern` object.
+impl ReadConcern {
+
+    /// Create a new `ReadConcern`.
+    ///
+    /// # Parameters
+    /// - `level` - The level, such as `local`, `majority` or `snapshot`.
+    ///
+    /// # Returns
+    /// The new `ReadConcern`.
+    pub fn new(level: &str) -> ReadConcern {
+        ReadConcern { level: level.to_string() }
+    }
+
+    /// Create a new `ReadConcern` with the `majority` level.
+    ///
+    /// # Returns
+    /// The new `ReadConcern`.
+    pub fn majority() -> ReadConcern {
+        ReadConcern::new("majority")
+    }
+
+    /// Get the read concern document.
+    ///
+    /// # Returns
+    /// The `Document` with the level.
+    pub fn to_document(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("level".to_string(), Bson::String(self.level.clone()));
+        document
+    }
+}
+
+/// Implementation for the `FindCommand` object.
+impl FindCommand {
+
+    /// Create a new `FindCommand` that finds every document.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    ///
+    /// # Returns
+    /// The new `FindCommand`.
+    pub fn new(database: &str, collection: &str) -> FindCommand {
+        FindCommand {
+            options: CommandOptions::new(database),
+            collection: collection.to_string(),
+            filter: None,
+            sort: None,
+            projection: None,
+            skip: None,
+            limit: None,
+            batch_size: None,
+            single_batch: None
+        }
+    }
+
+    /// Set the query filter.
+    ///
+    /// # Parameters
+    /// - `filter` - The filter `Document`.
+    ///
+    /// # Returns
+    /// The `FindCommand`.
+    pub fn filter(mut self, filter: Document) -> FindCommand {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Set the sort order.
+    ///
+    /// # Parameters
+    /// - `sort` - The sort specification `Document`.
+    ///
+    /// # Returns
+    /// The `FindCommand`.
+    pub fn sort(mut self, sort: Document) -> FindCommand {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Set the projection.
+    ///
+    /// # Parameters
+    /// - `projection` - The projection specification `Document`.
+    ///
+    /// # Returns
+    /// The `FindCommand`.
+    pub fn projection(mut self, projection: Document) -> FindCommand {
+        self.projection = Some(projection);
+        self
+    }
+
+    /// Set the number of documents to skip.
+    ///
+    /// # Parameters
+    /// - `skip` - The number of documents.
+    ///
+    /// # Returns
+    /// The `FindCommand`.
+    pub fn skip(mut self, skip: i64) -> FindCommand {
+        self.skip = Some(skip);
+        self
+    }
+
+    /// Set the maximum number of documents to return.
+    ///
+    /// # Parameters
+    /// - `limit` - The number of documents.
+    ///
+    /// # Returns
+    /// The `FindCommand`.
+    pub fn limit(mut self, limit: i64) -> FindCommand {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Set the number of documents in the first batch.
+    ///
+    /// # Parameters
+    /// - `batch_size` - The number of documents.
+    ///
+    /// # Returns
+    /// The `FindCommand`.
+    pub fn batch_size(mut self, batch_size: i32) -> FindCommand {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Set whether to close the cursor after the first batch.
+    ///
+    /// # Parameters
+    /// - `single_batch` - Whether to return a single batch.
+    ///
+    /// # Returns
+    /// The `FindCommand`.
+    pub fn single_batch(mut self, single_batch: bool) -> FindCommand {
+        self.single_batch = Some(single_batch);
+        self
+    }
+}
+
+impl Command for FindCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("find".to_string(), Bson::String(self.collection.clone()));
+        insert_document(&mut document, "filter", &self.filter);
+        insert_document(&mut document, "sort", &self.sort);
+        insert_document(&mut document, "projection", &self.projection);
+        insert_value(&mut document, "skip", self.skip.map(Bson::Int64));
+        insert_value(&mut document, "limit", self.limit.map(Bson::Int64));
+        insert_value(&mut document, "batchSize", self.batch_size.map(Bson::Int32));
+        insert_value(&mut document, "singleBatch", self.single_batch.map(Bson::Boolean));
+        document
+    }
+}
+
+/// Implementation for the `InsertCommand` object.
+impl InsertCommand {
+
+    /// Create a new `InsertCommand`.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    /// - `documents` - The `Document`s to insert.
+    ///
+    /// # Returns
+    /// The new `InsertCommand`.
+    pub fn new(database: &str, collection: &str, documents: Vec<Document>) -> InsertCommand {
+        InsertCommand { options: CommandOptions::new(database), collection: collection.to_string(), documents, ordered: None }
+    }
+
+    /// Set whether to stop at the first document that fails.
+    ///
+    /// # Parameters
+    /// - `ordered` - Whether the inserts are ordered.
+    ///
+    /// # Returns
+    /// The `InsertCommand`.
+    pub fn ordered(mut self, ordered: bool) -> InsertCommand {
+        self.ordered = Some(ordered);
+        self
+    }
+}
+
+impl Command for InsertCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("insert".to_string(), Bson::String(self.collection.clone()));
+        document.insert("documents".to_string(), documents(&self.documents));
+        insert_value(&mut document, "ordered", self.ordered.map(Bson::Boolean));
+        document
+    }
+}
+
+/// Implementation for the `UpdateCommand` object.
+impl UpdateCommand {
+
+    /// Create a new `UpdateCommand` with no statements.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    ///
+    /// # Returns
+    /// The new `UpdateCommand`.
+    pub fn new(database: &str, collection: &str) -> UpdateCommand {
+        UpdateCommand { options: CommandOptions::new(database), collection: collection.to_string(), updates: Vec::new(), ordered: None }
+    }
+
+    /// Add an update statement.
+    ///
+    /// # Parameters
+    /// - `statement` - The `UpdateStatement`.
+    ///
+    /// # Returns
+    /// The `UpdateCommand`.
+    pub fn statement(mut self, statement: UpdateStatement) -> UpdateCommand {
+        self.updates.push(statement);
+        self
+    }
+
+    /// Set whether to stop at the first statement that fails.
+    ///
+    /// # Parameters
+    /// - `ordered` - Whether the statements are ordered.
+    ///
+    /// # Returns
+    /// The `UpdateCommand`.
+    pub fn ordered(mut self, ordered: bool) -> UpdateCommand {
+        self.ordered = Some(ordered);
+        self
+    }
+}
+
+impl Command for UpdateCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("update".to_string(), Bson::String(self.collection.clone()));
+        let updates = self.updates.iter().map(|statement| Bson::Document(statement.to_document())).collect();
+        document.insert("updates".to_string(), Bson::Array(updates));
+        insert_value(&mut document, "ordered", self.ordered.map(Bson::Boolean));
+        document
+    }
+}
+
+/// Implementation for the `UpdateStatement` object.
+impl UpdateStatement {
+
+    /// Create a new `UpdateStatement` that updates the first matching
+    /// document.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    /// - `update` - The update `Document` of operators, or a replacement.
+    ///
+    /// # Returns
+    /// The new `UpdateStatement`.
+    pub fn new(filter: Document, update: Document) -> UpdateStatement {
+        UpdateStatement { filter, update, upsert: None, multi: None, array_filters: None }
+    }
+
+    /// Set whether to insert a document when none matches.
+    ///
+    /// # Parameters
+    /// - `upsert` - Whether to upsert.
+    ///
+    /// # Returns
+    /// The `UpdateStatement`.
+    pub fn upsert(mut self, upsert: bool) -> UpdateStatement {
+        self.upsert = Some(upsert);
+        self
+    }
+
+    /// Set whether to update every matching document.
+    ///
+    /// # Parameters
+    /// - `multi` - Whether to update many documents.
+    ///
+    /// # Returns
+    /// The `UpdateStatement`.
+    pub fn multi(mut self, multi: bool) -> UpdateStatement {
+        self.multi = Some(multi);
+        self
+    }
+
+    /// Set the array filters for `$[<identifier>]` paths in the update.
+    ///
+    /// # Parameters
+    /// - `array_filters` - The array filter `Document`s.
+    ///
+    /// # Returns
+    /// The `UpdateStatement`.
+    pub fn array_filters(mut self, array_filters: Vec<Document>) -> UpdateStatement {
+        self.array_filters = Some(array_filters);
+        self
+    }
+
+    fn to_document(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("q".to_string(), Bson::Document(self.filter.clone()));
+        document.insert("u".to_string(), Bson::Document(self.update.clone()));
+        insert_value(&mut document, "upsert", self.upsert.map(Bson::Boolean));
+        insert_value(&mut document, "multi", self.multi.map(Bson::Boolean));
+        insert_value(&mut document, "arrayFilters", self.array_filters.as_ref().map(|filters| documents(filters)));
+        document
+    }
+}
+
+/// Implementation for the `DeleteCommand` object.
+impl DeleteCommand {
+
+    /// Create a new `DeleteCommand` with no statements.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    ///
+    /// # Returns
+    /// The new `DeleteCommand`.
+    pub fn new(database: &str, collection: &str) -> DeleteCommand {
+        DeleteCommand { options: CommandOptions::new(database), collection: collection.to_string(), deletes: Vec::new(), ordered: None }
+    }
+
+    /// Add a statement deleting the first matching document.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    ///
+    /// # Returns
+    /// The `DeleteCommand`.
+    pub fn delete_one(mut self, filter: Document) -> DeleteCommand {
+        self.deletes.push((filter, 1));
+        self
+    }
+
+    /// Add a statement deleting every matching document.
+    ///
+    /// # Parameters
+    /// - `filter` - The query filter `Document`.
+    ///
+    /// # Returns
+    /// The `DeleteCommand`.
+    pub fn delete_many(mut self, filter: Document) -> DeleteCommand {
+        self.deletes.push((filter, 0));
+        self
+    }
+
+    /// Set whether to stop at the first statement that fails.
+    ///
+    /// # Parameters
+    /// - `ordered` - Whether the statements are ordered.
+    ///
+    /// # Returns
+    /// The `DeleteCommand`.
+    pub fn ordered(mut self, ordered: bool) -> DeleteCommand {
+        self.ordered = Some(ordered);
+        self
+    }
+}
+
+impl Command for DeleteCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("delete".to_string(), Bson::String(self.collection.clone()));
+        let deletes = self.deletes.iter().map(|(filter, limit)| {
+            let mut statement = Document::new();
+            statement.insert("q".to_string(), Bson::Document(filter.clone()));
+            statement.insert("limit".to_string(), Bson::Int32(*limit));
+            Bson::Document(statement)
+        }).collect();
+        document.insert("deletes".to_string(), Bson::Array(deletes));
+        insert_value(&mut document, "ordered", self.ordered.map(Bson::Boolean));
+        document
+    }
+}
+
+/// Implementation for the `AggregateCommand` object.
+impl AggregateCommand {
+
+    /// Create a new `AggregateCommand` on a collection.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    /// - `pipeline` - The pipeline stage `Document`s.
+    ///
+    /// # Returns
+    /// The new `AggregateCommand`.
+    pub fn new(database: &str, collection: &str, pipeline: Vec<Document>) -> AggregateCommand {
+        AggregateCommand {
+            options: CommandOptions::new(database),
+            collection: Some(collection.to_string()),
+            pipeline,
+            batch_size: None,
+            allow_disk_use: None
+        }
+    }
+
+    /// Create a new `AggregateCommand` on a database, such as one starting
+    /// with `$currentOp`, sent as `aggregate: 1`.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `pipeline` - The pipeline stage `Document`s.
+    ///
+    /// # Returns
+    /// The new `AggregateCommand`.
+    pub fn database(database: &str, pipeline: Vec<Document>) -> AggregateCommand {
+        AggregateCommand { options: CommandOptions::new(database), collection: None, pipeline, batch_size: None, allow_disk_use: None }
+    }
+
+    /// Set the number of documents in the first batch.
+    ///
+    /// # Parameters
+    /// - `batch_size` - The number of documents.
+    ///
+    /// # Returns
+    /// The `AggregateCommand`.
+    pub fn batch_size(mut self, batch_size: i32) -> AggregateCommand {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Set whether stages may write temporary files.
+    ///
+    /// # Parameters
+    /// - `allow_disk_use` - Whether to allow disk use.
+    ///
+    /// # Returns
+    /// The `AggregateCommand`.
+    pub fn allow_disk_use(mut self, allow_disk_use: bool) -> AggregateCommand {
+        self.allow_disk_use = Some(allow_disk_use);
+        self
+    }
+}
+
+impl Command for AggregateCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        let target = match self.collection {
+            Some(ref collection) => Bson::String(collection.clone()),
+            None => Bson::Int32(1)
+        };
+        document.insert("aggregate".to_string(), target);
+        document.insert("pipeline".to_string(), documents(&self.pipeline));
+        let mut cursor = Document::new();
+        insert_value(&mut cursor, "batchSize", self.batch_size.map(Bson::Int32));
+        document.insert("cursor".to_string(), Bson::Document(cursor));
+        insert_value(&mut document, "allowDiskUse", self.allow_disk_use.map(Bson::Boolean));
+        document
+    }
+}
+
+/// Implementation for the `GetMoreCommand` object.
+impl GetMoreCommand {
+
+    /// Create a new `GetMoreCommand`.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    /// - `cursor_id` - The id of the cursor.
+    ///
+    /// # Returns
+    /// The new `GetMoreCommand`.
+    pub fn new(database: &str, collection: &str, cursor_id: i64) -> GetMoreCommand {
+        GetMoreCommand {
+            options: CommandOptions::new(database),
+            collection: collection.to_string(),
+            cursor_id,
+            batch_size: None,
+            max_time_ms: None
+        }
+    }
+
+    /// Set the number of documents in the batch.
+    ///
+    /// # Parameters
+    /// - `batch_size` - The number of documents.
+    ///
+    /// # Returns
+    /// The `GetMoreCommand`.
+    pub fn batch_size(mut self, batch_size: i32) -> GetMoreCommand {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Set how long a tailable cursor waits for new documents.
+    ///
+    /// # Parameters
+    /// - `millis` - The time limit in milliseconds.
+    ///
+    /// # Returns
+    /// The `GetMoreCommand`.
+    pub fn max_time_ms(mut self, millis: i64) -> GetMoreCommand {
+        self.max_time_ms = Some(millis);
+        self
+    }
+}
+
+impl Command for GetMoreCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("getMore".to_string(), Bson::Int64(self.cursor_id));
+        document.insert("collection".to_string(), Bson::String(self.collection.clone()));
+        insert_value(&mut document, "batchSize", self.batch_size.map(Bson::Int32));
+        insert_value(&mut document, "maxTimeMS", self.max_time_ms.map(Bson::Int64));
+        document
+    }
+}
+
+/// Implementation for the `CreateIndexesCommand` object.
+impl CreateIndexesCommand {
+
+    /// Create a new `CreateIndexesCommand` with no indexes.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    ///
+    /// # Returns
+    /// The new `CreateIndexesCommand`.
+    pub fn new(database: &str, collection: &str) -> CreateIndexesCommand {
+        CreateIndexesCommand { options: CommandOptions::new(database), collection: collection.to_string(), indexes: Vec::new() }
+    }
+
+    /// Add an index to create.
+    ///
+    /// # Parameters
+    /// - `index` - The `IndexModel`.
+    ///
+    /// # Returns
+    /// The `CreateIndexesCommand`.
+    pub fn index(mut self, index: IndexModel) -> CreateIndexesCommand {
+        self.indexes.push(index);
+        self
+    }
+}
+
+impl Command for CreateIndexesCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("createIndexes".to_string(), Bson::String(self.collection.clone()));
+        let indexes = self.indexes.iter().map(|index| Bson::Document(index.to_document())).collect();
+        document.insert("indexes".to_string(), Bson::Array(indexes));
+        document
+    }
+}
+
+/// Implementation for the `IndexModel` object.
+impl IndexModel {
+
+    /// Create a new `IndexModel`, named after its keys as by the server,
+    /// such as `a_1_b_-1` for `{ a: 1, b: -1 }`.
+    ///
+    /// # Parameters
+    /// - `keys` - The index key `Document`.
+    ///
+    /// # Returns
+    /// The new `IndexModel`.
+    pub fn new(keys: Document) -> IndexModel {
+        IndexModel { keys, name: None, unique: None }
+    }
+
+    /// Set the name of the index.
+    ///
+    /// # Parameters
+    /// - `name` - The index name.
+    ///
+    /// # Returns
+    /// The `IndexModel`.
+    pub fn name(mut self, name: &str) -> IndexModel {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set whether the index rejects documents with duplicate keys.
+    ///
+    /// # Parameters
+    /// - `unique` - Whether the index is unique.
+    ///
+    /// # Returns
+    /// The `IndexModel`.
+    pub fn unique(mut self, unique: bool) -> IndexModel {
+        self.unique = Some(unique);
+        self
+    }
+
+    fn to_document(&self) -> Document {
+        let name = self.name.clone().unwrap_or_else(|| {
+            self.keys.iter().map(|(field, direction)| match (whole_number(direction), direction) {
+                (Some(direction), _) => format!("{}_{}", field, direction),
+                (None, Bson::String(kind)) => format!("{}_{}", field, kind),
+                (None, direction) => format!("{}_{}", field, direction)
+            }).collect::<Vec<String>>().join("_")
+        });
+        let mut document = Document::new();
+        document.insert("key".to_string(), Bson::Document(self.keys.clone()));
+        document.insert("name".to_string(), Bson::String(name));
+        insert_value(&mut document, "unique", self.unique.map(Bson::Boolean));
+        document
+    }
+}
+
+/// Implementation for the `FindAndModifyCommand` object.
+impl FindAndModifyCommand {
+
+    /// Create a new `FindAndModifyCommand` that updates the first matching
+    /// document.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    /// - `query` - The query filter `Document`.
+    /// - `update` - The update `Document` of operators, or a replacement.
+    ///
+    /// # Returns
+    /// The new `FindAndModifyCommand`.
+    pub fn update(database: &str, collection: &str, query: Document, update: Document) -> FindAndModifyCommand {
+        FindAndModifyCommand::with(database, collection, query, Some(update))
+    }
+
+    /// Create a new `FindAndModifyCommand` that removes the first matching
+    /// document.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `collection` - The collection name.
+    /// - `query` - The query filter `Document`.
+    ///
+    /// # Returns
+    /// The new `FindAndModifyCommand`.
+    pub fn remove(database: &str, collection: &str, query: Document) -> FindAndModifyCommand {
+        FindAndModifyCommand::with(database, collection, query, None)
+    }
+
+    /// Set the sort order choosing which matching document is modified.
+    ///
+    /// # Parameters
+    /// - `sort` - The sort specification `Document`.
+    ///
+    /// # Returns
+    /// The `FindAndModifyCommand`.
+    pub fn sort(mut self, sort: Document) -> FindAndModifyCommand {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Set the projection of the returned document, sent as `fields`.
+    ///
+    /// # Parameters
+    /// - `projection` - The projection specification `Document`.
+    ///
+    /// # Returns
+    /// The `FindAndModifyCommand`.
+    pub fn projection(mut self, projection: Document) -> FindAndModifyCommand {
+        self.projection = Some(projection);
+        self
+    }
+
+    /// Set whether to return the document after the update rather than
+    /// before, sent as `new`.
+    ///
+    /// # Parameters
+    /// - `return_new` - Whether to return the updated document.
+    ///
+    /// # Returns
+    /// The `FindAndModifyCommand`.
+    pub fn return_new(mut self, return_new: bool) -> FindAndModifyCommand {
+        self.return_new = Some(return_new);
+        self
+    }
+
+    /// Set whether to insert a document when none matches.
+    ///
+    /// # Parameters
+    /// - `upsert` - Whether to upsert.
+    ///
+    /// # Returns
+    /// The `FindAndModifyCommand`.
+    pub fn upsert(mut self, upsert: bool) -> FindAndModifyCommand {
+        self.upsert = Some(upsert);
+        self
+    }
+
+    /// Set the array filters for `$[<identifier>]` paths in the update.
+    ///
+    /// # Parameters
+    /// - `array_filters` - The array filter `Document`s.
+    ///
+    /// # Returns
+    /// The `FindAndModifyCommand`.
+    pub fn array_filters(mut self, array_filters: Vec<Document>) -> FindAndModifyCommand {
+        self.array_filters = Some(array_filters);
+        self
+    }
+
+    fn with(database: &str, collection: &str, query: Document, update: Option<Document>) -> FindAndModifyCommand {
+        FindAndModifyCommand {
+            options: CommandOptions::new(database),
+            collection: collection.to_string(),
+            query,
+            update,
+            sort: None,
+            projection: None,
+            return_new: None,
+            upsert: None,
+            array_filters: None
+        }
+    }
+}
+
+impl Command for FindAndModifyCommand {
+    fn options(&self) -> &CommandOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut CommandOptions {
+        &mut self.options
+    }
+
+    fn fields(&self) -> Document {
+        let mut document = Document::new();
+        document.insert("findAndModify".to_string(), Bson::String(self.collection.clone()));
+        document.insert("query".to_string(), Bson::Document(self.query.clone()));
+        insert_document(&mut document, "sort", &self.sort);
+        match self.update {
+            Some(ref update) => document.insert("update".to_string(), Bson::Document(update.clone())),
+            None => document.insert("remove".to_string(), Bson::Boolean(true))
+        };
+        insert_value(&mut document, "new", self.return_new.map(Bson::Boolean));
+        insert_document(&mut document, "fields", &self.projection);
+        insert_value(&mut document, "upsert", self.upsert.map(Bson::Boolean));
+        insert_value(&mut document, "arrayFilters", self.array_filters.as_ref().map(|filters| documents(filters)));
+        document
+    }
+}
+
+fn documents(documents: &[Document]) -> Bson {
+    Bson::Array(documents.iter().cloned().map(Bson::Document).collect())
+}
+
+fn insert_document(document: &mut Document, key: &str, value: &Option<Document>) {
+    insert_value(document, key, value.clone().map(Bson::Document));
+}
+
+fn insert_value(document: &mut Document, key: &str, value: Option<Bson>) {
+    if let Some(value) = value {
+        document.insert(key.to_string(), value);
+    }
+}
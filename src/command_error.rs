@@ -0,0 +1,127 @@
+use std::error::Error;
+use std::fmt;
+use bson::Bson;
+use document::Document;
+use filter::whole_number;
+use query_error::QueryError;
+
+/// The error returned by the server for a failed command, a reply with
+/// `ok: 0`, or for a reply that is not of the expected form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandError {
+    code: i32,
+    code_name: String,
+    message: String
+}
+
+/// The implementation for `CommandError`.
+impl CommandError {
+
+    /// Create a new `CommandError`.
+    ///
+    /// # Parameters
+    /// - `code` - The server error code, such as 11000.
+    /// - `code_name` - The name of the code, such as `DuplicateKey`.
+    /// - `message` - The message describing the error.
+    ///
+    /// # Returns
+    /// The new `CommandError`.
+    pub fn new<N: Into<String>, S: Into<String>>(code: i32, code_name: N, message: S) -> CommandError {
+        CommandError { code, code_name: code_name.into(), message: message.into() }
+    }
+
+    /// Create a new `CommandError` for a reply that is not of the expected
+    /// form, with code 0.
+    ///
+    /// # Parameters
+    /// - `message` - The message describing the problem.
+    ///
+    /// # Returns
+    /// The new `CommandError`.
+    pub fn invalid_reply<S: Into<String>>(message: S) -> CommandError {
+        CommandError::new(0, "InvalidReply", message)
+    }
+
+    /// Get the error from a command reply, if the command failed.
+    ///
+    /// # Parameters
+    /// - `reply` - The reply `Document`.
+    ///
+    /// # Returns
+    /// The `CommandError` if `ok` is not 1, otherwise `None`.
+    pub fn from_reply(reply: &Document) -> Option<CommandError> {
+        let ok = match reply.get("ok") {
+            Some(&Bson::Boolean(ok)) => ok,
+            Some(value) => whole_number(value) == Some(1),
+            None => false
+        };
+        if ok {
+            return None;
+        }
+        let code = reply.get("code").and_then(whole_number).unwrap_or(0) as i32;
+        let code_name = match reply.get("codeName") {
+            Some(Bson::String(name)) => name.clone(),
+            _ => String::new()
+        };
+        let message = match reply.get("errmsg") {
+            Some(Bson::String(message)) => message.clone(),
+            _ => "command failed".to_string()
+        };
+        Some(CommandError::new(code, code_name, message))
+    }
+
+    /// Get the server error code.
+    ///
+    /// # Returns
+    /// The code.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// Get the name of the server error code.
+    ///
+    /// # Returns
+    /// The code name `&str`, empty if the server did not send one.
+    pub fn code_name(&self) -> &str {
+        &self.code_name
+    }
+
+    /// Get the message describing the error.
+    ///
+    /// # Returns
+    /// The message `&str`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the error as the server sends it, a reply with `ok: 0`.
+    ///
+    /// # Returns
+    /// The reply `Document`.
+    pub fn to_document(&self) -> Document {
+        let mut reply = Document::new();
+        reply.insert("ok".to_string(), Bson::Double(0.0));
+        reply.insert("errmsg".to_string(), Bson::String(self.message.clone()));
+        reply.insert("code".to_string(), Bson::Int32(self.code));
+        reply.insert("codeName".to_string(), Bson::String(self.code_name.clone()));
+        reply
+    }
+}
+
+impl From<QueryError> for CommandError {
+    fn from(error: QueryError) -> CommandError {
+        CommandError::new(2, "BadValue", error.message())
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.code_name.is_empty() {
+            write!(f, "{} ({})", self.message, self.code)
+        } else {
+            write!(f, "{} ({}: {})", self.message, self.code_name, self.code)
+        }
+    }
+}
+
+impl Error for CommandError {}
@@ -0,0 +1,220 @@
+use bson::Bson;
+use command_error::CommandError;
+use document::Document;
+use filter::whole_number;
+
+/// The cursor of a `find`, `aggregate` or `getMore` reply.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CursorReply {
+    /// The cursor id, 0 once the cursor is exhausted.
+    pub id: i64,
+    /// The namespace of the cursor, such as `test.c`.
+    pub namespace: String,
+    /// The documents of `firstBatch` or `nextBatch`.
+    pub batch: Vec<Document>
+}
+
+/// The reply of an `insert`, `update` or `delete` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteReply {
+    /// The number of documents inserted, matched or deleted.
+    pub n: i64,
+    /// The number of documents changed by an `update`.
+    pub n_modified: Option<i64>,
+    /// The index of each upserting statement with the `_id` it inserted.
+    pub upserted: Vec<(usize, Bson)>,
+    /// The statements that failed.
+    pub write_errors: Vec<WriteError>,
+    /// The error if the write concern could not be satisfied.
+    pub write_concern_error: Option<CommandError>
+}
+
+/// A failed statement in the `writeErrors` of a write reply.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteError {
+    /// The index of the statement in the command.
+    pub index: usize,
+    /// The server error code.
+    pub code: i32,
+    /// The message describing the error.
+    pub message: String
+}
+
+/// The reply of a `findAndModify` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FindAndModifyReply {
+    /// The document before or after the change, if one matched or was
+    /// upserted.
+    pub value: Option<Document>,
+    /// The number of documents changed.
+    pub n: i64,
+    /// Whether an existing document was updated.
+    pub updated_existing: bool,
+    /// The `_id` of the upserted document.
+    pub upserted: Option<Bson>
+}
+
+/// Implementation for the `CursorReply` object.
+impl CursorReply {
+
+    /// Parse the reply of a command returning a cursor.
+    ///
+    /// # Parameters
+    /// - `reply` - The reply `Document`.
+    ///
+    /// # Returns
+    /// The `CursorReply`, or the `CommandError` if the command failed or the
+    /// reply has no cursor.
+    pub fn parse(reply: &Document) -> Result<CursorReply, CommandError> {
+        check(reply)?;
+        let cursor = match reply.get("cursor") {
+            Some(Bson::Document(cursor)) => cursor,
+            _ => return Err(CommandError::invalid_reply("reply has no cursor document"))
+        };
+        let id = match cursor.get("id") {
+            Some(id) => whole_number(id).ok_or_else(|| CommandError::invalid_reply("cursor id is not a number"))?,
+            None => return Err(CommandError::invalid_reply("cursor has no id"))
+        };
+        let namespace = match cursor.get("ns") {
+            Some(Bson::String(namespace)) => namespace.clone(),
+            _ => String::new()
+        };
+        let batch = match cursor.get("firstBatch").or_else(|| cursor.get("nextBatch")) {
+            Some(Bson::Array(batch)) => documents(batch, "cursor batch")?,
+            _ => return Err(CommandError::invalid_reply("cursor has no firstBatch or nextBatch"))
+        };
+        Ok(CursorReply { id, namespace, batch })
+    }
+
+    /// Determine if the server has more documents for the cursor.
+    ///
+    /// # Returns
+    /// True if the cursor id is not 0.
+    pub fn has_more(&self) -> bool {
+        self.id != 0
+    }
+}
+
+/// Implementation for the `WriteReply` object.
+impl WriteReply {
+
+    /// Parse the reply of a write command.
+    ///
+    /// # Parameters
+    /// - `reply` - The reply `Document`.
+    ///
+    /// # Returns
+    /// The `WriteReply`, or the `CommandError` if the whole command failed.
+    pub fn parse(reply: &Document) -> Result<WriteReply, CommandError> {
+        check(reply)?;
+        let n = number(reply, "n")?.unwrap_or(0);
+        let n_modified = number(reply, "nModified")?;
+        let upserted = match reply.get("upserted") {
+            Some(Bson::Array(upserted)) => documents(upserted, "upserted")?.into_iter().map(|mut upsert| {
+                let index = index(&upsert)?;
+                let id = upsert.remove("_id").ok_or_else(|| CommandError::invalid_reply("upserted entry has no _id"))?;
+                Ok((index, id))
+            }).collect::<Result<Vec<(usize, Bson)>, CommandError>>()?,
+            Some(_) => return Err(CommandError::invalid_reply("upserted is not an array")),
+            None => Vec::new()
+        };
+        let write_errors = match reply.get("writeErrors") {
+            Some(Bson::Array(errors)) => documents(errors, "writeErrors")?.iter().map(|error| {
+                Ok(WriteError { index: index(error)?, code: code(error), message: message(error) })
+            }).collect::<Result<Vec<WriteError>, CommandError>>()?,
+            Some(_) => return Err(CommandError::invalid_reply("writeErrors is not an array")),
+            None => Vec::new()
+        };
+        let write_concern_error = match reply.get("writeConcernError") {
+            Some(Bson::Document(error)) => {
+                let code_name = match error.get("codeName") {
+                    Some(Bson::String(name)) => name.clone(),
+                    _ => String::new()
+                };
+                Some(CommandError::new(code(error), code_name, message(error)))
+            },
+            Some(_) => return Err(CommandError::invalid_reply("writeConcernError is not a document")),
+            None => None
+        };
+        Ok(WriteReply { n, n_modified, upserted, write_errors, write_concern_error })
+    }
+
+    /// Determine if every statement succeeded and the write concern was
+    /// satisfied.
+    ///
+    /// # Returns
+    /// True if there are no write errors.
+    pub fn is_ok(&self) -> bool {
+        self.write_errors.is_empty() && self.write_concern_error.is_none()
+    }
+}
+
+/// Implementation for the `FindAndModifyReply` object.
+impl FindAndModifyReply {
+
+    /// Parse the reply of a `findAndModify` command.
+    ///
+    /// # Parameters
+    /// - `reply` - The reply `Document`.
+    ///
+    /// # Returns
+    /// The `FindAndModifyReply`, or the `CommandError` if the command failed.
+    pub fn parse(reply: &Document) -> Result<FindAndModifyReply, CommandError> {
+        check(reply)?;
+        let value = match reply.get("value") {
+            Some(Bson::Document(value)) => Some(value.clone()),
+            Some(Bson::Null) | None => None,
+            Some(_) => return Err(CommandError::invalid_reply("value is not a document"))
+        };
+        let (n, updated_existing, upserted) = match reply.get("lastErrorObject") {
+            Some(Bson::Document(last_error)) => {
+                let updated_existing = match last_error.get("updatedExisting") {
+                    Some(&Bson::Boolean(updated)) => updated,
+                    _ => false
+                };
+                (number(last_error, "n")?.unwrap_or(0), updated_existing, last_error.get("upserted").cloned())
+            },
+            _ => (0, false, None)
+        };
+        Ok(FindAndModifyReply { value, n, updated_existing, upserted })
+    }
+}
+
+fn check(reply: &Document) -> Result<(), CommandError> {
+    match CommandError::from_reply(reply) {
+        Some(error) => Err(error),
+        None => Ok(())
+    }
+}
+
+fn documents(values: &[Bson], name: &str) -> Result<Vec<Document>, CommandError> {
+    values.iter().map(|value| match value {
+        Bson::Document(document) => Ok(document.clone()),
+        _ => Err(CommandError::invalid_reply(format!("{} contains a value that is not a document", name)))
+    }).collect()
+}
+
+fn number(document: &Document, key: &str) -> Result<Option<i64>, CommandError> {
+    match document.get(key) {
+        Some(value) => whole_number(value).map(Some).ok_or_else(|| CommandError::invalid_reply(format!("{} is not a number", key))),
+        None => Ok(None)
+    }
+}
+
+fn index(document: &Document) -> Result<usize, CommandError> {
+    match number(document, "index")? {
+        Some(index) if index >= 0 => Ok(index as usize),
+        _ => Err(CommandError::invalid_reply("entry has no valid index"))
+    }
+}
+
+fn code(document: &Document) -> i32 {
+    document.get("code").and_then(whole_number).unwrap_or(0) as i32
+}
+
+fn message(document: &Document) -> String {
+    match document.get("errmsg") {
+        Some(Bson::String(message)) => message.clone(),
+        _ => String::new()
+    }
+}
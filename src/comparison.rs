@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+use bson::Bson;
+use decimal128::Decimal;
+use document::Document;
+
+/// Compare two `Bson` values using the MongoDB sort order. Values of
+/// different types are ordered by their type bracket:
+///
+/// MinKey < Undefined < Null < Numbers < Symbol/String < Document < Array <
+/// Binary < ObjectId < Boolean < DateTime < Timestamp < RegExp < DbPointer < Code <
+/// Code with scope < MaxKey
+///
+/// `Int32`, `Int64`, `Double` and `Decimal128` values share a bracket and
+/// compare by their numeric value, with `NaN` sorting before every other
+/// number. A `Double` is compared with a `Decimal128` by first rounding it to
+/// 34 significant digits, as the server does.
+///
+/// # Parameters
+/// - `left` - The left hand side `Bson` value.
+/// - `right` - The right hand side `Bson` value.
+///
+/// # Returns
+/// The `Ordering` of the two values.
+pub fn bson_cmp(left: &Bson, right: &Bson) -> Ordering {
+    let bracket = type_bracket(left).cmp(&type_bracket(right));
+    if bracket != Ordering::Equal {
+        return bracket;
+    }
+    match (left, right) {
+        (Bson::Double(l), Bson::Double(r)) => double_cmp(*l, *r),
+        (Bson::Double(l), Bson::Int32(r)) => double_cmp_i64(*l, i64::from(*r)),
+        (Bson::Double(l), Bson::Int64(r)) => double_cmp_i64(*l, *r),
+        (Bson::Int32(l), Bson::Double(r)) => double_cmp_i64(*r, i64::from(*l)).reverse(),
+        (Bson::Int32(l), Bson::Int32(r)) => l.cmp(r),
+        (Bson::Int32(l), Bson::Int64(r)) => i64::from(*l).cmp(r),
+        (Bson::Int64(l), Bson::Double(r)) => double_cmp_i64(*r, *l).reverse(),
+        (Bson::Int64(l), Bson::Int32(r)) => l.cmp(&i64::from(*r)),
+        (Bson::Int64(l), Bson::Int64(r)) => l.cmp(r),
+        (Bson::Decimal128(_), _) | (_, Bson::Decimal128(_)) => match (Decimal::from_bson(left), Decimal::from_bson(right)) {
+            (Some(l), Some(r)) => l.compare(&r),
+            _ => Ordering::Equal
+        },
+        (Bson::String(l), Bson::String(r)) | (Bson::String(l), Bson::Symbol(r)) |
+        (Bson::Symbol(l), Bson::String(r)) | (Bson::Symbol(l), Bson::Symbol(r)) => {
+            l.as_bytes().cmp(r.as_bytes())
+        },
+        (Bson::Document(l), Bson::Document(r)) => document_cmp(l, r),
+        (Bson::Array(l), Bson::Array(r)) => array_cmp(l, r),
+        (Bson::Binary(lt, l), Bson::Binary(rt, r)) => {
+            l.len().cmp(&r.len()).then(lt.cmp(rt)).then_with(|| l.cmp(r))
+        },
+        (Bson::ObjectId(l), Bson::ObjectId(r)) => l.cmp(r),
+        (Bson::Boolean(l), Bson::Boolean(r)) => l.cmp(r),
+        (Bson::DateTime(l), Bson::DateTime(r)) => l.cmp(r),
+        (Bson::Timestamp(l), Bson::Timestamp(r)) => l.cmp(r),
+        (Bson::RegExp(lp, lo), Bson::RegExp(rp, ro)) => {
+            lp.as_bytes().cmp(rp.as_bytes()).then_with(|| lo.as_bytes().cmp(ro.as_bytes()))
+        },
+        (Bson::DbPointer(ln, lid), Bson::DbPointer(rn, rid)) => {
+            ln.len().cmp(&rn.len())
+                .then_with(|| ln.as_bytes().cmp(rn.as_bytes()))
+                .then_with(|| lid.cmp(rid))
+        },
+        (Bson::Code(lc, ls), Bson::Code(rc, rs)) => {
+            lc.as_bytes().cmp(rc.as_bytes()).then_with(|| document_cmp(ls, rs))
+        },
+        _ => Ordering::Equal
+    }
+}
+
+/// Compare two `Document`s element by element using the MongoDB sort order.
+/// Each pair of elements is compared first by type bracket, then by key and
+/// then by value. When one document is a prefix of the other the shorter
+/// document sorts first.
+///
+/// # Parameters
+/// - `left` - The left hand side `Document`.
+/// - `right` - The right hand side `Document`.
+///
+/// # Returns
+/// The `Ordering` of the two documents.
+pub fn document_cmp(left: &Document, right: &Document) -> Ordering {
+    let mut left_iter = left.iter();
+    let mut right_iter = right.iter();
+    loop {
+        match (left_iter.next(), right_iter.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some((lk, lv)), Some((rk, rv))) => {
+                let ordering = type_bracket(lv).cmp(&type_bracket(rv))
+                    .then_with(|| lk.as_bytes().cmp(rk.as_bytes()))
+                    .then_with(|| bson_cmp(lv, rv));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+fn array_cmp(left: &[Bson], right: &[Bson]) -> Ordering {
+    for (l, r) in left.iter().zip(right.iter()) {
+        let ordering = bson_cmp(l, r);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    left.len().cmp(&right.len())
+}
+
+fn double_cmp(left: f64, right: f64) -> Ordering {
+    match (left.is_nan(), right.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => left.partial_cmp(&right).unwrap()
+    }
+}
+
+// Compares without converting the integer to a double, which would lose
+// precision for large `i64` values.
+fn double_cmp_i64(left: f64, right: i64) -> Ordering {
+    const TWO_POW_63: f64 = 9223372036854775808.0;
+    if left.is_nan() {
+        return Ordering::Less;
+    }
+    if left >= TWO_POW_63 {
+        return Ordering::Greater;
+    }
+    if left < -TWO_POW_63 {
+        return Ordering::Less;
+    }
+    let truncated = left.trunc();
+    match (truncated as i64).cmp(&right) {
+        Ordering::Equal => double_cmp(left - truncated, 0.0),
+        ordering => ordering
+    }
+}
+
+/// Get the canonical type bracket for a `Bson` value, used to order values of
+/// different types.
+///
+/// # Parameters
+/// - `value` - The `Bson` value.
+///
+/// # Returns
+/// The bracket number, lower numbers sorting first.
+pub fn type_bracket(value: &Bson) -> i32 {
+    match value {
+        Bson::MinKey => -1,
+        Bson::Undefined => 0,
+        Bson::Null => 5,
+        Bson::Double(_) | Bson::Int32(_) | Bson::Int64(_) | Bson::Decimal128(_) => 10,
+        Bson::String(_) | Bson::Symbol(_) => 15,
+        Bson::Document(_) => 20,
+        Bson::Array(_) => 25,
+        Bson::Binary(_, _) => 30,
+        Bson::ObjectId(_) => 35,
+        Bson::Boolean(_) => 40,
+        Bson::DateTime(_) => 45,
+        Bson::Timestamp(_) => 47,
+        Bson::RegExp(_, _) => 50,
+        Bson::DbPointer(_, _) => 55,
+        Bson::Code(_, scope) if scope.is_empty() => 60,
+        Bson::Code(_, _) => 65,
+        Bson::MaxKey => 127
+    }
+}
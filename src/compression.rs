@@ -0,0 +1,237 @@
+use std::io::{Error, ErrorKind, Result};
+use bson::Bson;
+use document::Document;
+#[cfg(any(feature = "snappy", feature = "zlib", feature = "zstd"))]
+use document_deserializer::invalid_data;
+
+/// A compressor for the bodies of `OP_COMPRESSED` messages.
+///
+/// Every compressor defined by the wire protocol is listed, but only `Noop`
+/// is always available: `Snappy`, `Zlib` and `Zstd` need the `snappy`,
+/// `zlib` and `zstd` cargo features, and return an `Unsupported` error from
+/// `compress` and `decompress` without them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compressor {
+    /// No compression, with id 0.
+    Noop,
+    /// Raw snappy, with id 1.
+    Snappy,
+    /// zlib at the default level, with id 2.
+    Zlib,
+    /// zstd at the default level, with id 3.
+    Zstd
+}
+
+/// Implementation for the `Compressor` object.
+impl Compressor {
+
+    /// Get the compressor with the id sent in an `OP_COMPRESSED` message.
+    ///
+    /// # Parameters
+    /// - `id` - The compressor id.
+    ///
+    /// # Returns
+    /// The `Compressor`, or `None` if the id is unknown.
+    pub fn from_id(id: u8) -> Option<Compressor> {
+        match id {
+            0 => Some(Compressor::Noop),
+            1 => Some(Compressor::Snappy),
+            2 => Some(Compressor::Zlib),
+            3 => Some(Compressor::Zstd),
+            _ => None
+        }
+    }
+
+    /// Get the compressor with the name used in the `compression` field of
+    /// a `hello` command.
+    ///
+    /// # Parameters
+    /// - `name` - The compressor name, such as `zstd`.
+    ///
+    /// # Returns
+    /// The `Compressor`, or `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Compressor> {
+        match name {
+            "noop" => Some(Compressor::Noop),
+            "snappy" => Some(Compressor::Snappy),
+            "zlib" => Some(Compressor::Zlib),
+            "zstd" => Some(Compressor::Zstd),
+            _ => None
+        }
+    }
+
+    /// Get the id of the compressor.
+    ///
+    /// # Returns
+    /// The compressor id.
+    pub fn id(self) -> u8 {
+        match self {
+            Compressor::Noop => 0,
+            Compressor::Snappy => 1,
+            Compressor::Zlib => 2,
+            Compressor::Zstd => 3
+        }
+    }
+
+    /// Get the name of the compressor.
+    ///
+    /// # Returns
+    /// The compressor name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compressor::Noop => "noop",
+            Compressor::Snappy => "snappy",
+            Compressor::Zlib => "zlib",
+            Compressor::Zstd => "zstd"
+        }
+    }
+
+    /// Determine if the compressor was enabled by its cargo feature.
+    ///
+    /// # Returns
+    /// True if the compressor can be used.
+    pub fn is_available(self) -> bool {
+        match self {
+            Compressor::Noop => true,
+            Compressor::Snappy => cfg!(feature = "snappy"),
+            Compressor::Zlib => cfg!(feature = "zlib"),
+            Compressor::Zstd => cfg!(feature = "zstd")
+        }
+    }
+
+    /// Compress bytes.
+    ///
+    /// # Parameters
+    /// - `bytes` - The bytes to compress.
+    ///
+    /// # Returns
+    /// The `Result` with the compressed bytes.
+    pub fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compressor::Noop => Ok(bytes.to_vec()),
+            Compressor::Snappy => snappy_compress(bytes),
+            Compressor::Zlib => zlib_compress(bytes),
+            Compressor::Zstd => zstd_compress(bytes)
+        }
+    }
+
+    /// Decompress bytes, checking that they decompress to the expected size.
+    ///
+    /// # Parameters
+    /// - `bytes` - The compressed bytes.
+    /// - `size` - The size of the bytes before compression.
+    ///
+    /// # Returns
+    /// The `Result` with the decompressed bytes, or an `InvalidData` error
+    /// if they are malformed or not of the expected size.
+    pub fn decompress(self, bytes: &[u8], size: usize) -> Result<Vec<u8>> {
+        let decompressed = match self {
+            Compressor::Noop => bytes.to_vec(),
+            Compressor::Snappy => snappy_decompress(bytes, size)?,
+            Compressor::Zlib => zlib_decompress(bytes, size)?,
+            Compressor::Zstd => zstd_decompress(bytes, size)?
+        };
+        if decompressed.len() != size {
+            return Err(Error::new(ErrorKind::InvalidData, format!(
+                "{} message decompressed to {} bytes, expected {}", self.name(), decompressed.len(), size
+            )));
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Choose the compressor to use with a server, the first in the
+/// `compression` array of its `hello` reply that the client offered and
+/// that is available.
+///
+/// # Parameters
+/// - `reply` - The `hello` reply `Document`.
+/// - `offered` - The compressors the client sent in its `hello` command.
+///
+/// # Returns
+/// The `Compressor`, or `None` if there is none in common.
+pub fn negotiate_compressor(reply: &Document, offered: &[Compressor]) -> Option<Compressor> {
+    match reply.get("compression") {
+        Some(Bson::Array(names)) => names.iter().filter_map(|name| match name {
+            Bson::String(name) => Compressor::from_name(name),
+            _ => None
+        }).find(|compressor| compressor.is_available() && offered.contains(compressor)),
+        _ => None
+    }
+}
+
+#[cfg(any(not(feature = "snappy"), not(feature = "zlib"), not(feature = "zstd")))]
+fn not_enabled(compressor: Compressor) -> Error {
+    Error::new(ErrorKind::Unsupported, format!("{} compression requires the {} feature", compressor.name(), compressor.name()))
+}
+
+#[cfg(feature = "snappy")]
+fn snappy_compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    ::snap::raw::Encoder::new().compress_vec(bytes).map_err(|error| Error::new(ErrorKind::InvalidInput, error.to_string()))
+}
+
+#[cfg(feature = "snappy")]
+fn snappy_decompress(bytes: &[u8], size: usize) -> Result<Vec<u8>> {
+    let length = ::snap::raw::decompress_len(bytes).map_err(|error| invalid_data(error.to_string()))?;
+    if length != size {
+        return Err(invalid_data(format!("snappy message decompresses to {} bytes, expected {}", length, size)));
+    }
+    ::snap::raw::Decoder::new().decompress_vec(bytes).map_err(|error| invalid_data(error.to_string()))
+}
+
+#[cfg(not(feature = "snappy"))]
+fn snappy_compress(_: &[u8]) -> Result<Vec<u8>> {
+    Err(not_enabled(Compressor::Snappy))
+}
+
+#[cfg(not(feature = "snappy"))]
+fn snappy_decompress(_: &[u8], _: usize) -> Result<Vec<u8>> {
+    Err(not_enabled(Compressor::Snappy))
+}
+
+#[cfg(feature = "zlib")]
+fn zlib_compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = ::flate2::write::ZlibEncoder::new(Vec::new(), ::flate2::Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+#[cfg(feature = "zlib")]
+fn zlib_decompress(bytes: &[u8], size: usize) -> Result<Vec<u8>> {
+    use std::io::Read;
+    let mut decompressed = Vec::with_capacity(size);
+    ::flate2::read::ZlibDecoder::new(bytes).take(size as u64 + 1).read_to_end(&mut decompressed)
+        .map_err(|error| invalid_data(error.to_string()))?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "zlib"))]
+fn zlib_compress(_: &[u8]) -> Result<Vec<u8>> {
+    Err(not_enabled(Compressor::Zlib))
+}
+
+#[cfg(not(feature = "zlib"))]
+fn zlib_decompress(_: &[u8], _: usize) -> Result<Vec<u8>> {
+    Err(not_enabled(Compressor::Zlib))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    ::zstd::bulk::compress(bytes, ::zstd::DEFAULT_COMPRESSION_LEVEL)
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(bytes: &[u8], size: usize) -> Result<Vec<u8>> {
+    ::zstd::bulk::decompress(bytes, size).map_err(|error| invalid_data(error.to_string()))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_compress(_: &[u8]) -> Result<Vec<u8>> {
+    Err(not_enabled(Compressor::Zstd))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_: &[u8], _: usize) -> Result<Vec<u8>> {
+    Err(not_enabled(Compressor::Zstd))
+}
@@ -0,0 +1,178 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Get the current time.
+///
+/// # Returns
+/// The milliseconds since the Unix epoch.
+pub fn now() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64 * 1_000 + i64::from(duration.subsec_millis()),
+        Err(_) => 0
+    }
+}
+
+/// Format milliseconds since the Unix epoch as an ISO-8601 UTC timestamp, for
+/// example `2017-02-08T14:30:00.000Z`.
+///
+/// # Parameters
+/// - `millis` - The milliseconds since the epoch.
+///
+/// # Returns
+/// The formatted `String`, or `None` if the year falls outside 0 to 9999.
+pub fn format_iso8601(millis: i64) -> Option<String> {
+    let days = millis.div_euclid(MILLIS_PER_DAY);
+    let time = millis.rem_euclid(MILLIS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3_600_000,
+        time / 60_000 % 60,
+        time / 1_000 % 60,
+        time % 1_000
+    ))
+}
+
+/// Parse an ISO-8601 timestamp into milliseconds since the Unix epoch. Accepts
+/// a date on its own (`2017-02-08`) or a date and time with optional
+/// fractional seconds and a `Z` or `+HH:MM` offset. A time without an offset
+/// is taken to be UTC.
+///
+/// # Parameters
+/// - `value` - The timestamp `&str`.
+///
+/// # Returns
+/// The milliseconds since the epoch, or `None` if the value is malformed.
+pub fn parse_iso8601(value: &str) -> Option<i64> {
+    let bytes = value.as_bytes();
+    let mut position = 0;
+    let year = read_digits(bytes, &mut position, 4)?;
+    expect_byte(bytes, &mut position, b'-')?;
+    let month = read_digits(bytes, &mut position, 2)?;
+    expect_byte(bytes, &mut position, b'-')?;
+    let day = read_digits(bytes, &mut position, 2)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut millis = days_from_civil(year, month, day) * MILLIS_PER_DAY;
+    if position == bytes.len() {
+        return Some(millis);
+    }
+    if bytes[position] != b'T' && bytes[position] != b' ' {
+        return None;
+    }
+    position += 1;
+    let hour = read_digits(bytes, &mut position, 2)?;
+    expect_byte(bytes, &mut position, b':')?;
+    let minute = read_digits(bytes, &mut position, 2)?;
+    let mut second = 0;
+    if bytes.get(position) == Some(&b':') {
+        position += 1;
+        second = read_digits(bytes, &mut position, 2)?;
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    millis += hour * 3_600_000 + minute * 60_000 + second * 1_000;
+    if bytes.get(position) == Some(&b'.') {
+        position += 1;
+        let start = position;
+        let mut fraction = 0;
+        while position < bytes.len() && bytes[position].is_ascii_digit() {
+            if position - start < 3 {
+                fraction = fraction * 10 + i64::from(bytes[position] - b'0');
+            }
+            position += 1;
+        }
+        if position == start {
+            return None;
+        }
+        for _ in (position - start)..3 {
+            fraction *= 10;
+        }
+        millis += fraction;
+    }
+    match bytes.get(position) {
+        None => Some(millis),
+        Some(&b'Z') if position + 1 == bytes.len() => Some(millis),
+        Some(&sign) if sign == b'+' || sign == b'-' => {
+            position += 1;
+            let offset_hours = read_digits(bytes, &mut position, 2)?;
+            if bytes.get(position) == Some(&b':') {
+                position += 1;
+            }
+            let offset_minutes = read_digits(bytes, &mut position, 2)?;
+            if position != bytes.len() {
+                return None;
+            }
+            let offset = offset_hours * 3_600_000 + offset_minutes * 60_000;
+            Some(if sign == b'+' { millis - offset } else { millis + offset })
+        },
+        _ => None
+    }
+}
+
+fn read_digits(bytes: &[u8], position: &mut usize, count: usize) -> Option<i64> {
+    let digits = bytes.get(*position..*position + count)?;
+    let mut value = 0;
+    for digit in digits {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        value = value * 10 + i64::from(digit - b'0');
+    }
+    *position += count;
+    Some(value)
+}
+
+fn expect_byte(bytes: &[u8], position: &mut usize, expected: u8) -> Option<()> {
+    if bytes.get(*position) == Some(&expected) {
+        *position += 1;
+        Some(())
+    } else {
+        None
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+// Converts a proleptic Gregorian date to days since the epoch.
+// See http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Converts days since the epoch to a proleptic Gregorian (year, month, day).
+// See http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
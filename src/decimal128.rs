@@ -0,0 +1,662 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::f64;
+use std::fmt;
+use bson::Bson;
+
+// The largest coefficient, thirty four nines.
+const MAX_COEFFICIENT: u128 = 9_999_999_999_999_999_999_999_999_999_999_999;
+const MAX_DIGITS: usize = 34;
+const EXPONENT_BIAS: i32 = 6176;
+const MIN_EXPONENT: i32 = -6176;
+const MAX_EXPONENT: i32 = 6111;
+
+// How far apart the exponents of two addends may be before the smaller one is
+// replaced by a single digit that only affects rounding.
+const SHIFT_LIMIT: i32 = 80;
+
+/// Parse a decimal string, such as `"1.5"`, `"-2E+10"`, `"Infinity"` or
+/// `"NaN"`, into the 16 bytes of a `Decimal128` value.
+///
+/// Up to 34 significant digits are kept exactly, along with the number of
+/// trailing zeros written, so `"1.50"` and `"1.5"` parse to different bytes
+/// that compare as equal.
+///
+/// # Parameters
+/// - `value` - The decimal string.
+///
+/// # Returns
+/// The bytes, or `None` if the string is not a decimal or cannot be
+/// represented without rounding.
+pub fn parse_decimal128(value: &str) -> Option<[u8; 16]> {
+    Decimal::parse(value).map(|decimal| decimal.to_bytes())
+}
+
+/// Format the 16 bytes of a `Decimal128` value as a decimal string, using
+/// scientific notation for very large or very small exponents as the server
+/// does.
+///
+/// # Parameters
+/// - `value` - The bytes of the `Decimal128`.
+///
+/// # Returns
+/// The decimal string.
+pub fn format_decimal128(value: &[u8; 16]) -> String {
+    Decimal::from_bytes(value).to_string()
+}
+
+/// A decoded `Decimal128` value: the sign, coefficient and exponent of a
+/// finite number, or one of the special values.
+#[derive(Clone, Copy, Debug, Hash, PartialEq)]
+pub enum Decimal {
+    Finite { negative: bool, coefficient: u128, exponent: i32 },
+    Infinity(bool),
+    NaN
+}
+
+/// Implementation for the `Decimal` object.
+impl Decimal {
+
+    /// Decode the 16 little-endian bytes of a `Decimal128` value. A
+    /// coefficient too large to be canonical decodes as zero.
+    ///
+    /// # Parameters
+    /// - `bytes` - The bytes of the `Decimal128`.
+    ///
+    /// # Returns
+    /// The `Decimal`.
+    pub fn from_bytes(bytes: &[u8; 16]) -> Decimal {
+        let bits = u128::from_le_bytes(*bytes);
+        let negative = bits >> 127 == 1;
+        match (bits >> 122) & 0x1F {
+            0x1F => return Decimal::NaN,
+            0x1E => return Decimal::Infinity(negative),
+            _ => {}
+        }
+        let (exponent, coefficient) = if (bits >> 125) & 0x3 == 0x3 {
+            ((bits >> 111) & 0x3FFF, 0)
+        } else {
+            ((bits >> 113) & 0x3FFF, bits & ((1 << 113) - 1))
+        };
+        Decimal::Finite {
+            negative,
+            coefficient: if coefficient > MAX_COEFFICIENT { 0 } else { coefficient },
+            exponent: exponent as i32 - EXPONENT_BIAS
+        }
+    }
+
+    /// Encode the value as the 16 little-endian bytes of a `Decimal128`.
+    ///
+    /// # Returns
+    /// The bytes.
+    pub fn to_bytes(self) -> [u8; 16] {
+        let bits = match self {
+            Decimal::Finite { negative, coefficient, exponent } => {
+                (negative as u128) << 127 | ((exponent + EXPONENT_BIAS) as u128) << 113 | coefficient
+            },
+            Decimal::Infinity(negative) => (negative as u128) << 127 | 0x1E << 122,
+            Decimal::NaN => 0x1F << 122
+        };
+        bits.to_le_bytes()
+    }
+
+    /// Convert a number to a `Decimal`. Integers convert exactly and doubles
+    /// are rounded to 34 significant digits, as the server does when
+    /// comparing them with decimals.
+    ///
+    /// # Parameters
+    /// - `value` - The `Bson` value.
+    ///
+    /// # Returns
+    /// The `Decimal`, or `None` if the value is not a number.
+    pub fn from_bson(value: &Bson) -> Option<Decimal> {
+        match *value {
+            Bson::Int32(number) => Some(Decimal::from_i64(i64::from(number))),
+            Bson::Int64(number) => Some(Decimal::from_i64(number)),
+            Bson::Double(number) => Some(Decimal::from_f64(number)),
+            Bson::Decimal128(ref bytes) => Some(Decimal::from_bytes(bytes)),
+            _ => None
+        }
+    }
+
+    /// Convert an integer to a `Decimal` exactly.
+    ///
+    /// # Parameters
+    /// - `value` - The integer.
+    ///
+    /// # Returns
+    /// The `Decimal`.
+    pub fn from_i64(value: i64) -> Decimal {
+        Decimal::Finite { negative: value < 0, coefficient: u128::from(value.unsigned_abs()), exponent: 0 }
+    }
+
+    /// Convert a number to a `Decimal` for arithmetic. Doubles are rounded to
+    /// 15 significant digits, as the server does when combining them with
+    /// decimals, so that adding `0.1` adds exactly one tenth.
+    ///
+    /// # Parameters
+    /// - `value` - The `Bson` value.
+    ///
+    /// # Returns
+    /// The `Decimal`, or `None` if the value is not a number.
+    pub fn from_number(value: &Bson) -> Option<Decimal> {
+        match *value {
+            Bson::Double(number) if number.is_finite() => Decimal::parse(&format!("{:.14e}", number)),
+            _ => Decimal::from_bson(value)
+        }
+    }
+
+    /// Convert a double to a `Decimal` rounded to 34 significant digits.
+    ///
+    /// # Parameters
+    /// - `value` - The double.
+    ///
+    /// # Returns
+    /// The `Decimal`.
+    pub fn from_f64(value: f64) -> Decimal {
+        if value.is_nan() {
+            Decimal::NaN
+        } else if value.is_infinite() {
+            Decimal::Infinity(value < 0.0)
+        } else {
+            Decimal::parse(&format!("{:.33e}", value)).expect("a formatted double")
+        }
+    }
+
+    /// Parse a decimal string. See `parse_decimal128`.
+    ///
+    /// # Parameters
+    /// - `value` - The decimal string.
+    ///
+    /// # Returns
+    /// The `Decimal`, or `None` if the string is invalid or inexact.
+    pub fn parse(value: &str) -> Option<Decimal> {
+        let (negative, unsigned) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value)
+        };
+        let lower = unsigned.to_ascii_lowercase();
+        if lower == "inf" || lower == "infinity" {
+            return Some(Decimal::Infinity(negative));
+        }
+        if lower == "nan" {
+            return Some(Decimal::NaN);
+        }
+        let (mantissa, exponent) = match lower.find('e') {
+            Some(index) => (&lower[..index], parse_exponent(&lower[index + 1..])?),
+            None => (&lower[..], 0)
+        };
+        let (whole, fraction) = match mantissa.find('.') {
+            Some(index) => (&mantissa[..index], &mantissa[index + 1..]),
+            None => (mantissa, "")
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        if !whole.bytes().chain(fraction.bytes()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let digits: Vec<u8> = whole.bytes().chain(fraction.bytes()).skip_while(|&c| c == b'0').collect();
+        let mut exponent = exponent - fraction.len() as i64;
+        let mut significant = digits.len();
+        while significant > MAX_DIGITS {
+            if digits[significant - 1] != b'0' {
+                return None;
+            }
+            significant -= 1;
+            exponent += 1;
+        }
+        let coefficient = digits[..significant].iter().fold(0u128, |total, c| total * 10 + u128::from(c - b'0'));
+        Decimal::clamp(negative, coefficient, exponent)
+    }
+
+    /// Bring the exponent into range without changing the value, by padding
+    /// or removing trailing zeros of the coefficient.
+    ///
+    /// # Returns
+    /// The `Decimal`, or `None` if the value cannot be represented exactly.
+    fn clamp(negative: bool, mut coefficient: u128, mut exponent: i64) -> Option<Decimal> {
+        if coefficient == 0 {
+            exponent = exponent.clamp(i64::from(MIN_EXPONENT), i64::from(MAX_EXPONENT));
+        }
+        while exponent > i64::from(MAX_EXPONENT) && coefficient * 10 <= MAX_COEFFICIENT {
+            coefficient *= 10;
+            exponent -= 1;
+        }
+        while exponent < i64::from(MIN_EXPONENT) && coefficient.is_multiple_of(10) {
+            coefficient /= 10;
+            exponent += 1;
+        }
+        if exponent > i64::from(MAX_EXPONENT) || exponent < i64::from(MIN_EXPONENT) {
+            return None;
+        }
+        Some(Decimal::Finite { negative, coefficient, exponent: exponent as i32 })
+    }
+
+    /// Convert the value to the nearest double.
+    ///
+    /// # Returns
+    /// The double.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Decimal::Finite { negative, coefficient, exponent } => {
+                let magnitude: f64 = format!("{}e{}", coefficient, exponent).parse().expect("a decimal exponent form");
+                if negative { -magnitude } else { magnitude }
+            },
+            Decimal::Infinity(negative) => if negative { f64::NEG_INFINITY } else { f64::INFINITY },
+            Decimal::NaN => f64::NAN
+        }
+    }
+
+    /// Convert the value to an integer if it is whole and fits in an `i64`.
+    ///
+    /// # Returns
+    /// The integer, or `None`.
+    pub fn to_i64(self) -> Option<i64> {
+        let (negative, coefficient, exponent) = match self {
+            Decimal::Finite { negative, coefficient, exponent } => (negative, coefficient, exponent),
+            _ => return None
+        };
+        let magnitude = if coefficient == 0 {
+            0
+        } else if exponent >= 0 {
+            coefficient.checked_mul(10u128.checked_pow(exponent as u32)?)?
+        } else {
+            let divisor = 10u128.checked_pow(exponent.unsigned_abs())?;
+            if !coefficient.is_multiple_of(divisor) {
+                return None;
+            }
+            coefficient / divisor
+        };
+        let magnitude = i128::try_from(magnitude).ok()?;
+        i64::try_from(if negative { -magnitude } else { magnitude }).ok()
+    }
+
+    /// Remove the fractional part of the value, rounding toward zero.
+    ///
+    /// # Returns
+    /// The whole `Decimal`.
+    pub fn truncate(self) -> Decimal {
+        match self {
+            Decimal::Finite { negative, coefficient, exponent } if exponent < 0 => {
+                let coefficient = 10u128.checked_pow(exponent.unsigned_abs()).map_or(0, |divisor| coefficient / divisor);
+                Decimal::Finite { negative, coefficient, exponent: 0 }
+            },
+            other => other
+        }
+    }
+
+    /// Get the canonical form of the value, with the trailing zeros of the
+    /// coefficient removed and zero made positive, so that numerically equal
+    /// values have the same form.
+    ///
+    /// # Returns
+    /// The canonical `Decimal`.
+    pub fn normalize(self) -> Decimal {
+        match self {
+            Decimal::Finite { coefficient: 0, .. } => Decimal::Finite { negative: false, coefficient: 0, exponent: 0 },
+            Decimal::Finite { negative, mut coefficient, mut exponent } => {
+                while coefficient.is_multiple_of(10) {
+                    coefficient /= 10;
+                    exponent += 1;
+                }
+                Decimal::Finite { negative, coefficient, exponent }
+            },
+            other => other
+        }
+    }
+
+    /// Add two values, rounding the sum to 34 significant digits with ties
+    /// to even.
+    ///
+    /// # Parameters
+    /// - `other` - The value to add.
+    ///
+    /// # Returns
+    /// The sum.
+    pub fn add(self, other: Decimal) -> Decimal {
+        let (left, right) = match (self, other) {
+            (Decimal::NaN, _) | (_, Decimal::NaN) => return Decimal::NaN,
+            (Decimal::Infinity(left), Decimal::Infinity(right)) if left != right => return Decimal::NaN,
+            (Decimal::Infinity(_), _) => return self,
+            (_, Decimal::Infinity(_)) => return other,
+            (Decimal::Finite { negative: ln, coefficient: lc, exponent: le },
+             Decimal::Finite { negative: rn, coefficient: rc, exponent: re }) => ((ln, lc, le), (rn, rc, re))
+        };
+        let ((high_negative, high, high_exponent), (low_negative, mut low, mut low_exponent)) = if left.2 >= right.2 {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        if high_exponent - low_exponent > SHIFT_LIMIT {
+            if high == 0 {
+                return Decimal::Finite { negative: low_negative, coefficient: low, exponent: low_exponent };
+            }
+            low = u128::from(low != 0);
+            low_exponent = high_exponent - SHIFT_LIMIT;
+        }
+        let mut high_digits = vec![0; (high_exponent - low_exponent) as usize];
+        high_digits.extend(to_digits(high));
+        let low_digits = to_digits(low);
+        let (negative, digits) = if high_negative == low_negative {
+            (high_negative, add_digits(&high_digits, &low_digits))
+        } else {
+            match compare_digits(&high_digits, &low_digits) {
+                Ordering::Greater => (high_negative, subtract_digits(&high_digits, &low_digits)),
+                Ordering::Less => (low_negative, subtract_digits(&low_digits, &high_digits)),
+                Ordering::Equal => (false, Vec::new())
+            }
+        };
+        round(negative, digits, i64::from(low_exponent), false)
+    }
+
+    /// Subtract a value, rounding the difference to 34 significant digits
+    /// with ties to even.
+    ///
+    /// # Parameters
+    /// - `other` - The value to subtract.
+    ///
+    /// # Returns
+    /// The difference.
+    pub fn subtract(self, other: Decimal) -> Decimal {
+        self.add(match other {
+            Decimal::Finite { negative, coefficient, exponent } => Decimal::Finite { negative: !negative, coefficient, exponent },
+            Decimal::Infinity(negative) => Decimal::Infinity(!negative),
+            Decimal::NaN => Decimal::NaN
+        })
+    }
+
+    /// Multiply two values, rounding the product to 34 significant digits
+    /// with ties to even.
+    ///
+    /// # Parameters
+    /// - `other` - The value to multiply by.
+    ///
+    /// # Returns
+    /// The product.
+    pub fn multiply(self, other: Decimal) -> Decimal {
+        match (self, other) {
+            (Decimal::NaN, _) | (_, Decimal::NaN) => Decimal::NaN,
+            (Decimal::Infinity(left), Decimal::Infinity(right)) => Decimal::Infinity(left != right),
+            (Decimal::Infinity(infinite), Decimal::Finite { negative, coefficient, .. }) |
+            (Decimal::Finite { negative, coefficient, .. }, Decimal::Infinity(infinite)) => {
+                if coefficient == 0 { Decimal::NaN } else { Decimal::Infinity(infinite != negative) }
+            },
+            (Decimal::Finite { negative: ln, coefficient: lc, exponent: le },
+             Decimal::Finite { negative: rn, coefficient: rc, exponent: re }) => {
+                let digits = multiply_digits(&to_digits(lc), &to_digits(rc));
+                round(ln != rn, digits, i64::from(le) + i64::from(re), false)
+            }
+        }
+    }
+
+    /// Divide by a value, rounding the quotient to 34 significant digits
+    /// with ties to even. An exact quotient keeps no more trailing zeros than
+    /// the difference of the exponents requires, so `6 / 2` is `3`.
+    ///
+    /// # Parameters
+    /// - `other` - The divisor.
+    ///
+    /// # Returns
+    /// The quotient, infinite when dividing a non-zero value by zero.
+    pub fn divide(self, other: Decimal) -> Decimal {
+        let ((ln, lc, le), (rn, rc, re)) = match (self, other) {
+            (Decimal::NaN, _) | (_, Decimal::NaN) | (Decimal::Infinity(_), Decimal::Infinity(_)) => return Decimal::NaN,
+            (Decimal::Infinity(infinite), Decimal::Finite { negative, .. }) => return Decimal::Infinity(infinite != negative),
+            (Decimal::Finite { negative, .. }, Decimal::Infinity(infinite)) => {
+                return Decimal::Finite { negative: negative != infinite, coefficient: 0, exponent: 0 };
+            },
+            (Decimal::Finite { negative: ln, coefficient: lc, exponent: le },
+             Decimal::Finite { negative: rn, coefficient: rc, exponent: re }) => ((ln, lc, le), (rn, rc, re))
+        };
+        if rc == 0 {
+            return if lc == 0 { Decimal::NaN } else { Decimal::Infinity(ln != rn) };
+        }
+        let ideal = i64::from(le) - i64::from(re);
+        let scale = (MAX_DIGITS as i32 + 1 + digit_count(rc) - digit_count(lc)).max(0);
+        let mut dividend = vec![0; scale as usize];
+        dividend.extend(to_digits(lc));
+        let mut quotient = vec![0; dividend.len()];
+        let mut remainder = 0u128;
+        for (index, &digit) in dividend.iter().enumerate().rev() {
+            remainder = remainder * 10 + u128::from(digit);
+            quotient[index] = (remainder / rc) as u8;
+            remainder %= rc;
+        }
+        let mut exponent = ideal - i64::from(scale);
+        if remainder == 0 {
+            while exponent < ideal && quotient.first() == Some(&0) {
+                quotient.remove(0);
+                exponent += 1;
+            }
+        }
+        round(ln != rn, quotient, exponent, remainder != 0)
+    }
+
+    /// Get the remainder of dividing by a value, truncating the quotient
+    /// toward zero. The remainder has the sign of the dividend and is exact.
+    ///
+    /// # Parameters
+    /// - `other` - The divisor.
+    ///
+    /// # Returns
+    /// The remainder, `NaN` when dividing by zero or dividing infinity.
+    pub fn remainder(self, other: Decimal) -> Decimal {
+        let ((ln, lc, le), (rc, re)) = match (self, other) {
+            (Decimal::Finite { .. }, Decimal::Infinity(_)) => return self,
+            (Decimal::Finite { negative: ln, coefficient: lc, exponent: le },
+             Decimal::Finite { coefficient: rc, exponent: re, .. }) if rc != 0 => ((ln, lc, le), (rc, re)),
+            _ => return Decimal::NaN
+        };
+        let exponent = le.min(re);
+        let mut dividend = vec![0; (le - exponent) as usize];
+        dividend.extend(to_digits(lc));
+        let mut divisor = vec![0; (re - exponent) as usize];
+        divisor.extend(to_digits(rc));
+        let mut remainder: Vec<u8> = Vec::new();
+        for &digit in dividend.iter().rev() {
+            remainder.insert(0, digit);
+            while compare_digits(&remainder, &divisor) != Ordering::Less {
+                remainder = subtract_digits(&remainder, &divisor);
+            }
+            remainder.truncate(significant(&remainder).len());
+        }
+        round(ln, remainder, i64::from(exponent), false)
+    }
+
+    /// Compare two values numerically. `NaN` sorts before every other value
+    /// and equals itself, and zeros are equal regardless of sign or exponent.
+    ///
+    /// # Parameters
+    /// - `other` - The value to compare with.
+    ///
+    /// # Returns
+    /// The `Ordering` of the two values.
+    pub fn compare(&self, other: &Decimal) -> Ordering {
+        match (*self, *other) {
+            (Decimal::NaN, Decimal::NaN) => Ordering::Equal,
+            (Decimal::NaN, _) => Ordering::Less,
+            (_, Decimal::NaN) => Ordering::Greater,
+            (Decimal::Infinity(left), Decimal::Infinity(right)) => right.cmp(&left),
+            (Decimal::Infinity(negative), _) => if negative { Ordering::Less } else { Ordering::Greater },
+            (_, Decimal::Infinity(negative)) => if negative { Ordering::Greater } else { Ordering::Less },
+            (Decimal::Finite { negative: ln, coefficient: lc, exponent: le },
+             Decimal::Finite { negative: rn, coefficient: rc, exponent: re }) => {
+                let (ln, rn) = (ln && lc != 0, rn && rc != 0);
+                if ln != rn {
+                    return if ln { Ordering::Less } else { Ordering::Greater };
+                }
+                let magnitude = match (lc, rc) {
+                    (0, 0) => Ordering::Equal,
+                    (0, _) => Ordering::Less,
+                    (_, 0) => Ordering::Greater,
+                    _ => magnitude_cmp(lc, le, rc, re)
+                };
+                if ln { magnitude.reverse() } else { magnitude }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (negative, coefficient, exponent) = match *self {
+            Decimal::Finite { negative, coefficient, exponent } => (negative, coefficient, exponent),
+            Decimal::Infinity(negative) => return f.write_str(if negative { "-Infinity" } else { "Infinity" }),
+            Decimal::NaN => return f.write_str("NaN")
+        };
+        if negative {
+            f.write_str("-")?;
+        }
+        let digits = coefficient.to_string();
+        let adjusted = exponent + digits.len() as i32 - 1;
+        if exponent <= 0 && adjusted >= -6 {
+            let point = digits.len() as i32 + exponent;
+            if exponent == 0 {
+                f.write_str(&digits)
+            } else if point > 0 {
+                write!(f, "{}.{}", &digits[..point as usize], &digits[point as usize..])
+            } else {
+                write!(f, "0.{}{}", "0".repeat(-point as usize), digits)
+            }
+        } else {
+            f.write_str(&digits[..1])?;
+            if digits.len() > 1 {
+                write!(f, ".{}", &digits[1..])?;
+            }
+            write!(f, "E{}{}", if adjusted < 0 { "-" } else { "+" }, adjusted.abs())
+        }
+    }
+}
+
+// Parses an exponent, saturating far beyond the representable range so
+// that huge exponents still fail to clamp rather than overflow.
+fn parse_exponent(value: &str) -> Option<i64> {
+    let (negative, digits) = match value.as_bytes().first() {
+        Some(b'-') => (true, &value[1..]),
+        Some(b'+') => (false, &value[1..]),
+        _ => (false, value)
+    };
+    if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let magnitude = digits.bytes().fold(0i64, |total, c| (total * 10 + i64::from(c - b'0')).min(1 << 32));
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+// Rounds a sign, little-endian digits and exponent to at most 34 significant
+// digits with ties to even, as if `sticky` were a non-zero digit below all of
+// the given ones, then brings the exponent into range, overflowing to
+// infinity and underflowing toward zero.
+fn round(negative: bool, mut digits: Vec<u8>, mut exponent: i64, sticky: bool) -> Decimal {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    let cut = (digits.len() as i64 - MAX_DIGITS as i64).max(i64::from(MIN_EXPONENT) - exponent);
+    let mut round_up = false;
+    if cut > 0 {
+        let cut = cut as usize;
+        if digits.len() < cut {
+            digits.resize(cut, 0);
+        }
+        let dropped: Vec<u8> = digits.drain(..cut).collect();
+        let rest = sticky || dropped[..cut - 1].iter().any(|&digit| digit != 0);
+        let odd = digits.first().is_some_and(|&digit| digit % 2 == 1);
+        round_up = dropped[cut - 1] > 5 || (dropped[cut - 1] == 5 && (rest || odd));
+        exponent += cut as i64;
+    }
+    let mut coefficient = digits.iter().rev().fold(0u128, |total, &digit| total * 10 + u128::from(digit));
+    if round_up {
+        coefficient += 1;
+        if coefficient > MAX_COEFFICIENT {
+            coefficient /= 10;
+            exponent += 1;
+        }
+    }
+    Decimal::clamp(negative, coefficient, exponent).unwrap_or(Decimal::Infinity(negative))
+}
+
+// Splits a coefficient into its decimal digits, least significant first.
+fn to_digits(mut value: u128) -> Vec<u8> {
+    let mut digits = Vec::new();
+    while value > 0 {
+        digits.push((value % 10) as u8);
+        value /= 10;
+    }
+    digits
+}
+
+fn significant(digits: &[u8]) -> &[u8] {
+    let length = digits.iter().rposition(|&digit| digit != 0).map_or(0, |index| index + 1);
+    &digits[..length]
+}
+
+fn compare_digits(left: &[u8], right: &[u8]) -> Ordering {
+    let (left, right) = (significant(left), significant(right));
+    left.len().cmp(&right.len()).then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0;
+    for index in 0..left.len().max(right.len()) {
+        let sum = left.get(index).unwrap_or(&0) + right.get(index).unwrap_or(&0) + carry;
+        result.push(sum % 10);
+        carry = sum / 10;
+    }
+    if carry > 0 {
+        result.push(carry);
+    }
+    result
+}
+
+// Subtracts the digits of a smaller or equal number.
+fn subtract_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(left.len());
+    let mut borrow = 0;
+    for (index, &digit) in left.iter().enumerate() {
+        let subtrahend = right.get(index).unwrap_or(&0) + borrow;
+        if digit >= subtrahend {
+            result.push(digit - subtrahend);
+            borrow = 0;
+        } else {
+            result.push(digit + 10 - subtrahend);
+            borrow = 1;
+        }
+    }
+    result
+}
+
+fn multiply_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u32; left.len() + right.len()];
+    for (i, &l) in left.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &r) in right.iter().enumerate() {
+            let total = result[i + j] + u32::from(l) * u32::from(r) + carry;
+            result[i + j] = total % 10;
+            carry = total / 10;
+        }
+        result[i + right.len()] += carry;
+    }
+    result.into_iter().map(|digit| digit as u8).collect()
+}
+
+fn digit_count(coefficient: u128) -> i32 {
+    coefficient.checked_ilog10().map_or(1, |log| log as i32 + 1)
+}
+
+// Compares the magnitudes of two non-zero values by their adjusted exponents,
+// then by their coefficients scaled to the same number of digits.
+fn magnitude_cmp(left: u128, left_exponent: i32, right: u128, right_exponent: i32) -> Ordering {
+    let (left_digits, right_digits) = (digit_count(left), digit_count(right));
+    let adjusted = (left_exponent + left_digits).cmp(&(right_exponent + right_digits));
+    if adjusted != Ordering::Equal {
+        return adjusted;
+    }
+    if left_digits < right_digits {
+        (left * 10u128.pow((right_digits - left_digits) as u32)).cmp(&right)
+    } else {
+        left.cmp(&(right * 10u128.pow((left_digits - right_digits) as u32)))
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt::{self, Display, Formatter, Write};
+use base64;
+use bson::Bson;
+use datetime;
+use decimal128::format_decimal128;
+use document::Document;
+
+/// Displays the value using MongoDB shell syntax, for example
+/// `{ "a": NumberLong(42), "d": ISODate("2017-02-08T14:30:00.000Z") }`. The
+/// alternate flag (`{:#}`) prints over multiple lines with two space
+/// indentation.
+impl Display for Bson {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let indent = if f.alternate() { Some(2) } else { None };
+        write_bson(f, self, indent, 0)
+    }
+}
+
+/// Displays the document using MongoDB shell syntax. The alternate flag
+/// (`{:#}`) prints over multiple lines with two space indentation.
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let indent = if f.alternate() { Some(2) } else { None };
+        write_document(f, self, indent, 0)
+    }
+}
+
+/// The implementation for pretty printing `Bson` values.
+impl Bson {
+
+    /// Format the value in MongoDB shell syntax over multiple lines.
+    ///
+    /// # Parameters
+    /// - `indent` - The number of spaces to indent each nesting level.
+    ///
+    /// # Returns
+    /// The formatted `String`.
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        let mut output = String::new();
+        write_bson(&mut output, self, Some(indent), 0).unwrap();
+        output
+    }
+}
+
+/// The implementation for pretty printing `Document`s.
+impl Document {
+
+    /// Format the document in MongoDB shell syntax over multiple lines.
+    ///
+    /// # Parameters
+    /// - `indent` - The number of spaces to indent each nesting level.
+    ///
+    /// # Returns
+    /// The formatted `String`.
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        let mut output = String::new();
+        write_document(&mut output, self, Some(indent), 0).unwrap();
+        output
+    }
+}
+
+fn write_bson<W: Write>(w: &mut W, value: &Bson, indent: Option<usize>, depth: usize) -> fmt::Result {
+    match value {
+        Bson::Double(v) => write_double(w, *v),
+        Bson::String(v) => write_string(w, v),
+        Bson::Document(v) => write_document(w, v, indent, depth),
+        Bson::Array(v) => write_array(w, v, indent, depth),
+        Bson::Binary(4, v) if v.len() == 16 => {
+            write!(w, "UUID(\"")?;
+            for (i, byte) in v.iter().enumerate() {
+                if i == 4 || i == 6 || i == 8 || i == 10 {
+                    w.write_char('-')?;
+                }
+                write!(w, "{:02x}", byte)?;
+            }
+            write!(w, "\")")
+        },
+        Bson::Binary(t, v) => write!(w, "BinData({}, \"{}\")", t, base64::encode(v)),
+        Bson::Undefined => write!(w, "undefined"),
+        Bson::ObjectId(id) => {
+            write!(w, "ObjectId(\"")?;
+            write_hex(w, id)?;
+            write!(w, "\")")
+        },
+        Bson::Boolean(v) => write!(w, "{}", v),
+        Bson::DateTime(v) => match datetime::format_iso8601(*v) {
+            Some(date) => write!(w, "ISODate(\"{}\")", date),
+            None => write!(w, "new Date({})", v)
+        },
+        Bson::Null => write!(w, "null"),
+        Bson::RegExp(pattern, options) => {
+            if pattern.is_empty() || has_unescaped_slash(pattern) || pattern.contains('\n') {
+                write!(w, "RegExp(")?;
+                write_string(w, pattern)?;
+                write!(w, ", ")?;
+                write_string(w, options)?;
+                write!(w, ")")
+            } else {
+                write!(w, "/{}/{}", pattern, options)
+            }
+        },
+        Bson::DbPointer(name, id) => {
+            write!(w, "DBPointer(")?;
+            write_string(w, name)?;
+            write!(w, ", ObjectId(\"")?;
+            write_hex(w, id)?;
+            write!(w, "\"))")
+        },
+        Bson::Code(code, scope) => {
+            write!(w, "Code(")?;
+            write_string(w, code)?;
+            if !scope.is_empty() {
+                write!(w, ", ")?;
+                write_document(w, scope, indent, depth)?;
+            }
+            write!(w, ")")
+        },
+        Bson::Symbol(v) => {
+            write!(w, "Symbol(")?;
+            write_string(w, v)?;
+            write!(w, ")")
+        },
+        Bson::Int32(v) => write!(w, "{}", v),
+        Bson::Timestamp(v) => write!(w, "Timestamp({}, {})", v >> 32, v & 0xFFFF_FFFF),
+        Bson::Int64(v) => write!(w, "NumberLong({})", v),
+        Bson::Decimal128(ref v) => write!(w, "NumberDecimal(\"{}\")", format_decimal128(v)),
+        Bson::MinKey => write!(w, "MinKey()"),
+        Bson::MaxKey => write!(w, "MaxKey()")
+    }
+}
+
+fn write_document<W: Write>(w: &mut W, document: &Document, indent: Option<usize>, depth: usize) -> fmt::Result {
+    if document.is_empty() {
+        return write!(w, "{{}}");
+    }
+    write!(w, "{{")?;
+    for (i, (key, value)) in document.iter().enumerate() {
+        if i > 0 {
+            w.write_char(',')?;
+        }
+        write_separator(w, indent, depth + 1)?;
+        write_string(w, key)?;
+        write!(w, ": ")?;
+        write_bson(w, value, indent, depth + 1)?;
+    }
+    write_separator(w, indent, depth)?;
+    write!(w, "}}")
+}
+
+fn write_array<W: Write>(w: &mut W, values: &[Bson], indent: Option<usize>, depth: usize) -> fmt::Result {
+    if values.is_empty() {
+        return write!(w, "[]");
+    }
+    write!(w, "[")?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            w.write_char(',')?;
+        }
+        write_separator(w, indent, depth + 1)?;
+        write_bson(w, value, indent, depth + 1)?;
+    }
+    write_separator(w, indent, depth)?;
+    write!(w, "]")
+}
+
+// Writes the whitespace between elements: a single space when printing on one
+// line, otherwise a newline followed by the indentation for the depth.
+fn write_separator<W: Write>(w: &mut W, indent: Option<usize>, depth: usize) -> fmt::Result {
+    match indent {
+        None => w.write_char(' '),
+        Some(width) => {
+            w.write_char('\n')?;
+            for _ in 0..(width * depth) {
+                w.write_char(' ')?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_double<W: Write>(w: &mut W, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        write!(w, "NaN")
+    } else if value.is_infinite() {
+        write!(w, "{}", if value > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        write!(w, "{:?}", value)
+    }
+}
+
+fn write_string<W: Write>(w: &mut W, value: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            '\u{08}' => w.write_str("\\b")?,
+            '\u{0C}' => w.write_str("\\f")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?
+        }
+    }
+    w.write_char('"')
+}
+
+fn write_hex<W: Write>(w: &mut W, bytes: &[u8]) -> fmt::Result {
+    for byte in bytes {
+        write!(w, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+fn has_unescaped_slash(pattern: &str) -> bool {
+    let mut escaped = false;
+    for c in pattern.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '/' if !escaped => return true,
+            _ => escaped = false
+        }
+    }
+    false
+}
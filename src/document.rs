@@ -1,4 +1,5 @@
-use linked_hash_map::LinkedHashMap;
+use std::collections::{BTreeMap, HashMap};
+use linked_hash_map::{IntoIter, Iter, LinkedHashMap};
 use bson::Bson;
 
 /// Represents a BSON document.
@@ -31,6 +32,29 @@ impl Document {
         return self.elements.get(key);
     }
 
+    /// Get a mutable reference to a value in the document for the provided
+    /// key.
+    ///
+    /// # Parameters
+    /// - `key` - The `&str` key.
+    ///
+    /// # Returns
+    /// The `Option` with the mutable `Bson` value.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Bson> {
+        self.elements.get_mut(key)
+    }
+
+    /// Determine if the document has an element with the provided key.
+    ///
+    /// # Parameters
+    /// - `key` - The `&str` key.
+    ///
+    /// # Returns
+    /// True if the key is present.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.elements.contains_key(key)
+    }
+
     /// Insert an element into the `Document`.
     ///
     /// # Parameters
@@ -42,6 +66,92 @@ impl Document {
     pub fn insert(&mut self, key: String, value: Bson) -> Option<Bson> {
         return self.elements.insert(key, value);
     }
+
+    /// Remove an element from the `Document`, keeping the order of the others.
+    ///
+    /// # Parameters
+    /// - `key` - The `&str` key.
+    ///
+    /// # Returns
+    /// The removed `Bson` value in an `Option`.
+    pub fn remove(&mut self, key: &str) -> Option<Bson> {
+        self.elements.remove(key)
+    }
+
+    /// Get an iterator over the elements in the document, in insertion order.
+    ///
+    /// # Returns
+    /// The `Iter` over the key and value pairs.
+    pub fn iter(&self) -> Iter<'_, String, Bson> {
+        self.elements.iter()
+    }
+
+    /// Get the number of elements in the document.
+    ///
+    /// # Returns
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Determine if the document has no elements.
+    ///
+    /// # Returns
+    /// True if the document is empty.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+/// The implementation for consuming a `Document` as an iterator over its
+/// elements, in insertion order.
+impl IntoIterator for Document {
+    type Item = (String, Bson);
+    type IntoIter = IntoIter<String, Bson>;
+
+    fn into_iter(self) -> IntoIter<String, Bson> {
+        self.elements.into_iter()
+    }
+}
+
+/// The from implementation for converting a `HashMap` to a `Document`. Since a
+/// `HashMap` has no ordering the keys are inserted in an unspecified order.
+impl<T> From<HashMap<String, T>> for Document where T: Into<Bson> {
+
+    /// Convert from a `HashMap` to a `Document`.
+    ///
+    /// # Parameters
+    /// - `map` - The `HashMap` to convert from.
+    ///
+    /// # Returns
+    /// The `Document`.
+    fn from(map: HashMap<String, T>) -> Document {
+        let mut document = Document::new();
+        for (key, value) in map {
+            document.insert(key, value.into());
+        }
+        document
+    }
+}
+
+/// The from implementation for converting a `BTreeMap` to a `Document`. The
+/// keys are inserted in sorted order.
+impl<T> From<BTreeMap<String, T>> for Document where T: Into<Bson> {
+
+    /// Convert from a `BTreeMap` to a `Document`.
+    ///
+    /// # Parameters
+    /// - `map` - The `BTreeMap` to convert from.
+    ///
+    /// # Returns
+    /// The `Document`.
+    fn from(map: BTreeMap<String, T>) -> Document {
+        let mut document = Document::new();
+        for (key, value) in map {
+            document.insert(key, value.into());
+        }
+        document
+    }
 }
 
 /// Provides a convenient way for creating documents.
@@ -0,0 +1,283 @@
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{Error, ErrorKind, Read, Result};
+use std::str;
+use bson::Bson;
+use document::Document;
+
+/// The smallest possible BSON document: a length and a terminator.
+pub const MIN_DOCUMENT_SIZE: usize = 5;
+
+/// The deepest nesting of documents and arrays that will be parsed, the
+/// same limit as the server's.
+pub const MAX_NESTING_DEPTH: usize = 200;
+
+/// The `DocumentDeserializer` object that can deserialize documents.
+pub struct DocumentDeserializer<'a, R: ?Sized> where R: Read + 'a {
+    reader: &'a mut R,
+}
+
+/// Implementation for the `DocumentDeserializer` object.
+impl<'a, R> DocumentDeserializer<'a, R> where R: Read + 'a {
+
+    /// Create the new `DocumentDeserializer` object.
+    ///
+    /// # Parameters
+    /// - `reader` - The reader to use.
+    ///
+    /// # Returns
+    /// The new `DocumentDeserializer` object.
+    pub fn new(reader: &'a mut R) -> DocumentDeserializer<'a, R> {
+        DocumentDeserializer { reader }
+    }
+
+    /// Deserialize a single document from raw BSON, reading exactly the
+    /// number of bytes given by its length prefix. The bytes are read as
+    /// they arrive rather than allocated up front, so a bogus prefix cannot
+    /// allocate more than the reader holds.
+    ///
+    /// # Returns
+    /// The `Result` with the `Document`.
+    pub fn deserialize(&mut self) -> Result<Document> {
+        let length = self.reader.read_i32::<LittleEndian>()?;
+        let length = document_length(length)?;
+        let mut bytes = (length as i32).to_le_bytes().to_vec();
+        (&mut *self.reader).take(length as u64 - 4).read_to_end(&mut bytes)?;
+        if bytes.len() != length {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "unexpected end of document"));
+        }
+        parse_document(&bytes)
+    }
+}
+
+/// Validate a document length prefix.
+///
+/// # Parameters
+/// - `length` - The length read from the prefix.
+///
+/// # Returns
+/// The length as a `usize`, or an `InvalidData` error if it is too small.
+pub fn document_length(length: i32) -> Result<usize> {
+    if length < MIN_DOCUMENT_SIZE as i32 {
+        return Err(invalid_data(format!("invalid document length {}", length)));
+    }
+    Ok(length as usize)
+}
+
+/// Parse a complete document from a byte slice. The slice must contain
+/// exactly one document, nested no deeper than `MAX_NESTING_DEPTH`.
+///
+/// # Parameters
+/// - `bytes` - The raw BSON bytes.
+///
+/// # Returns
+/// The `Result` with the `Document`.
+pub fn parse_document(bytes: &[u8]) -> Result<Document> {
+    let mut parser = Parser { bytes, position: 0, depth: 0 };
+    let document = parser.document()?;
+    if parser.position != bytes.len() {
+        return Err(invalid_data("trailing bytes after document".to_string()));
+    }
+    Ok(document)
+}
+
+/// Parse a single element value of the given type from a byte slice. The
+/// slice must contain exactly the value.
+///
+/// # Parameters
+/// - `element_type` - The element type byte.
+/// - `bytes` - The raw value bytes.
+///
+/// # Returns
+/// The `Result` with the `Bson` value.
+pub fn parse_value(element_type: u8, bytes: &[u8]) -> Result<Bson> {
+    let mut parser = Parser { bytes, position: 0, depth: 0 };
+    let value = parser.value(element_type)?;
+    if parser.position != bytes.len() {
+        return Err(invalid_data(format!("value of type 0x{:02X} has trailing bytes", element_type)));
+    }
+    Ok(value)
+}
+
+/// Create an `InvalidData` error with the message.
+///
+/// # Parameters
+/// - `message` - The error message.
+///
+/// # Returns
+/// The `Error`.
+pub fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+
+    fn document(&mut self) -> Result<Document> {
+        let mut document = Document::new();
+        self.elements(|key, value| {
+            document.insert(key, value);
+        })?;
+        Ok(document)
+    }
+
+    fn array(&mut self) -> Result<Vec<Bson>> {
+        let mut values = Vec::new();
+        self.elements(|_, value| values.push(value))?;
+        Ok(values)
+    }
+
+    fn elements<F>(&mut self, f: F) -> Result<()> where F: FnMut(String, Bson) {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(invalid_data(format!("document nesting exceeds the maximum depth of {}", MAX_NESTING_DEPTH)));
+        }
+        self.depth += 1;
+        let result = self.nested_elements(f);
+        self.depth -= 1;
+        result
+    }
+
+    fn nested_elements<F>(&mut self, mut f: F) -> Result<()> where F: FnMut(String, Bson) {
+        let start = self.position;
+        let length = document_length(self.i32()?)?;
+        let end = start.checked_add(length).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid_data(format!("document length {} exceeds available bytes", length)))?;
+        loop {
+            let element_type = self.u8()?;
+            if element_type == 0 {
+                break;
+            }
+            let key = self.cstring()?;
+            let value = self.value(element_type)?;
+            f(key, value);
+            if self.position >= end {
+                return Err(invalid_data("document is missing its terminator".to_string()));
+            }
+        }
+        if self.position != end {
+            return Err(invalid_data(format!("document length {} does not match its contents", length)));
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, element_type: u8) -> Result<Bson> {
+        Ok(match element_type {
+            0x01 => Bson::Double(f64::from_bits(self.u64()?)),
+            0x02 => Bson::String(self.string()?),
+            0x03 => Bson::Document(self.document()?),
+            0x04 => Bson::Array(self.array()?),
+            0x05 => {
+                let length = self.i32()?;
+                if length < 0 {
+                    return Err(invalid_data(format!("invalid binary length {}", length)));
+                }
+                let subtype = self.u8()?;
+                Bson::Binary(subtype, self.take(length as usize)?.to_vec())
+            },
+            0x06 => Bson::Undefined,
+            0x07 => Bson::ObjectId(self.object_id()?),
+            0x08 => match self.u8()? {
+                0 => Bson::Boolean(false),
+                1 => Bson::Boolean(true),
+                other => return Err(invalid_data(format!("invalid boolean value {}", other)))
+            },
+            0x09 => Bson::DateTime(self.u64()? as i64),
+            0x0A => Bson::Null,
+            0x0B => {
+                let pattern = self.cstring()?;
+                Bson::RegExp(pattern, self.cstring()?)
+            },
+            0x0C => {
+                let name = self.string()?;
+                Bson::DbPointer(name, self.object_id()?)
+            },
+            0x0D => Bson::Code(self.string()?, Document::new()),
+            0x0E => Bson::Symbol(self.string()?),
+            0x0F => {
+                let start = self.position;
+                let length = self.i32()?;
+                let code = self.string()?;
+                let scope = self.document()?;
+                if length < 0 || self.position - start != length as usize {
+                    return Err(invalid_data(format!("invalid code with scope length {}", length)));
+                }
+                Bson::Code(code, scope)
+            },
+            0x10 => Bson::Int32(self.i32()?),
+            0x11 => Bson::Timestamp(self.u64()?),
+            0x12 => Bson::Int64(self.u64()? as i64),
+            0x13 => {
+                let mut value = [0u8; 16];
+                value.copy_from_slice(self.take(16)?);
+                Bson::Decimal128(value)
+            },
+            0x7F => Bson::MaxKey,
+            0xFF => Bson::MinKey,
+            other => return Err(invalid_data(format!("invalid element type 0x{:02X}", other)))
+        })
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        match self.position.checked_add(count) {
+            Some(end) if end <= self.bytes.len() => {
+                let slice = &self.bytes[self.position..end];
+                self.position = end;
+                Ok(slice)
+            },
+            _ => Err(Error::new(ErrorKind::UnexpectedEof, "unexpected end of document"))
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        self.take(4)?.read_i32::<LittleEndian>()
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.take(8)?.read_u64::<LittleEndian>()
+    }
+
+    fn object_id(&mut self) -> Result<[u8; 12]> {
+        let mut id = [0u8; 12];
+        id.copy_from_slice(self.take(12)?);
+        Ok(id)
+    }
+
+    fn cstring(&mut self) -> Result<String> {
+        let rest = &self.bytes[self.position..];
+        match rest.iter().position(|b| *b == 0) {
+            Some(length) => {
+                let value = utf8(&rest[..length])?;
+                self.position += length + 1;
+                Ok(value)
+            },
+            None => Err(invalid_data("unterminated cstring".to_string()))
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let length = self.i32()?;
+        if length < 1 {
+            return Err(invalid_data(format!("invalid string length {}", length)));
+        }
+        let bytes = self.take(length as usize)?;
+        if bytes[bytes.len() - 1] != 0 {
+            return Err(invalid_data("string is missing its terminator".to_string()));
+        }
+        utf8(&bytes[..bytes.len() - 1])
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<String> {
+    match str::from_utf8(bytes) {
+        Ok(value) => Ok(value.to_string()),
+        Err(error) => Err(invalid_data(format!("invalid utf-8: {}", error)))
+    }
+}
@@ -0,0 +1,380 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Error, ErrorKind, Read, Result};
+use std::ops::Range;
+use document::Document;
+use document_deserializer::{invalid_data, parse_document, MIN_DOCUMENT_SIZE};
+use raw_document::RawDocumentBuf;
+
+/// The largest document size accepted while recovering when no maximum has
+/// been set, matching the server's 16MiB limit.
+pub const DEFAULT_RECOVERY_MAX_DOCUMENT_SIZE: usize = 16 * 1024 * 1024;
+
+// How far a corruption scan may run ahead of the last confirmed skipped byte
+// before the bytes in between are dropped from the buffer.
+const MAX_SCAN_WINDOW: usize = 64 * 1024;
+
+/// The `DocumentReader` object that reads a stream of concatenated BSON
+/// documents, such as a `.bson` file written by mongodump, yielding each
+/// document in turn.
+///
+/// A single buffer is reused for the bytes of every document. Reading stops
+/// at the end of the stream, or after an error that leaves the position of
+/// the next document unknown, such as an invalid length prefix or a
+/// truncated document. A document that is framed correctly but has malformed
+/// contents is returned as an error and reading continues with the next one.
+///
+/// With `recover` enabled, malformed data is skipped instead of returned as
+/// an error: the reader scans forward byte by byte for the next offset that
+/// holds a plausible document, one with a valid length, a terminator and
+/// contents that parse, and continues from there. Each skipped range is
+/// recorded and available from `skipped_ranges`. Errors from the underlying
+/// reader are still returned.
+pub struct DocumentReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    start: usize,
+    position: u64,
+    document_offset: u64,
+    max_document_size: Option<usize>,
+    recover: bool,
+    skipped: Vec<Range<u64>>,
+    done: bool
+}
+
+// The result of looking for a document at an offset in the buffer.
+enum Frame {
+    End,
+    Corrupt(Error),
+    Complete(usize)
+}
+
+/// Implementation for the `DocumentReader` object.
+impl<R> DocumentReader<R> where R: Read {
+
+    /// Create the new `DocumentReader` object.
+    ///
+    /// # Parameters
+    /// - `reader` - The reader to read documents from.
+    ///
+    /// # Returns
+    /// The new `DocumentReader` object.
+    pub fn new(reader: R) -> DocumentReader<R> {
+        DocumentReader {
+            reader,
+            buffer: Vec::new(),
+            start: 0,
+            position: 0,
+            document_offset: 0,
+            max_document_size: None,
+            recover: false,
+            skipped: Vec::new(),
+            done: false
+        }
+    }
+
+    /// Set the largest document size in bytes that will be read. A length
+    /// prefix larger than this is treated as corruption instead of allocating
+    /// a buffer for it.
+    ///
+    /// # Parameters
+    /// - `size` - The maximum document size.
+    ///
+    /// # Returns
+    /// The `DocumentReader` object.
+    pub fn max_document_size(mut self, size: usize) -> DocumentReader<R> {
+        self.max_document_size = Some(size);
+        self
+    }
+
+    /// Enable recovery from corrupt data. When no maximum document size has
+    /// been set, `DEFAULT_RECOVERY_MAX_DOCUMENT_SIZE` is used so that a
+    /// corrupt length prefix cannot cause an unbounded read.
+    ///
+    /// # Returns
+    /// The `DocumentReader` object.
+    pub fn recover(mut self) -> DocumentReader<R> {
+        self.recover = true;
+        if self.max_document_size.is_none() {
+            self.max_document_size = Some(DEFAULT_RECOVERY_MAX_DOCUMENT_SIZE);
+        }
+        self
+    }
+
+    /// Get the byte ranges of the stream that were skipped while recovering
+    /// from corrupt data.
+    ///
+    /// # Returns
+    /// The skipped ranges, in stream order.
+    pub fn skipped_ranges(&self) -> &[Range<u64>] {
+        &self.skipped
+    }
+
+    /// Get the number of bytes consumed from the stream so far.
+    ///
+    /// # Returns
+    /// The byte position.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Get the byte offset in the stream at which the most recently read
+    /// document started.
+    ///
+    /// # Returns
+    /// The byte offset.
+    pub fn document_offset(&self) -> u64 {
+        self.document_offset
+    }
+
+    /// Get a reference to the underlying reader.
+    ///
+    /// # Returns
+    /// The reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Unwrap the underlying reader. When recovering, bytes that were read
+    /// ahead while scanning for a document are lost.
+    ///
+    /// # Returns
+    /// The reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Convert into a reader that yields `RawDocumentBuf`s without parsing
+    /// their elements.
+    ///
+    /// # Returns
+    /// The `RawDocumentReader` object.
+    pub fn raw(self) -> RawDocumentReader<R> {
+        RawDocumentReader { inner: self }
+    }
+
+    /// Read the next document, converting its bytes with the provided
+    /// function. When recovering, a conversion error marks the document as
+    /// corrupt.
+    ///
+    /// # Parameters
+    /// - `convert` - The function converting the document bytes.
+    ///
+    /// # Returns
+    /// `None` at the end of the stream, otherwise the converted `Result`.
+    fn next_with<T, F>(&mut self, convert: F) -> Option<Result<T>> where F: Fn(&[u8]) -> Result<T> {
+        if self.done {
+            return None;
+        }
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        loop {
+            let corruption = match self.frame(self.start) {
+                Ok(Frame::End) => {
+                    self.done = true;
+                    return None;
+                },
+                Ok(Frame::Complete(length)) => {
+                    let result = convert(&self.buffer[self.start..self.start + length]);
+                    if result.is_ok() || !self.recover {
+                        self.document_offset = self.position;
+                        self.consume(length);
+                        return Some(result);
+                    }
+                    None
+                },
+                Ok(Frame::Corrupt(error)) => Some(error),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+            if let Some(error) = corruption {
+                if !self.recover {
+                    self.document_offset = self.position;
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+            if let Err(error) = self.resynchronize(&convert) {
+                self.done = true;
+                return Some(Err(error));
+            }
+            if self.done {
+                return None;
+            }
+        }
+    }
+
+    /// Skip forward from a corrupt document to the next offset holding a
+    /// document that converts successfully, or to the end of the stream.
+    ///
+    /// # Parameters
+    /// - `convert` - The function converting the document bytes.
+    ///
+    /// # Returns
+    /// The `Result`, erroring only if the underlying reader fails.
+    fn resynchronize<T, F>(&mut self, convert: &F) -> Result<()> where F: Fn(&[u8]) -> Result<T> {
+        let corrupt_start = self.position;
+        let mut candidate = self.start + 1;
+        loop {
+            if candidate - self.start >= MAX_SCAN_WINDOW {
+                self.consume(candidate - self.start);
+                self.buffer.drain(..self.start);
+                candidate -= self.start;
+                self.start = 0;
+            }
+            match self.frame(candidate)? {
+                Frame::End => {
+                    self.consume(self.buffer.len() - self.start);
+                    self.done = true;
+                    break;
+                },
+                Frame::Complete(length) if convert(&self.buffer[candidate..candidate + length]).is_ok() => {
+                    self.consume(candidate - self.start);
+                    break;
+                },
+                _ => candidate += 1
+            }
+        }
+        self.skipped.push(corrupt_start..self.position);
+        Ok(())
+    }
+
+    /// Check for a complete, correctly framed document at the offset in the
+    /// buffer, reading more of the stream as needed.
+    ///
+    /// # Parameters
+    /// - `offset` - The offset in the buffer.
+    ///
+    /// # Returns
+    /// The `Frame`, or an error if the underlying reader fails.
+    fn frame(&mut self, offset: usize) -> Result<Frame> {
+        let available = self.fill(offset + 4)? - offset;
+        if available == 0 {
+            return Ok(Frame::End);
+        }
+        let stream_offset = self.position + (offset - self.start) as u64;
+        if available < 4 {
+            return Ok(Frame::Corrupt(truncated(stream_offset)));
+        }
+        let length = LittleEndian::read_i32(&self.buffer[offset..]);
+        if length < MIN_DOCUMENT_SIZE as i32 {
+            return Ok(Frame::Corrupt(invalid_data(format!(
+                "invalid document length {} at offset {}", length, stream_offset
+            ))));
+        }
+        let length = length as usize;
+        if let Some(max) = self.max_document_size {
+            if length > max {
+                return Ok(Frame::Corrupt(invalid_data(format!(
+                    "document length {} at offset {} exceeds the maximum of {}",
+                    length, stream_offset, max
+                ))));
+            }
+        }
+        if self.fill(offset + length)? < offset + length {
+            return Ok(Frame::Corrupt(truncated(stream_offset)));
+        }
+        if self.buffer[offset + length - 1] != 0 {
+            return Ok(Frame::Corrupt(invalid_data(format!(
+                "document at offset {} is missing its terminator", stream_offset
+            ))));
+        }
+        Ok(Frame::Complete(length))
+    }
+
+    /// Read from the stream until the buffer holds at least `length` bytes or
+    /// the stream ends.
+    ///
+    /// # Parameters
+    /// - `length` - The number of bytes wanted in the buffer.
+    ///
+    /// # Returns
+    /// The number of bytes in the buffer.
+    fn fill(&mut self, length: usize) -> Result<usize> {
+        let filled = self.buffer.len();
+        if filled < length {
+            // Read only what arrives rather than reserving the whole length
+            // up front, since the length comes from an untrusted prefix.
+            (&mut self.reader).take((length - filled) as u64).read_to_end(&mut self.buffer)?;
+        }
+        Ok(self.buffer.len())
+    }
+
+    fn consume(&mut self, length: usize) {
+        self.start += length;
+        self.position += length as u64;
+    }
+}
+
+impl<R> Iterator for DocumentReader<R> where R: Read {
+    type Item = Result<Document>;
+
+    fn next(&mut self) -> Option<Result<Document>> {
+        self.next_with(parse_document)
+    }
+}
+
+/// The `RawDocumentReader` object that reads a stream of concatenated BSON
+/// documents, yielding each one as a `RawDocumentBuf`. Created with
+/// `DocumentReader::raw`. When recovering, documents are still parsed to
+/// check that their contents are valid.
+pub struct RawDocumentReader<R> {
+    inner: DocumentReader<R>
+}
+
+/// Implementation for the `RawDocumentReader` object.
+impl<R> RawDocumentReader<R> where R: Read {
+
+    /// Get the byte ranges of the stream that were skipped while recovering
+    /// from corrupt data.
+    ///
+    /// # Returns
+    /// The skipped ranges, in stream order.
+    pub fn skipped_ranges(&self) -> &[Range<u64>] {
+        self.inner.skipped_ranges()
+    }
+
+    /// Get the number of bytes consumed from the stream so far.
+    ///
+    /// # Returns
+    /// The byte position.
+    pub fn position(&self) -> u64 {
+        self.inner.position()
+    }
+
+    /// Get the byte offset in the stream at which the most recently read
+    /// document started.
+    ///
+    /// # Returns
+    /// The byte offset.
+    pub fn document_offset(&self) -> u64 {
+        self.inner.document_offset()
+    }
+
+    /// Unwrap the underlying reader.
+    ///
+    /// # Returns
+    /// The reader.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+impl<R> Iterator for RawDocumentReader<R> where R: Read {
+    type Item = Result<RawDocumentBuf>;
+
+    fn next(&mut self) -> Option<Result<RawDocumentBuf>> {
+        let recover = self.inner.recover;
+        self.inner.next_with(|bytes| {
+            if recover {
+                parse_document(bytes)?;
+            }
+            RawDocumentBuf::new(bytes.to_vec())
+        })
+    }
+}
+
+fn truncated(offset: u64) -> Error {
+    Error::new(ErrorKind::UnexpectedEof, format!("truncated document at offset {}", offset))
+}
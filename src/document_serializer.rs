@@ -1,6 +1,9 @@
 use byteorder::{LittleEndian, WriteBytesExt};
-use std::io::{Result, Write};
+use std::borrow::Cow;
+use std::io::{Error, ErrorKind, Result, Write};
+use bson::Bson;
 use document::Document;
+use type_serializer::{write_cstring, TypeSerializer};
 
 /// The `DocumentSerializer` object that can serialize documents.
 pub struct DocumentSerializer<'a, W: ?Sized> where W: Write + 'a {
@@ -17,9 +20,9 @@ impl<'a, W> DocumentSerializer<'a, W> where W: Write + 'a {
     ///
     /// # Returns
     /// The new `DocumentSerializer` object.
-    pub fn new(writer: &mut W) -> DocumentSerializer<W> {
+    pub fn new(writer: &'a mut W) -> DocumentSerializer<'a, W> {
         DocumentSerializer {
-            writer: writer
+            writer
         }
     }
 
@@ -31,6 +34,47 @@ impl<'a, W> DocumentSerializer<'a, W> where W: Write + 'a {
     /// # Returns
     /// The `Result` object.
     pub fn serialize(&mut self, document: &Document) -> Result<()> {
-        Ok(())
+        self.serialize_elements(document.iter().map(|(key, value)| (Cow::Borrowed(key.as_str()), value)))
     }
+
+    /// Serialize the provided values to raw BSON as an array, which is a
+    /// document keyed by each value's index.
+    ///
+    /// # Parameters
+    /// - `values` - The BSON values.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn serialize_array(&mut self, values: &[Bson]) -> Result<()> {
+        self.serialize_elements(values.iter().enumerate().map(|(i, value)| (Cow::Owned(i.to_string()), value)))
+    }
+
+    fn serialize_elements<'b, I>(&mut self, elements: I) -> Result<()> where I: Iterator<Item = (Cow<'b, str>, &'b Bson)> {
+        let mut buffer = Vec::new();
+        for (key, value) in elements {
+            serialize_element(&mut buffer, &key, value)?;
+        }
+        let length = buffer.len() + 5;
+        if length > i32::MAX as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, "document is too large"));
+        }
+        self.writer.write_i32::<LittleEndian>(length as i32)?;
+        self.writer.write_all(&buffer)?;
+        self.writer.write_u8(0)
+    }
+}
+
+/// Serialize a single element: its type, key and value.
+///
+/// # Parameters
+/// - `writer` - The writer to write to.
+/// - `key` - The element key.
+/// - `value` - The element value.
+///
+/// # Returns
+/// The `Result` object.
+pub fn serialize_element<W: Write>(writer: &mut W, key: &str, value: &Bson) -> Result<()> {
+    writer.write_u8(value.element_type())?;
+    write_cstring(writer, key)?;
+    TypeSerializer::new(writer).serialize(value)
 }
@@ -0,0 +1,261 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::io::{Error, ErrorKind, Result, Seek, SeekFrom, Write};
+use bson::Bson;
+use document::Document;
+use document_serializer::{serialize_element, DocumentSerializer};
+use raw_document::RawDocumentBuf;
+use type_serializer::write_cstring;
+
+/// The `DocumentWriter` object that writes a sequence of BSON documents to a
+/// stream, such as a `.bson` file readable by `DocumentReader`.
+///
+/// Complete documents can be written to any writer. When the writer is also
+/// seekable, a document can be written incrementally: `start_document` opens
+/// it, `start_array` and `start_document_element` open nested values,
+/// `write_element` and `push` write elements into the innermost open value,
+/// and `end` closes it, seeking back to fill in its length. This allows
+/// writing a document with a huge array without holding it in memory.
+pub struct DocumentWriter<W> {
+    writer: W,
+    buffer: Vec<u8>,
+    bytes_written: u64,
+    open: Vec<OpenDocument>
+}
+
+// A document or array that has been started but not yet ended.
+struct OpenDocument {
+    start: u64,
+    next_index: Option<usize>
+}
+
+/// Implementation for the `DocumentWriter` object.
+impl<W> DocumentWriter<W> where W: Write {
+
+    /// Create the new `DocumentWriter` object.
+    ///
+    /// # Parameters
+    /// - `writer` - The writer to write documents to.
+    ///
+    /// # Returns
+    /// The new `DocumentWriter` object.
+    pub fn new(writer: W) -> DocumentWriter<W> {
+        DocumentWriter {
+            writer,
+            buffer: Vec::new(),
+            bytes_written: 0,
+            open: Vec::new()
+        }
+    }
+
+    /// Write a complete document.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` to write.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn write_document(&mut self, document: &Document) -> Result<()> {
+        self.ensure_closed()?;
+        self.buffer.clear();
+        DocumentSerializer::new(&mut self.buffer).serialize(document)?;
+        self.flush_buffer()
+    }
+
+    /// Write a complete document that is already in raw form.
+    ///
+    /// # Parameters
+    /// - `document` - The `RawDocumentBuf` to write.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn write_raw_document(&mut self, document: &RawDocumentBuf) -> Result<()> {
+        self.ensure_closed()?;
+        self.writer.write_all(document.as_bytes())?;
+        self.bytes_written += document.len() as u64;
+        Ok(())
+    }
+
+    /// Get the total number of bytes written.
+    ///
+    /// # Returns
+    /// The number of bytes.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Flush the underlying writer.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
+    /// Get a reference to the underlying writer.
+    ///
+    /// # Returns
+    /// The writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Unwrap the underlying writer.
+    ///
+    /// # Returns
+    /// The writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn ensure_closed(&self) -> Result<()> {
+        if self.open.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_input("a document is still open"))
+        }
+    }
+
+    fn flush_buffer(&mut self) -> Result<()> {
+        self.writer.write_all(&self.buffer)?;
+        self.bytes_written += self.buffer.len() as u64;
+        Ok(())
+    }
+}
+
+/// Implementation for writing documents incrementally to a seekable writer.
+impl<W> DocumentWriter<W> where W: Write + Seek {
+
+    /// Start a new top level document. Its length is written once it is
+    /// closed with `end`.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn start_document(&mut self) -> Result<()> {
+        self.ensure_closed()?;
+        self.open_document(None)
+    }
+
+    /// Start a document valued element in the innermost open document or
+    /// array.
+    ///
+    /// # Parameters
+    /// - `key` - The element key. Ignored when the innermost value is an array.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn start_document_element(&mut self, key: &str) -> Result<()> {
+        self.start_element(0x03, key)?;
+        self.open_document(None)
+    }
+
+    /// Start an array valued element in the innermost open document or array.
+    ///
+    /// # Parameters
+    /// - `key` - The element key. Ignored when the innermost value is an array.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn start_array(&mut self, key: &str) -> Result<()> {
+        self.start_element(0x04, key)?;
+        self.open_document(Some(0))
+    }
+
+    /// Write an element into the innermost open document.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The element value.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn write_element(&mut self, key: &str, value: &Bson) -> Result<()> {
+        match self.open.last() {
+            None => return Err(invalid_input("no document is open")),
+            Some(document) if document.next_index.is_some() => {
+                return Err(invalid_input("the innermost open value is an array"));
+            },
+            Some(_) => {}
+        }
+        self.buffer.clear();
+        serialize_element(&mut self.buffer, key, value)?;
+        self.flush_buffer()
+    }
+
+    /// Append a value to the innermost open array.
+    ///
+    /// # Parameters
+    /// - `value` - The value to append.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn push(&mut self, value: &Bson) -> Result<()> {
+        let key = self.next_array_key()?;
+        self.buffer.clear();
+        serialize_element(&mut self.buffer, &key, value)?;
+        self.flush_buffer()
+    }
+
+    /// Close the innermost open document or array, writing its terminator and
+    /// seeking back to fill in its length.
+    ///
+    /// # Returns
+    /// The `Result` object.
+    pub fn end(&mut self) -> Result<()> {
+        let document = match self.open.pop() {
+            Some(document) => document,
+            None => return Err(invalid_input("no document is open"))
+        };
+        self.writer.write_u8(0)?;
+        self.bytes_written += 1;
+        let length = self.bytes_written - document.start;
+        if length > i32::MAX as u64 {
+            return Err(invalid_input("document is too large"));
+        }
+        self.writer.seek(SeekFrom::Current(-(length as i64)))?;
+        self.writer.write_i32::<LittleEndian>(length as i32)?;
+        self.writer.seek(SeekFrom::Current(length as i64 - 4))?;
+        Ok(())
+    }
+
+    /// Get the number of documents and arrays currently open.
+    ///
+    /// # Returns
+    /// The nesting depth.
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    fn start_element(&mut self, element_type: u8, key: &str) -> Result<()> {
+        let key = match self.open.last() {
+            None => return Err(invalid_input("no document is open")),
+            Some(document) if document.next_index.is_some() => self.next_array_key()?,
+            Some(_) => key.to_string()
+        };
+        self.buffer.clear();
+        self.buffer.push(element_type);
+        write_cstring(&mut self.buffer, &key)?;
+        self.flush_buffer()
+    }
+
+    fn open_document(&mut self, next_index: Option<usize>) -> Result<()> {
+        self.open.push(OpenDocument { start: self.bytes_written, next_index });
+        self.writer.write_i32::<LittleEndian>(0)?;
+        self.bytes_written += 4;
+        Ok(())
+    }
+
+    fn next_array_key(&mut self) -> Result<String> {
+        match self.open.last_mut() {
+            Some(OpenDocument { next_index: Some(ref mut index), .. }) => {
+                let key = index.to_string();
+                *index += 1;
+                Ok(key)
+            },
+            _ => Err(invalid_input("no array is open"))
+        }
+    }
+}
+
+fn invalid_input(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
@@ -0,0 +1,852 @@
+use std::cmp::Ordering;
+use std::slice;
+use bson::Bson;
+use comparison::bson_cmp;
+use datetime;
+use decimal128::{format_decimal128, Decimal};
+use document::Document;
+use filter::truthy;
+use query_error::QueryError;
+
+/// A parsed aggregation expression that computes a value from a document.
+///
+/// An expression is a literal, a `"$field.path"` reference into the current
+/// document, a `"$$variable.path"` reference, a document or array of
+/// expressions, or an operator document such as `{ "$add": ["$a", 1] }`.
+/// The variables `ROOT` and `CURRENT` refer to the document evaluated, and
+/// `$let`, `$map`, `$filter` and `$reduce` bind their own.
+///
+/// The supported operators are `$literal`, `$let`, the arithmetic `$add`,
+/// `$subtract`, `$multiply`, `$divide` and `$mod`, the string `$concat`,
+/// `$substrCP`, `$toUpper` and `$toLower`, the comparison `$eq`, `$ne`,
+/// `$gt`, `$gte`, `$lt`, `$lte` and `$cmp`, the conditional `$cond`,
+/// `$switch` and `$ifNull`, the array `$map`, `$filter`, `$reduce` and
+/// `$size`, and the conversions `$toString`, `$toInt`, `$toLong`,
+/// `$toDouble`, `$toDecimal`, `$toBool` and `$convert`. Arithmetic with a
+/// `Decimal128` operand produces a `Decimal128`.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    node: Node
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Literal(Bson),
+    Variable(String, Vec<String>),
+    Document(Vec<(String, Node)>),
+    Array(Vec<Node>),
+    Operator(Operator, Vec<Node>),
+    Let(Vec<(String, Node)>, Box<Node>),
+    Map(Box<Node>, String, Box<Node>),
+    Filter(Box<Node>, String, Box<Node>, Option<Box<Node>>),
+    Reduce(Box<Node>, Box<Node>, Box<Node>),
+    Switch(Vec<(Node, Node)>, Option<Box<Node>>),
+    Convert(Box<Node>, Box<Node>, Option<Box<Node>>, Option<Box<Node>>)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Mod,
+    Concat,
+    SubstrCP,
+    ToUpper,
+    ToLower,
+    Compare(Comparison),
+    Cmp,
+    Cond,
+    IfNull,
+    Size,
+    ToString,
+    ToInt,
+    ToLong,
+    ToDouble,
+    ToDecimal,
+    ToBool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte
+}
+
+// The variables visible while evaluating, innermost last.
+struct Scope {
+    variables: Vec<(String, Bson)>
+}
+
+/// Implementation for the `Expression` object.
+impl Expression {
+
+    /// Parse an aggregation expression.
+    ///
+    /// # Parameters
+    /// - `expression` - The expression as a `Bson` value.
+    ///
+    /// # Returns
+    /// The `Result` with the `Expression`, or a `QueryError` carrying the
+    /// server's message if the expression is invalid.
+    pub fn parse(expression: &Bson) -> Result<Expression, QueryError> {
+        let mut variables = vec!["ROOT".to_string(), "CURRENT".to_string(), "REMOVE".to_string()];
+        Ok(Expression { node: parse(expression, &mut variables)? })
+    }
+
+    /// Evaluate the expression against a document.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` bound to `ROOT` and `CURRENT`.
+    ///
+    /// # Returns
+    /// The `Result` with the value, or `None` if the expression resolves to a
+    /// missing field, or a `QueryError` if evaluation fails.
+    pub fn evaluate(&self, document: &Document) -> Result<Option<Bson>, QueryError> {
+        let root = Bson::Document(document.clone());
+        let mut scope = Scope {
+            variables: vec![("ROOT".to_string(), root.clone()), ("CURRENT".to_string(), root)]
+        };
+        evaluate(&self.node, &mut scope)
+    }
+}
+
+fn parse(expression: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    match *expression {
+        Bson::String(ref path) if path.starts_with("$$") => {
+            let mut parts = path[2..].split('.').map(String::from);
+            let name = parts.next().unwrap_or_default();
+            if !variables.contains(&name) {
+                return Err(QueryError::new(format!("Use of undefined variable: {}", name)));
+            }
+            Ok(Node::Variable(name, parts.collect()))
+        },
+        Bson::String(ref path) if path.starts_with('$') => {
+            let parts: Vec<String> = path[1..].split('.').map(String::from).collect();
+            if parts.iter().any(String::is_empty) {
+                return Err(QueryError::new(format!("FieldPath field names may not be empty strings: '{}'", path)));
+            }
+            Ok(Node::Variable("CURRENT".to_string(), parts))
+        },
+        Bson::Array(ref elements) => {
+            Ok(Node::Array(elements.iter().map(|element| parse(element, variables)).collect::<Result<_, _>>()?))
+        },
+        Bson::Document(ref document) => match document.iter().next() {
+            Some((name, operand)) if name.starts_with('$') => {
+                if document.len() > 1 {
+                    return Err(QueryError::new(format!(
+                        "an expression specification must contain exactly one field, the name of the expression. Found {} fields in {}",
+                        document.len(), document
+                    )));
+                }
+                parse_operator(name, operand, variables)
+            },
+            _ => {
+                let mut fields = Vec::new();
+                for (key, value) in document.iter() {
+                    if key.starts_with('$') {
+                        return Err(QueryError::new(format!(
+                            "Field names may not start with '$'. Got '{}' in {}", key, document
+                        )));
+                    }
+                    fields.push((key.clone(), parse(value, variables)?));
+                }
+                Ok(Node::Document(fields))
+            }
+        },
+        ref other => Ok(Node::Literal(other.clone()))
+    }
+}
+
+fn parse_operator(name: &str, operand: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    let (operator, arity) = match name {
+        "$literal" => return Ok(Node::Literal(operand.clone())),
+        "$let" => return parse_let(operand, variables),
+        "$map" => return parse_map(operand, variables),
+        "$filter" => return parse_filter(operand, variables),
+        "$reduce" => return parse_reduce(operand, variables),
+        "$switch" => return parse_switch(operand, variables),
+        "$convert" => return parse_convert(operand, variables),
+        "$cond" => {
+            if let Bson::Document(ref arguments) = *operand {
+                let arguments = [
+                    required(name, arguments, "if")?,
+                    required(name, arguments, "then")?,
+                    required(name, arguments, "else")?
+                ];
+                let nodes = arguments.iter().map(|argument| parse(argument, variables)).collect::<Result<_, _>>()?;
+                return Ok(Node::Operator(Operator::Cond, nodes));
+            }
+            (Operator::Cond, Some(3))
+        },
+        "$add" => (Operator::Add, None),
+        "$subtract" => (Operator::Subtract, Some(2)),
+        "$multiply" => (Operator::Multiply, None),
+        "$divide" => (Operator::Divide, Some(2)),
+        "$mod" => (Operator::Mod, Some(2)),
+        "$concat" => (Operator::Concat, None),
+        "$substrCP" => (Operator::SubstrCP, Some(3)),
+        "$toUpper" => (Operator::ToUpper, Some(1)),
+        "$toLower" => (Operator::ToLower, Some(1)),
+        "$eq" => (Operator::Compare(Comparison::Eq), Some(2)),
+        "$ne" => (Operator::Compare(Comparison::Ne), Some(2)),
+        "$gt" => (Operator::Compare(Comparison::Gt), Some(2)),
+        "$gte" => (Operator::Compare(Comparison::Gte), Some(2)),
+        "$lt" => (Operator::Compare(Comparison::Lt), Some(2)),
+        "$lte" => (Operator::Compare(Comparison::Lte), Some(2)),
+        "$cmp" => (Operator::Cmp, Some(2)),
+        "$ifNull" => (Operator::IfNull, None),
+        "$size" => (Operator::Size, Some(1)),
+        "$toString" => (Operator::ToString, Some(1)),
+        "$toInt" => (Operator::ToInt, Some(1)),
+        "$toLong" => (Operator::ToLong, Some(1)),
+        "$toDouble" => (Operator::ToDouble, Some(1)),
+        "$toDecimal" => (Operator::ToDecimal, Some(1)),
+        "$toBool" => (Operator::ToBool, Some(1)),
+        _ => return Err(QueryError::new(format!("Unrecognized expression '{}'", name)))
+    };
+    let arguments = match *operand {
+        Bson::Array(ref elements) => elements.iter().map(|element| parse(element, variables)).collect::<Result<Vec<_>, _>>()?,
+        ref other => vec![parse(other, variables)?]
+    };
+    match arity {
+        Some(count) if arguments.len() != count => Err(QueryError::new(format!(
+            "Expression {} takes exactly {} arguments. {} were passed in.", name, count, arguments.len()
+        ))),
+        None if operator == Operator::IfNull && arguments.len() < 2 => Err(QueryError::new(
+            "$ifNull needs at least two arguments, had: 1"
+        )),
+        _ => Ok(Node::Operator(operator, arguments))
+    }
+}
+
+fn arguments<'a>(name: &str, operand: &'a Bson) -> Result<&'a Document, QueryError> {
+    match *operand {
+        Bson::Document(ref arguments) => Ok(arguments),
+        ref other => Err(QueryError::new(format!(
+            "{} only supports an object as its argument, but found {}", name, other.type_name()
+        )))
+    }
+}
+
+fn required<'a>(name: &str, arguments: &'a Document, field: &str) -> Result<&'a Bson, QueryError> {
+    arguments.get(field).ok_or_else(|| QueryError::new(format!("Missing '{}' parameter to {}", field, name)))
+}
+
+fn check_fields(name: &str, arguments: &Document, allowed: &[&str]) -> Result<(), QueryError> {
+    match arguments.iter().find(|(key, _)| !allowed.contains(&key.as_str())) {
+        Some((key, _)) => Err(QueryError::new(format!("Unrecognized parameter to {}: {}", name, key))),
+        None => Ok(())
+    }
+}
+
+fn variable_name(name: &Bson) -> Result<String, QueryError> {
+    match *name {
+        Bson::String(ref name) if name.starts_with(|c: char| c.is_ascii_lowercase() || !c.is_ascii())
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_') => Ok(name.clone()),
+        Bson::String(ref name) => Err(QueryError::new(format!(
+            "'{}' starts with an invalid character for a user variable name", name
+        ))),
+        ref other => Err(QueryError::new(format!("variable name must be a string, found {}", other.type_name())))
+    }
+}
+
+// Parse an expression with extra variables in scope.
+fn parse_with(expression: &Bson, variables: &mut Vec<String>, names: &[String]) -> Result<Node, QueryError> {
+    let depth = variables.len();
+    variables.extend(names.iter().cloned());
+    let node = parse(expression, variables);
+    variables.truncate(depth);
+    node
+}
+
+fn parse_let(operand: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    let arguments = arguments("$let", operand)?;
+    check_fields("$let", arguments, &["vars", "in"])?;
+    let vars = match *required("$let", arguments, "vars")? {
+        Bson::Document(ref vars) => vars,
+        ref other => return Err(QueryError::new(format!(
+            "invalid parameter: expected an object (vars), found {}", other.type_name()
+        )))
+    };
+    let mut bindings = Vec::new();
+    for (name, value) in vars.iter() {
+        let name = variable_name(&Bson::String(name.clone()))?;
+        bindings.push((name, parse(value, variables)?));
+    }
+    let names: Vec<String> = bindings.iter().map(|(name, _)| name.clone()).collect();
+    let body = parse_with(required("$let", arguments, "in")?, variables, &names)?;
+    Ok(Node::Let(bindings, Box::new(body)))
+}
+
+fn parse_map(operand: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    let arguments = arguments("$map", operand)?;
+    check_fields("$map", arguments, &["input", "as", "in"])?;
+    let input = parse(required("$map", arguments, "input")?, variables)?;
+    let name = match arguments.get("as") {
+        Some(name) => variable_name(name)?,
+        None => "this".to_string()
+    };
+    let body = parse_with(required("$map", arguments, "in")?, variables, slice::from_ref(&name))?;
+    Ok(Node::Map(Box::new(input), name, Box::new(body)))
+}
+
+fn parse_filter(operand: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    let arguments = arguments("$filter", operand)?;
+    check_fields("$filter", arguments, &["input", "as", "cond", "limit"])?;
+    let input = parse(required("$filter", arguments, "input")?, variables)?;
+    let name = match arguments.get("as") {
+        Some(name) => variable_name(name)?,
+        None => "this".to_string()
+    };
+    let condition = parse_with(required("$filter", arguments, "cond")?, variables, slice::from_ref(&name))?;
+    let limit = match arguments.get("limit") {
+        Some(limit) => Some(Box::new(parse(limit, variables)?)),
+        None => None
+    };
+    Ok(Node::Filter(Box::new(input), name, Box::new(condition), limit))
+}
+
+fn parse_reduce(operand: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    let arguments = arguments("$reduce", operand)?;
+    check_fields("$reduce", arguments, &["input", "initialValue", "in"])?;
+    let input = parse(required("$reduce", arguments, "input")?, variables)?;
+    let initial = parse(required("$reduce", arguments, "initialValue")?, variables)?;
+    let names = ["value".to_string(), "this".to_string()];
+    let body = parse_with(required("$reduce", arguments, "in")?, variables, &names)?;
+    Ok(Node::Reduce(Box::new(input), Box::new(initial), Box::new(body)))
+}
+
+fn parse_switch(operand: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    let arguments = arguments("$switch", operand)?;
+    check_fields("$switch", arguments, &["branches", "default"])?;
+    let branches = match *required("$switch", arguments, "branches")? {
+        Bson::Array(ref branches) => branches,
+        ref other => return Err(QueryError::new(format!(
+            "$switch expected an array for 'branches', found: {}", other.type_name()
+        )))
+    };
+    let mut cases = Vec::new();
+    for branch in branches {
+        let branch = match *branch {
+            Bson::Document(ref branch) => branch,
+            ref other => return Err(QueryError::new(format!(
+                "$switch expected each branch to be an object, found: {}", other.type_name()
+            )))
+        };
+        check_fields("$switch", branch, &["case", "then"])?;
+        let case = branch.get("case").ok_or_else(|| QueryError::new("$switch requires each branch have a 'case' expression"))?;
+        let then = branch.get("then").ok_or_else(|| QueryError::new("$switch requires each branch have a 'then' expression."))?;
+        cases.push((parse(case, variables)?, parse(then, variables)?));
+    }
+    if cases.is_empty() {
+        return Err(QueryError::new("$switch requires at least one branch."));
+    }
+    let default = match arguments.get("default") {
+        Some(default) => Some(Box::new(parse(default, variables)?)),
+        None => None
+    };
+    Ok(Node::Switch(cases, default))
+}
+
+fn parse_convert(operand: &Bson, variables: &mut Vec<String>) -> Result<Node, QueryError> {
+    let arguments = arguments("$convert", operand)?;
+    check_fields("$convert", arguments, &["input", "to", "onError", "onNull"])?;
+    let input = parse(required("$convert", arguments, "input")?, variables)?;
+    let to = parse(required("$convert", arguments, "to")?, variables)?;
+    let on_error = match arguments.get("onError") {
+        Some(value) => Some(Box::new(parse(value, variables)?)),
+        None => None
+    };
+    let on_null = match arguments.get("onNull") {
+        Some(value) => Some(Box::new(parse(value, variables)?)),
+        None => None
+    };
+    Ok(Node::Convert(Box::new(input), Box::new(to), on_error, on_null))
+}
+
+fn evaluate(node: &Node, scope: &mut Scope) -> Result<Option<Bson>, QueryError> {
+    Ok(match *node {
+        Node::Literal(ref value) => Some(value.clone()),
+        Node::Variable(ref name, ref path) => {
+            if name == "REMOVE" {
+                return Ok(None);
+            }
+            let value = scope.variables.iter().rev().find(|(variable, _)| variable == name).map(|(_, value)| value);
+            value.and_then(|value| resolve(value, path))
+        },
+        Node::Document(ref fields) => {
+            let mut document = Document::new();
+            for (key, field) in fields {
+                if let Some(value) = evaluate(field, scope)? {
+                    document.insert(key.clone(), value);
+                }
+            }
+            Some(Bson::Document(document))
+        },
+        Node::Array(ref elements) => {
+            let mut values = Vec::with_capacity(elements.len());
+            for element in elements {
+                values.push(evaluate(element, scope)?.unwrap_or(Bson::Null));
+            }
+            Some(Bson::Array(values))
+        },
+        Node::Operator(operator, ref arguments) => return apply_operator(operator, arguments, scope),
+        Node::Let(ref bindings, ref body) => {
+            let mut values = Vec::with_capacity(bindings.len());
+            for (name, binding) in bindings {
+                values.push((name.clone(), evaluate(binding, scope)?.unwrap_or(Bson::Null)));
+            }
+            return with_variables(scope, values, |scope| evaluate(body, scope));
+        },
+        Node::Map(ref input, ref name, ref body) => {
+            let elements = match array_input("$map", input, scope)? {
+                Some(elements) => elements,
+                None => return Ok(Some(Bson::Null))
+            };
+            let mut mapped = Vec::with_capacity(elements.len());
+            for element in elements {
+                let value = with_variables(scope, vec![(name.clone(), element)], |scope| evaluate(body, scope))?;
+                mapped.push(value.unwrap_or(Bson::Null));
+            }
+            Some(Bson::Array(mapped))
+        },
+        Node::Filter(ref input, ref name, ref condition, ref limit) => {
+            let elements = match array_input("$filter", input, scope)? {
+                Some(elements) => elements,
+                None => return Ok(Some(Bson::Null))
+            };
+            let limit = match *limit {
+                Some(ref limit) => match evaluate(limit, scope)?.as_ref().and_then(integer) {
+                    Some(limit) if limit > 0 => limit as usize,
+                    _ => return Err(QueryError::new("$filter: limit must be represented as a positive 32-bit integral value"))
+                },
+                None => elements.len()
+            };
+            let mut kept = Vec::new();
+            for element in elements {
+                if kept.len() == limit {
+                    break;
+                }
+                let keep = with_variables(scope, vec![(name.clone(), element.clone())], |scope| evaluate(condition, scope))?;
+                if keep.as_ref().is_some_and(truthy) {
+                    kept.push(element);
+                }
+            }
+            Some(Bson::Array(kept))
+        },
+        Node::Reduce(ref input, ref initial, ref body) => {
+            let elements = match array_input("$reduce", input, scope)? {
+                Some(elements) => elements,
+                None => return Ok(Some(Bson::Null))
+            };
+            let mut value = evaluate(initial, scope)?.unwrap_or(Bson::Null);
+            for element in elements {
+                let variables = vec![("value".to_string(), value), ("this".to_string(), element)];
+                value = with_variables(scope, variables, |scope| evaluate(body, scope))?.unwrap_or(Bson::Null);
+            }
+            Some(value)
+        },
+        Node::Switch(ref cases, ref default) => {
+            for (case, then) in cases {
+                if evaluate(case, scope)?.as_ref().is_some_and(truthy) {
+                    return evaluate(then, scope);
+                }
+            }
+            match *default {
+                Some(ref default) => return evaluate(default, scope),
+                None => return Err(QueryError::new(
+                    "$switch could not find a matching branch for an input, and no default was specified."
+                ))
+            }
+        },
+        Node::Convert(ref input, ref to, ref on_error, ref on_null) => {
+            let value = evaluate(input, scope)?;
+            let target = match evaluate(to, scope)? {
+                Some(Bson::String(target)) => target,
+                Some(target) => match integer(&target).and_then(type_alias) {
+                    Some(target) => target.to_string(),
+                    None => return Err(QueryError::new(format!("Unknown type name: {}", target)))
+                },
+                None => return Err(QueryError::new("Missing 'to' parameter to $convert"))
+            };
+            match value {
+                None | Some(Bson::Null) | Some(Bson::Undefined) => match *on_null {
+                    Some(ref on_null) => return evaluate(on_null, scope),
+                    None => Some(Bson::Null)
+                },
+                Some(value) => match convert(&value, &target) {
+                    Ok(converted) => Some(converted),
+                    Err(error) => match *on_error {
+                        Some(ref on_error) if !error.message().starts_with("Unknown type name") => {
+                            return evaluate(on_error, scope);
+                        },
+                        _ => return Err(error)
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn with_variables<F>(scope: &mut Scope, variables: Vec<(String, Bson)>, f: F) -> Result<Option<Bson>, QueryError>
+        where F: FnOnce(&mut Scope) -> Result<Option<Bson>, QueryError> {
+    let depth = scope.variables.len();
+    scope.variables.extend(variables);
+    let result = f(scope);
+    scope.variables.truncate(depth);
+    result
+}
+
+// Evaluate the input of an array operator, which may be null or missing.
+fn array_input(name: &str, input: &Node, scope: &mut Scope) -> Result<Option<Vec<Bson>>, QueryError> {
+    match evaluate(input, scope)? {
+        Some(Bson::Array(elements)) => Ok(Some(elements)),
+        None | Some(Bson::Null) | Some(Bson::Undefined) => Ok(None),
+        Some(other) => Err(QueryError::new(format!(
+            "input to {} must be an array not {}", name, other.type_name()
+        )))
+    }
+}
+
+/// Resolve a field path within a value, collecting the values found in each
+/// embedded document of an array.
+fn resolve(value: &Bson, path: &[String]) -> Option<Bson> {
+    if path.is_empty() {
+        return Some(value.clone());
+    }
+    match *value {
+        Bson::Document(ref document) => document.get(&path[0]).and_then(|value| resolve(value, &path[1..])),
+        Bson::Array(ref elements) => Some(Bson::Array(elements.iter().filter_map(|element| match *element {
+            Bson::Document(_) | Bson::Array(_) => resolve(element, path),
+            _ => None
+        }).collect())),
+        _ => None
+    }
+}
+
+fn apply_operator(operator: Operator, arguments: &[Node], scope: &mut Scope) -> Result<Option<Bson>, QueryError> {
+    match operator {
+        Operator::Cond => {
+            let condition = evaluate(&arguments[0], scope)?;
+            return evaluate(&arguments[if condition.as_ref().is_some_and(truthy) { 1 } else { 2 }], scope);
+        },
+        Operator::IfNull => {
+            for argument in &arguments[..arguments.len() - 1] {
+                match evaluate(argument, scope)? {
+                    None | Some(Bson::Null) | Some(Bson::Undefined) => {},
+                    value => return Ok(value)
+                }
+            }
+            return evaluate(&arguments[arguments.len() - 1], scope);
+        },
+        _ => {}
+    }
+    let mut values = Vec::with_capacity(arguments.len());
+    for argument in arguments {
+        values.push(evaluate(argument, scope)?);
+    }
+    let nullish = values.iter().any(|value| matches!(*value, None | Some(Bson::Null) | Some(Bson::Undefined)));
+    let values: Vec<Bson> = values.into_iter().map(|value| value.unwrap_or(Bson::Null)).collect();
+    Ok(Some(match operator {
+        Operator::Compare(comparison) => {
+            let ordering = bson_cmp(&values[0], &values[1]);
+            Bson::Boolean(match comparison {
+                Comparison::Eq => ordering == Ordering::Equal,
+                Comparison::Ne => ordering != Ordering::Equal,
+                Comparison::Gt => ordering == Ordering::Greater,
+                Comparison::Gte => ordering != Ordering::Less,
+                Comparison::Lt => ordering == Ordering::Less,
+                Comparison::Lte => ordering != Ordering::Greater
+            })
+        },
+        Operator::Cmp => Bson::Int32(match bson_cmp(&values[0], &values[1]) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1
+        }),
+        Operator::Size => match values[0] {
+            Bson::Array(ref elements) => Bson::Int32(elements.len() as i32),
+            ref other => return Err(QueryError::new(format!(
+                "The argument to $size must be an array, but was of type: {}", other.type_name()
+            )))
+        },
+        Operator::ToUpper | Operator::ToLower => {
+            let string = match values[0] {
+                Bson::Null | Bson::Undefined => String::new(),
+                ref other => to_string(other, operator_name(operator))?
+            };
+            Bson::String(if operator == Operator::ToUpper { string.to_uppercase() } else { string.to_lowercase() })
+        },
+        _ if nullish => Bson::Null,
+        Operator::Add => add(&values)?,
+        Operator::Subtract => subtract(&values[0], &values[1])?,
+        Operator::Multiply => {
+            let mut product = Bson::Int32(1);
+            for value in &values {
+                product = arithmetic(&product, value, i64::checked_mul, |l, r| l * r, Decimal::multiply)
+                    .ok_or_else(|| non_numeric("$multiply", value))?;
+            }
+            product
+        },
+        Operator::Divide => {
+            let (dividend, divisor) = match (number(&values[0]), number(&values[1])) {
+                (Some(dividend), Some(divisor)) => (dividend, divisor),
+                _ => return Err(QueryError::new(format!(
+                    "$divide only supports numeric types, not {} and {}", values[0].type_name(), values[1].type_name()
+                )))
+            };
+            if is_zero(&values[1]) {
+                return Err(QueryError::new("can't $divide by zero"));
+            }
+            match (Decimal::from_number(&values[0]), Decimal::from_number(&values[1])) {
+                (Some(dividend), Some(divisor)) if is_decimal(&values[0]) || is_decimal(&values[1]) => {
+                    Bson::Decimal128(dividend.divide(divisor).to_bytes())
+                },
+                _ => Bson::Double(dividend / divisor)
+            }
+        },
+        Operator::Mod => {
+            if number(&values[0]).is_none() || number(&values[1]).is_none() {
+                return Err(QueryError::new(format!(
+                    "$mod only supports numeric types, not {} and {}", values[0].type_name(), values[1].type_name()
+                )));
+            }
+            if is_zero(&values[1]) {
+                return Err(QueryError::new("can't $mod by zero"));
+            }
+            arithmetic(&values[0], &values[1], i64::checked_rem, |l, r| l % r, Decimal::remainder).unwrap_or(Bson::Null)
+        },
+        Operator::Concat => {
+            let mut string = String::new();
+            for value in &values {
+                match *value {
+                    Bson::String(ref part) => string.push_str(part),
+                    ref other => return Err(QueryError::new(format!(
+                        "$concat only supports strings, not {}", other.type_name()
+                    )))
+                }
+            }
+            Bson::String(string)
+        },
+        Operator::SubstrCP => {
+            let string = to_string(&values[0], "$substrCP")?;
+            let (start, length) = match (integer(&values[1]), integer(&values[2])) {
+                (Some(start), Some(length)) if start >= 0 && length >= 0 => (start as usize, length as usize),
+                _ => return Err(QueryError::new(
+                    "$substrCP: starting index and length must be non-negative integers"
+                ))
+            };
+            Bson::String(string.chars().skip(start).take(length).collect())
+        },
+        Operator::ToString => Bson::String(to_string(&values[0], "$convert")?),
+        Operator::ToInt => convert(&values[0], "int")?,
+        Operator::ToLong => convert(&values[0], "long")?,
+        Operator::ToDouble => convert(&values[0], "double")?,
+        Operator::ToDecimal => convert(&values[0], "decimal")?,
+        Operator::ToBool => convert(&values[0], "bool")?,
+        Operator::Cond | Operator::IfNull => Bson::Null
+    }))
+}
+
+fn operator_name(operator: Operator) -> &'static str {
+    match operator {
+        Operator::ToUpper => "$toUpper",
+        _ => "$toLower"
+    }
+}
+
+fn non_numeric(name: &str, value: &Bson) -> QueryError {
+    QueryError::new(format!("{} only supports numeric types, not {}", name, value.type_name()))
+}
+
+fn add(values: &[Bson]) -> Result<Bson, QueryError> {
+    let mut sum = Bson::Int32(0);
+    let mut date = None;
+    for value in values {
+        match *value {
+            Bson::DateTime(millis) if date.is_none() => date = Some(millis),
+            Bson::DateTime(_) => return Err(QueryError::new("only one date allowed in an $add expression")),
+            _ => {
+                sum = arithmetic(&sum, value, i64::checked_add, |l, r| l + r, Decimal::add)
+                    .ok_or_else(|| non_numeric("$add", value))?;
+            }
+        }
+    }
+    Ok(match date {
+        Some(millis) => Bson::DateTime(millis.wrapping_add(number(&sum).unwrap_or(0.0).round() as i64)),
+        None => sum
+    })
+}
+
+fn subtract(left: &Bson, right: &Bson) -> Result<Bson, QueryError> {
+    match (left, right) {
+        (Bson::DateTime(l), Bson::DateTime(r)) => Ok(Bson::Int64(l.wrapping_sub(*r))),
+        (Bson::DateTime(l), _) => match number(right) {
+            Some(r) => Ok(Bson::DateTime(l.wrapping_sub(r.round() as i64))),
+            None => Err(QueryError::new(format!(
+                "can't $subtract {} from date", right.type_name()
+            )))
+        },
+        _ => arithmetic(left, right, i64::checked_sub, |l, r| l - r, Decimal::subtract).ok_or_else(|| QueryError::new(format!(
+            "can't $subtract {} from {}", right.type_name(), left.type_name()
+        )))
+    }
+}
+
+/// Combine two numbers, keeping `Int32` when both are and the result fits,
+/// widening to `Int64`, and to `Double` if an integer result overflows or
+/// either number is a double. If either number is a decimal the result is a
+/// `Decimal128`.
+///
+/// # Returns
+/// The result, or `None` if either value is not a number.
+fn arithmetic<I, D, M>(left: &Bson, right: &Bson, integers: I, doubles: D, decimals: M) -> Option<Bson>
+        where I: Fn(i64, i64) -> Option<i64>, D: Fn(f64, f64) -> f64, M: Fn(Decimal, Decimal) -> Decimal {
+    if is_decimal(left) || is_decimal(right) {
+        let (l, r) = (Decimal::from_number(left)?, Decimal::from_number(right)?);
+        return Some(Bson::Decimal128(decimals(l, r).to_bytes()));
+    }
+    let both_int32 = matches!((left, right), (Bson::Int32(_), Bson::Int32(_)));
+    match (integer_value(left), integer_value(right)) {
+        (Some(l), Some(r)) => Some(match integers(l, r) {
+            Some(result) if both_int32 && result >= i64::from(i32::MIN) && result <= i64::from(i32::MAX) => {
+                Bson::Int32(result as i32)
+            },
+            Some(result) => Bson::Int64(result),
+            None => Bson::Double(doubles(l as f64, r as f64))
+        }),
+        _ => Some(Bson::Double(doubles(number(left)?, number(right)?)))
+    }
+}
+
+fn integer_value(value: &Bson) -> Option<i64> {
+    match *value {
+        Bson::Int32(number) => Some(i64::from(number)),
+        Bson::Int64(number) => Some(number),
+        _ => None
+    }
+}
+
+fn number(value: &Bson) -> Option<f64> {
+    match *value {
+        Bson::Int32(number) => Some(f64::from(number)),
+        Bson::Int64(number) => Some(number as f64),
+        Bson::Double(number) => Some(number),
+        Bson::Decimal128(ref bytes) => Some(Decimal::from_bytes(bytes).to_f64()),
+        _ => None
+    }
+}
+
+fn is_decimal(value: &Bson) -> bool {
+    matches!(*value, Bson::Decimal128(_))
+}
+
+fn is_zero(value: &Bson) -> bool {
+    Decimal::from_bson(value).is_some_and(|number| number.compare(&Decimal::from_i64(0)) == Ordering::Equal)
+}
+
+// A number with no fractional part as an integer.
+fn integer(value: &Bson) -> Option<i64> {
+    match *value {
+        Bson::Double(number) if number.fract() == 0.0 && number.abs() < 9.2e18 => Some(number as i64),
+        Bson::Decimal128(ref bytes) => Decimal::from_bytes(bytes).to_i64(),
+        _ => integer_value(value)
+    }
+}
+
+// The type name for a numeric BSON type code accepted by `$convert`.
+fn type_alias(code: i64) -> Option<&'static str> {
+    match code {
+        1 => Some("double"),
+        2 => Some("string"),
+        8 => Some("bool"),
+        16 => Some("int"),
+        18 => Some("long"),
+        19 => Some("decimal"),
+        _ => None
+    }
+}
+
+fn to_string(value: &Bson, name: &str) -> Result<String, QueryError> {
+    Ok(match *value {
+        Bson::String(ref string) | Bson::Symbol(ref string) => string.clone(),
+        Bson::Int32(number) => number.to_string(),
+        Bson::Int64(number) => number.to_string(),
+        Bson::Double(number) => number.to_string(),
+        Bson::Decimal128(ref bytes) => format_decimal128(bytes),
+        Bson::Boolean(flag) => flag.to_string(),
+        Bson::ObjectId(ref id) => id.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        Bson::DateTime(millis) => match datetime::format_iso8601(millis) {
+            Some(formatted) => formatted,
+            None => millis.to_string()
+        },
+        ref other if name == "$convert" => return Err(unsupported(other, "string")),
+        ref other => return Err(QueryError::new(format!(
+            "{} requires a string, number or date argument, found: {}", name, other.type_name()
+        )))
+    })
+}
+
+fn unsupported(value: &Bson, target: &str) -> QueryError {
+    QueryError::new(format!(
+        "Unsupported conversion from {} to {} in $convert with no onError value", value.type_name(), target
+    ))
+}
+
+/// Convert a value that is not null to a named type, as `$convert` does.
+fn convert(value: &Bson, target: &str) -> Result<Bson, QueryError> {
+    let overflow = || QueryError::new(format!(
+        "Conversion would overflow target type in $convert with no onError value: {}", value
+    ));
+    let unparsable = |string: &str| QueryError::new(format!(
+        "Failed to parse number '{}' in $convert with no onError value: Did not consume whole string.", string
+    ));
+    Ok(match target {
+        "string" => Bson::String(to_string(value, "$convert")?),
+        "bool" => match *value {
+            Bson::Null | Bson::Undefined => Bson::Null,
+            _ => Bson::Boolean(truthy(value))
+        },
+        "double" => match *value {
+            Bson::Boolean(flag) => Bson::Double(if flag { 1.0 } else { 0.0 }),
+            Bson::DateTime(millis) => Bson::Double(millis as f64),
+            Bson::String(ref string) => Bson::Double(string.trim().parse().map_err(|_| unparsable(string))?),
+            ref other => Bson::Double(number(other).ok_or_else(|| unsupported(other, target))?)
+        },
+        "decimal" => match *value {
+            Bson::Boolean(flag) => Bson::Decimal128(Decimal::from_i64(i64::from(flag)).to_bytes()),
+            Bson::DateTime(millis) => Bson::Decimal128(Decimal::from_i64(millis).to_bytes()),
+            Bson::String(ref string) => {
+                Bson::Decimal128(Decimal::parse(string.trim()).ok_or_else(|| unparsable(string))?.to_bytes())
+            },
+            ref other => Bson::Decimal128(Decimal::from_number(other).ok_or_else(|| unsupported(other, target))?.to_bytes())
+        },
+        "int" | "long" => {
+            let result = match *value {
+                Bson::Boolean(flag) => i64::from(flag),
+                Bson::Int32(number) => i64::from(number),
+                Bson::Int64(number) => number,
+                Bson::Double(number) if number.is_nan() || number.abs() >= 9.2e18 => return Err(overflow()),
+                Bson::Double(number) => number.trunc() as i64,
+                Bson::Decimal128(ref bytes) => Decimal::from_bytes(bytes).truncate().to_i64().ok_or_else(overflow)?,
+                Bson::DateTime(millis) if target == "long" => millis,
+                Bson::String(ref string) => string.parse().map_err(|_| unparsable(string))?,
+                ref other => return Err(unsupported(other, target))
+            };
+            if target == "long" {
+                Bson::Int64(result)
+            } else if result >= i64::from(i32::MIN) && result <= i64::from(i32::MAX) {
+                Bson::Int32(result as i32)
+            } else {
+                return Err(overflow());
+            }
+        },
+        other => return Err(QueryError::new(format!("Unknown type name: {}", other)))
+    })
+}
@@ -0,0 +1,559 @@
+use regex::{Regex, RegexBuilder};
+use std::cmp::Ordering;
+use std::mem;
+use bson::Bson;
+use comparison::{bson_cmp, type_bracket};
+use decimal128::Decimal;
+use document::Document;
+use path::{get_mut, lookup};
+use query_error::QueryError;
+
+/// A MongoDB query filter that can be evaluated against documents in memory,
+/// such as `{ "a.b": { "$gt": 1 }, "$or": [{ "c": "x" }, { "d": { "$exists": false } }] }`.
+///
+/// The supported operators are `$eq`, `$ne`, `$gt`, `$gte`, `$lt`, `$lte`,
+/// `$in`, `$nin`, `$and`, `$or`, `$nor`, `$not`, `$exists`, `$type`,
+/// `$regex` with `$options`, `$size`, `$all`, `$elemMatch` and `$mod`.
+///
+/// Fields are found by dotted path. When a path passes through an array, the
+/// rest of the path is looked up in each element, and a condition on an
+/// array field also matches if any element satisfies it, as on the server.
+/// Values are compared using the BSON sort order, and comparison operators
+/// only match values in the same type bracket as their operand.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    clauses: Vec<Clause>
+}
+
+#[derive(Clone, Debug)]
+enum Clause {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Nor(Vec<Filter>),
+    Field(String, Vec<Condition>)
+}
+
+#[derive(Clone, Debug)]
+enum Condition {
+    Eq(Bson),
+    Compare(Bson, Ordering, bool),
+    In(Vec<Condition>),
+    Exists(bool),
+    Type(Vec<u8>),
+    Regex(Pattern),
+    Size(usize),
+    All(Vec<Condition>),
+    ElemMatch(ElemMatch),
+    Mod(i64, i64),
+    Not(Vec<Condition>)
+}
+
+#[derive(Clone, Debug)]
+struct Pattern {
+    regex: Regex,
+    pattern: String,
+    options: String
+}
+
+#[derive(Clone, Debug)]
+enum ElemMatch {
+    Conditions(Vec<Condition>),
+    Filter(Filter)
+}
+
+/// Implementation for the `Filter` object.
+impl Filter {
+
+    /// Parse a filter document.
+    ///
+    /// # Parameters
+    /// - `filter` - The filter `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the `Filter`, or a `QueryError` if the filter uses
+    /// an unknown operator or an operator is given an invalid operand.
+    pub fn parse(filter: &Document) -> Result<Filter, QueryError> {
+        let mut clauses = Vec::new();
+        for (key, value) in filter.iter() {
+            match key.as_str() {
+                "$and" => clauses.push(Clause::And(parse_filters(key, value)?)),
+                "$or" => clauses.push(Clause::Or(parse_filters(key, value)?)),
+                "$nor" => clauses.push(Clause::Nor(parse_filters(key, value)?)),
+                "$comment" => {},
+                _ if key.starts_with('$') => {
+                    return Err(QueryError::new(format!("unknown top level operator {}", key)));
+                },
+                _ => clauses.push(Clause::Field(key.clone(), parse_conditions(value)?))
+            }
+        }
+        Ok(Filter { clauses })
+    }
+
+    /// Determine if a document matches the filter.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` to test.
+    ///
+    /// # Returns
+    /// True if the document matches.
+    pub fn matches(&self, document: &Document) -> bool {
+        self.clauses.iter().all(|clause| match *clause {
+            Clause::And(ref filters) => filters.iter().all(|filter| filter.matches(document)),
+            Clause::Or(ref filters) => filters.iter().any(|filter| filter.matches(document)),
+            Clause::Nor(ref filters) => !filters.iter().any(|filter| filter.matches(document)),
+            Clause::Field(ref path, ref conditions) => {
+                let values = lookup(document, path);
+                conditions.iter().all(|condition| condition.matches(&values))
+            }
+        })
+    }
+}
+
+/// A condition on a single value, such as the operand of `$pull`: an
+/// operator document applied to the value, a query applied to a document
+/// value, a regular expression, or otherwise a value to compare equal to.
+#[derive(Clone, Debug)]
+pub struct ValueMatcher {
+    elem_match: ElemMatch
+}
+
+/// Implementation for the `ValueMatcher` object.
+impl ValueMatcher {
+
+    /// Parse the condition.
+    ///
+    /// # Parameters
+    /// - `condition` - The condition `Bson` value.
+    ///
+    /// # Returns
+    /// The `Result` with the `ValueMatcher`.
+    pub fn parse(condition: &Bson) -> Result<ValueMatcher, QueryError> {
+        let elem_match = match *condition {
+            Bson::Document(ref document) if !is_operator_document(document) => {
+                ElemMatch::Filter(Filter::parse(document)?)
+            },
+            ref other => ElemMatch::Conditions(parse_conditions(other)?)
+        };
+        Ok(ValueMatcher { elem_match })
+    }
+
+    /// Determine if a value matches the condition.
+    ///
+    /// # Parameters
+    /// - `value` - The `Bson` value.
+    ///
+    /// # Returns
+    /// True if the value matches.
+    pub fn matches(&self, value: &Bson) -> bool {
+        self.elem_match.matches(value)
+    }
+}
+
+/// Determine if a filter has a condition on a path or on a path within it,
+/// including within `$and`, `$or` and `$nor`.
+///
+/// # Parameters
+/// - `filter` - The `Filter`.
+/// - `path` - The dotted path.
+///
+/// # Returns
+/// True if the path is referenced.
+pub fn references(filter: &Filter, path: &str) -> bool {
+    filter.clauses.iter().any(|clause| match *clause {
+        Clause::And(ref filters) | Clause::Or(ref filters) | Clause::Nor(ref filters) => {
+            filters.iter().any(|filter| references(filter, path))
+        },
+        Clause::Field(ref field, _) => {
+            field == path || (field.starts_with(path) && field.as_bytes()[path.len()] == b'.')
+        }
+    })
+}
+
+/// Find the index of the first element of the array at a path that satisfies
+/// a filter, as the positional `$` operator does, by testing the document
+/// with the array reduced to each element in turn.
+///
+/// # Parameters
+/// - `filter` - The `Filter` that matched the document.
+/// - `document` - The `Document`.
+/// - `path` - The dotted path of the array.
+///
+/// # Returns
+/// The index, or `None` if the filter does not reference the path or no
+/// element satisfies it.
+pub fn positional_index(filter: &Filter, document: &Document, path: &str) -> Option<usize> {
+    if !references(filter, path) {
+        return None;
+    }
+    let mut candidate = document.clone();
+    let elements = match get_mut(&mut candidate, path) {
+        Some(&mut Bson::Array(ref mut elements)) => mem::take(elements),
+        _ => return None
+    };
+    elements.iter().position(|element| {
+        if let Some(value) = get_mut(&mut candidate, path) {
+            *value = Bson::Array(vec![element.clone()]);
+        }
+        filter.matches(&candidate)
+    })
+}
+
+impl Condition {
+
+    /// Determine if the values found at a path satisfy the condition.
+    ///
+    /// # Parameters
+    /// - `values` - The values found, with `None` where the path was missing.
+    ///
+    /// # Returns
+    /// True if the condition is satisfied.
+    fn matches(&self, values: &[Option<&Bson>]) -> bool {
+        match *self {
+            Condition::Eq(Bson::Null) => {
+                values.iter().any(Option::is_none) || any_value(values, |value| *value == Bson::Null)
+            },
+            Condition::Eq(ref operand) => any_value(values, |value| equals(value, operand)),
+            Condition::Compare(Bson::Null, _, true) => Condition::Eq(Bson::Null).matches(values),
+            Condition::Compare(ref operand, ordering, or_equal) => any_value(values, |value| {
+                if !comparable(value, operand) {
+                    return false;
+                }
+                let result = bson_cmp(value, operand);
+                result == ordering || (or_equal && result == Ordering::Equal)
+            }),
+            Condition::In(ref conditions) => conditions.iter().any(|condition| condition.matches(values)),
+            Condition::Exists(exists) => values.iter().any(Option::is_some) == exists,
+            Condition::Type(ref types) => any_value(values, |value| types.contains(&value.element_type())),
+            Condition::Regex(ref pattern) => any_value(values, |value| pattern.matches(value)),
+            Condition::Size(size) => values.iter().any(|value| match *value {
+                Some(Bson::Array(elements)) => elements.len() == size,
+                _ => false
+            }),
+            Condition::All(ref conditions) => {
+                !conditions.is_empty() && conditions.iter().all(|condition| condition.matches(values))
+            },
+            Condition::ElemMatch(ref elem_match) => values.iter().any(|value| match *value {
+                Some(Bson::Array(elements)) => elements.iter().any(|element| elem_match.matches(element)),
+                _ => false
+            }),
+            Condition::Mod(divisor, remainder) => any_value(values, |value| {
+                truncated(value).is_some_and(|number| number.wrapping_rem(divisor) == remainder)
+            }),
+            Condition::Not(ref conditions) => !conditions.iter().all(|condition| condition.matches(values))
+        }
+    }
+}
+
+impl Pattern {
+
+    fn new(pattern: &str, options: &str) -> Result<Pattern, QueryError> {
+        let mut builder = RegexBuilder::new(pattern);
+        for option in options.chars() {
+            match option {
+                'i' => builder.case_insensitive(true),
+                'm' => builder.multi_line(true),
+                's' => builder.dot_matches_new_line(true),
+                'x' => builder.ignore_whitespace(true),
+                'u' => &mut builder,
+                other => return Err(QueryError::new(format!("invalid regular expression option {}", other)))
+            };
+        }
+        match builder.build() {
+            Ok(regex) => Ok(Pattern { regex, pattern: pattern.to_string(), options: options.to_string() }),
+            Err(error) => Err(QueryError::new(format!("invalid regular expression /{}/: {}", pattern, error)))
+        }
+    }
+
+    fn matches(&self, value: &Bson) -> bool {
+        match *value {
+            Bson::String(ref string) | Bson::Symbol(ref string) => self.regex.is_match(string),
+            Bson::RegExp(ref pattern, ref options) => *pattern == self.pattern && *options == self.options,
+            _ => false
+        }
+    }
+}
+
+impl ElemMatch {
+
+    fn matches(&self, element: &Bson) -> bool {
+        match *self {
+            ElemMatch::Conditions(ref conditions) => {
+                conditions.iter().all(|condition| condition.matches(&[Some(element)]))
+            },
+            ElemMatch::Filter(ref filter) => match *element {
+                Bson::Document(ref document) => filter.matches(document),
+                _ => false
+            }
+        }
+    }
+}
+
+/// Determine if any value, or any element of an array value, satisfies the
+/// predicate.
+fn any_value<F>(values: &[Option<&Bson>], predicate: F) -> bool where F: Fn(&Bson) -> bool {
+    values.iter().filter_map(|value| *value).any(|value| {
+        predicate(value) || match *value {
+            Bson::Array(ref elements) => elements.iter().any(&predicate),
+            _ => false
+        }
+    })
+}
+
+fn equals(left: &Bson, right: &Bson) -> bool {
+    bson_cmp(left, right) == Ordering::Equal
+}
+
+// Comparison operators only match values of the same type bracket, except
+// that every value compares with MinKey and MaxKey.
+fn comparable(value: &Bson, operand: &Bson) -> bool {
+    match *operand {
+        Bson::MinKey | Bson::MaxKey => true,
+        _ => type_bracket(value) == type_bracket(operand)
+    }
+}
+
+/// Get a numeric value truncated to an integer.
+///
+/// # Parameters
+/// - `value` - The `Bson` value.
+///
+/// # Returns
+/// The integer, or `None` if the value is not a finite number.
+pub fn truncated(value: &Bson) -> Option<i64> {
+    match *value {
+        Bson::Int32(number) => Some(i64::from(number)),
+        Bson::Int64(number) => Some(number),
+        Bson::Double(number) if number.is_finite() && number.abs() < 9.2e18 => Some(number.trunc() as i64),
+        Bson::Decimal128(ref bytes) => Decimal::from_bytes(bytes).truncate().to_i64(),
+        _ => None
+    }
+}
+
+/// Get a numeric value as an integer if it is a whole number.
+///
+/// # Parameters
+/// - `value` - The `Bson` value.
+///
+/// # Returns
+/// The integer, or `None` if the value is not a whole number.
+pub fn whole_number(value: &Bson) -> Option<i64> {
+    match *value {
+        Bson::Double(number) if number.fract() != 0.0 => None,
+        Bson::Decimal128(ref bytes) => Decimal::from_bytes(bytes).to_i64(),
+        _ => truncated(value)
+    }
+}
+
+fn parse_filters(operator: &str, value: &Bson) -> Result<Vec<Filter>, QueryError> {
+    match *value {
+        Bson::Array(ref elements) if !elements.is_empty() => elements.iter().map(|element| match *element {
+            Bson::Document(ref document) => Filter::parse(document),
+            _ => Err(QueryError::new(format!("{} elements must be documents", operator)))
+        }).collect(),
+        _ => Err(QueryError::new(format!("{} must be a non-empty array", operator)))
+    }
+}
+
+fn parse_conditions(value: &Bson) -> Result<Vec<Condition>, QueryError> {
+    match *value {
+        Bson::Document(ref document) if is_operator_document(document) => parse_operators(document),
+        Bson::RegExp(ref pattern, ref options) => Ok(vec![Condition::Regex(Pattern::new(pattern, options)?)]),
+        ref other => Ok(vec![Condition::Eq(other.clone())])
+    }
+}
+
+fn is_operator_document(document: &Document) -> bool {
+    document.iter().next().is_some_and(|(key, _)| key.starts_with('$'))
+}
+
+fn parse_operators(document: &Document) -> Result<Vec<Condition>, QueryError> {
+    let mut conditions = Vec::new();
+    let mut regex = None;
+    let mut options = None;
+    for (key, value) in document.iter() {
+        let condition = match key.as_str() {
+            "$eq" => Condition::Eq(value.clone()),
+            "$ne" => Condition::Not(vec![Condition::Eq(value.clone())]),
+            "$gt" => Condition::Compare(value.clone(), Ordering::Greater, false),
+            "$gte" => Condition::Compare(value.clone(), Ordering::Greater, true),
+            "$lt" => Condition::Compare(value.clone(), Ordering::Less, false),
+            "$lte" => Condition::Compare(value.clone(), Ordering::Less, true),
+            "$in" => Condition::In(parse_values(key, value)?),
+            "$nin" => Condition::Not(vec![Condition::In(parse_values(key, value)?)]),
+            "$exists" => Condition::Exists(truthy(value)),
+            "$type" => Condition::Type(parse_types(value)?),
+            "$size" => match whole_number(value) {
+                Some(size) if size >= 0 => Condition::Size(size as usize),
+                _ => return Err(QueryError::new("$size must be a non-negative whole number"))
+            },
+            "$all" => Condition::All(parse_all(value)?),
+            "$elemMatch" => Condition::ElemMatch(parse_elem_match(value)?),
+            "$mod" => parse_mod(value)?,
+            "$not" => match *value {
+                Bson::Document(ref operators) if is_operator_document(operators) => {
+                    Condition::Not(parse_operators(operators)?)
+                },
+                Bson::RegExp(ref pattern, ref options) => {
+                    Condition::Not(vec![Condition::Regex(Pattern::new(pattern, options)?)])
+                },
+                _ => return Err(QueryError::new("$not must be a regular expression or a document of operators"))
+            },
+            "$regex" => {
+                regex = Some(value);
+                continue;
+            },
+            "$options" => {
+                options = Some(value);
+                continue;
+            },
+            _ if key.starts_with('$') => return Err(QueryError::new(format!("unknown operator {}", key))),
+            _ => return Err(QueryError::new(format!("cannot mix operators and the field {}", key)))
+        };
+        conditions.push(condition);
+    }
+    match (regex, options) {
+        (Some(regex), options) => conditions.push(Condition::Regex(parse_regex(regex, options)?)),
+        (None, Some(_)) => return Err(QueryError::new("$options requires $regex")),
+        (None, None) => {}
+    }
+    Ok(conditions)
+}
+
+fn parse_regex(regex: &Bson, options: Option<&Bson>) -> Result<Pattern, QueryError> {
+    let options = match options {
+        Some(Bson::String(options)) => Some(options.as_str()),
+        Some(_) => return Err(QueryError::new("$options must be a string")),
+        None => None
+    };
+    match *regex {
+        Bson::String(ref pattern) => Pattern::new(pattern, options.unwrap_or("")),
+        Bson::RegExp(ref pattern, ref regex_options) => {
+            Pattern::new(pattern, options.unwrap_or(regex_options))
+        },
+        _ => Err(QueryError::new("$regex must be a string or regular expression"))
+    }
+}
+
+// The values of `$in` and `$nin`, where regular expressions match by pattern.
+fn parse_values(operator: &str, value: &Bson) -> Result<Vec<Condition>, QueryError> {
+    match *value {
+        Bson::Array(ref elements) => elements.iter().map(|element| match *element {
+            Bson::RegExp(ref pattern, ref options) => Ok(Condition::Regex(Pattern::new(pattern, options)?)),
+            Bson::Document(ref document) if is_operator_document(document) => {
+                Err(QueryError::new(format!("{} cannot contain operators", operator)))
+            },
+            ref other => Ok(Condition::Eq(other.clone()))
+        }).collect(),
+        _ => Err(QueryError::new(format!("{} must be an array", operator)))
+    }
+}
+
+fn parse_all(value: &Bson) -> Result<Vec<Condition>, QueryError> {
+    match *value {
+        Bson::Array(ref elements) => elements.iter().map(|element| match *element {
+            Bson::Document(ref document) if is_operator_document(document) => match document.get("$elemMatch") {
+                Some(elem_match) if document.len() == 1 => Ok(Condition::ElemMatch(parse_elem_match(elem_match)?)),
+                _ => Err(QueryError::new("$all can only contain $elemMatch operators"))
+            },
+            Bson::RegExp(ref pattern, ref options) => Ok(Condition::Regex(Pattern::new(pattern, options)?)),
+            ref other => Ok(Condition::Eq(other.clone()))
+        }).collect(),
+        _ => Err(QueryError::new("$all must be an array"))
+    }
+}
+
+fn parse_elem_match(value: &Bson) -> Result<ElemMatch, QueryError> {
+    match *value {
+        Bson::Document(ref document) => {
+            let is_conditions = document.iter().next().is_some_and(|(key, _)| {
+                key.starts_with('$') && key != "$and" && key != "$or" && key != "$nor"
+            });
+            if is_conditions {
+                Ok(ElemMatch::Conditions(parse_operators(document)?))
+            } else {
+                Ok(ElemMatch::Filter(Filter::parse(document)?))
+            }
+        },
+        _ => Err(QueryError::new("$elemMatch must be a document"))
+    }
+}
+
+fn parse_mod(value: &Bson) -> Result<Condition, QueryError> {
+    let operands = match *value {
+        Bson::Array(ref elements) if elements.len() == 2 => (truncated(&elements[0]), truncated(&elements[1])),
+        _ => return Err(QueryError::new("$mod must be an array of a divisor and a remainder"))
+    };
+    match operands {
+        (Some(0), Some(_)) => Err(QueryError::new("$mod divisor cannot be 0")),
+        (Some(divisor), Some(remainder)) => Ok(Condition::Mod(divisor, remainder)),
+        _ => Err(QueryError::new("$mod divisor and remainder must be numbers"))
+    }
+}
+
+// Type names and numbers accepted by `$type`, mapped to element type bytes.
+fn parse_types(value: &Bson) -> Result<Vec<u8>, QueryError> {
+    let mut types = Vec::new();
+    let specs = match *value {
+        Bson::Array(ref elements) => elements.iter().collect(),
+        ref other => vec![other]
+    };
+    for spec in specs {
+        let element_types: &[u8] = match *spec {
+            Bson::String(ref name) => match name.as_str() {
+                "double" => &[0x01],
+                "string" => &[0x02],
+                "object" => &[0x03],
+                "array" => &[0x04],
+                "binData" => &[0x05],
+                "undefined" => &[0x06],
+                "objectId" => &[0x07],
+                "bool" => &[0x08],
+                "date" => &[0x09],
+                "null" => &[0x0A],
+                "regex" => &[0x0B],
+                "dbPointer" => &[0x0C],
+                "javascript" => &[0x0D],
+                "symbol" => &[0x0E],
+                "javascriptWithScope" => &[0x0F],
+                "int" => &[0x10],
+                "timestamp" => &[0x11],
+                "long" => &[0x12],
+                "decimal" => &[0x13],
+                "minKey" => &[0xFF],
+                "maxKey" => &[0x7F],
+                "number" => &[0x01, 0x10, 0x12, 0x13],
+                _ => return Err(QueryError::new(format!("unknown $type alias {}", name)))
+            },
+            _ => match whole_number(spec) {
+                Some(-1) => &[0xFF],
+                Some(code) if (1..=19).contains(&code) || code == 127 => {
+                    types.push(code as u8);
+                    continue;
+                },
+                _ => return Err(QueryError::new("$type must be a type name or number"))
+            }
+        };
+        types.extend_from_slice(element_types);
+    }
+    Ok(types)
+}
+
+/// Determine the truthiness of a value as the server does for flags such as
+/// `$exists`.
+///
+/// # Parameters
+/// - `value` - The `Bson` value.
+///
+/// # Returns
+/// False for `false`, zero, null and undefined, otherwise true.
+pub fn truthy(value: &Bson) -> bool {
+    match *value {
+        Bson::Boolean(flag) => flag,
+        Bson::Int32(number) => number != 0,
+        Bson::Int64(number) => number != 0,
+        Bson::Double(number) => number != 0.0,
+        Bson::Decimal128(ref bytes) => Decimal::from_bytes(bytes).compare(&Decimal::from_i64(0)) != Ordering::Equal,
+        Bson::Null | Bson::Undefined => false,
+        _ => true
+    }
+}
@@ -0,0 +1,267 @@
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use bson::Bson;
+use comparison::bson_cmp;
+use decimal128::Decimal;
+use document::Document;
+
+/// A wrapper around a `Bson` value that implements `Eq` and `Hash`, so it can
+/// be used as a `HashMap` key or in a `HashSet`.
+///
+/// Doubles are compared and hashed by their canonical value: all `NaN`s are
+/// equal to each other and `-0.0` is equal to `0.0`. Decimals are likewise
+/// compared by value, so `1.50` is equal to `1.5`. By default values of
+/// different numeric types are never equal. A wrapper created with
+/// `HashableBson::numeric` instead treats numerically equal `Int32`, `Int64`,
+/// `Double` and `Decimal128` values as equal and hashes them identically.
+#[derive(Clone, Debug)]
+pub struct HashableBson {
+    value: Bson,
+    numeric: bool
+}
+
+/// The implementation for `HashableBson`.
+impl HashableBson {
+
+    /// Create a new `HashableBson` that compares numbers by type and value.
+    ///
+    /// # Parameters
+    /// - `value` - The `Bson` value to wrap.
+    ///
+    /// # Returns
+    /// The new `HashableBson`.
+    pub fn new(value: Bson) -> HashableBson {
+        HashableBson { value, numeric: false }
+    }
+
+    /// Create a new `HashableBson` that compares numbers by value only.
+    ///
+    /// # Parameters
+    /// - `value` - The `Bson` value to wrap.
+    ///
+    /// # Returns
+    /// The new `HashableBson`.
+    pub fn numeric(value: Bson) -> HashableBson {
+        HashableBson { value, numeric: true }
+    }
+
+    /// Get the wrapped value.
+    ///
+    /// # Returns
+    /// The `Bson` value.
+    pub fn value(&self) -> &Bson {
+        &self.value
+    }
+
+    /// Unwrap the value.
+    ///
+    /// # Returns
+    /// The `Bson` value.
+    pub fn into_inner(self) -> Bson {
+        self.value
+    }
+}
+
+impl PartialEq for HashableBson {
+    fn eq(&self, other: &HashableBson) -> bool {
+        self.numeric == other.numeric && bson_eq(&self.value, &other.value, self.numeric)
+    }
+}
+
+impl Eq for HashableBson {}
+
+impl Hash for HashableBson {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_bson(&self.value, self.numeric, state);
+    }
+}
+
+/// A wrapper around a `Document` that implements `Eq` and `Hash`, following
+/// the same rules as `HashableBson`. Documents are hashed in key order, so
+/// documents with the same elements in a different order are not equal.
+#[derive(Clone, Debug)]
+pub struct HashableDocument {
+    document: Document,
+    numeric: bool
+}
+
+/// The implementation for `HashableDocument`.
+impl HashableDocument {
+
+    /// Create a new `HashableDocument` that compares numbers by type and value.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` to wrap.
+    ///
+    /// # Returns
+    /// The new `HashableDocument`.
+    pub fn new(document: Document) -> HashableDocument {
+        HashableDocument { document, numeric: false }
+    }
+
+    /// Create a new `HashableDocument` that compares numbers by value only.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` to wrap.
+    ///
+    /// # Returns
+    /// The new `HashableDocument`.
+    pub fn numeric(document: Document) -> HashableDocument {
+        HashableDocument { document, numeric: true }
+    }
+
+    /// Get the wrapped document.
+    ///
+    /// # Returns
+    /// The `Document`.
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Unwrap the document.
+    ///
+    /// # Returns
+    /// The `Document`.
+    pub fn into_inner(self) -> Document {
+        self.document
+    }
+}
+
+impl PartialEq for HashableDocument {
+    fn eq(&self, other: &HashableDocument) -> bool {
+        self.numeric == other.numeric && document_eq(&self.document, &other.document, self.numeric)
+    }
+}
+
+impl Eq for HashableDocument {}
+
+impl Hash for HashableDocument {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_document(&self.document, self.numeric, state);
+    }
+}
+
+/// Hashes the value consistently with its `PartialEq` implementation, so
+/// `-0.0` and `0.0` hash identically.
+impl Hash for Bson {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_bson(self, false, state);
+    }
+}
+
+/// Hashes the elements in key order.
+impl Hash for Document {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_document(self, false, state);
+    }
+}
+
+fn canonical_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        0x7ff8_0000_0000_0000
+    } else if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
+fn is_number(value: &Bson) -> bool {
+    matches!(value, Bson::Double(_) | Bson::Int32(_) | Bson::Int64(_) | Bson::Decimal128(_))
+}
+
+fn bson_eq(left: &Bson, right: &Bson, numeric: bool) -> bool {
+    if numeric && is_number(left) && is_number(right) {
+        return bson_cmp(left, right) == Ordering::Equal;
+    }
+    match (left, right) {
+        (Bson::Double(l), Bson::Double(r)) => canonical_bits(*l) == canonical_bits(*r),
+        (Bson::Decimal128(l), Bson::Decimal128(r)) => Decimal::from_bytes(l).compare(&Decimal::from_bytes(r)) == Ordering::Equal,
+        (Bson::Document(l), Bson::Document(r)) => document_eq(l, r, numeric),
+        (Bson::Array(l), Bson::Array(r)) => {
+            l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)| bson_eq(l, r, numeric))
+        },
+        (Bson::Code(lc, ls), Bson::Code(rc, rs)) => lc == rc && document_eq(ls, rs, numeric),
+        _ => left == right
+    }
+}
+
+fn document_eq(left: &Document, right: &Document, numeric: bool) -> bool {
+    left.len() == right.len() && left.iter().zip(right.iter()).all(|((lk, lv), (rk, rv))| {
+        lk == rk && bson_eq(lv, rv, numeric)
+    })
+}
+
+fn hash_bson<H: Hasher>(value: &Bson, numeric: bool, state: &mut H) {
+    if numeric && is_number(value) {
+        return hash_number(value, state);
+    }
+    match value {
+        Bson::Double(v) => { 0x01u8.hash(state); canonical_bits(*v).hash(state); },
+        Bson::String(v) => { 0x02u8.hash(state); v.hash(state); },
+        Bson::Document(v) => { 0x03u8.hash(state); hash_document(v, numeric, state); },
+        Bson::Array(v) => {
+            0x04u8.hash(state);
+            v.len().hash(state);
+            for element in v {
+                hash_bson(element, numeric, state);
+            }
+        },
+        Bson::Binary(t, v) => { 0x05u8.hash(state); t.hash(state); v.hash(state); },
+        Bson::Undefined => 0x06u8.hash(state),
+        Bson::ObjectId(v) => { 0x07u8.hash(state); v.hash(state); },
+        Bson::Boolean(v) => { 0x08u8.hash(state); v.hash(state); },
+        Bson::DateTime(v) => { 0x09u8.hash(state); v.hash(state); },
+        Bson::Null => 0x0Au8.hash(state),
+        Bson::RegExp(p, o) => { 0x0Bu8.hash(state); p.hash(state); o.hash(state); },
+        Bson::DbPointer(n, id) => { 0x0Cu8.hash(state); n.hash(state); id.hash(state); },
+        Bson::Code(c, s) => { 0x0Du8.hash(state); c.hash(state); hash_document(s, numeric, state); },
+        Bson::Symbol(v) => { 0x0Eu8.hash(state); v.hash(state); },
+        Bson::Int32(v) => { 0x10u8.hash(state); v.hash(state); },
+        Bson::Timestamp(v) => { 0x11u8.hash(state); v.hash(state); },
+        Bson::Int64(v) => { 0x12u8.hash(state); v.hash(state); },
+        Bson::Decimal128(v) => { 0x13u8.hash(state); Decimal::from_bytes(v).normalize().hash(state); },
+        Bson::MinKey => 0xFFu8.hash(state),
+        Bson::MaxKey => 0x7Fu8.hash(state)
+    }
+}
+
+// Numbers that are whole and fit in an `i64` hash as that integer, so that
+// `Int32(1)`, `Int64(1)`, `Double(1.0)` and `Decimal128(1.0)` all produce the
+// same hash. Other decimals hash as the double they equal, if there is one.
+fn hash_number<H: Hasher>(value: &Bson, state: &mut H) {
+    0x10u8.hash(state);
+    match *value {
+        Bson::Int32(v) => i64::from(v).hash(state),
+        Bson::Int64(v) => v.hash(state),
+        Bson::Double(v) => hash_double(v, state),
+        Bson::Decimal128(ref v) => {
+            let decimal = Decimal::from_bytes(v);
+            let double = decimal.to_f64();
+            if let Some(integer) = decimal.to_i64() {
+                integer.hash(state);
+            } else if decimal.compare(&Decimal::from_f64(double)) == Ordering::Equal {
+                hash_double(double, state);
+            } else {
+                decimal.normalize().hash(state);
+            }
+        },
+        _ => unreachable!()
+    }
+}
+
+fn hash_double<H: Hasher>(value: f64, state: &mut H) {
+    if value.trunc() == value && (-9223372036854775808.0..9223372036854775808.0).contains(&value) {
+        (value as i64).hash(state);
+    } else {
+        canonical_bits(value).hash(state);
+    }
+}
+
+fn hash_document<H: Hasher>(document: &Document, numeric: bool, state: &mut H) {
+    document.len().hash(state);
+    for (key, value) in document.iter() {
+        key.hash(state);
+        hash_bson(value, numeric, state);
+    }
+}
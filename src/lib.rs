@@ -1,14 +1,102 @@
 extern crate byteorder;
+#[cfg(feature = "codec")]
+extern crate bytes;
+#[cfg(feature = "zlib")]
+extern crate flate2;
+#[cfg(feature = "async")]
+extern crate futures_core;
 extern crate linked_hash_map;
+extern crate regex;
+#[cfg(feature = "snappy")]
+extern crate snap;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "codec")]
+extern crate tokio_util;
+#[cfg(feature = "zstd")]
+extern crate zstd;
 
+#[cfg(feature = "async")]
+pub use async_io::{read_document_async, write_document_async, AsyncDocumentReader, ReadDocument, WriteDocument};
 pub use bson::Bson;
+#[cfg(feature = "codec")]
+pub use codec::{BsonCodec, DEFAULT_MAX_FRAME_LENGTH};
+pub use collection::{Collection, FindOptions, UpdateOptions, UpdateResult};
+pub use command::{
+    AggregateCommand, Command, CommandOptions, CreateIndexesCommand, DeleteCommand, FindAndModifyCommand, FindCommand,
+    GetMoreCommand, IndexModel, InsertCommand, ReadConcern, UpdateCommand, UpdateStatement, WriteConcern
+};
+pub use command_error::CommandError;
+pub use command_reply::{CursorReply, FindAndModifyReply, WriteError, WriteReply};
+pub use comparison::{bson_cmp, document_cmp, type_bracket};
+pub use compression::{negotiate_compressor, Compressor};
+pub use decimal128::{format_decimal128, parse_decimal128};
 pub use document::Document;
+pub use document_deserializer::DocumentDeserializer;
+pub use document_reader::{DocumentReader, RawDocumentReader, DEFAULT_RECOVERY_MAX_DOCUMENT_SIZE};
 pub use document_serializer::DocumentSerializer;
+pub use document_writer::DocumentWriter;
+pub use expression::Expression;
+pub use filter::Filter;
+pub use hashable::{HashableBson, HashableDocument};
+pub use mock_server::MockServer;
+pub use object_id::new_object_id;
+pub use pipeline::{Documents, Pipeline};
+pub use projection::{project, Projection};
+pub use push_parser::{ParseEvent, PushParser};
+pub use query_error::QueryError;
+pub use raw_document::RawDocumentBuf;
+pub use shell_parser::{ShellParseError, ShellParser};
+pub use sort_spec::SortSpec;
+pub use store::{Scan, Store};
 pub use type_serializer::TypeSerializer;
+pub use update::{apply_update, Update};
+pub use visitor::{visit_bytes, Visit, Visitor};
+pub use walk::WalkAction;
+pub use wire::{
+    DocumentSequence, Message, MsgHeader, OpCompressed, OpMsg, OpQuery, OpReply, DEFAULT_MAX_MESSAGE_SIZE, OP_COMPRESSED,
+    OP_MSG, OP_QUERY, OP_REPLY
+};
 
+#[cfg(feature = "async")]
+mod async_io;
+mod base64;
 #[macro_use]
 mod bson;
+#[cfg(feature = "codec")]
+mod codec;
+mod collection;
+mod command;
+mod command_error;
+mod command_reply;
+mod comparison;
+mod compression;
+mod crc32c;
+mod datetime;
+mod decimal128;
+mod display;
 #[macro_use]
 mod document;
+mod document_deserializer;
+mod document_reader;
 mod document_serializer;
+mod document_writer;
+mod expression;
+mod filter;
+mod hashable;
+mod mock_server;
+mod object_id;
+mod path;
+mod pipeline;
+mod projection;
+mod push_parser;
+mod query_error;
+mod raw_document;
+mod shell_parser;
+mod sort_spec;
+mod store;
 mod type_serializer;
+mod update;
+mod visitor;
+mod walk;
+mod wire;
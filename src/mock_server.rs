@@ -0,0 +1,655 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use bson::Bson;
+use collection::{Collection, FindOptions, UpdateOptions, UpdateResult};
+use command_error::CommandError;
+use compression::Compressor;
+use datetime;
+use document::Document;
+use filter::{whole_number, Filter};
+use pipeline::Pipeline;
+use query_error::QueryError;
+use wire::{Message, OpMsg, OpReply, DEFAULT_MAX_MESSAGE_SIZE};
+
+// The number of documents in the first batch of a cursor when the command
+// does not give a batch size, as on the server.
+const DEFAULT_BATCH_SIZE: usize = 101;
+
+/// The `MockServer` object, a stand-in for `mongod` listening on a loopback
+/// port, for testing client code without a real server.
+///
+/// The server speaks `OP_MSG`, and legacy `OP_QUERY` for the initial
+/// handshake, and answers `hello`/`isMaster`, `ping`, `buildInfo`, `find`,
+/// `getMore`, `killCursors`, `insert`, `update`, `delete` and `aggregate`
+/// from in-memory `Collection`s, with cursors and command error documents as
+/// the server returns them. Replies can be scripted with `respond` and
+/// `fail`, which queue a reply for the next command of a name ahead of the
+/// built-in handling. A `hello` listing compressors is answered with those
+/// that are enabled, and compressed requests get compressed replies. Each
+/// connection is served on its own thread, and the server shuts down when
+/// dropped.
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+    running: Arc<AtomicBool>,
+    connections: Arc<Mutex<Vec<TcpStream>>>,
+    acceptor: Option<JoinHandle<()>>
+}
+
+// The state shared by the connections.
+#[derive(Default)]
+struct State {
+    collections: HashMap<String, Collection>,
+    cursors: HashMap<i64, Cursor>,
+    next_cursor_id: i64,
+    next_connection_id: i32,
+    responses: HashMap<String, VecDeque<Document>>,
+    received: Vec<Document>
+}
+
+// The documents remaining in an open cursor.
+struct Cursor {
+    namespace: String,
+    documents: VecDeque<Document>
+}
+
+/// Implementation for the `MockServer` object.
+impl MockServer {
+
+    /// Start a server on a free port of 127.0.0.1.
+    ///
+    /// # Returns
+    /// The `Result` with the running `MockServer`.
+    pub fn start() -> io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let running = Arc::new(AtomicBool::new(true));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let acceptor = {
+            let state = state.clone();
+            let running = running.clone();
+            let connections = connections.clone();
+            thread::spawn(move || accept(listener, state, running, connections))
+        };
+        Ok(MockServer { address, state, running, connections, acceptor: Some(acceptor) })
+    }
+
+    /// Get the address the server listens on.
+    ///
+    /// # Returns
+    /// The `SocketAddr`.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Get a connection string for the server.
+    ///
+    /// # Returns
+    /// The URI, such as `mongodb://127.0.0.1:27017`.
+    pub fn uri(&self) -> String {
+        format!("mongodb://{}", self.address)
+    }
+
+    /// Queue a reply for the next command of a name, in place of the
+    /// built-in handling. Replies queued for the same command are returned
+    /// in turn.
+    ///
+    /// # Parameters
+    /// - `command` - The command name, such as `find`.
+    /// - `reply` - The reply `Document`.
+    pub fn respond(&self, command: &str, reply: Document) {
+        self.lock().responses.entry(command.to_string()).or_default().push_back(reply);
+    }
+
+    /// Queue a command error for the next command of a name.
+    ///
+    /// # Parameters
+    /// - `command` - The command name, such as `insert`.
+    /// - `code` - The error code, such as 91.
+    /// - `code_name` - The name of the code, such as `ShutdownInProgress`.
+    /// - `message` - The error message.
+    pub fn fail(&self, command: &str, code: i32, code_name: &str, message: &str) {
+        self.respond(command, CommandError::new(code, code_name, message).to_document());
+    }
+
+    /// Access a collection of the server, creating it if it does not exist,
+    /// such as to seed documents or check the effect of writes.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `name` - The collection name.
+    /// - `f` - The function given the `Collection`.
+    ///
+    /// # Returns
+    /// The result of the function.
+    pub fn collection<F, T>(&self, database: &str, name: &str, f: F) -> T where F: FnOnce(&mut Collection) -> T {
+        let namespace = format!("{}.{}", database, name);
+        let mut state = self.lock();
+        f(state.collections.entry(namespace.clone()).or_insert_with(|| Collection::new(&namespace)))
+    }
+
+    /// Get the commands received so far, in order, including document
+    /// sequences as array fields.
+    ///
+    /// # Returns
+    /// The command `Document`s.
+    pub fn received(&self) -> Vec<Document> {
+        self.lock().received.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.address);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+        for connection in lock(&self.connections).drain(..) {
+            let _ = connection.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Implementation for the `State` object.
+impl State {
+
+    /// Answer a command.
+    ///
+    /// # Parameters
+    /// - `database` - The database the command runs on.
+    /// - `command` - The command `Document`.
+    /// - `connection_id` - The identifier of the connection.
+    ///
+    /// # Returns
+    /// The reply `Document`.
+    fn run(&mut self, database: &str, command: &Document, connection_id: i32) -> Document {
+        self.received.push(command.clone());
+        let name = command.iter().next().map(|(name, _)| name.clone()).unwrap_or_default();
+        if let Some(reply) = self.responses.get_mut(&name).and_then(VecDeque::pop_front) {
+            return reply;
+        }
+        let reply = match name.as_str() {
+            "hello" | "isMaster" | "ismaster" => Ok(hello(&name, command, connection_id)),
+            "ping" => Ok(document! { "ok" => 1.0 }),
+            "buildInfo" | "buildinfo" => Ok(build_info()),
+            "find" => self.find(database, command),
+            "getMore" => self.get_more(database, command),
+            "killCursors" => self.kill_cursors(database, command),
+            "insert" => self.insert(database, command),
+            "update" => self.update(database, command),
+            "delete" => self.delete(database, command),
+            "aggregate" => self.aggregate(database, command),
+            _ => Err(CommandError::new(59, "CommandNotFound", format!("no such command: '{}'", name)))
+        };
+        reply.unwrap_or_else(|error| error.to_document())
+    }
+
+    fn find(&mut self, database: &str, command: &Document) -> CommandResult {
+        let namespace = namespace(database, command, "find")?;
+        let filter = document_field(command, "find", "filter")?.unwrap_or_else(Document::new);
+        let mut options = FindOptions::new();
+        if let Some(projection) = document_field(command, "find", "projection")? {
+            options = options.projection(projection);
+        }
+        if let Some(sort) = document_field(command, "find", "sort")? {
+            options = options.sort(sort);
+        }
+        if let Some(skip) = count_field(command, "find", "skip")? {
+            options = options.skip(skip);
+        }
+        let mut single_batch = bool_field(command, "find", "singleBatch")?;
+        match number_field(command, "find", "limit")? {
+            Some(limit) if limit < 0 => {
+                options = options.limit(limit.unsigned_abs() as usize);
+                single_batch = true;
+            },
+            Some(limit) if limit > 0 => options = options.limit(limit as usize),
+            _ => {}
+        }
+        let batch_size = count_field(command, "find", "batchSize")?.unwrap_or(DEFAULT_BATCH_SIZE);
+        let documents = match self.collections.get(&namespace) {
+            Some(collection) => collection.find(&filter, &options)?,
+            None => {
+                Filter::parse(&filter)?;
+                Vec::new()
+            }
+        };
+        Ok(self.cursor_reply(namespace, documents, batch_size, single_batch))
+    }
+
+    fn get_more(&mut self, database: &str, command: &Document) -> CommandResult {
+        let id = match command.get("getMore") {
+            Some(&Bson::Int64(id)) => id,
+            Some(value) => return Err(wrong_type("getMore", "getMore", value, "long")),
+            None => return Err(missing("getMore", "getMore"))
+        };
+        let collection = match command.get("collection") {
+            Some(Bson::String(collection)) => collection,
+            Some(value) => return Err(wrong_type("getMore", "collection", value, "string")),
+            None => return Err(missing("getMore", "collection"))
+        };
+        let namespace = format!("{}.{}", database, collection);
+        let batch_size = count_field(command, "getMore", "batchSize")?.unwrap_or(usize::MAX);
+        let (batch, exhausted) = match self.cursors.get_mut(&id) {
+            Some(cursor) if cursor.namespace == namespace => {
+                let count = batch_size.min(cursor.documents.len());
+                let batch: Vec<Bson> = cursor.documents.drain(..count).map(Bson::Document).collect();
+                (batch, cursor.documents.is_empty())
+            },
+            Some(cursor) => return Err(CommandError::new(13, "Unauthorized", format!(
+                "Requested getMore on namespace '{}', but cursor belongs to a different namespace {}", namespace, cursor.namespace
+            ))),
+            None => return Err(CommandError::new(43, "CursorNotFound", format!("cursor id {} not found", id)))
+        };
+        if exhausted {
+            self.cursors.remove(&id);
+        }
+        Ok(document! {
+            "cursor" => { "nextBatch" => (Bson::Array(batch)), "id" => (if exhausted { 0i64 } else { id }), "ns" => namespace },
+            "ok" => 1.0
+        })
+    }
+
+    fn kill_cursors(&mut self, database: &str, command: &Document) -> CommandResult {
+        let namespace = namespace(database, command, "killCursors")?;
+        let mut killed = Vec::new();
+        let mut not_found = Vec::new();
+        for id in array_field(command, "killCursors", "cursors")? {
+            let id = match id {
+                Bson::Int64(id) => id,
+                ref value => return Err(wrong_type("killCursors", "cursors", value, "long"))
+            };
+            match self.cursors.get(&id) {
+                Some(cursor) if cursor.namespace == namespace => {
+                    self.cursors.remove(&id);
+                    killed.push(Bson::Int64(id));
+                },
+                _ => not_found.push(Bson::Int64(id))
+            }
+        }
+        Ok(document! {
+            "cursorsKilled" => (Bson::Array(killed)),
+            "cursorsNotFound" => (Bson::Array(not_found)),
+            "cursorsAlive" => [],
+            "cursorsUnknown" => [],
+            "ok" => 1.0
+        })
+    }
+
+    fn insert(&mut self, database: &str, command: &Document) -> CommandResult {
+        let namespace = namespace(database, command, "insert")?;
+        let documents = array_field(command, "insert", "documents")?;
+        let ordered = command.get("ordered").map_or(Ok(true), |_| bool_field(command, "insert", "ordered"))?;
+        let collection = self.collection(&namespace);
+        let mut inserted = 0usize;
+        let mut errors = Vec::new();
+        for (index, document) in documents.into_iter().enumerate() {
+            let result = match document {
+                Bson::Document(document) => collection.insert_one(document).map(|_| ()),
+                _ => Err(QueryError::new("Document to insert must be an object"))
+            };
+            match result {
+                Ok(()) => inserted += 1,
+                Err(error) => {
+                    errors.push(write_error(index, &error));
+                    if ordered {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(write_reply(document! { "n" => (inserted as i32) }, errors))
+    }
+
+    fn update(&mut self, database: &str, command: &Document) -> CommandResult {
+        let namespace = namespace(database, command, "update")?;
+        let updates = array_field(command, "update", "updates")?;
+        let ordered = command.get("ordered").map_or(Ok(true), |_| bool_field(command, "update", "ordered"))?;
+        let collection = self.collection(&namespace);
+        let mut matched = 0;
+        let mut modified = 0;
+        let mut upserted = Vec::new();
+        let mut errors = Vec::new();
+        for (index, statement) in updates.into_iter().enumerate() {
+            match update_one(collection, statement) {
+                Ok(result) => {
+                    matched += result.matched_count;
+                    modified += result.modified_count;
+                    if let Some(id) = result.upserted_id {
+                        matched += 1;
+                        upserted.push(Bson::Document(document! { "index" => (index as i32), "_id" => id }));
+                    }
+                },
+                Err(error) => {
+                    errors.push(write_error(index, &error));
+                    if ordered {
+                        break;
+                    }
+                }
+            }
+        }
+        let mut reply = document! { "n" => (matched as i32), "nModified" => (modified as i32) };
+        if !upserted.is_empty() {
+            reply.insert("upserted".to_string(), Bson::Array(upserted));
+        }
+        Ok(write_reply(reply, errors))
+    }
+
+    fn delete(&mut self, database: &str, command: &Document) -> CommandResult {
+        let namespace = namespace(database, command, "delete")?;
+        let deletes = array_field(command, "delete", "deletes")?;
+        let ordered = command.get("ordered").map_or(Ok(true), |_| bool_field(command, "delete", "ordered"))?;
+        let collection = self.collection(&namespace);
+        let mut deleted = 0;
+        let mut errors = Vec::new();
+        for (index, statement) in deletes.into_iter().enumerate() {
+            match delete_one(collection, statement) {
+                Ok(count) => deleted += count,
+                Err(error) => {
+                    errors.push(write_error(index, &error));
+                    if ordered {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(write_reply(document! { "n" => (deleted as i32) }, errors))
+    }
+
+    fn aggregate(&mut self, database: &str, command: &Document) -> CommandResult {
+        let namespace = match command.get("aggregate") {
+            Some(Bson::String(collection)) => Some(format!("{}.{}", database, collection)),
+            Some(value) if whole_number(value) == Some(1) => None,
+            Some(value) => return Err(wrong_type("aggregate", "aggregate", value, "string")),
+            None => return Err(missing("aggregate", "aggregate"))
+        };
+        let mut stages = Vec::new();
+        for stage in array_field(command, "aggregate", "pipeline")? {
+            match stage {
+                Bson::Document(stage) => stages.push(stage),
+                ref value => return Err(wrong_type("aggregate", "pipeline", value, "object"))
+            }
+        }
+        let batch_size = match document_field(command, "aggregate", "cursor")? {
+            Some(cursor) => count_field(&cursor, "aggregate", "batchSize")?.unwrap_or(DEFAULT_BATCH_SIZE),
+            None => return Err(CommandError::new(
+                9, "FailedToParse", "The 'cursor' option is required, except for aggregate with the explain argument".to_string()
+            ))
+        };
+        let pipeline = Pipeline::parse(&stages)?;
+        let input = match namespace.as_ref().and_then(|namespace| self.collections.get(namespace)) {
+            Some(collection) => collection.find(&Document::new(), &FindOptions::new())?,
+            None => Vec::new()
+        };
+        let documents = pipeline.run(input).collect::<Result<Vec<Document>, QueryError>>()?;
+        let namespace = namespace.unwrap_or_else(|| format!("{}.$cmd.aggregate", database));
+        Ok(self.cursor_reply(namespace, documents, batch_size, false))
+    }
+
+    /// Reply with the first batch of a cursor, keeping any remaining
+    /// documents for `getMore`.
+    fn cursor_reply(&mut self, namespace: String, documents: Vec<Document>, batch_size: usize, single_batch: bool) -> Document {
+        let mut documents: VecDeque<Document> = documents.into();
+        let count = batch_size.min(documents.len());
+        let first: Vec<Bson> = documents.drain(..count).map(Bson::Document).collect();
+        let id = if documents.is_empty() || single_batch {
+            0
+        } else {
+            self.next_cursor_id += 1;
+            self.cursors.insert(self.next_cursor_id, Cursor { namespace: namespace.clone(), documents });
+            self.next_cursor_id
+        };
+        document! {
+            "cursor" => { "firstBatch" => (Bson::Array(first)), "id" => id, "ns" => namespace },
+            "ok" => 1.0
+        }
+    }
+
+    fn collection(&mut self, namespace: &str) -> &mut Collection {
+        self.collections.entry(namespace.to_string()).or_insert_with(|| Collection::new(namespace))
+    }
+}
+
+type CommandResult<T = Document> = Result<T, CommandError>;
+
+/// Accept connections until the server is dropped.
+fn accept(listener: TcpListener, state: Arc<Mutex<State>>, running: Arc<AtomicBool>, connections: Arc<Mutex<Vec<TcpStream>>>) {
+    for stream in listener.incoming() {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue
+        };
+        if let Ok(clone) = stream.try_clone() {
+            lock(&connections).push(clone);
+        }
+        let connection_id = {
+            let mut state = lock(&state);
+            state.next_connection_id += 1;
+            state.next_connection_id
+        };
+        let state = state.clone();
+        thread::spawn(move || serve(stream, state, connection_id));
+    }
+}
+
+/// Answer the messages on a connection until it closes or sends a message
+/// that cannot be answered.
+fn serve(mut stream: TcpStream, state: Arc<Mutex<State>>, connection_id: i32) {
+    let mut request_id = 0;
+    loop {
+        let (header, message) = match Message::read_from(&mut stream, DEFAULT_MAX_MESSAGE_SIZE) {
+            Ok(message) => message,
+            Err(_) => return
+        };
+        let (message, compressor) = match message {
+            Message::Compressed(compressed) => match compressed.decompress() {
+                Ok(message) => (message, Compressor::from_id(compressed.compressor_id)),
+                Err(_) => return
+            },
+            message => (message, None)
+        };
+        let reply = match message {
+            Message::Msg(msg) => {
+                let command = msg.command();
+                let database = match command.get("$db") {
+                    Some(Bson::String(database)) => database.clone(),
+                    _ => "admin".to_string()
+                };
+                let reply = lock(&state).run(&database, &command, connection_id);
+                if msg.flags & OpMsg::MORE_TO_COME != 0 {
+                    continue;
+                }
+                Message::Msg(OpMsg::new(reply))
+            },
+            Message::Query(query) => {
+                let database = query.full_collection_name.split('.').next().unwrap_or_default().to_string();
+                let command = match query.query.get("$query") {
+                    Some(Bson::Document(command)) => command.clone(),
+                    _ => query.query
+                };
+                Message::Reply(OpReply::new(lock(&state).run(&database, &command, connection_id)))
+            },
+            _ => return
+        };
+        let reply = match compressor {
+            Some(compressor) => match reply.compress(compressor) {
+                Ok(reply) => reply,
+                Err(_) => return
+            },
+            None => reply
+        };
+        request_id += 1;
+        let written = reply.encode(request_id, header.request_id).and_then(|bytes| stream.write_all(&bytes));
+        if written.is_err() {
+            return;
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn hello(name: &str, command: &Document, connection_id: i32) -> Document {
+    let mut reply = document! { "helloOk" => true };
+    reply.insert(if name == "hello" { "isWritablePrimary" } else { "ismaster" }.to_string(), Bson::Boolean(true));
+    reply.insert("maxBsonObjectSize".to_string(), Bson::Int32(16 * 1024 * 1024));
+    reply.insert("maxMessageSizeBytes".to_string(), Bson::Int32(DEFAULT_MAX_MESSAGE_SIZE as i32));
+    reply.insert("maxWriteBatchSize".to_string(), Bson::Int32(100_000));
+    reply.insert("localTime".to_string(), Bson::DateTime(datetime::now()));
+    reply.insert("logicalSessionTimeoutMinutes".to_string(), Bson::Int32(30));
+    reply.insert("connectionId".to_string(), Bson::Int32(connection_id));
+    reply.insert("minWireVersion".to_string(), Bson::Int32(0));
+    reply.insert("maxWireVersion".to_string(), Bson::Int32(17));
+    reply.insert("readOnly".to_string(), Bson::Boolean(false));
+    if let Some(Bson::Array(names)) = command.get("compression") {
+        let available = names.iter().filter(|name| match name {
+            Bson::String(name) => Compressor::from_name(name).is_some_and(Compressor::is_available),
+            _ => false
+        }).cloned().collect();
+        reply.insert("compression".to_string(), Bson::Array(available));
+    }
+    reply.insert("ok".to_string(), Bson::Double(1.0));
+    reply
+}
+
+fn build_info() -> Document {
+    document! {
+        "version" => "6.0.0",
+        "versionArray" => (Bson::Array(vec![Bson::Int32(6), Bson::Int32(0), Bson::Int32(0), Bson::Int32(0)])),
+        "bits" => 64,
+        "maxBsonObjectSize" => (16 * 1024 * 1024),
+        "ok" => 1.0
+    }
+}
+
+fn write_error(index: usize, error: &QueryError) -> Bson {
+    let code = if error.message().starts_with("E11000") { 11000 } else { 2 };
+    Bson::Document(document! { "index" => (index as i32), "code" => code, "errmsg" => (error.message()) })
+}
+
+fn write_reply(mut reply: Document, errors: Vec<Bson>) -> Document {
+    if !errors.is_empty() {
+        reply.insert("writeErrors".to_string(), Bson::Array(errors));
+    }
+    reply.insert("ok".to_string(), Bson::Double(1.0));
+    reply
+}
+
+fn update_one(collection: &mut Collection, statement: Bson) -> Result<UpdateResult, QueryError> {
+    let statement = match statement {
+        Bson::Document(statement) => statement,
+        _ => return Err(QueryError::new("update statement must be an object"))
+    };
+    let filter = match statement.get("q") {
+        Some(Bson::Document(filter)) => filter.clone(),
+        _ => return Err(QueryError::new("update statement 'q' must be an object"))
+    };
+    let update = match statement.get("u") {
+        Some(Bson::Document(update)) => update.clone(),
+        _ => return Err(QueryError::new("update statement 'u' must be an object"))
+    };
+    let mut options = UpdateOptions::new().upsert(statement.get("upsert") == Some(&Bson::Boolean(true)));
+    if let Some(Bson::Array(filters)) = statement.get("arrayFilters") {
+        let mut array_filters = Vec::new();
+        for filter in filters {
+            match filter {
+                Bson::Document(filter) => array_filters.push(filter.clone()),
+                _ => return Err(QueryError::new("arrayFilters must be objects"))
+            }
+        }
+        options = options.array_filters(array_filters);
+    }
+    if statement.get("multi") == Some(&Bson::Boolean(true)) {
+        collection.update_many(&filter, &update, &options)
+    } else {
+        collection.update_one(&filter, &update, &options)
+    }
+}
+
+fn delete_one(collection: &mut Collection, statement: Bson) -> Result<usize, QueryError> {
+    let statement = match statement {
+        Bson::Document(statement) => statement,
+        _ => return Err(QueryError::new("delete statement must be an object"))
+    };
+    let filter = match statement.get("q") {
+        Some(Bson::Document(filter)) => filter.clone(),
+        _ => return Err(QueryError::new("delete statement 'q' must be an object"))
+    };
+    match statement.get("limit").and_then(whole_number) {
+        Some(0) => collection.delete_many(&filter),
+        Some(1) => collection.delete_one(&filter),
+        _ => Err(QueryError::new("The limit field in delete objects must be 0 or 1"))
+    }
+}
+
+fn namespace(database: &str, command: &Document, name: &str) -> CommandResult<String> {
+    match command.get(name) {
+        Some(Bson::String(collection)) => Ok(format!("{}.{}", database, collection)),
+        Some(value) => Err(wrong_type(name, name, value, "string")),
+        None => Err(missing(name, name))
+    }
+}
+
+fn document_field(command: &Document, name: &str, key: &str) -> CommandResult<Option<Document>> {
+    match command.get(key) {
+        Some(Bson::Document(document)) => Ok(Some(document.clone())),
+        Some(value) => Err(wrong_type(name, key, value, "object")),
+        None => Ok(None)
+    }
+}
+
+fn array_field(command: &Document, name: &str, key: &str) -> CommandResult<Vec<Bson>> {
+    match command.get(key) {
+        Some(Bson::Array(values)) => Ok(values.clone()),
+        Some(value) => Err(wrong_type(name, key, value, "array")),
+        None => Err(missing(name, key))
+    }
+}
+
+fn bool_field(command: &Document, name: &str, key: &str) -> CommandResult<bool> {
+    match command.get(key) {
+        Some(&Bson::Boolean(value)) => Ok(value),
+        Some(value) => Err(wrong_type(name, key, value, "bool")),
+        None => Ok(false)
+    }
+}
+
+fn number_field(command: &Document, name: &str, key: &str) -> CommandResult<Option<i64>> {
+    match command.get(key) {
+        Some(value) => whole_number(value).map(Some).ok_or_else(|| wrong_type(name, key, value, "long")),
+        None => Ok(None)
+    }
+}
+
+fn count_field(command: &Document, name: &str, key: &str) -> CommandResult<Option<usize>> {
+    match number_field(command, name, key)? {
+        Some(value) if value < 0 => Err(CommandError::new(51024, "Location51024", format!(
+            "BSON field '{}' value must be >= 0, actual value '{}'", key, value
+        ))),
+        value => Ok(value.map(|value| value as usize))
+    }
+}
+
+fn wrong_type(name: &str, key: &str, value: &Bson, expected: &str) -> CommandError {
+    CommandError::new(14, "TypeMismatch", format!(
+        "BSON field '{}.{}' is the wrong type '{}', expected type '{}'", name, key, value.type_name(), expected
+    ))
+}
+
+fn missing(name: &str, key: &str) -> CommandError {
+    CommandError::new(40414, "Location40414", format!("BSON field '{}.{}' is missing but a required field", name, key))
+}
@@ -0,0 +1,46 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::process;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Once;
+use datetime;
+
+static INIT: Once = Once::new();
+static PROCESS_UNIQUE: AtomicU64 = AtomicU64::new(0);
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Generate a new ObjectId, made of the current time in seconds, a random
+/// value unique to the process, and a counter starting at a random value,
+/// as the drivers do.
+///
+/// # Returns
+/// The 12 bytes of the ObjectId.
+pub fn new_object_id() -> [u8; 12] {
+    let process_unique = process_unique();
+    let seconds = (datetime::now() / 1_000) as u32;
+    let count = COUNTER.fetch_add(1, Ordering::SeqCst) as u32;
+    let mut id = [0u8; 12];
+    id[..4].copy_from_slice(&seconds.to_be_bytes());
+    id[4..9].copy_from_slice(&process_unique);
+    id[9..].copy_from_slice(&count.to_be_bytes()[1..]);
+    id
+}
+
+fn process_unique() -> [u8; 5] {
+    INIT.call_once(|| {
+        let random = random();
+        PROCESS_UNIQUE.store(random >> 24, Ordering::SeqCst);
+        COUNTER.store((random & 0xFF_FFFF) as usize, Ordering::SeqCst);
+    });
+    let mut bytes = [0u8; 5];
+    bytes.copy_from_slice(&PROCESS_UNIQUE.load(Ordering::SeqCst).to_be_bytes()[3..]);
+    bytes
+}
+
+// A random number seeded by the standard library's per-process hash keys.
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(process::id());
+    hasher.write_i64(datetime::now());
+    hasher.finish()
+}
@@ -0,0 +1,89 @@
+use bson::Bson;
+use document::Document;
+
+/// Find the values at a dotted path in a document, following MongoDB's
+/// implicit array traversal: when the path reaches an array before its last
+/// part, the rest of the path is looked up in each embedded document of the
+/// array, and a numeric part also selects the element at that index.
+///
+/// # Parameters
+/// - `document` - The `Document` to look in.
+/// - `path` - The dotted path.
+///
+/// # Returns
+/// Each value found, with `None` recorded wherever the path was missing.
+pub fn lookup<'a>(document: &'a Document, path: &str) -> Vec<Option<&'a Bson>> {
+    let parts: Vec<&str> = path.split('.').collect();
+    let mut values = Vec::new();
+    lookup_in_document(document, &parts, &mut values);
+    values
+}
+
+fn lookup_in_document<'a>(document: &'a Document, parts: &[&str], values: &mut Vec<Option<&'a Bson>>) {
+    match document.get(parts[0]) {
+        Some(value) => lookup_in_value(value, &parts[1..], values),
+        None => values.push(None)
+    }
+}
+
+fn lookup_in_value<'a>(value: &'a Bson, parts: &[&str], values: &mut Vec<Option<&'a Bson>>) {
+    if parts.is_empty() {
+        values.push(Some(value));
+        return;
+    }
+    match *value {
+        Bson::Document(ref document) => lookup_in_document(document, parts, values),
+        Bson::Array(ref elements) => {
+            let found = values.len();
+            if let Some(element) = array_index(parts[0]).and_then(|index| elements.get(index)) {
+                lookup_in_value(element, &parts[1..], values);
+            }
+            for element in elements {
+                if let Bson::Document(ref document) = *element {
+                    lookup_in_document(document, parts, values);
+                }
+            }
+            if values.len() == found {
+                values.push(None);
+            }
+        },
+        _ => values.push(None)
+    }
+}
+
+/// Find the value at a dotted path in a document without implicit array
+/// traversal, where a numeric part selects the element of an array.
+///
+/// # Parameters
+/// - `document` - The `Document` to look in.
+/// - `path` - The dotted path.
+///
+/// # Returns
+/// The value if the path exists.
+pub fn get_mut<'a>(document: &'a mut Document, path: &str) -> Option<&'a mut Bson> {
+    let mut parts = path.split('.');
+    let mut value = document.get_mut(parts.next()?)?;
+    for part in parts {
+        value = match *value {
+            Bson::Document(ref mut document) => document.get_mut(part)?,
+            Bson::Array(ref mut elements) => elements.get_mut(array_index(part)?)?,
+            _ => return None
+        };
+    }
+    Some(value)
+}
+
+/// Parse a path part as an array index.
+///
+/// # Parameters
+/// - `part` - The path part.
+///
+/// # Returns
+/// The index if the part is made only of digits.
+pub fn array_index(part: &str) -> Option<usize> {
+    if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) {
+        part.parse().ok()
+    } else {
+        None
+    }
+}
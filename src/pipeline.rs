@@ -0,0 +1,634 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter;
+use bson::Bson;
+use comparison::bson_cmp;
+use decimal128::Decimal;
+use document::Document;
+use expression::Expression;
+use filter::{whole_number, Filter};
+use hashable::HashableBson;
+use projection::Projection;
+use query_error::QueryError;
+use sort_spec::SortSpec;
+
+/// The documents flowing between the stages of a `Pipeline`.
+pub type Documents<'a> = Box<dyn Iterator<Item = Result<Document, QueryError>> + 'a>;
+
+/// A parsed aggregation pipeline that runs over documents in memory.
+///
+/// The supported stages are `$match`, `$project`, `$addFields` and its
+/// alias `$set`, `$unset`, `$sort`, `$limit`, `$skip`, `$unwind`, `$group`
+/// with the `$sum`, `$avg`, `$min`, `$max`, `$push`, `$addToSet`, `$first`
+/// and `$last` accumulators, `$count`, and `$replaceRoot` and its alias
+/// `$replaceWith`. Documents stream through the stages lazily, except that
+/// `$sort`, `$group` and `$count` gather all of their input first.
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    stages: Vec<Stage>
+}
+
+#[derive(Clone, Debug)]
+enum Stage {
+    Match(Filter),
+    Project(Projection, Vec<(String, Expression)>),
+    AddFields(Vec<(String, Expression)>),
+    Sort(SortSpec),
+    Limit(usize),
+    Skip(usize),
+    Unwind(Unwind),
+    Group(Expression, Vec<(String, Accumulator, Expression)>),
+    Count(String),
+    ReplaceRoot(Expression)
+}
+
+#[derive(Clone, Debug)]
+struct Unwind {
+    path: String,
+    include_array_index: Option<String>,
+    preserve_null_and_empty_arrays: bool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Accumulator {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Push,
+    AddToSet,
+    First,
+    Last
+}
+
+// The running state of one accumulator for one group.
+enum State {
+    Sum(Bson),
+    Avg(Bson, usize),
+    Extreme(Option<Bson>, Ordering),
+    Push(Vec<Bson>),
+    AddToSet(Vec<Bson>),
+    First(Option<Bson>),
+    Last(Bson)
+}
+
+/// Implementation for the `Pipeline` object.
+impl Pipeline {
+
+    /// Parse the stages of a pipeline.
+    ///
+    /// # Parameters
+    /// - `stages` - The stage `Document`s, such as `{ "$limit": 5 }`.
+    ///
+    /// # Returns
+    /// The `Result` with the `Pipeline`, or a `QueryError` carrying the
+    /// server's message if a stage is invalid.
+    pub fn parse(stages: &[Document]) -> Result<Pipeline, QueryError> {
+        Ok(Pipeline { stages: stages.iter().map(parse_stage).collect::<Result<_, _>>()? })
+    }
+
+    /// Run the pipeline over documents.
+    ///
+    /// # Parameters
+    /// - `documents` - The input `Document`s.
+    ///
+    /// # Returns
+    /// The iterator of output `Document`s, yielding an error if a stage
+    /// fails, such as an expression that cannot be evaluated.
+    pub fn run<'a, I>(&'a self, documents: I) -> Documents<'a> where I: IntoIterator<Item = Document>, I::IntoIter: 'a {
+        let mut results: Documents<'a> = Box::new(documents.into_iter().map(Ok));
+        for stage in &self.stages {
+            results = stage.apply(results);
+        }
+        results
+    }
+}
+
+impl Stage {
+
+    fn apply<'a>(&'a self, input: Documents<'a>) -> Documents<'a> {
+        match *self {
+            Stage::Match(ref filter) => Box::new(input.filter(move |result| match *result {
+                Ok(ref document) => filter.matches(document),
+                Err(_) => true
+            })),
+            Stage::Project(ref projection, ref computed) => Box::new(input.map(move |result| {
+                let document = result?;
+                let mut projected = projection.apply(&document)?;
+                add_fields(&document, &mut projected, computed)?;
+                Ok(projected)
+            })),
+            Stage::AddFields(ref fields) => Box::new(input.map(move |result| {
+                let document = result?;
+                let mut updated = document.clone();
+                add_fields(&document, &mut updated, fields)?;
+                Ok(updated)
+            })),
+            Stage::Sort(ref spec) => blocking(input, move |mut documents| {
+                documents.sort_by(|left, right| spec.compare(left, right));
+                Ok(documents)
+            }),
+            Stage::Limit(limit) => Box::new(input.take(limit)),
+            Stage::Skip(skip) => {
+                let mut remaining = skip;
+                Box::new(input.filter(move |result| {
+                    if result.is_err() || remaining == 0 {
+                        return true;
+                    }
+                    remaining -= 1;
+                    false
+                }))
+            },
+            Stage::Unwind(ref unwind) => Box::new(input.flat_map(move |result| match result {
+                Ok(document) => unwind.apply(document).into_iter().map(Ok).collect(),
+                Err(error) => vec![Err(error)]
+            })),
+            Stage::Group(ref id, ref accumulators) => blocking(input, move |documents| group(id, accumulators, documents)),
+            Stage::Count(ref field) => blocking(input, move |documents| {
+                if documents.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let mut count = Document::new();
+                count.insert(field.clone(), Bson::Int32(documents.len() as i32));
+                Ok(vec![count])
+            }),
+            Stage::ReplaceRoot(ref root) => Box::new(input.map(move |result| {
+                let document = result?;
+                match root.evaluate(&document)? {
+                    Some(Bson::Document(root)) => Ok(root),
+                    other => {
+                        let value = other.unwrap_or(Bson::Undefined);
+                        Err(QueryError::new(format!(
+                            "'newRoot' expression must evaluate to an object, but resulting value was: {}. Type of resulting value: '{}'. Input document: {}",
+                            value, if value == Bson::Undefined { "missing" } else { value.type_name() }, document
+                        )))
+                    }
+                }
+            }))
+        }
+    }
+}
+
+/// Run a stage that needs all of its input at once, without pulling any
+/// input until the first output is asked for.
+fn blocking<'a, F>(input: Documents<'a>, f: F) -> Documents<'a>
+        where F: FnOnce(Vec<Document>) -> Result<Vec<Document>, QueryError> + 'a {
+    let mut pending = Some((input, f));
+    Box::new(iter::once(()).flat_map(move |_| {
+        let (input, f) = pending.take().expect("a blocking stage runs once");
+        match input.collect::<Result<Vec<_>, _>>().and_then(f) {
+            Ok(documents) => documents.into_iter().map(Ok).collect(),
+            Err(error) => vec![Err(error)]
+        }
+    }))
+}
+
+fn parse_stage(stage: &Document) -> Result<Stage, QueryError> {
+    let (name, spec) = match stage.iter().next() {
+        Some((name, spec)) if stage.len() == 1 => (name, spec),
+        _ => return Err(QueryError::new("A pipeline stage specification object must contain exactly one field."))
+    };
+    Ok(match name.as_str() {
+        "$match" => Stage::Match(Filter::parse(stage_document(name, spec)?)?),
+        "$project" => parse_project(stage_document(name, spec)?)?,
+        "$addFields" | "$set" => {
+            let mut fields = Vec::new();
+            flatten_expressions(stage_document(name, spec)?, "", &mut fields)?;
+            Stage::AddFields(fields)
+        },
+        "$unset" => {
+            let paths = match *spec {
+                Bson::String(ref path) => vec![path.clone()],
+                Bson::Array(ref paths) if !paths.is_empty() => paths.iter().map(|path| match *path {
+                    Bson::String(ref path) => Ok(path.clone()),
+                    _ => Err(QueryError::new("$unset specification must be a string or an array containing only string values"))
+                }).collect::<Result<_, _>>()?,
+                _ => return Err(QueryError::new("$unset specification must be a string or an array with at least one field"))
+            };
+            let mut exclusion = Document::new();
+            for path in paths {
+                exclusion.insert(path, Bson::Int32(0));
+            }
+            Stage::Project(Projection::parse(&exclusion)?, Vec::new())
+        },
+        "$sort" => {
+            let spec = SortSpec::parse(stage_document(name, spec)?)?;
+            if spec.is_empty() {
+                return Err(QueryError::new("$sort stage must have at least one sort key"));
+            }
+            Stage::Sort(spec)
+        },
+        "$limit" => match whole_number(spec) {
+            Some(limit) if limit > 0 => Stage::Limit(limit as usize),
+            _ => return Err(QueryError::new(format!("invalid argument to $limit stage: the limit must be positive: {}", spec)))
+        },
+        "$skip" => match whole_number(spec) {
+            Some(skip) if skip >= 0 => Stage::Skip(skip as usize),
+            _ => return Err(QueryError::new(format!(
+                "invalid argument to $skip stage: Expected a non-negative number in: $skip: {}", spec
+            )))
+        },
+        "$unwind" => Stage::Unwind(parse_unwind(spec)?),
+        "$group" => parse_group(stage_document(name, spec)?)?,
+        "$count" => match *spec {
+            Bson::String(ref field) if field.is_empty() => return Err(QueryError::new("the count field must be a non-empty string")),
+            Bson::String(ref field) if field.starts_with('$') => return Err(QueryError::new("the count field cannot be a $-prefixed path")),
+            Bson::String(ref field) if field.contains('.') => return Err(QueryError::new("the count field cannot contain '.'")),
+            Bson::String(ref field) => Stage::Count(field.clone()),
+            _ => return Err(QueryError::new("the count field must be a non-empty string"))
+        },
+        "$replaceRoot" => match stage_document(name, spec)?.get("newRoot") {
+            Some(root) => Stage::ReplaceRoot(Expression::parse(root)?),
+            None => return Err(QueryError::new("no newRoot specified for the $replaceRoot stage"))
+        },
+        "$replaceWith" => Stage::ReplaceRoot(Expression::parse(spec)?),
+        other => return Err(QueryError::new(format!("Unrecognized pipeline stage name: '{}'", other)))
+    })
+}
+
+fn stage_document<'a>(name: &str, spec: &'a Bson) -> Result<&'a Document, QueryError> {
+    match *spec {
+        Bson::Document(ref spec) => Ok(spec),
+        ref other => Err(QueryError::new(format!(
+            "{} specification must be an object, found {}", name, other.type_name()
+        )))
+    }
+}
+
+/// Split a `$project` specification into the fields it includes or excludes
+/// and the fields it computes, nested documents of which are flattened into
+/// dotted paths.
+fn parse_project(spec: &Document) -> Result<Stage, QueryError> {
+    if spec.is_empty() {
+        return Err(QueryError::new("Invalid $project :: caused by :: projection specification must have at least one field"));
+    }
+    let mut flags = Vec::new();
+    let mut computed = Vec::new();
+    flatten_project(spec, "", &mut flags, &mut computed)?;
+    let excludes = flags.iter().any(|(path, include)| !include && path != "_id");
+    if excludes && !computed.is_empty() {
+        return Err(QueryError::new(
+            "Invalid $project :: caused by :: Cannot use expression other than $meta in exclusion projection"
+        ));
+    }
+    let mut projection = Document::new();
+    for (path, include) in flags {
+        projection.insert(path, Bson::Boolean(include));
+    }
+    for (path, _) in &computed {
+        projection.insert(path.clone(), Bson::Boolean(true));
+    }
+    Ok(Stage::Project(Projection::parse(&projection)?, computed))
+}
+
+fn flatten_project(spec: &Document, prefix: &str, flags: &mut Vec<(String, bool)>,
+        computed: &mut Vec<(String, Expression)>) -> Result<(), QueryError> {
+    for (key, value) in spec.iter() {
+        let path = format!("{}{}", prefix, key);
+        match *value {
+            Bson::Boolean(include) => flags.push((path, include)),
+            Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_) => flags.push((path, whole_number(value) != Some(0))),
+            Bson::Document(ref nested) if !is_operator(nested) => {
+                flatten_project(nested, &format!("{}.", path), flags, computed)?;
+            },
+            _ => computed.push((path, Expression::parse(value)?))
+        }
+    }
+    Ok(())
+}
+
+fn flatten_expressions(spec: &Document, prefix: &str, fields: &mut Vec<(String, Expression)>) -> Result<(), QueryError> {
+    for (key, value) in spec.iter() {
+        let path = format!("{}{}", prefix, key);
+        match *value {
+            Bson::Document(ref nested) if !nested.is_empty() && !is_operator(nested) => {
+                flatten_expressions(nested, &format!("{}.", path), fields)?;
+            },
+            _ => fields.push((path, Expression::parse(value)?))
+        }
+    }
+    Ok(())
+}
+
+fn is_operator(document: &Document) -> bool {
+    document.iter().next().is_some_and(|(key, _)| key.starts_with('$'))
+}
+
+/// Evaluate expressions against a document and set the results at their
+/// paths in the output, removing the paths that evaluate to missing values.
+fn add_fields(document: &Document, output: &mut Document, fields: &[(String, Expression)]) -> Result<(), QueryError> {
+    for (path, expression) in fields {
+        match expression.evaluate(document)? {
+            Some(value) => set_path(output, path, value),
+            None => remove_path(output, path)
+        }
+    }
+    Ok(())
+}
+
+fn set_path(document: &mut Document, path: &str, value: Bson) {
+    let (key, rest) = match path.find('.') {
+        Some(dot) => (&path[..dot], Some(&path[dot + 1..])),
+        None => (path, None)
+    };
+    let rest = match rest {
+        Some(rest) => rest,
+        None => {
+            match document.get_mut(key) {
+                Some(existing) => *existing = value,
+                None => {
+                    document.insert(key.to_string(), value);
+                }
+            }
+            return;
+        }
+    };
+    match document.get_mut(key) {
+        Some(Bson::Document(child)) => set_path(child, rest, value),
+        Some(existing) => {
+            let mut child = Document::new();
+            set_path(&mut child, rest, value);
+            *existing = Bson::Document(child);
+        },
+        None => {
+            let mut child = Document::new();
+            set_path(&mut child, rest, value);
+            document.insert(key.to_string(), Bson::Document(child));
+        }
+    }
+}
+
+fn remove_path(document: &mut Document, path: &str) {
+    match path.find('.') {
+        Some(dot) => {
+            if let Some(Bson::Document(child)) = document.get_mut(&path[..dot]) {
+                remove_path(child, &path[dot + 1..]);
+            }
+        },
+        None => {
+            document.remove(path);
+        }
+    }
+}
+
+fn get_path<'a>(document: &'a Document, path: &str) -> Option<&'a Bson> {
+    let mut parts = path.split('.');
+    let mut value = document.get(parts.next()?)?;
+    for part in parts {
+        value = match *value {
+            Bson::Document(ref document) => document.get(part)?,
+            _ => return None
+        };
+    }
+    Some(value)
+}
+
+fn parse_unwind(spec: &Bson) -> Result<Unwind, QueryError> {
+    let (path, options) = match *spec {
+        Bson::String(ref path) => (path, None),
+        Bson::Document(ref options) => match options.get("path") {
+            Some(Bson::String(path)) => (path, Some(options)),
+            Some(_) => return Err(QueryError::new("expected a string as the path for $unwind stage")),
+            None => return Err(QueryError::new("no path specified to $unwind stage"))
+        },
+        ref other => return Err(QueryError::new(format!(
+            "expected either a string or an object as specification for $unwind stage, got {}", other.type_name()
+        )))
+    };
+    if !path.starts_with('$') || path.len() == 1 {
+        return Err(QueryError::new(format!(
+            "path option to $unwind stage should be prefixed with a '$': {}", path
+        )));
+    }
+    let mut unwind = Unwind { path: path[1..].to_string(), include_array_index: None, preserve_null_and_empty_arrays: false };
+    for (key, value) in options.iter().flat_map(|options| options.iter()) {
+        match (key.as_str(), value) {
+            ("path", _) => {},
+            ("includeArrayIndex", Bson::String(field)) if !field.is_empty() && !field.starts_with('$') => {
+                unwind.include_array_index = Some(field.clone());
+            },
+            ("includeArrayIndex", _) => return Err(QueryError::new(
+                "includeArrayIndex option to $unwind stage should be a non-empty string that does not start with '$'"
+            )),
+            ("preserveNullAndEmptyArrays", Bson::Boolean(preserve)) => unwind.preserve_null_and_empty_arrays = *preserve,
+            ("preserveNullAndEmptyArrays", _) => return Err(QueryError::new(
+                "expected a boolean for the preserveNullAndEmptyArrays option to $unwind stage"
+            )),
+            (other, _) => return Err(QueryError::new(format!("unrecognized option to $unwind stage: {}", other)))
+        }
+    }
+    Ok(unwind)
+}
+
+impl Unwind {
+
+    fn apply(&self, document: Document) -> Vec<Document> {
+        let elements = match get_path(&document, &self.path) {
+            Some(Bson::Array(elements)) if !elements.is_empty() => elements.clone(),
+            Some(Bson::Array(_)) | Some(Bson::Null) | None => {
+                if !self.preserve_null_and_empty_arrays {
+                    return Vec::new();
+                }
+                let mut preserved = document;
+                if let Some(Bson::Array(_)) = get_path(&preserved, &self.path) {
+                    remove_path(&mut preserved, &self.path);
+                }
+                if let Some(ref field) = self.include_array_index {
+                    set_path(&mut preserved, field, Bson::Null);
+                }
+                return vec![preserved];
+            },
+            Some(_) => {
+                let mut single = document;
+                if let Some(ref field) = self.include_array_index {
+                    set_path(&mut single, field, Bson::Null);
+                }
+                return vec![single];
+            }
+        };
+        elements.into_iter().enumerate().map(|(index, element)| {
+            let mut unwound = document.clone();
+            set_path(&mut unwound, &self.path, element);
+            if let Some(ref field) = self.include_array_index {
+                set_path(&mut unwound, field, Bson::Int64(index as i64));
+            }
+            unwound
+        }).collect()
+    }
+}
+
+fn parse_group(spec: &Document) -> Result<Stage, QueryError> {
+    let id = match spec.get("_id") {
+        Some(id) => Expression::parse(id)?,
+        None => return Err(QueryError::new("a group specification must specify an _id"))
+    };
+    let mut accumulators = Vec::new();
+    for (field, value) in spec.iter() {
+        if field == "_id" {
+            continue;
+        }
+        if field.contains('.') {
+            return Err(QueryError::new(format!("The field name '{}' cannot contain '.'", field)));
+        }
+        let (name, operand) = match *value {
+            Bson::Document(ref accumulator) if accumulator.len() == 1 => accumulator.iter().next().expect("one field"),
+            _ => return Err(QueryError::new(format!("The field '{}' must be an accumulator object", field)))
+        };
+        let accumulator = match name.as_str() {
+            "$sum" => Accumulator::Sum,
+            "$avg" => Accumulator::Avg,
+            "$min" => Accumulator::Min,
+            "$max" => Accumulator::Max,
+            "$push" => Accumulator::Push,
+            "$addToSet" => Accumulator::AddToSet,
+            "$first" => Accumulator::First,
+            "$last" => Accumulator::Last,
+            other => return Err(QueryError::new(format!("unknown group operator '{}'", other)))
+        };
+        if let Bson::Array(_) = *operand {
+            return Err(QueryError::new(format!("The {} accumulator is a unary operator", name)));
+        }
+        accumulators.push((field.clone(), accumulator, Expression::parse(operand)?));
+    }
+    Ok(Stage::Group(id, accumulators))
+}
+
+fn group(id: &Expression, accumulators: &[(String, Accumulator, Expression)], documents: Vec<Document>)
+        -> Result<Vec<Document>, QueryError> {
+    let mut groups: Vec<(Bson, Vec<State>)> = Vec::new();
+    let mut index: HashMap<HashableBson, usize> = HashMap::new();
+    for document in documents {
+        let key = id.evaluate(&document)?.unwrap_or(Bson::Null);
+        let position = *index.entry(HashableBson::numeric(key.clone())).or_insert_with(|| {
+            let states = accumulators.iter().map(|&(_, accumulator, _)| State::new(accumulator)).collect();
+            groups.push((key, states));
+            groups.len() - 1
+        });
+        for (state, (_, _, expression)) in groups[position].1.iter_mut().zip(accumulators) {
+            state.add(expression.evaluate(&document)?);
+        }
+    }
+    Ok(groups.into_iter().map(|(key, states)| {
+        let mut output = Document::new();
+        output.insert("_id".to_string(), key);
+        for (state, (field, _, _)) in states.into_iter().zip(accumulators) {
+            output.insert(field.clone(), state.finish());
+        }
+        output
+    }).collect())
+}
+
+impl State {
+
+    fn new(accumulator: Accumulator) -> State {
+        match accumulator {
+            Accumulator::Sum => State::Sum(Bson::Int32(0)),
+            Accumulator::Avg => State::Avg(Bson::Int32(0), 0),
+            Accumulator::Min => State::Extreme(None, Ordering::Less),
+            Accumulator::Max => State::Extreme(None, Ordering::Greater),
+            Accumulator::Push => State::Push(Vec::new()),
+            Accumulator::AddToSet => State::AddToSet(Vec::new()),
+            Accumulator::First => State::First(None),
+            Accumulator::Last => State::Last(Bson::Null)
+        }
+    }
+
+    fn add(&mut self, value: Option<Bson>) {
+        match *self {
+            State::Sum(ref mut sum) => {
+                if let Some(total) = value.as_ref().and_then(|value| sum_numbers(sum, value)) {
+                    *sum = total;
+                }
+            },
+            State::Avg(ref mut total, ref mut count) => {
+                if let Some(sum) = value.as_ref().and_then(|value| sum_numbers(total, value)) {
+                    *total = sum;
+                    *count += 1;
+                }
+            },
+            State::Extreme(ref mut extreme, wanted) => match value {
+                None | Some(Bson::Null) | Some(Bson::Undefined) => {},
+                Some(value) => {
+                    if extreme.as_ref().is_none_or(|current| bson_cmp(&value, current) == wanted) {
+                        *extreme = Some(value);
+                    }
+                }
+            },
+            State::Push(ref mut values) => {
+                if let Some(value) = value {
+                    values.push(value);
+                }
+            },
+            State::AddToSet(ref mut values) => {
+                if let Some(value) = value {
+                    if !values.iter().any(|existing| bson_cmp(existing, &value) == Ordering::Equal) {
+                        values.push(value);
+                    }
+                }
+            },
+            State::First(ref mut first) => {
+                if first.is_none() {
+                    *first = Some(value.unwrap_or(Bson::Null));
+                }
+            },
+            State::Last(ref mut last) => *last = value.unwrap_or(Bson::Null)
+        }
+    }
+
+    fn finish(self) -> Bson {
+        match self {
+            State::Sum(sum) => sum,
+            State::Avg(_, 0) => Bson::Null,
+            State::Avg(Bson::Decimal128(ref total), count) => {
+                Bson::Decimal128(Decimal::from_bytes(total).divide(Decimal::from_i64(count as i64)).to_bytes())
+            },
+            State::Avg(total, count) => Bson::Double(as_f64(&total).unwrap_or(0.0) / count as f64),
+            State::Extreme(extreme, _) => extreme.unwrap_or(Bson::Null),
+            State::Push(values) | State::AddToSet(values) => Bson::Array(values),
+            State::First(first) => first.unwrap_or(Bson::Null),
+            State::Last(last) => last
+        }
+    }
+}
+
+/// Add a value to a `$sum`, ignoring values that are not numbers. Once a
+/// decimal is added the sum is a `Decimal128`.
+fn sum_numbers(sum: &Bson, value: &Bson) -> Option<Bson> {
+    Some(match (sum, value) {
+        (Bson::Int32(l), Bson::Int32(r)) => match l.checked_add(*r) {
+            Some(total) => Bson::Int32(total),
+            None => Bson::Int64(i64::from(*l) + i64::from(*r))
+        },
+        (Bson::Int32(_), Bson::Int64(_)) | (Bson::Int64(_), Bson::Int32(_)) | (Bson::Int64(_), Bson::Int64(_)) => {
+            let (l, r) = (whole(sum)?, whole(value)?);
+            match l.checked_add(r) {
+                Some(total) => Bson::Int64(total),
+                None => Bson::Double(l as f64 + r as f64)
+            }
+        },
+        (Bson::Decimal128(_), _) | (_, Bson::Decimal128(_)) => {
+            Bson::Decimal128(Decimal::from_number(sum)?.add(Decimal::from_number(value)?).to_bytes())
+        },
+        (_, Bson::Int32(_)) | (_, Bson::Int64(_)) | (_, Bson::Double(_)) => {
+            Bson::Double(as_f64(sum)? + as_f64(value)?)
+        },
+        _ => return None
+    })
+}
+
+fn whole(value: &Bson) -> Option<i64> {
+    match *value {
+        Bson::Int32(number) => Some(i64::from(number)),
+        Bson::Int64(number) => Some(number),
+        _ => None
+    }
+}
+
+fn as_f64(value: &Bson) -> Option<f64> {
+    match *value {
+        Bson::Int32(number) => Some(f64::from(number)),
+        Bson::Int64(number) => Some(number as f64),
+        Bson::Double(number) => Some(number),
+        _ => None
+    }
+}
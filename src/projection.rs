@@ -0,0 +1,282 @@
+use bson::Bson;
+use document::Document;
+use filter::{positional_index, truthy, whole_number, Filter, ValueMatcher};
+use query_error::QueryError;
+
+/// Project a document with a projection specification, with the same
+/// semantics as the server's `find`.
+///
+/// # Parameters
+/// - `document` - The source `Document`.
+/// - `spec` - The projection specification `Document`.
+///
+/// # Returns
+/// The `Result` with the projected `Document`, or a `QueryError` carrying the
+/// server's message if the specification is invalid.
+pub fn project(document: &Document, spec: &Document) -> Result<Document, QueryError> {
+    Projection::parse(spec)?.apply(document)
+}
+
+/// A parsed projection that trims documents in memory.
+///
+/// A projection either includes the fields it names, or excludes them and
+/// keeps the rest, and cannot mix the two except for `_id`, which is included
+/// unless excluded explicitly. Fields may be dotted paths, which apply within
+/// embedded documents and the documents in arrays. `{ "$slice": n }` or
+/// `{ "$slice": [skip, limit] }` limits the elements of an array,
+/// `{ "$elemMatch": condition }` keeps only the first matching element of an
+/// array, and a path ending in `.$` keeps only the element matched by the
+/// projection's `query`. Fields keep their order in the source document.
+#[derive(Clone, Debug)]
+pub struct Projection {
+    fields: Vec<(String, Node)>,
+    inclusion: bool,
+    positional: Option<String>,
+    query: Option<Filter>
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Include,
+    Exclude,
+    Slice(i64, i64),
+    ElemMatch(ValueMatcher),
+    Positional,
+    Children(Vec<(String, Node)>)
+}
+
+/// Implementation for the `Projection` object.
+impl Projection {
+
+    /// Parse a projection specification.
+    ///
+    /// # Parameters
+    /// - `spec` - The projection specification `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the `Projection`.
+    pub fn parse(spec: &Document) -> Result<Projection, QueryError> {
+        let mut fields = Vec::new();
+        let mut inclusion: Option<bool> = None;
+        let mut positional = None;
+        for (key, value) in spec.iter() {
+            let (path, node) = parse_field(key, value)?;
+            if let Node::Positional = node {
+                if positional.is_some() {
+                    return Err(QueryError::new("Cannot specify more than one positional projection per query."));
+                }
+                positional = Some(path.clone());
+            }
+            let includes = match node {
+                Node::Include | Node::ElemMatch(_) | Node::Positional => Some(true),
+                Node::Exclude => Some(false),
+                _ => None
+            };
+            if let Some(includes) = includes.filter(|_| path != "_id") {
+                match inclusion {
+                    Some(true) if !includes => return Err(QueryError::new(format!(
+                        "Cannot do exclusion on field {} in inclusion projection", path
+                    ))),
+                    Some(false) if includes => return Err(QueryError::new(format!(
+                        "Cannot do inclusion on field {} in exclusion projection", path
+                    ))),
+                    _ => inclusion = Some(includes)
+                }
+            }
+            let parts: Vec<&str> = path.split('.').collect();
+            insert(&mut fields, &parts, node, &path)?;
+        }
+        let inclusion = inclusion.unwrap_or_else(|| match spec.get("_id") {
+            Some(value) => truthy(value),
+            None => false
+        });
+        if inclusion && !fields.iter().any(|(key, _)| key == "_id") {
+            fields.insert(0, ("_id".to_string(), Node::Include));
+        }
+        Ok(Projection { fields, inclusion, positional, query: None })
+    }
+
+    /// Set the query that selected the documents, used to find the element
+    /// kept by a positional `.$` projection.
+    ///
+    /// # Parameters
+    /// - `query` - The query `Filter`.
+    ///
+    /// # Returns
+    /// The `Projection`.
+    pub fn query(mut self, query: Filter) -> Projection {
+        self.query = Some(query);
+        self
+    }
+
+    /// Project a document.
+    ///
+    /// # Parameters
+    /// - `document` - The source `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the projected `Document`, or a `QueryError` if a
+    /// positional projection finds no element matched by the query.
+    pub fn apply(&self, document: &Document) -> Result<Document, QueryError> {
+        let index = match self.positional {
+            Some(ref path) => {
+                let index = self.query.as_ref().and_then(|query| positional_index(query, document, path));
+                if index.is_none() && is_array_at(document, path) {
+                    return Err(QueryError::new(
+                        "Executor error during find command :: caused by :: positional operator '.$' couldn't find a matching element in the array"
+                    ));
+                }
+                index
+            },
+            None => None
+        };
+        Ok(project_document(document, &self.fields, self.inclusion, index))
+    }
+}
+
+fn parse_field(key: &str, value: &Bson) -> Result<(String, Node), QueryError> {
+    if key.is_empty() || key.split('.').any(str::is_empty) {
+        return Err(QueryError::new(format!("FieldPath field names may not be empty strings: '{}'", key)));
+    }
+    if let Some(path) = key.strip_suffix(".$") {
+        if path.split('.').any(|part| part.starts_with('$')) || !truthy(value) {
+            return Err(QueryError::new(format!("Invalid positional projection: {}: {}", key, value)));
+        }
+        return Ok((path.to_string(), Node::Positional));
+    }
+    if key.split('.').any(|part| part.starts_with('$')) {
+        return Err(QueryError::new(format!(
+            "FieldPath field names may not start with '$'. Consider using $getField or $setField: '{}'", key
+        )));
+    }
+    let node = match *value {
+        Bson::Document(ref operator) => match operator.iter().next() {
+            Some((name, operand)) if operator.len() == 1 && name == "$slice" => parse_slice(operand)?,
+            Some((name, operand)) if operator.len() == 1 && name == "$elemMatch" => {
+                if key.contains('.') {
+                    return Err(QueryError::new("Cannot use $elemMatch projection on a nested field."));
+                }
+                match *operand {
+                    Bson::Document(_) => Node::ElemMatch(ValueMatcher::parse(operand)?),
+                    _ => return Err(QueryError::new("elemMatch: Invalid argument, object required."))
+                }
+            },
+            _ => return Err(QueryError::new(format!("Unsupported projection option: {}: {}", key, value)))
+        },
+        Bson::Boolean(_) | Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_) => {
+            if truthy(value) { Node::Include } else { Node::Exclude }
+        },
+        _ => return Err(QueryError::new(format!("Unsupported projection option: {}: {}", key, value)))
+    };
+    Ok((key.to_string(), node))
+}
+
+fn parse_slice(operand: &Bson) -> Result<Node, QueryError> {
+    if let Some(count) = whole_number(operand) {
+        return Ok(if count < 0 { Node::Slice(count, count.saturating_neg()) } else { Node::Slice(0, count) });
+    }
+    match *operand {
+        Bson::Array(ref range) if range.len() == 2 => {
+            match (whole_number(&range[0]), whole_number(&range[1])) {
+                (Some(_), Some(limit)) if limit <= 0 => {
+                    Err(QueryError::new(format!("Invalid $slice syntax. The limit must be positive: {}", operand)))
+                },
+                (Some(skip), Some(limit)) => Ok(Node::Slice(skip, limit)),
+                _ => Err(QueryError::new(format!("Invalid $slice syntax: {}", operand)))
+            }
+        },
+        _ => Err(QueryError::new(format!(
+            "Argument to $slice must be a number or an array of two numbers, but was: {}", operand
+        )))
+    }
+}
+
+/// Add a field to the tree of projected fields, rejecting a path that is
+/// also projected whole or within.
+fn insert(fields: &mut Vec<(String, Node)>, parts: &[&str], node: Node, path: &str) -> Result<(), QueryError> {
+    let position = fields.iter().position(|(key, _)| key == parts[0]);
+    if parts.len() == 1 {
+        if position.is_some() {
+            return Err(QueryError::new(format!("Path collision at {}", path)));
+        }
+        fields.push((parts[0].to_string(), node));
+        return Ok(());
+    }
+    let index = match position {
+        Some(index) => index,
+        None => {
+            fields.push((parts[0].to_string(), Node::Children(Vec::new())));
+            fields.len() - 1
+        }
+    };
+    match fields[index].1 {
+        Node::Children(ref mut children) => insert(children, &parts[1..], node, path),
+        _ => Err(QueryError::new(format!("Path collision at {}", path)))
+    }
+}
+
+fn is_array_at(document: &Document, path: &str) -> bool {
+    let mut value = match document.get(path.split('.').next().unwrap_or("")) {
+        Some(value) => value,
+        None => return false
+    };
+    for part in path.split('.').skip(1) {
+        value = match *value {
+            Bson::Document(ref document) => match document.get(part) {
+                Some(value) => value,
+                None => return false
+            },
+            _ => return false
+        };
+    }
+    matches!(*value, Bson::Array(_))
+}
+
+fn project_document(document: &Document, fields: &[(String, Node)], inclusion: bool, index: Option<usize>) -> Document {
+    let mut projected = Document::new();
+    for (key, value) in document.iter() {
+        let value = match fields.iter().find(|(field, _)| field == key) {
+            Some((_, node)) => project_value(value, node, inclusion, index),
+            None if inclusion => None,
+            None => Some(value.clone())
+        };
+        if let Some(value) = value {
+            projected.insert(key.clone(), value);
+        }
+    }
+    projected
+}
+
+fn project_value(value: &Bson, node: &Node, inclusion: bool, index: Option<usize>) -> Option<Bson> {
+    match (node, value) {
+        (Node::Include, _) => Some(value.clone()),
+        (Node::Exclude, _) => None,
+        (Node::Slice(skip, limit), Bson::Array(elements)) => {
+            let (skip, limit) = (*skip, *limit);
+            let length = elements.len() as i64;
+            let start = if skip < 0 { (length + skip).max(0) } else { skip.min(length) } as usize;
+            Some(Bson::Array(elements.iter().skip(start).take(limit as usize).cloned().collect()))
+        },
+        (Node::Slice(..), _) => Some(value.clone()),
+        (Node::ElemMatch(matcher), Bson::Array(elements)) => {
+            elements.iter().find(|element| matcher.matches(element)).map(|element| Bson::Array(vec![element.clone()]))
+        },
+        (Node::ElemMatch(_), _) => None,
+        (Node::Positional, Bson::Array(elements)) => {
+            index.and_then(|index| elements.get(index)).map(|element| Bson::Array(vec![element.clone()]))
+        },
+        (Node::Positional, _) => Some(value.clone()),
+        (Node::Children(children), Bson::Document(document)) => {
+            Some(Bson::Document(project_document(document, children, inclusion, index)))
+        },
+        (Node::Children(_), Bson::Array(elements)) => Some(Bson::Array(elements.iter().filter_map(|element| {
+            match *element {
+                Bson::Document(_) | Bson::Array(_) => project_value(element, node, inclusion, index),
+                _ if inclusion => None,
+                _ => Some(element.clone())
+            }
+        }).collect())),
+        (Node::Children(_), _) if inclusion => None,
+        (Node::Children(_), _) => Some(value.clone())
+    }
+}
@@ -0,0 +1,368 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::Result;
+use std::str;
+use bson::Bson;
+use document::Document;
+use document_deserializer::{document_length, invalid_data, parse_value, MAX_NESTING_DEPTH};
+
+/// An event emitted by the `PushParser` as the parts of a document arrive.
+///
+/// The elements of an array are emitted without a `Key` event. Values that
+/// are not documents or arrays, including code with scope, are emitted whole
+/// once all of their bytes have arrived.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseEvent {
+    StartDocument,
+    EndDocument,
+    StartArray,
+    EndArray,
+    Key(String),
+    Value(Bson)
+}
+
+/// The `PushParser` object that parses a stream of concatenated BSON
+/// documents from bytes fed to it in chunks of any size, without blocking on
+/// a reader.
+///
+/// Bytes are passed in with `feed`, after which `next_event` returns each
+/// event that can be completed with the bytes so far, or `next_document`
+/// returns each completed top level document. Bytes are consumed as each
+/// event is emitted, so only an incomplete element is held between feeds and
+/// earlier chunks are never parsed again. The two methods share the parse
+/// position and should not be mixed on the same parser.
+///
+/// Any malformed data is returned as an error, after which the parser yields
+/// nothing more. That includes documents and arrays nested more than 200
+/// levels deep and, when `max_document_size` is set, top level documents
+/// longer than it.
+pub struct PushParser {
+    buffer: Vec<u8>,
+    start: usize,
+    position: u64,
+    max_document_size: Option<usize>,
+    state: State,
+    open: Vec<OpenDocument>,
+    builders: Vec<Builder>,
+    key: Option<String>,
+    failed: bool
+}
+
+// What the parser expects to read next.
+#[derive(Clone, Copy)]
+enum State {
+    Length(bool),
+    ElementType,
+    Key(u8),
+    Value(u8)
+}
+
+// A document or array whose start has been emitted but not its end.
+struct OpenDocument {
+    end: u64,
+    is_array: bool
+}
+
+// A document or array being assembled by `next_document`, with its key in the
+// enclosing document.
+struct Builder {
+    key: Option<String>,
+    value: Bson
+}
+
+/// Implementation for the `PushParser` object.
+impl PushParser {
+
+    /// Create the new `PushParser` object.
+    ///
+    /// # Returns
+    /// The new `PushParser` object.
+    pub fn new() -> PushParser {
+        PushParser {
+            buffer: Vec::new(),
+            start: 0,
+            position: 0,
+            max_document_size: None,
+            state: State::Length(false),
+            open: Vec::new(),
+            builders: Vec::new(),
+            key: None,
+            failed: false
+        }
+    }
+
+    /// Set the largest top level document size in bytes that will be parsed.
+    /// A length prefix larger than this is returned as an error instead of
+    /// buffering the document's values as they arrive.
+    ///
+    /// # Parameters
+    /// - `size` - The maximum document size.
+    ///
+    /// # Returns
+    /// The `PushParser` object.
+    pub fn max_document_size(mut self, size: usize) -> PushParser {
+        self.max_document_size = Some(size);
+        self
+    }
+
+    /// Append the next chunk of bytes from the stream.
+    ///
+    /// # Parameters
+    /// - `bytes` - The bytes.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Get the number of bytes consumed from the stream so far.
+    ///
+    /// # Returns
+    /// The byte position.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Get the number of documents and arrays currently open.
+    ///
+    /// # Returns
+    /// The nesting depth.
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Get the number of bytes fed but not yet consumed by an event.
+    ///
+    /// # Returns
+    /// The number of bytes.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Get the next event that the bytes fed so far complete.
+    ///
+    /// # Returns
+    /// `None` if more bytes are needed, otherwise the `Result` with the
+    /// `ParseEvent`.
+    pub fn next_event(&mut self) -> Option<Result<ParseEvent>> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            match self.step() {
+                Ok(Some(Some(event))) => return Some(Ok(event)),
+                Ok(Some(None)) => {},
+                Ok(None) => return None,
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+
+    /// Get the next top level document that the bytes fed so far complete.
+    ///
+    /// # Returns
+    /// `None` if more bytes are needed, otherwise the `Result` with the
+    /// `Document`.
+    pub fn next_document(&mut self) -> Option<Result<Document>> {
+        loop {
+            let event = match self.next_event()? {
+                Ok(event) => event,
+                Err(error) => return Some(Err(error))
+            };
+            let value = match event {
+                ParseEvent::StartDocument => {
+                    self.builders.push(Builder { key: self.key.take(), value: Bson::Document(Document::new()) });
+                    continue;
+                },
+                ParseEvent::StartArray => {
+                    self.builders.push(Builder { key: self.key.take(), value: Bson::Array(Vec::new()) });
+                    continue;
+                },
+                ParseEvent::Key(key) => {
+                    self.key = Some(key);
+                    continue;
+                },
+                ParseEvent::Value(value) => {
+                    let key = self.key.take();
+                    self.add(key, value);
+                    continue;
+                },
+                ParseEvent::EndDocument | ParseEvent::EndArray => self.builders.pop()
+            };
+            if let Some(Builder { key, value }) = value {
+                if self.builders.is_empty() {
+                    if let Bson::Document(document) = value {
+                        return Some(Ok(document));
+                    }
+                } else {
+                    self.add(key, value);
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, key: Option<String>, value: Bson) {
+        match self.builders.last_mut() {
+            Some(&mut Builder { value: Bson::Document(ref mut document), .. }) => {
+                document.insert(key.unwrap_or_default(), value);
+            },
+            Some(&mut Builder { value: Bson::Array(ref mut values), .. }) => values.push(value),
+            _ => {}
+        }
+    }
+
+    /// Advance by one token if the buffered bytes hold all of it.
+    ///
+    /// # Returns
+    /// `None` if more bytes are needed, otherwise the event for the token if
+    /// it produced one.
+    fn step(&mut self) -> Result<Option<Option<ParseEvent>>> {
+        let available = self.buffer.len() - self.start;
+        match self.state {
+            State::Length(is_array) => {
+                if available < 4 {
+                    return Ok(None);
+                }
+                let length = document_length(LittleEndian::read_i32(&self.buffer[self.start..]))?;
+                if self.open.len() == MAX_NESTING_DEPTH {
+                    return Err(invalid_data(format!("document nesting exceeds the maximum depth of {}", MAX_NESTING_DEPTH)));
+                }
+                match self.max_document_size {
+                    Some(max) if self.open.is_empty() && length > max => {
+                        return Err(invalid_data(format!(
+                            "document length {} at offset {} exceeds the maximum of {}", length, self.position, max
+                        )));
+                    },
+                    _ => {}
+                }
+                let end = self.position + length as u64;
+                self.check_end(end)?;
+                self.consume(4);
+                self.open.push(OpenDocument { end, is_array });
+                self.state = State::ElementType;
+                Ok(Some(Some(if is_array { ParseEvent::StartArray } else { ParseEvent::StartDocument })))
+            },
+            State::ElementType => {
+                if available < 1 {
+                    return Ok(None);
+                }
+                let element_type = self.buffer[self.start];
+                self.check_end(self.position + 1)?;
+                self.consume(1);
+                if element_type != 0 {
+                    self.state = State::Key(element_type);
+                    return Ok(Some(None));
+                }
+                let document = self.open.pop().expect("an open document");
+                if self.position != document.end {
+                    return Err(invalid_data(format!(
+                        "document ending at offset {} is missing its terminator", document.end
+                    )));
+                }
+                self.state = if self.open.is_empty() { State::Length(false) } else { State::ElementType };
+                Ok(Some(Some(if document.is_array { ParseEvent::EndArray } else { ParseEvent::EndDocument })))
+            },
+            State::Key(element_type) => {
+                let length = match self.buffer[self.start..].iter().position(|b| *b == 0) {
+                    Some(length) => length + 1,
+                    None => return self.need_more(available)
+                };
+                self.check_end(self.position + length as u64)?;
+                let key = match str::from_utf8(&self.buffer[self.start..self.start + length - 1]) {
+                    Ok(key) => key.to_string(),
+                    Err(error) => return Err(invalid_data(format!("invalid utf-8: {}", error)))
+                };
+                self.consume(length);
+                self.state = match element_type {
+                    0x03 => State::Length(false),
+                    0x04 => State::Length(true),
+                    other => State::Value(other)
+                };
+                let in_array = self.open.last().is_some_and(|document| document.is_array);
+                Ok(Some(if in_array { None } else { Some(ParseEvent::Key(key)) }))
+            },
+            State::Value(element_type) => {
+                let length = match value_length(element_type, &self.buffer[self.start..])? {
+                    Some(length) => length,
+                    None => return self.need_more(available)
+                };
+                self.check_end(self.position + length as u64)?;
+                if length > available {
+                    return Ok(None);
+                }
+                let value = parse_value(element_type, &self.buffer[self.start..self.start + length])?;
+                self.consume(length);
+                self.state = State::ElementType;
+                Ok(Some(Some(ParseEvent::Value(value))))
+            }
+        }
+    }
+
+    fn check_end(&self, end: u64) -> Result<()> {
+        match self.open.last() {
+            Some(document) if end > document.end => Err(invalid_data(format!(
+                "element extends past the end of the document at offset {}", document.end
+            ))),
+            _ => Ok(())
+        }
+    }
+
+    fn need_more<T>(&self, available: usize) -> Result<Option<T>> {
+        self.check_end(self.position + available as u64 + 1)?;
+        Ok(None)
+    }
+
+    fn consume(&mut self, length: usize) {
+        self.start += length;
+        self.position += length as u64;
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> PushParser {
+        PushParser::new()
+    }
+}
+
+/// Get the length of a value of the element type at the start of the bytes.
+///
+/// # Parameters
+/// - `element_type` - The element type byte.
+/// - `bytes` - The buffered bytes.
+///
+/// # Returns
+/// The length, which may be more than the bytes given, or `None` if more bytes
+/// are needed to know it.
+fn value_length(element_type: u8, bytes: &[u8]) -> Result<Option<usize>> {
+    let prefixed = |extra: usize, minimum: i32| -> Result<Option<usize>> {
+        if bytes.len() < 4 {
+            return Ok(None);
+        }
+        let length = LittleEndian::read_i32(bytes);
+        if length < minimum {
+            return Err(invalid_data(format!("invalid length {} for element type 0x{:02X}", length, element_type)));
+        }
+        Ok(Some(length as usize + extra))
+    };
+    let length = match element_type {
+        0x06 | 0x0A | 0x7F | 0xFF => 0,
+        0x08 => 1,
+        0x10 => 4,
+        0x01 | 0x09 | 0x11 | 0x12 => 8,
+        0x07 => 12,
+        0x13 => 16,
+        0x02 | 0x0D | 0x0E => return prefixed(4, 1),
+        0x05 => return prefixed(5, 0),
+        0x0C => return prefixed(16, 1),
+        0x0F => return prefixed(0, 14),
+        0x0B => {
+            let mut nuls = bytes.iter().enumerate().filter(|&(_, b)| *b == 0).map(|(i, _)| i);
+            nuls.next();
+            return Ok(nuls.next().map(|i| i + 1));
+        },
+        other => return Err(invalid_data(format!("invalid element type 0x{:02X}", other)))
+    };
+    Ok(Some(length))
+}
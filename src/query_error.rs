@@ -0,0 +1,39 @@
+use std::error::Error;
+use std::fmt;
+
+/// The error returned when a query, such as a filter, is invalid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    message: String
+}
+
+/// The implementation for `QueryError`.
+impl QueryError {
+
+    /// Create a new `QueryError`.
+    ///
+    /// # Parameters
+    /// - `message` - The message describing the error.
+    ///
+    /// # Returns
+    /// The new `QueryError`.
+    pub fn new<S: Into<String>>(message: S) -> QueryError {
+        QueryError { message: message.into() }
+    }
+
+    /// Get the message describing the error.
+    ///
+    /// # Returns
+    /// The message `&str`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for QueryError {}
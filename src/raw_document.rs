@@ -0,0 +1,93 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::Result;
+use document::Document;
+use document_deserializer::{document_length, invalid_data, parse_document};
+use visitor::{visit_bytes, Visit, Visitor};
+
+/// An owned BSON document kept in its raw byte form. The length prefix and
+/// terminator are checked on creation, while the elements are only parsed
+/// when converted to a `Document`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawDocumentBuf {
+    bytes: Vec<u8>
+}
+
+/// The implementation for `RawDocumentBuf`.
+impl RawDocumentBuf {
+
+    /// Create a new `RawDocumentBuf` from the bytes of a single document.
+    ///
+    /// # Parameters
+    /// - `bytes` - The raw BSON bytes.
+    ///
+    /// # Returns
+    /// The `Result` with the `RawDocumentBuf`, or an `InvalidData` error if the
+    /// length prefix or terminator are wrong.
+    pub fn new(bytes: Vec<u8>) -> Result<RawDocumentBuf> {
+        if bytes.len() < 4 {
+            return Err(invalid_data("document is shorter than its length prefix".to_string()));
+        }
+        let length = document_length(LittleEndian::read_i32(&bytes))?;
+        if length != bytes.len() {
+            return Err(invalid_data(format!(
+                "document length {} does not match the {} bytes given", length, bytes.len()
+            )));
+        }
+        if bytes[length - 1] != 0 {
+            return Err(invalid_data("document is missing its terminator".to_string()));
+        }
+        Ok(RawDocumentBuf { bytes })
+    }
+
+    /// Get the raw bytes of the document.
+    ///
+    /// # Returns
+    /// The bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Unwrap the raw bytes of the document.
+    ///
+    /// # Returns
+    /// The bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Get the length of the document in bytes.
+    ///
+    /// # Returns
+    /// The length.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Determine if the document has no elements.
+    ///
+    /// # Returns
+    /// True if the document is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.len() == 5
+    }
+
+    /// Parse the elements into a `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the `Document`.
+    pub fn to_document(&self) -> Result<Document> {
+        parse_document(&self.bytes)
+    }
+
+    /// Walk the elements with a `Visitor`, without parsing them into a
+    /// `Document`.
+    ///
+    /// # Parameters
+    /// - `visitor` - The `Visitor` to call.
+    ///
+    /// # Returns
+    /// The `Result` with the `Visit` that ended the walk.
+    pub fn visit<V>(&self, visitor: &mut V) -> Result<Visit> where V: Visitor + ?Sized {
+        visit_bytes(&self.bytes, visitor)
+    }
+}
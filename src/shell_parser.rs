@@ -0,0 +1,562 @@
+use std::char;
+use std::error::Error;
+use std::fmt;
+use base64;
+use bson::Bson;
+use datetime;
+use decimal128::parse_decimal128;
+use document::Document;
+use document_deserializer::MAX_NESTING_DEPTH;
+use object_id::new_object_id;
+
+/// The error returned when shell syntax cannot be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShellParseError {
+    message: String,
+    position: usize
+}
+
+/// The implementation for `ShellParseError`.
+impl ShellParseError {
+
+    /// Get the message describing the error.
+    ///
+    /// # Returns
+    /// The message `&str`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the byte offset in the input where the error was found.
+    ///
+    /// # Returns
+    /// The byte offset.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for ShellParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl Error for ShellParseError {}
+
+/// The `ShellParser` object that parses MongoDB shell (JavaScript) literals,
+/// such as `{ _id: ObjectId("..."), n: NumberLong(5), re: /^a/i }`.
+///
+/// Keys may be unquoted, strings may use single or double quotes and trailing
+/// commas are allowed. Integer literals become `Int32` values, or `Int64` when
+/// they do not fit, and literals with a fraction or exponent become `Double`
+/// values. The supported constructors are `ObjectId`, `ISODate`, `Date`,
+/// `NumberInt`, `NumberLong`, `NumberDecimal`, `BinData`, `HexData`, `UUID`,
+/// `Timestamp`, `RegExp`, `Code`, `Symbol`, `DBPointer`, `MinKey` and
+/// `MaxKey`, each of which may be preceded by `new`. `ObjectId()` without an
+/// argument generates a new id. Documents, arrays and constructor arguments
+/// may nest at most 200 levels deep.
+pub struct ShellParser<'a> {
+    input: &'a str,
+    position: usize,
+    depth: usize
+}
+
+/// Implementation for the `ShellParser` object.
+impl<'a> ShellParser<'a> {
+
+    /// Create the new `ShellParser` object.
+    ///
+    /// # Parameters
+    /// - `input` - The shell syntax to parse.
+    ///
+    /// # Returns
+    /// The new `ShellParser` object.
+    pub fn new(input: &'a str) -> ShellParser<'a> {
+        ShellParser { input, position: 0, depth: 0 }
+    }
+
+    /// Parse the input as a single document.
+    ///
+    /// # Returns
+    /// The `Result` with the parsed `Document`.
+    pub fn parse_document(&mut self) -> Result<Document, ShellParseError> {
+        self.skip_whitespace()?;
+        if self.peek() != Some(b'{') {
+            return Err(self.error("Expected a document"));
+        }
+        let document = self.parse_object()?;
+        self.expect_end()?;
+        Ok(document)
+    }
+
+    /// Parse the input as a single value of any type.
+    ///
+    /// # Returns
+    /// The `Result` with the parsed `Bson` value.
+    pub fn parse_value(&mut self) -> Result<Bson, ShellParseError> {
+        let value = self.value()?;
+        self.expect_end()?;
+        Ok(value)
+    }
+
+    fn expect_end(&mut self) -> Result<(), ShellParseError> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(b';') {
+            self.position += 1;
+            self.skip_whitespace()?;
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("Unexpected trailing input"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Bson, ShellParseError> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some(b'{') => Ok(Bson::Document(self.parse_object()?)),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') | Some(b'\'') => Ok(Bson::String(self.parse_string()?)),
+            Some(b'/') => self.parse_regex_literal(),
+            Some(c) if c == b'-' || c == b'+' || c == b'.' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) if is_identifier_start(c) => self.parse_identifier_value(),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of input"))
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Document, ShellParseError> {
+        self.expect(b'{')?;
+        self.enter()?;
+        let mut document = Document::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(b'}') {
+                self.position += 1;
+                self.depth -= 1;
+                return Ok(document);
+            }
+            let key = self.parse_key()?;
+            self.skip_whitespace()?;
+            self.expect(b':')?;
+            let value = self.value()?;
+            document.insert(key, value);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {},
+                _ => return Err(self.error("Expected ',' or '}'"))
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Bson, ShellParseError> {
+        self.parse_array_like(b'[', b']')
+    }
+
+    fn parse_key(&mut self) -> Result<String, ShellParseError> {
+        match self.peek() {
+            Some(b'"') | Some(b'\'') => self.parse_string(),
+            Some(c) if is_identifier_start(c) || c.is_ascii_digit() => {
+                let start = self.position;
+                while self.peek().is_some_and(is_identifier_part) {
+                    self.position += 1;
+                }
+                Ok(self.input[start..self.position].to_string())
+            },
+            _ => Err(self.error("Expected a key"))
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ShellParseError> {
+        let start = self.position;
+        let quote = self.next_byte().unwrap();
+        let mut value = String::new();
+        loop {
+            let c = match self.next_char() {
+                Some(c) => c,
+                None => return Err(self.error_at("Unterminated string", start))
+            };
+            match c {
+                c if c as u32 == u32::from(quote) => return Ok(value),
+                '\\' => self.parse_escape(&mut value)?,
+                '\n' => return Err(self.error_at("Unterminated string", start)),
+                c => value.push(c)
+            }
+        }
+    }
+
+    fn parse_escape(&mut self, value: &mut String) -> Result<(), ShellParseError> {
+        let c = match self.next_char() {
+            Some(c) => c,
+            None => return Err(self.error("Unterminated escape sequence"))
+        };
+        match c {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            'b' => value.push('\u{08}'),
+            'f' => value.push('\u{0C}'),
+            'v' => value.push('\u{0B}'),
+            '0' => value.push('\0'),
+            '\n' => {},
+            'x' => {
+                let code = self.parse_hex_digits(2)?;
+                value.push(char::from_u32(code).unwrap());
+            },
+            'u' => {
+                let high = self.parse_hex_digits(4)?;
+                let code = if (0xD800..0xDC00).contains(&high) && self.input[self.position..].starts_with("\\u") {
+                    self.position += 2;
+                    let low = self.parse_hex_digits(4)?;
+                    0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                } else {
+                    high
+                };
+                match char::from_u32(code) {
+                    Some(c) => value.push(c),
+                    None => return Err(self.error("Invalid unicode escape"))
+                }
+            },
+            c => value.push(c)
+        }
+        Ok(())
+    }
+
+    fn parse_hex_digits(&mut self, count: usize) -> Result<u32, ShellParseError> {
+        let digits = match self.input.get(self.position..self.position + count) {
+            Some(digits) => digits,
+            None => return Err(self.error("Invalid escape sequence"))
+        };
+        match u32::from_str_radix(digits, 16) {
+            Ok(code) => {
+                self.position += count;
+                Ok(code)
+            },
+            Err(_) => Err(self.error("Invalid escape sequence"))
+        }
+    }
+
+    fn parse_regex_literal(&mut self) -> Result<Bson, ShellParseError> {
+        let start = self.position;
+        self.position += 1;
+        let mut in_class = false;
+        loop {
+            match self.next_char() {
+                None | Some('\n') => return Err(self.error_at("Unterminated regular expression", start)),
+                Some('\\') => {
+                    if self.next_char().is_none() {
+                        return Err(self.error_at("Unterminated regular expression", start));
+                    }
+                },
+                Some('[') => in_class = true,
+                Some(']') => in_class = false,
+                Some('/') if !in_class => break,
+                Some(_) => {}
+            }
+        }
+        let pattern = self.input[start + 1..self.position - 1].to_string();
+        let options_start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.position += 1;
+        }
+        let options = self.input[options_start..self.position].to_string();
+        Ok(Bson::RegExp(pattern, sort_options(&options)))
+    }
+
+    fn parse_number(&mut self) -> Result<Bson, ShellParseError> {
+        let start = self.position;
+        let negative = self.peek() == Some(b'-');
+        if self.peek() == Some(b'-') || self.peek() == Some(b'+') {
+            self.position += 1;
+        }
+        if self.input[self.position..].starts_with("Infinity") {
+            self.position += "Infinity".len();
+            return Ok(Bson::Double(if negative { -f64::INFINITY } else { f64::INFINITY }));
+        }
+        let mut is_double = false;
+        while let Some(c) = self.peek() {
+            match c {
+                b'0'..=b'9' => {},
+                b'.' | b'e' | b'E' => is_double = true,
+                b'+' | b'-' if matches!(self.input.as_bytes()[self.position - 1], b'e' | b'E') => {},
+                _ => break
+            }
+            self.position += 1;
+        }
+        let text = self.input[start..self.position].trim_start_matches('+');
+        if !is_double {
+            if let Ok(value) = text.parse::<i64>() {
+                if (i64::from(i32::MIN)..=i64::from(i32::MAX)).contains(&value) {
+                    return Ok(Bson::Int32(value as i32));
+                }
+                return Ok(Bson::Int64(value));
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(value) => Ok(Bson::Double(value)),
+            Err(_) => Err(self.error_at("Invalid number", start))
+        }
+    }
+
+    fn parse_identifier_value(&mut self) -> Result<Bson, ShellParseError> {
+        let start = self.position;
+        let mut name = self.parse_identifier();
+        if name == "new" {
+            self.skip_whitespace()?;
+            name = self.parse_identifier();
+        }
+        match name {
+            "true" => return Ok(Bson::Boolean(true)),
+            "false" => return Ok(Bson::Boolean(false)),
+            "null" => return Ok(Bson::Null),
+            "undefined" => return Ok(Bson::Undefined),
+            "NaN" => return Ok(Bson::Double(f64::NAN)),
+            "Infinity" => return Ok(Bson::Double(f64::INFINITY)),
+            _ => {}
+        }
+        self.skip_whitespace()?;
+        let args = if self.peek() == Some(b'(') {
+            self.parse_arguments()?
+        } else if name == "MinKey" || name == "MaxKey" {
+            Vec::new()
+        } else {
+            return Err(self.error_at(&format!("Unknown identifier '{}'", name), start));
+        };
+        self.construct(name, args, start)
+    }
+
+    fn parse_identifier(&mut self) -> &'a str {
+        let start = self.position;
+        while self.peek().is_some_and(is_identifier_part) {
+            self.position += 1;
+        }
+        &self.input[start..self.position]
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<Bson>, ShellParseError> {
+        match self.parse_array_like(b'(', b')')? {
+            Bson::Array(values) => Ok(values),
+            _ => unreachable!()
+        }
+    }
+
+    fn parse_array_like(&mut self, open: u8, close: u8) -> Result<Bson, ShellParseError> {
+        self.expect(open)?;
+        self.enter()?;
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(close) {
+                self.position += 1;
+                self.depth -= 1;
+                return Ok(Bson::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(c) if c == close => {},
+                _ => return Err(self.error(&format!("Expected ',' or '{}'", close as char)))
+            }
+        }
+    }
+
+    fn construct(&self, name: &str, args: Vec<Bson>, start: usize) -> Result<Bson, ShellParseError> {
+        let invalid = |message: &str| Err(self.error_at(&format!("{}: {}", name, message), start));
+        match (name, args.as_slice()) {
+            ("ObjectId", []) => Ok(Bson::ObjectId(new_object_id())),
+            ("ObjectId", [Bson::String(hex)]) => match decode_hex(hex) {
+                Some(ref bytes) if bytes.len() == 12 => {
+                    let mut id = [0u8; 12];
+                    id.copy_from_slice(bytes);
+                    Ok(Bson::ObjectId(id))
+                },
+                _ => invalid("expected a 24 character hex string")
+            },
+            ("ISODate", []) | ("Date", []) => Ok(Bson::DateTime(datetime::now())),
+            ("ISODate", [Bson::String(value)]) | ("Date", [Bson::String(value)]) => {
+                match datetime::parse_iso8601(value) {
+                    Some(millis) => Ok(Bson::DateTime(millis)),
+                    None => invalid("invalid ISO-8601 date")
+                }
+            },
+            ("ISODate", [value]) | ("Date", [value]) => match integer(value) {
+                Some(millis) => Ok(Bson::DateTime(millis)),
+                None => invalid("expected a string or number")
+            },
+            ("NumberInt", [value]) => match integer(value) {
+                Some(value) if (i64::from(i32::MIN)..=i64::from(i32::MAX)).contains(&value) => {
+                    Ok(Bson::Int32(value as i32))
+                },
+                _ => invalid("expected a 32-bit integer")
+            },
+            ("NumberInt", []) => Ok(Bson::Int32(0)),
+            ("NumberLong", [value]) => match integer(value) {
+                Some(value) => Ok(Bson::Int64(value)),
+                None => invalid("expected a 64-bit integer")
+            },
+            ("NumberLong", []) => Ok(Bson::Int64(0)),
+            ("NumberDecimal", [value]) => match decimal(value) {
+                Some(bytes) => Ok(Bson::Decimal128(bytes)),
+                None => invalid("expected a decimal string or number")
+            },
+            ("NumberDecimal", []) => Ok(Bson::Decimal128(parse_decimal128("0").expect("a decimal"))),
+            ("BinData", [subtype, Bson::String(data)]) => match (integer(subtype), base64::decode(data)) {
+                (Some(subtype), Some(bytes)) if (0..=255).contains(&subtype) => {
+                    Ok(Bson::Binary(subtype as u8, bytes))
+                },
+                _ => invalid("expected a subtype and a base64 string")
+            },
+            ("HexData", [subtype, Bson::String(data)]) => match (integer(subtype), decode_hex(data)) {
+                (Some(subtype), Some(bytes)) if (0..=255).contains(&subtype) => {
+                    Ok(Bson::Binary(subtype as u8, bytes))
+                },
+                _ => invalid("expected a subtype and a hex string")
+            },
+            ("UUID", [Bson::String(value)]) => match decode_hex(&value.replace('-', "")) {
+                Some(bytes) if bytes.len() == 16 => Ok(Bson::Binary(4, bytes)),
+                _ => invalid("expected a 32 digit hex string")
+            },
+            ("Timestamp", [time, increment]) => match (integer(time), integer(increment)) {
+                (Some(time), Some(increment)) => Ok(timestamp(time, increment)),
+                _ => invalid("expected a time and an increment")
+            },
+            ("Timestamp", [Bson::Document(document)]) => {
+                match (document.get("t").and_then(integer), document.get("i").and_then(integer)) {
+                    (Some(time), Some(increment)) => Ok(timestamp(time, increment)),
+                    _ => invalid("expected a document with 't' and 'i' fields")
+                }
+            },
+            ("Timestamp", []) => Ok(Bson::Timestamp(0)),
+            ("RegExp", [Bson::String(pattern)]) => Ok(Bson::RegExp(pattern.clone(), String::new())),
+            ("RegExp", [Bson::String(pattern), Bson::String(options)]) => {
+                Ok(Bson::RegExp(pattern.clone(), sort_options(options)))
+            },
+            ("Code", [Bson::String(code)]) => Ok(Bson::Code(code.clone(), Document::new())),
+            ("Code", [Bson::String(code), Bson::Document(scope)]) => Ok(Bson::Code(code.clone(), scope.clone())),
+            ("Symbol", [Bson::String(value)]) => Ok(Bson::Symbol(value.clone())),
+            ("DBPointer", [Bson::String(name), Bson::ObjectId(id)]) => Ok(Bson::DbPointer(name.clone(), *id)),
+            ("MinKey", []) => Ok(Bson::MinKey),
+            ("MaxKey", []) => Ok(Bson::MaxKey),
+            ("ObjectId", _) | ("ISODate", _) | ("Date", _) | ("NumberInt", _) | ("NumberLong", _) |
+            ("NumberDecimal", _) | ("BinData", _) | ("HexData", _) | ("UUID", _) | ("Timestamp", _) | ("RegExp", _) |
+            ("Code", _) | ("Symbol", _) | ("DBPointer", _) | ("MinKey", _) | ("MaxKey", _) => {
+                invalid("invalid arguments")
+            },
+            _ => Err(self.error_at(&format!("Unknown constructor '{}'", name), start))
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), ShellParseError> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(self.error(&format!("Nesting exceeds the maximum depth of {}", MAX_NESTING_DEPTH)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), ShellParseError> {
+        loop {
+            let rest = &self.input[self.position..];
+            if rest.starts_with("//") {
+                self.position += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                match rest.find("*/") {
+                    Some(end) => self.position += end + 2,
+                    None => return Err(self.error("Unterminated comment"))
+                }
+            } else if let Some(c) = rest.chars().next().filter(|c| c.is_whitespace()) {
+                self.position += c.len_utf8();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), ShellParseError> {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", expected as char)))
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).cloned()
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        let byte = self.peek();
+        self.position += 1;
+        byte
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.input[self.position..].chars().next();
+        if let Some(c) = c {
+            self.position += c.len_utf8();
+        }
+        c
+    }
+
+    fn error(&self, message: &str) -> ShellParseError {
+        self.error_at(message, self.position)
+    }
+
+    fn error_at(&self, message: &str, position: usize) -> ShellParseError {
+        ShellParseError { message: message.to_string(), position }
+    }
+}
+
+fn is_identifier_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || c == b'$'
+}
+
+fn is_identifier_part(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+}
+
+// Accepts numbers and numeric strings, as the shell constructors do.
+fn integer(value: &Bson) -> Option<i64> {
+    match value {
+        Bson::Int32(v) => Some(i64::from(*v)),
+        Bson::Int64(v) => Some(*v),
+        Bson::Double(v) if v.trunc() == *v => Some(*v as i64),
+        Bson::String(v) => v.trim().parse().ok(),
+        _ => None
+    }
+}
+
+// Doubles are converted from their shortest round trip representation, so
+// that `NumberDecimal(0.1)` is exactly 0.1.
+fn decimal(value: &Bson) -> Option<[u8; 16]> {
+    match value {
+        Bson::Int32(v) => parse_decimal128(&v.to_string()),
+        Bson::Int64(v) => parse_decimal128(&v.to_string()),
+        Bson::Double(v) => parse_decimal128(&v.to_string()),
+        Bson::String(v) => parse_decimal128(v.trim()),
+        _ => None
+    }
+}
+
+fn timestamp(time: i64, increment: i64) -> Bson {
+    Bson::Timestamp(((time as u64) << 32) | (increment as u64 & 0xFFFF_FFFF))
+}
+
+// BSON requires regular expression options to be stored in alphabetical
+// order.
+fn sort_options(options: &str) -> String {
+    let mut chars: Vec<char> = options.chars().collect();
+    chars.sort();
+    chars.into_iter().collect()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return None;
+    }
+    (0..value.len()).step_by(2).map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok()).collect()
+}
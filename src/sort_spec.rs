@@ -0,0 +1,103 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use bson::Bson;
+use comparison::bson_cmp;
+use document::Document;
+use filter::whole_number;
+use path::lookup;
+use query_error::QueryError;
+
+/// A parsed sort specification, such as `{ "a": 1, "b.c": -1 }`, that
+/// orders documents the way the server does.
+///
+/// Each key is a dotted path, compared in turn until one differs. A missing
+/// field sorts as null and an empty array sorts before null. When the path
+/// reaches arrays, the values found are all candidates for the sort key and
+/// the smallest is used for an ascending key and the largest for a
+/// descending one. Values of different types are ordered by their BSON type
+/// bracket, as by `bson_cmp`. Use `compare` with `slice::sort_by` to sort.
+#[derive(Clone, Debug)]
+pub struct SortSpec {
+    keys: Vec<(String, bool)>
+}
+
+/// Implementation for the `SortSpec` object.
+impl SortSpec {
+
+    /// Parse a sort specification.
+    ///
+    /// # Parameters
+    /// - `spec` - The sort specification `Document`, mapping paths to 1 for
+    ///   ascending or -1 for descending order.
+    ///
+    /// # Returns
+    /// The `Result` with the `SortSpec`, or a `QueryError` carrying the
+    /// server's message if the specification is invalid.
+    pub fn parse(spec: &Document) -> Result<SortSpec, QueryError> {
+        let mut keys = Vec::with_capacity(spec.len());
+        for (path, direction) in spec.iter() {
+            if path.is_empty() || path.split('.').any(str::is_empty) {
+                return Err(QueryError::new(format!("FieldPath field names may not be empty strings: '{}'", path)));
+            }
+            match whole_number(direction) {
+                Some(1) => keys.push((path.clone(), false)),
+                Some(-1) => keys.push((path.clone(), true)),
+                _ => return Err(QueryError::new("$sort key ordering must be 1 (for ascending) or -1 (for descending)"))
+            }
+        }
+        Ok(SortSpec { keys })
+    }
+
+    /// Determine if the specification has no keys, leaving documents in their
+    /// original order.
+    ///
+    /// # Returns
+    /// True if there are no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Compare two documents by the specification.
+    ///
+    /// # Parameters
+    /// - `left` - The first `Document`.
+    /// - `right` - The second `Document`.
+    ///
+    /// # Returns
+    /// The `Ordering` of the first document relative to the second.
+    pub fn compare(&self, left: &Document, right: &Document) -> Ordering {
+        for (path, descending) in &self.keys {
+            let ordering = bson_cmp(&sort_key(left, path, *descending), &sort_key(right, path, *descending));
+            if ordering != Ordering::Equal {
+                return if *descending { ordering.reverse() } else { ordering };
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// Get the value a document sorts by for one key.
+///
+/// # Parameters
+/// - `document` - The `Document`.
+/// - `path` - The dotted path of the key.
+/// - `descending` - Whether the key sorts in descending order.
+///
+/// # Returns
+/// The smallest candidate value for an ascending key, otherwise the largest.
+fn sort_key<'a>(document: &'a Document, path: &str, descending: bool) -> Cow<'a, Bson> {
+    let mut candidates: Vec<Cow<'a, Bson>> = Vec::new();
+    for value in lookup(document, path) {
+        match value {
+            None => candidates.push(Cow::Owned(Bson::Null)),
+            Some(Bson::Array(elements)) if elements.is_empty() => candidates.push(Cow::Owned(Bson::Undefined)),
+            Some(Bson::Array(elements)) => candidates.extend(elements.iter().map(Cow::Borrowed)),
+            Some(value) => candidates.push(Cow::Borrowed(value))
+        }
+    }
+    let wanted = if descending { Ordering::Greater } else { Ordering::Less };
+    candidates.into_iter().fold(None, |key: Option<Cow<'a, Bson>>, candidate| match key {
+        Some(key) if bson_cmp(&candidate, &key) != wanted => Some(key),
+        _ => Some(candidate)
+    }).unwrap_or(Cow::Owned(Bson::Null))
+}
@@ -0,0 +1,388 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+use std::vec;
+use linked_hash_map::LinkedHashMap;
+use bson::Bson;
+use crc32c::checksum;
+use document::Document;
+use document_deserializer::{document_length, invalid_data, parse_document};
+use document_serializer::DocumentSerializer;
+use hashable::HashableBson;
+
+/// The `Store` object, an append-only file of documents keyed by `_id`.
+///
+/// The file is a log of records, each a BSON document followed by the
+/// little-endian CRC-32C of its bytes. Putting a document appends
+/// `{ op: "put", doc: <document> }` and deleting one appends the tombstone
+/// `{ op: "delete", _id: <id> }`, so the latest record for an `_id` wins.
+/// Opening the store replays the log to rebuild an in-memory index from
+/// each live `_id` to the offset of its record. A torn final record, such
+/// as one left by a crash part way through an append, is truncated away,
+/// but a corrupt record followed by more of the log fails the open and
+/// leaves the file untouched.
+///
+/// Superseded records and tombstones stay in the file until `compact`
+/// rewrites the live documents to a new file and renames it over the old
+/// one, so a crash during compaction leaves the original log intact.
+/// Appends are not synced to disk until `sync` is called. Reads use
+/// positioned reads on the shared file, so `get` and `scan` never move a
+/// cursor that another reader depends on.
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+    file: File,
+    index: LinkedHashMap<HashableBson, u64>,
+    records: usize,
+    end: u64
+}
+
+/// The iterator over the documents in a `Store`, returned by `Store::scan`.
+pub struct Scan<'a> {
+    file: &'a File,
+    offsets: vec::IntoIter<u64>
+}
+
+// A record in the log.
+enum Record {
+    Put(Document),
+    Delete(Bson)
+}
+
+// Reads a file from an offset without using or moving its cursor.
+struct PositionedReader<'a> {
+    file: &'a File,
+    offset: u64
+}
+
+/// Implementation for the `Store` object.
+impl Store {
+
+    /// Open the store at the path, creating the file if it does not exist.
+    ///
+    /// # Parameters
+    /// - `path` - The path of the log file.
+    ///
+    /// # Returns
+    /// The `Result` with the `Store`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Store> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+        let mut index = LinkedHashMap::new();
+        let mut records = 0;
+        let mut end = 0;
+        {
+            let mut reader = BufReader::new(&file);
+            loop {
+                match read_record(&mut reader) {
+                    Ok(Some((record, length))) => {
+                        match record {
+                            Record::Put(document) => {
+                                index.insert(id_key(&document)?, end);
+                            },
+                            Record::Delete(id) => {
+                                index.remove(&HashableBson::numeric(id));
+                            }
+                        }
+                        records += 1;
+                        end += length;
+                    },
+                    Ok(None) => break,
+                    Err(ref error) if is_torn(&file, end, error)? => {
+                        file.set_len(end)?;
+                        break;
+                    },
+                    Err(error) => return Err(error)
+                }
+            }
+        }
+        Ok(Store { path, file, index, records, end })
+    }
+
+    /// Get the path of the log file.
+    ///
+    /// # Returns
+    /// The path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the number of live documents.
+    ///
+    /// # Returns
+    /// The number of documents.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Determine if the store has no live documents.
+    ///
+    /// # Returns
+    /// True if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Get the number of records in the log that `compact` would remove,
+    /// the superseded documents and tombstones.
+    ///
+    /// # Returns
+    /// The number of dead records.
+    pub fn dead_records(&self) -> usize {
+        self.records - self.index.len()
+    }
+
+    /// Get the document with the `_id`.
+    ///
+    /// # Parameters
+    /// - `id` - The `_id` value.
+    ///
+    /// # Returns
+    /// The `Result` with the `Document`, if there is one.
+    pub fn get(&self, id: &Bson) -> Result<Option<Document>> {
+        match self.index.get(&HashableBson::numeric(id.clone())) {
+            Some(&offset) => read_document_at(&self.file, offset).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    /// Put a document, replacing any document with the same `_id`.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document`, which must have an `_id`.
+    ///
+    /// # Returns
+    /// The `Result`, with an `InvalidInput` error if the document has no `_id`.
+    pub fn put(&mut self, document: &Document) -> Result<()> {
+        let key = id_key(document)?;
+        let mut record = Document::new();
+        record.insert("op".to_string(), Bson::String("put".to_string()));
+        record.insert("doc".to_string(), Bson::Document(document.clone()));
+        let offset = self.append(&record)?;
+        self.index.insert(key, offset);
+        Ok(())
+    }
+
+    /// Delete the document with the `_id`.
+    ///
+    /// # Parameters
+    /// - `id` - The `_id` value.
+    ///
+    /// # Returns
+    /// The `Result` with whether there was a document to delete.
+    pub fn delete(&mut self, id: &Bson) -> Result<bool> {
+        let key = HashableBson::numeric(id.clone());
+        if !self.index.contains_key(&key) {
+            return Ok(false);
+        }
+        let mut record = Document::new();
+        record.insert("op".to_string(), Bson::String("delete".to_string()));
+        record.insert("_id".to_string(), id.clone());
+        self.append(&record)?;
+        self.index.remove(&key);
+        Ok(true)
+    }
+
+    /// Iterate over the live documents, in the order they were last put.
+    ///
+    /// # Returns
+    /// The `Scan` iterator.
+    pub fn scan(&self) -> Scan<'_> {
+        let offsets: Vec<u64> = self.index.values().cloned().collect();
+        Scan { file: &self.file, offsets: offsets.into_iter() }
+    }
+
+    /// Sync the appended records to disk.
+    ///
+    /// # Returns
+    /// The `Result`.
+    pub fn sync(&self) -> Result<()> {
+        self.file.sync_data()
+    }
+
+    /// Rewrite the log with only the live documents, replacing the old file
+    /// by renaming the new one over it.
+    ///
+    /// # Returns
+    /// The `Result`. On error the store is left using the original log.
+    pub fn compact(&mut self) -> Result<()> {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".compact");
+        let temporary = PathBuf::from(temporary);
+        let mut index = LinkedHashMap::with_capacity(self.index.len());
+        let mut end = 0;
+        {
+            let mut output = File::create(&temporary)?;
+            let mut bytes = Vec::new();
+            for (key, &offset) in self.index.iter() {
+                let document = read_document_at(&self.file, offset)?;
+                let mut record = Document::new();
+                record.insert("op".to_string(), Bson::String("put".to_string()));
+                record.insert("doc".to_string(), Bson::Document(document));
+                bytes.clear();
+                encode_record(&record, &mut bytes)?;
+                output.write_all(&bytes)?;
+                index.insert(key.clone(), end);
+                end += bytes.len() as u64;
+            }
+            output.sync_all()?;
+        }
+        fs::rename(&temporary, &self.path)?;
+        if let Some(parent) = self.path.parent() {
+            if let Ok(directory) = File::open(if parent.as_os_str().is_empty() { Path::new(".") } else { parent }) {
+                let _ = directory.sync_all();
+            }
+        }
+        self.file = OpenOptions::new().read(true).append(true).open(&self.path)?;
+        self.records = index.len();
+        self.index = index;
+        self.end = end;
+        Ok(())
+    }
+
+    /// Append a record to the log.
+    ///
+    /// # Parameters
+    /// - `record` - The record `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the offset of the record.
+    fn append(&mut self, record: &Document) -> Result<u64> {
+        let mut bytes = Vec::new();
+        encode_record(record, &mut bytes)?;
+        if let Err(error) = self.file.write_all(&bytes) {
+            let _ = self.file.set_len(self.end);
+            return Err(error);
+        }
+        let offset = self.end;
+        self.end += bytes.len() as u64;
+        self.records += 1;
+        Ok(offset)
+    }
+}
+
+impl<'a> Iterator for Scan<'a> {
+    type Item = Result<Document>;
+
+    fn next(&mut self) -> Option<Result<Document>> {
+        self.offsets.next().map(|offset| read_document_at(self.file, offset))
+    }
+}
+
+impl<'a> Read for PositionedReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = read_at(self.file, buf, self.offset)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    use std::os::unix::fs::FileExt;
+    file.read_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    use std::os::windows::fs::FileExt;
+    file.seek_read(buf, offset)
+}
+
+/// Get the index key of a document's `_id`.
+fn id_key(document: &Document) -> Result<HashableBson> {
+    match document.get("_id") {
+        Some(id) => Ok(HashableBson::numeric(id.clone())),
+        None => Err(Error::new(ErrorKind::InvalidInput, "document has no _id"))
+    }
+}
+
+/// Determine if an error reading the record at an offset means the log
+/// ends with a torn append. That is the case when the file ends inside the
+/// record, when a corrupt record runs exactly to the end of the file, or
+/// when the rest of the file is zero filled. Anything else is corruption
+/// in the middle of the log.
+fn is_torn(file: &File, offset: u64, error: &Error) -> Result<bool> {
+    match error.kind() {
+        ErrorKind::UnexpectedEof => return Ok(true),
+        ErrorKind::InvalidData => {},
+        _ => return Ok(false)
+    }
+    let size = file.metadata()?.len();
+    let mut prefix = [0u8; 4];
+    PositionedReader { file, offset }.read_exact(&mut prefix)?;
+    if let Ok(length) = document_length(LittleEndian::read_i32(&prefix)) {
+        if offset + length as u64 + 4 == size {
+            return Ok(true);
+        }
+    }
+    let mut reader = PositionedReader { file, offset };
+    let mut chunk = [0u8; 4096];
+    loop {
+        match reader.read(&mut chunk)? {
+            0 => return Ok(true),
+            n if chunk[..n].iter().all(|&byte| byte == 0) => {},
+            _ => return Ok(false)
+        }
+    }
+}
+
+/// Serialize a record followed by its checksum.
+fn encode_record(record: &Document, bytes: &mut Vec<u8>) -> Result<()> {
+    let start = bytes.len();
+    DocumentSerializer::new(bytes).serialize(record)?;
+    let crc = checksum(&bytes[start..]);
+    let mut suffix = [0u8; 4];
+    LittleEndian::write_u32(&mut suffix, crc);
+    bytes.extend_from_slice(&suffix);
+    Ok(())
+}
+
+/// Read the document of the put record at an offset.
+fn read_document_at(file: &File, offset: u64) -> Result<Document> {
+    match read_record(&mut BufReader::new(PositionedReader { file, offset }))? {
+        Some((Record::Put(document), _)) => Ok(document),
+        _ => Err(invalid_data(format!("no document record at offset {}", offset)))
+    }
+}
+
+/// Read the next record from the log.
+///
+/// # Returns
+/// The `Result` with the record and its length in bytes, or `None` at the
+/// end of the log.
+fn read_record<R: Read>(reader: &mut R) -> Result<Option<(Record, u64)>> {
+    let mut prefix = [0u8; 4];
+    let mut read = 0;
+    while read < prefix.len() {
+        match reader.read(&mut prefix[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "truncated record length")),
+            Ok(n) => read += n,
+            Err(ref error) if error.kind() == ErrorKind::Interrupted => {},
+            Err(error) => return Err(error)
+        }
+    }
+    // Read the rest as it arrives, so a torn or garbage length cannot
+    // allocate more than the log holds.
+    let length = document_length(LittleEndian::read_i32(&prefix))?;
+    let mut bytes = prefix.to_vec();
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length + 4 {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "truncated record"));
+    }
+    let (document, crc) = bytes.split_at(length);
+    if checksum(document) != LittleEndian::read_u32(crc) {
+        return Err(invalid_data("record checksum mismatch".to_string()));
+    }
+    let mut record = parse_document(document)?;
+    let record = match (record.remove("op"), record.remove("doc"), record.remove("_id")) {
+        (Some(Bson::String(ref op)), Some(Bson::Document(document)), None) if op == "put" && document.contains_key("_id") => {
+            Record::Put(document)
+        },
+        (Some(Bson::String(ref op)), None, Some(id)) if op == "delete" => Record::Delete(id),
+        _ => return Err(invalid_data("unrecognized record".to_string()))
+    };
+    Ok(Some((record, bytes.len() as u64)))
+}
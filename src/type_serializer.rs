@@ -1,9 +1,10 @@
 use byteorder::{LittleEndian, WriteBytesExt};
-use std::io::{Result, Write};
+use std::io::{Error, ErrorKind, Result, Write};
 use bson::Bson;
 use document::Document;
+use document_serializer::DocumentSerializer;
 
-/// The `TyepSerializer` object that can serialize BSON types.
+/// The `TypeSerializer` object that can serialize BSON types.
 pub struct TypeSerializer<'a, W: ?Sized> where W: Write + 'a {
     writer: &'a mut W,
 }
@@ -18,9 +19,9 @@ impl<'a, W> TypeSerializer<'a, W> where W: Write + 'a {
     ///
     /// # Returns
     /// The new `TypeSerializer` object.
-    pub fn new(writer: &mut W) -> TypeSerializer<W> {
+    pub fn new(writer: &'a mut W) -> TypeSerializer<'a, W> {
         TypeSerializer {
-            writer: writer
+            writer
         }
     }
 
@@ -32,49 +33,60 @@ impl<'a, W> TypeSerializer<'a, W> where W: Write + 'a {
     /// # Returns
     /// The `Result` object.
     pub fn serialize(&mut self, bson: &Bson) -> Result<()> {
-        match bson {
-            &Bson::Double(value) => self.serialize_double(value),
-            &Bson::String(ref value) => self.serialize_string(value),
-            &Bson::Document(ref value) => self.serialize_document(value),
-            &Bson::Array(ref value) => self.serialize_array(value),
-            &Bson::Binary(t, ref value) => self.serialize_binary(t, value),
-            &Bson::Undefined => self.serialize_null(),
-            &Bson::Boolean(value) => self.serialize_boolean(value),
-            &Bson::DateTime(value) => self.serialize_datetime(value),
-            &Bson::Null => self.serialize_null(),
-            &Bson::RegExp(ref pattern, ref options) => self.serialize_regexp(pattern, options),
-            &Bson::DbPointer(ref name, ref id) => self.serialize_dbpointer(name, id),
-            &Bson::Code(ref code, ref scope) => self.serialize_code(code, scope),
-            &Bson::Symbol(ref value) => self.serialize_string(value),
-            &Bson::Int32(value) => self.serialize_i32(value),
-            &Bson::Timestamp(value) => self.serialize_u64(value),
-            &Bson::Int64(value) => self.serialize_i64(value),
-            &Bson::MinKey => self.serialize_minkey(),
-            &Bson::MaxKey => self.serialize_maxkey()
+        match *bson {
+            Bson::Double(value) => self.serialize_double(value),
+            Bson::String(ref value) => self.serialize_string(value),
+            Bson::Document(ref value) => self.serialize_document(value),
+            Bson::Array(ref value) => self.serialize_array(value),
+            Bson::Binary(t, ref value) => self.serialize_binary(t, value),
+            Bson::Undefined => self.serialize_null(),
+            Bson::ObjectId(ref id) => self.serialize_objectid(id),
+            Bson::Boolean(value) => self.serialize_boolean(value),
+            Bson::DateTime(value) => self.serialize_datetime(value),
+            Bson::Null => self.serialize_null(),
+            Bson::RegExp(ref pattern, ref options) => self.serialize_regexp(pattern, options),
+            Bson::DbPointer(ref name, ref id) => self.serialize_dbpointer(name, id),
+            Bson::Code(ref code, ref scope) => self.serialize_code(code, scope),
+            Bson::Symbol(ref value) => self.serialize_string(value),
+            Bson::Int32(value) => self.serialize_i32(value),
+            Bson::Timestamp(value) => self.serialize_u64(value),
+            Bson::Int64(value) => self.serialize_i64(value),
+            Bson::Decimal128(ref value) => self.serialize_decimal128(value),
+            Bson::MinKey => self.serialize_minkey(),
+            Bson::MaxKey => self.serialize_maxkey()
         }
     }
 
     fn serialize_double(&mut self, value: f64) -> Result<()> {
-        Ok(())
+        self.writer.write_f64::<LittleEndian>(value)
     }
 
     fn serialize_string(&mut self, value: &str) -> Result<()> {
-        self.writer.write_i32::<LittleEndian>(value.len() as i32 + 1);
-        self.writer.write_all(value.as_bytes());
-        self.writer.write_u8(0);
-        Ok(())
+        self.writer.write_i32::<LittleEndian>(value.len() as i32 + 1)?;
+        self.writer.write_all(value.as_bytes())?;
+        self.writer.write_u8(0)
+    }
+
+    fn serialize_cstring(&mut self, value: &str) -> Result<()> {
+        write_cstring(self.writer, value)
     }
 
     fn serialize_document(&mut self, value: &Document) -> Result<()> {
-        Ok(())
+        DocumentSerializer::new(self.writer).serialize(value)
     }
 
-    fn serialize_array(&mut self, value: &Vec<Bson>) -> Result<()> {
-        Ok(())
+    fn serialize_array(&mut self, value: &[Bson]) -> Result<()> {
+        DocumentSerializer::new(self.writer).serialize_array(value)
     }
 
-    fn serialize_binary(&mut self, t: u8, value: &Vec<u8>) -> Result<()> {
-        Ok(())
+    fn serialize_binary(&mut self, t: u8, value: &[u8]) -> Result<()> {
+        self.writer.write_i32::<LittleEndian>(value.len() as i32)?;
+        self.writer.write_u8(t)?;
+        self.writer.write_all(value)
+    }
+
+    fn serialize_objectid(&mut self, id: &[u8; 12]) -> Result<()> {
+        self.writer.write_all(id)
     }
 
     fn serialize_null(&mut self) -> Result<()> {
@@ -82,35 +94,48 @@ impl<'a, W> TypeSerializer<'a, W> where W: Write + 'a {
     }
 
     fn serialize_boolean(&mut self, value: bool) -> Result<()> {
-        Ok(())
+        self.writer.write_u8(value as u8)
     }
 
     fn serialize_datetime(&mut self, value: i64) -> Result<()> {
-        Ok(())
+        self.writer.write_i64::<LittleEndian>(value)
     }
 
     fn serialize_regexp(&mut self, pattern: &str, options: &str) -> Result<()> {
-        Ok(())
+        self.serialize_cstring(pattern)?;
+        self.serialize_cstring(options)
     }
 
     fn serialize_dbpointer(&mut self, name: &str, id: &[u8; 12]) -> Result<()> {
-        Ok(())
+        self.serialize_string(name)?;
+        self.writer.write_all(id)
     }
 
     fn serialize_code(&mut self, code: &str, scope: &Document) -> Result<()> {
-        Ok(())
+        if scope.is_empty() {
+            return self.serialize_string(code);
+        }
+        let mut buffer = Vec::new();
+        TypeSerializer::new(&mut buffer).serialize_string(code)?;
+        DocumentSerializer::new(&mut buffer).serialize(scope)?;
+        self.writer.write_i32::<LittleEndian>(buffer.len() as i32 + 4)?;
+        self.writer.write_all(&buffer)
     }
 
     fn serialize_i32(&mut self, value: i32) -> Result<()> {
-        Ok(())
+        self.writer.write_i32::<LittleEndian>(value)
     }
 
     fn serialize_u64(&mut self, value: u64) -> Result<()> {
-        Ok(())
+        self.writer.write_u64::<LittleEndian>(value)
     }
 
     fn serialize_i64(&mut self, value: i64) -> Result<()> {
-        Ok(())
+        self.writer.write_i64::<LittleEndian>(value)
+    }
+
+    fn serialize_decimal128(&mut self, value: &[u8; 16]) -> Result<()> {
+        self.writer.write_all(value)
     }
 
     fn serialize_minkey(&mut self) -> Result<()> {
@@ -121,3 +146,19 @@ impl<'a, W> TypeSerializer<'a, W> where W: Write + 'a {
         Ok(())
     }
 }
+
+/// Write a null terminated string, as used for keys and regular expressions.
+///
+/// # Parameters
+/// - `writer` - The writer to write to.
+/// - `value` - The string, which must not contain a null byte.
+///
+/// # Returns
+/// The `Result` object.
+pub fn write_cstring<W: Write + ?Sized>(writer: &mut W, value: &str) -> Result<()> {
+    if value.as_bytes().contains(&0) {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("{:?} contains a null byte", value)));
+    }
+    writer.write_all(value.as_bytes())?;
+    writer.write_u8(0)
+}
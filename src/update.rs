@@ -0,0 +1,911 @@
+use std::cmp::Ordering;
+use bson::Bson;
+use comparison::bson_cmp;
+use datetime;
+use decimal128::Decimal;
+use document::Document;
+use filter::{positional_index, whole_number, Filter, ValueMatcher};
+use path::array_index;
+use query_error::QueryError;
+
+// The most null elements an update may add to pad an array up to an index,
+// as enforced by the server.
+const MAX_ARRAY_PADDING: usize = 1_500_000;
+
+/// Apply an update document to a document, with the same semantics as the
+/// server. The update is either a document of update operators, or a
+/// replacement document that keeps the existing `_id`.
+///
+/// # Parameters
+/// - `document` - The `Document` to update.
+/// - `update` - The update `Document`.
+///
+/// # Returns
+/// The `Result`, with a `QueryError` carrying the server's message if the
+/// update is invalid or cannot be applied, in which case the document is
+/// left unchanged.
+pub fn apply_update(document: &mut Document, update: &Document) -> Result<(), QueryError> {
+    Update::parse(update)?.apply(document)
+}
+
+/// A parsed update that can be applied to documents in memory.
+///
+/// The supported operators are `$set`, `$unset`, `$inc`, `$mul`, `$min`,
+/// `$max`, `$rename`, `$setOnInsert`, `$currentDate`, `$push` with `$each`,
+/// `$slice`, `$sort` and `$position`, `$pull`, `$pullAll`, `$addToSet` with
+/// `$each`, and `$pop`. Paths may use the positional operator `$`, which
+/// needs the update's `query`, as well as `$[]` and `$[<identifier>]`, which
+/// need matching `array_filters`.
+///
+/// As on the server, operators are applied in order of their paths, which
+/// decides the order of any new fields, and an update fails if two of its
+/// paths overlap or if it would change the `_id`.
+#[derive(Clone, Debug)]
+pub struct Update {
+    kind: Kind,
+    array_filters: Vec<(String, Filter)>,
+    query: Option<Filter>
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Replacement(Document),
+    Operations(Vec<Operation>)
+}
+
+#[derive(Clone, Debug)]
+struct Operation {
+    path: String,
+    parts: Vec<String>,
+    modifier: Modifier
+}
+
+#[derive(Clone, Debug)]
+enum Modifier {
+    Set(Bson),
+    SetOnInsert(Bson),
+    Unset,
+    Inc(Bson),
+    Mul(Bson),
+    Min(Bson),
+    Max(Bson),
+    Rename(String, Vec<String>),
+    CurrentDate(bool),
+    Push(Push),
+    AddToSet(Vec<Bson>),
+    Pull(ValueMatcher),
+    PullAll(Vec<Bson>),
+    Pop(bool)
+}
+
+#[derive(Clone, Debug)]
+struct Push {
+    values: Vec<Bson>,
+    position: Option<i64>,
+    sort: Option<PushSort>,
+    slice: Option<i64>
+}
+
+#[derive(Clone, Debug)]
+enum PushSort {
+    Value(bool),
+    Fields(Vec<(String, bool)>)
+}
+
+/// Implementation for the `Update` object.
+impl Update {
+
+    /// Parse an update document.
+    ///
+    /// # Parameters
+    /// - `update` - The update `Document`.
+    ///
+    /// # Returns
+    /// The `Result` with the `Update`.
+    pub fn parse(update: &Document) -> Result<Update, QueryError> {
+        let operators = update.iter().filter(|&(key, _)| key.starts_with('$')).count();
+        let kind = if operators == 0 && !update.is_empty() {
+            Kind::Replacement(update.clone())
+        } else {
+            Kind::Operations(parse_operations(update)?)
+        };
+        Ok(Update { kind, array_filters: Vec::new(), query: None })
+    }
+
+    /// Set the array filters that choose the elements updated through
+    /// `$[<identifier>]` paths, such as `{ "x.score": { "$lt": 50 } }`.
+    ///
+    /// # Parameters
+    /// - `filters` - The array filter documents.
+    ///
+    /// # Returns
+    /// The `Result` with the `Update`, or a `QueryError` if a filter is
+    /// invalid or not used by the update.
+    pub fn array_filters(mut self, filters: &[Document]) -> Result<Update, QueryError> {
+        self.array_filters.clear();
+        for filter in filters {
+            let identifier = array_filter_identifier(filter)?;
+            if self.array_filters.iter().any(|(existing, _)| *existing == identifier) {
+                return Err(QueryError::new(format!(
+                    "Found multiple array filters with the same top-level field name {}", identifier
+                )));
+            }
+            let used = match self.kind {
+                Kind::Operations(ref operations) => operations.iter().any(|operation| {
+                    operation.parts.iter().any(|part| *part == format!("$[{}]", identifier))
+                }),
+                Kind::Replacement(_) => false
+            };
+            if !used {
+                return Err(QueryError::new(format!(
+                    "The array filter for identifier '{}' was not used in the update", identifier
+                )));
+            }
+            self.array_filters.push((identifier, Filter::parse(filter)?));
+        }
+        Ok(self)
+    }
+
+    /// Set the query that selected the document, used to find the element
+    /// updated through the positional `$` operator.
+    ///
+    /// # Parameters
+    /// - `query` - The query `Filter`.
+    ///
+    /// # Returns
+    /// The `Update`.
+    pub fn query(mut self, query: Filter) -> Update {
+        self.query = Some(query);
+        self
+    }
+
+    /// Apply the update to an existing document. `$setOnInsert` is ignored.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` to update.
+    ///
+    /// # Returns
+    /// The `Result`. On error the document is left unchanged.
+    pub fn apply(&self, document: &mut Document) -> Result<(), QueryError> {
+        self.apply_to(document, false)
+    }
+
+    /// Apply the update to a document being inserted by an upsert, including
+    /// `$setOnInsert` and allowing the `_id` to be set.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` being inserted.
+    ///
+    /// # Returns
+    /// The `Result`. On error the document is left unchanged.
+    pub fn apply_insert(&self, document: &mut Document) -> Result<(), QueryError> {
+        self.apply_to(document, true)
+    }
+
+    fn apply_to(&self, document: &mut Document, insert: bool) -> Result<(), QueryError> {
+        let updated = match self.kind {
+            Kind::Replacement(ref replacement) => replace(document, replacement),
+            Kind::Operations(ref operations) => {
+                let mut updated = document.clone();
+                let now = datetime::now();
+                for operation in operations {
+                    if let Modifier::SetOnInsert(_) = operation.modifier {
+                        if !insert {
+                            continue;
+                        }
+                    }
+                    for parts in self.expand(&updated, document, operation)? {
+                        operation.apply(&mut updated, &parts, now)?;
+                    }
+                }
+                updated
+            }
+        };
+        if !insert && document.get("_id").is_some_and(|id| updated.get("_id") != Some(id)) {
+            return Err(QueryError::new(
+                "Performing an update on the path '_id' would modify the immutable field '_id'"
+            ));
+        }
+        *document = updated;
+        Ok(())
+    }
+
+    /// Expand the positional operators in the path of an operation into the
+    /// concrete paths of the elements they select.
+    ///
+    /// # Parameters
+    /// - `document` - The `Document` being updated.
+    /// - `original` - The `Document` before the update, used for `$`.
+    /// - `operation` - The `Operation`.
+    ///
+    /// # Returns
+    /// The `Result` with the concrete paths.
+    fn expand(&self, document: &Document, original: &Document, operation: &Operation)
+            -> Result<Vec<Vec<String>>, QueryError> {
+        if !operation.parts.iter().any(|part| part.starts_with('$')) {
+            return Ok(vec![operation.parts.clone()]);
+        }
+        if operation.parts[0].starts_with('$') {
+            return Err(QueryError::new(format!(
+                "Cannot have a positional operator in the first position in path '{}'", operation.path
+            )));
+        }
+        let mut paths = Vec::new();
+        let root = &operation.parts[0];
+        let mut prefix = vec![root.clone()];
+        self.expand_from(document.get(root), original, operation, 1, &mut prefix, &mut paths)?;
+        Ok(paths)
+    }
+
+    fn expand_from(&self, value: Option<&Bson>, original: &Document, operation: &Operation, index: usize,
+            prefix: &mut Vec<String>, paths: &mut Vec<Vec<String>>) -> Result<(), QueryError> {
+        if index == operation.parts.len() {
+            paths.push(prefix.clone());
+            return Ok(());
+        }
+        let part = &operation.parts[index];
+        if !part.starts_with('$') {
+            prefix.push(part.clone());
+            self.expand_from(value.and_then(|value| child(value, part)), original, operation, index + 1, prefix, paths)?;
+            prefix.pop();
+            return Ok(());
+        }
+        let elements = match value {
+            Some(Bson::Array(elements)) => elements,
+            _ if part == "$" => return Err(positional_not_found()),
+            Some(other) => return Err(QueryError::new(format!(
+                "Cannot apply array updates to non-array element {}: {}", prefix[prefix.len() - 1], other
+            ))),
+            None => return Err(QueryError::new(format!(
+                "The path '{}' must exist in the document in order to apply array updates.", prefix.join(".")
+            )))
+        };
+        let selected: Vec<usize> = if part == "$" {
+            let index = self.query.as_ref().and_then(|query| positional_index(query, original, &prefix.join(".")));
+            vec![index.ok_or_else(positional_not_found)?]
+        } else if part == "$[]" {
+            (0..elements.len()).collect()
+        } else {
+            let identifier = &part[2..part.len() - 1];
+            let filter = match self.array_filters.iter().find(|(id, _)| id == identifier) {
+                Some((_, filter)) => filter,
+                None => return Err(QueryError::new(format!(
+                    "No array filter found for identifier '{}' in path '{}'", identifier, operation.path
+                )))
+            };
+            (0..elements.len()).filter(|&i| {
+                let mut candidate = Document::new();
+                candidate.insert(identifier.to_string(), elements[i].clone());
+                filter.matches(&candidate)
+            }).collect()
+        };
+        for i in selected {
+            prefix.push(i.to_string());
+            self.expand_from(elements.get(i), original, operation, index + 1, prefix, paths)?;
+            prefix.pop();
+        }
+        Ok(())
+    }
+}
+
+impl Operation {
+
+    /// Apply the operation at a concrete path.
+    fn apply(&self, document: &mut Document, parts: &[String], now: i64) -> Result<(), QueryError> {
+        let path = parts.join(".");
+        let key = &parts[parts.len() - 1];
+        let id = id_summary(document);
+        match self.modifier {
+            Modifier::Set(ref value) | Modifier::SetOnInsert(ref value) => set(document, parts, value.clone()),
+            Modifier::Unset => {
+                take(document, parts, false);
+                Ok(())
+            },
+            Modifier::Inc(ref operand) | Modifier::Mul(ref operand) => {
+                let (name, multiply) = match self.modifier {
+                    Modifier::Inc(_) => ("$inc", false),
+                    _ => ("$mul", true)
+                };
+                let result = match get(document, parts) {
+                    None if multiply => zero(operand),
+                    None => operand.clone(),
+                    Some(existing) if !is_number(existing) => return Err(QueryError::new(format!(
+                        "Cannot apply {} to a value of non-numeric type. {} has the field '{}' of non-numeric type {}",
+                        name, id, key, existing.type_name()
+                    ))),
+                    Some(existing) => match arithmetic(existing, operand, multiply) {
+                        Some(result) => result,
+                        None => return Err(QueryError::new(format!(
+                            "Failed to apply {} operations to current value ({}) for document {}", name, existing, id
+                        )))
+                    }
+                };
+                set(document, parts, result)
+            },
+            Modifier::Min(ref operand) | Modifier::Max(ref operand) => {
+                let wanted = match self.modifier {
+                    Modifier::Min(_) => Ordering::Less,
+                    _ => Ordering::Greater
+                };
+                match get(document, parts) {
+                    Some(existing) if bson_cmp(operand, existing) != wanted => Ok(()),
+                    _ => set(document, parts, operand.clone())
+                }
+            },
+            Modifier::Rename(ref target_path, ref target) => {
+                check_not_in_array(document, parts, "source", &path, &id)?;
+                check_not_in_array(document, target, "destination", target_path, &id)?;
+                match take(document, parts, true) {
+                    Some(value) => set(document, target, value),
+                    None => Ok(())
+                }
+            },
+            Modifier::CurrentDate(timestamp) => {
+                let value = if timestamp {
+                    Bson::Timestamp(((now / 1_000) as u64) << 32 | 1)
+                } else {
+                    Bson::DateTime(now)
+                };
+                set(document, parts, value)
+            },
+            Modifier::Push(ref push) => {
+                let elements = array_mut(document, parts, true, |value| format!(
+                    "The field '{}' must be an array but is of type {} in document {}", path, value.type_name(), id
+                ))?;
+                push.apply(elements.expect("a created array"));
+                Ok(())
+            },
+            Modifier::AddToSet(ref values) => {
+                let elements = array_mut(document, parts, true, |value| format!(
+                    "Cannot apply $addToSet to non-array field. Field named '{}' has non-array type {}",
+                    key, value.type_name()
+                ))?.expect("a created array");
+                for value in values {
+                    if !elements.iter().any(|element| bson_cmp(element, value) == Ordering::Equal) {
+                        elements.push(value.clone());
+                    }
+                }
+                Ok(())
+            },
+            Modifier::Pull(ref matcher) => {
+                let elements = array_mut(document, parts, false, |_| "Cannot apply $pull to a non-array value".to_string())?;
+                if let Some(elements) = elements {
+                    elements.retain(|element| !matcher.matches(element));
+                }
+                Ok(())
+            },
+            Modifier::PullAll(ref values) => {
+                let elements = array_mut(document, parts, false, |_| "Cannot apply $pull to a non-array value".to_string())?;
+                if let Some(elements) = elements {
+                    elements.retain(|element| !values.iter().any(|value| bson_cmp(element, value) == Ordering::Equal));
+                }
+                Ok(())
+            },
+            Modifier::Pop(first) => {
+                let elements = array_mut(document, parts, false, |value| format!(
+                    "Path '{}' contains an element of non-array type '{}'", path, value.type_name()
+                ))?;
+                if let Some(elements) = elements {
+                    if first && !elements.is_empty() {
+                        elements.remove(0);
+                    } else {
+                        elements.pop();
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Push {
+
+    fn apply(&self, elements: &mut Vec<Bson>) {
+        let length = elements.len() as i64;
+        let position = match self.position {
+            Some(position) if position < 0 => (length + position).max(0),
+            Some(position) => position.min(length),
+            None => length
+        } as usize;
+        let tail = elements.split_off(position);
+        elements.extend(self.values.iter().cloned());
+        elements.extend(tail);
+        match self.sort {
+            Some(PushSort::Value(descending)) => elements.sort_by(|left, right| {
+                directed(bson_cmp(left, right), descending)
+            }),
+            Some(PushSort::Fields(ref fields)) => elements.sort_by(|left, right| {
+                fields.iter().fold(Ordering::Equal, |ordering, &(ref field, descending)| {
+                    ordering.then_with(|| directed(bson_cmp(&sort_key(left, field), &sort_key(right, field)), descending))
+                })
+            }),
+            None => {}
+        }
+        match self.slice {
+            Some(slice) if slice >= 0 => elements.truncate(slice as usize),
+            Some(slice) => {
+                let keep = slice.unsigned_abs() as usize;
+                if elements.len() > keep {
+                    let excess = elements.len() - keep;
+                    elements.drain(..excess);
+                }
+            },
+            None => {}
+        }
+    }
+}
+
+fn directed(ordering: Ordering, descending: bool) -> Ordering {
+    if descending { ordering.reverse() } else { ordering }
+}
+
+// The value of a dotted field in an array element for `$push` with `$sort`,
+// with missing fields sorting as null.
+fn sort_key(element: &Bson, field: &str) -> Bson {
+    let mut value = element;
+    for part in field.split('.') {
+        value = match *value {
+            Bson::Document(ref document) => match document.get(part) {
+                Some(value) => value,
+                None => return Bson::Null
+            },
+            _ => return Bson::Null
+        };
+    }
+    value.clone()
+}
+
+/// Build the document replacing another with its `_id` first, taken from the
+/// replacement if it has one so that `apply_to` can check it is unchanged.
+fn replace(document: &Document, replacement: &Document) -> Document {
+    let mut replaced = Document::new();
+    if let Some(id) = replacement.get("_id").or_else(|| document.get("_id")) {
+        replaced.insert("_id".to_string(), id.clone());
+    }
+    for (key, value) in replacement.iter() {
+        if key != "_id" {
+            replaced.insert(key.clone(), value.clone());
+        }
+    }
+    replaced
+}
+
+fn parse_operations(update: &Document) -> Result<Vec<Operation>, QueryError> {
+    let mut operations = Vec::new();
+    for (operator, fields) in update.iter() {
+        let fields = match *fields {
+            Bson::Document(ref fields) => fields,
+            ref other => return Err(QueryError::new(format!(
+                "Modifiers operate on fields but we found type {} instead. For example: {{$mod: {{<field>: ...}}}} not {{{}: {}}}",
+                other.type_name(), operator, other
+            )))
+        };
+        for (path, operand) in fields.iter() {
+            let modifier = parse_modifier(operator, path, operand)?;
+            operations.push(Operation { path: path.clone(), parts: split_path(path)?, modifier });
+        }
+    }
+    check_conflicts(&operations)?;
+    operations.sort_by(|left, right| left.path.as_bytes().cmp(right.path.as_bytes()));
+    Ok(operations)
+}
+
+fn parse_modifier(operator: &str, path: &str, operand: &Bson) -> Result<Modifier, QueryError> {
+    Ok(match operator {
+        "$set" => Modifier::Set(operand.clone()),
+        "$setOnInsert" => Modifier::SetOnInsert(operand.clone()),
+        "$unset" => Modifier::Unset,
+        "$inc" | "$mul" => {
+            if !is_number(operand) {
+                let verb = if operator == "$inc" { "increment" } else { "multiply" };
+                return Err(QueryError::new(format!(
+                    "Cannot {} with non-numeric argument: {{{}: {}}}", verb, path, operand
+                )));
+            }
+            if operator == "$inc" { Modifier::Inc(operand.clone()) } else { Modifier::Mul(operand.clone()) }
+        },
+        "$min" => Modifier::Min(operand.clone()),
+        "$max" => Modifier::Max(operand.clone()),
+        "$rename" => parse_rename(path, operand)?,
+        "$currentDate" => Modifier::CurrentDate(parse_current_date(operand)?),
+        "$push" => Modifier::Push(parse_push(operand)?),
+        "$addToSet" => Modifier::AddToSet(match *operand {
+            Bson::Document(ref document) if document.contains_key("$each") => {
+                if document.len() > 1 {
+                    return Err(QueryError::new(format!("Found unexpected fields after $each in $addToSet: {}", document)));
+                }
+                match document.get("$each") {
+                    Some(Bson::Array(values)) => values.clone(),
+                    Some(other) => return Err(QueryError::new(format!(
+                        "The argument to $each in $addToSet must be an array but it was of type {}", other.type_name()
+                    ))),
+                    None => Vec::new()
+                }
+            },
+            ref other => vec![other.clone()]
+        }),
+        "$pull" => match *operand {
+            Bson::Document(_) | Bson::RegExp(_, _) => Modifier::Pull(ValueMatcher::parse(operand)?),
+            _ => Modifier::PullAll(vec![operand.clone()])
+        },
+        "$pullAll" => match *operand {
+            Bson::Array(ref values) => Modifier::PullAll(values.clone()),
+            ref other => return Err(QueryError::new(format!(
+                "$pullAll requires an array argument but was given a {}", other.type_name()
+            )))
+        },
+        "$pop" => match whole_number(operand) {
+            Some(1) => Modifier::Pop(false),
+            Some(-1) => Modifier::Pop(true),
+            _ => return Err(QueryError::new(format!("$pop expects 1 or -1, found: {}", operand)))
+        },
+        _ => return Err(QueryError::new(format!(
+            "Unknown modifier: {}. Expected a valid update modifier or pipeline-style update specified as an array",
+            operator
+        )))
+    })
+}
+
+fn parse_rename(path: &str, operand: &Bson) -> Result<Modifier, QueryError> {
+    let target = match *operand {
+        Bson::String(ref target) => target,
+        ref other => return Err(QueryError::new(format!(
+            "The 'to' field for $rename must be a string: {}: {}", path, other
+        )))
+    };
+    if path.split('.').any(|part| part.starts_with('$')) {
+        return Err(QueryError::new(format!("The source field for $rename may not be dynamic: {}", path)));
+    }
+    if target.split('.').any(|part| part.starts_with('$')) {
+        return Err(QueryError::new(format!("The destination field for $rename may not be dynamic: {}", target)));
+    }
+    if path == target {
+        return Err(QueryError::new(format!(
+            "The source and target field for $rename must differ: {}: \"{}\"", path, target
+        )));
+    }
+    if is_prefix(path, target) || is_prefix(target, path) {
+        return Err(QueryError::new(format!(
+            "The source and target field for $rename must not be on the same path: {}: \"{}\"", path, target
+        )));
+    }
+    Ok(Modifier::Rename(target.clone(), split_path(target)?))
+}
+
+fn parse_current_date(operand: &Bson) -> Result<bool, QueryError> {
+    match *operand {
+        Bson::Boolean(_) => Ok(false),
+        Bson::Document(ref document) => match document.get("$type") {
+            Some(Bson::String(name)) if document.len() == 1 && name == "date" => Ok(false),
+            Some(Bson::String(name)) if document.len() == 1 && name == "timestamp" => Ok(true),
+            _ => Err(QueryError::new(
+                "The '$type' string field is required to be 'date' or 'timestamp': {$currentDate: {field : {$type: 'date'}}}"
+            ))
+        },
+        ref other => Err(QueryError::new(format!(
+            "{} is not valid type for $currentDate. Please use a boolean ('true') or a $type expression ({{$type: 'timestamp/date'}}).",
+            other.type_name()
+        )))
+    }
+}
+
+fn parse_push(operand: &Bson) -> Result<Push, QueryError> {
+    let mut push = Push { values: Vec::new(), position: None, sort: None, slice: None };
+    let modifiers = match *operand {
+        Bson::Document(ref document) if document.contains_key("$each") => document,
+        ref other => {
+            push.values.push(other.clone());
+            return Ok(push);
+        }
+    };
+    for (key, value) in modifiers.iter() {
+        match key.as_str() {
+            "$each" => match *value {
+                Bson::Array(ref values) => push.values = values.clone(),
+                ref other => return Err(QueryError::new(format!(
+                    "The argument to $each in $push must be an array but it was of type: {}", other.type_name()
+                )))
+            },
+            "$slice" => match whole_number(value) {
+                Some(slice) => push.slice = Some(slice),
+                None => return Err(QueryError::new(format!(
+                    "The value for $slice must be an integer value but was given type: {}", value.type_name()
+                )))
+            },
+            "$position" => match whole_number(value) {
+                Some(position) => push.position = Some(position),
+                None => return Err(QueryError::new(format!(
+                    "The value for $position must be an integer value, not of type: {}", value.type_name()
+                )))
+            },
+            "$sort" => push.sort = Some(parse_push_sort(value)?),
+            other => return Err(QueryError::new(format!("Unrecognized clause in $push: {}", other)))
+        }
+    }
+    Ok(push)
+}
+
+fn parse_push_sort(value: &Bson) -> Result<PushSort, QueryError> {
+    let invalid = || QueryError::new(
+        "The $sort is invalid: use 1/-1 to sort the whole element, or {field:1/-1} to sort embedded fields"
+    );
+    match *value {
+        Bson::Document(ref fields) if !fields.is_empty() => fields.iter().map(|(field, direction)| {
+            match whole_number(direction) {
+                Some(1) => Ok((field.clone(), false)),
+                Some(-1) => Ok((field.clone(), true)),
+                _ => Err(invalid())
+            }
+        }).collect::<Result<Vec<_>, _>>().map(PushSort::Fields),
+        _ => match whole_number(value) {
+            Some(1) => Ok(PushSort::Value(false)),
+            Some(-1) => Ok(PushSort::Value(true)),
+            _ => Err(invalid())
+        }
+    }
+}
+
+fn array_filter_identifier(filter: &Document) -> Result<String, QueryError> {
+    let mut identifier: Option<&str> = None;
+    for (key, _) in filter.iter() {
+        let top = key.split('.').next().unwrap_or("");
+        match identifier {
+            Some(existing) if existing != top => return Err(QueryError::new(format!(
+                "Error parsing array filter :: caused by :: Expected a single top-level field name, found '{}' and '{}'",
+                existing, top
+            ))),
+            _ => identifier = Some(top)
+        }
+    }
+    match identifier {
+        Some(identifier) if identifier.starts_with(|c: char| c.is_ascii_lowercase())
+                && identifier.chars().all(|c| c.is_ascii_alphanumeric()) => Ok(identifier.to_string()),
+        Some(identifier) => Err(QueryError::new(format!(
+            "Error parsing array filter :: caused by :: The top-level field name must be an alphanumeric string beginning with a lowercase letter, found '{}'",
+            identifier
+        ))),
+        None => Err(QueryError::new("Cannot use an expression without a top-level field name in arrayFilters"))
+    }
+}
+
+fn split_path(path: &str) -> Result<Vec<String>, QueryError> {
+    if path.is_empty() {
+        return Err(QueryError::new("An empty update path is not valid."));
+    }
+    let parts: Vec<String> = path.split('.').map(String::from).collect();
+    if parts.iter().any(String::is_empty) {
+        return Err(QueryError::new(format!(
+            "The update path '{}' contains an empty field name, which is not allowed.", path
+        )));
+    }
+    Ok(parts)
+}
+
+fn check_conflicts(operations: &[Operation]) -> Result<(), QueryError> {
+    let mut paths: Vec<&str> = Vec::new();
+    for operation in operations {
+        paths.push(&operation.path);
+        if let Modifier::Rename(ref target, _) = operation.modifier {
+            paths.push(target);
+        }
+    }
+    for (i, path) in paths.iter().enumerate() {
+        for other in &paths[..i] {
+            let (longer, shorter) = if path.len() >= other.len() { (path, other) } else { (other, path) };
+            if is_prefix(shorter, longer) || path == other {
+                return Err(QueryError::new(format!(
+                    "Updating the path '{}' would create a conflict at '{}'", longer, shorter
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Whether the path is a dotted prefix of the other path.
+fn is_prefix(path: &str, other: &str) -> bool {
+    other.len() > path.len() && other.starts_with(path) && other.as_bytes()[path.len()] == b'.'
+}
+
+fn positional_not_found() -> QueryError {
+    QueryError::new("The positional operator did not find the match needed from the query.")
+}
+
+// The `_id` of a document as the server shows it in error messages.
+fn id_summary(document: &Document) -> String {
+    match document.get("_id") {
+        Some(id) => format!("{{_id: {}}}", id),
+        None => "{}".to_string()
+    }
+}
+
+fn is_number(value: &Bson) -> bool {
+    matches!(*value, Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_))
+}
+
+fn zero(value: &Bson) -> Bson {
+    match *value {
+        Bson::Int32(_) => Bson::Int32(0),
+        Bson::Int64(_) => Bson::Int64(0),
+        Bson::Decimal128(_) => Bson::Decimal128(Decimal::from_i64(0).to_bytes()),
+        _ => Bson::Double(0.0)
+    }
+}
+
+/// Add or multiply two numbers, widening `Int32` results to `Int64` on
+/// overflow, using `Decimal128` if either number is a decimal and otherwise
+/// `Double` if either number is a double.
+///
+/// # Returns
+/// The result, or `None` if an `Int64` result overflows.
+fn arithmetic(left: &Bson, right: &Bson, multiply: bool) -> Option<Bson> {
+    let integer = |l: i64, r: i64| if multiply { l.checked_mul(r) } else { l.checked_add(r) };
+    match (left, right) {
+        (Bson::Int32(l), Bson::Int32(r)) => {
+            let result = integer(i64::from(*l), i64::from(*r))?;
+            Some(if result >= i64::from(i32::MIN) && result <= i64::from(i32::MAX) {
+                Bson::Int32(result as i32)
+            } else {
+                Bson::Int64(result)
+            })
+        },
+        (Bson::Int32(l), Bson::Int64(r)) => integer(i64::from(*l), *r).map(Bson::Int64),
+        (Bson::Int64(l), Bson::Int32(r)) => integer(*l, i64::from(*r)).map(Bson::Int64),
+        (Bson::Int64(l), Bson::Int64(r)) => integer(*l, *r).map(Bson::Int64),
+        (Bson::Decimal128(_), _) | (_, Bson::Decimal128(_)) => {
+            let (l, r) = (Decimal::from_number(left)?, Decimal::from_number(right)?);
+            Some(Bson::Decimal128(if multiply { l.multiply(r) } else { l.add(r) }.to_bytes()))
+        },
+        _ => {
+            let (l, r) = (as_f64(left)?, as_f64(right)?);
+            Some(Bson::Double(if multiply { l * r } else { l + r }))
+        }
+    }
+}
+
+fn as_f64(value: &Bson) -> Option<f64> {
+    match *value {
+        Bson::Int32(number) => Some(f64::from(number)),
+        Bson::Int64(number) => Some(number as f64),
+        Bson::Double(number) => Some(number),
+        _ => None
+    }
+}
+
+fn child<'a>(value: &'a Bson, part: &str) -> Option<&'a Bson> {
+    match *value {
+        Bson::Document(ref document) => document.get(part),
+        Bson::Array(ref elements) => array_index(part).and_then(|index| elements.get(index)),
+        _ => None
+    }
+}
+
+fn get<'a>(document: &'a Document, parts: &[String]) -> Option<&'a Bson> {
+    let mut value = document.get(&parts[0])?;
+    for part in &parts[1..] {
+        value = child(value, part)?;
+    }
+    Some(value)
+}
+
+fn set(document: &mut Document, parts: &[String], value: Bson) -> Result<(), QueryError> {
+    if let Some(slot) = locate(document, parts, true)? {
+        *slot = value;
+    }
+    Ok(())
+}
+
+/// Remove the value at a path. A removed array element is replaced with null
+/// unless `remove_elements` is set.
+fn take(document: &mut Document, parts: &[String], remove_elements: bool) -> Option<Bson> {
+    let key = &parts[parts.len() - 1];
+    if parts.len() == 1 {
+        return document.remove(key);
+    }
+    match locate(document, &parts[..parts.len() - 1], false) {
+        Ok(Some(&mut Bson::Document(ref mut parent))) => parent.remove(key),
+        Ok(Some(&mut Bson::Array(ref mut elements))) => match array_index(key) {
+            Some(index) if index < elements.len() && remove_elements => Some(elements.remove(index)),
+            Some(index) if index < elements.len() => Some(::std::mem::replace(&mut elements[index], Bson::Null)),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Get the array at a path for an array operator, creating an empty array if
+/// the path is missing and `create` is set.
+fn array_mut<'a, F>(document: &'a mut Document, parts: &[String], create: bool, message: F)
+        -> Result<Option<&'a mut Vec<Bson>>, QueryError> where F: FnOnce(&Bson) -> String {
+    let missing = get(document, parts).is_none();
+    let slot = match locate(document, parts, create)? {
+        Some(slot) => slot,
+        None => return Ok(None)
+    };
+    if missing {
+        *slot = Bson::Array(Vec::new());
+    }
+    match *slot {
+        Bson::Array(ref mut elements) => Ok(Some(elements)),
+        ref other => Err(QueryError::new(message(other)))
+    }
+}
+
+fn check_not_in_array(document: &Document, parts: &[String], role: &str, path: &str, id: &str) -> Result<(), QueryError> {
+    let mut value = match document.get(&parts[0]) {
+        Some(value) => value,
+        None => return Ok(())
+    };
+    for (i, part) in parts[1..].iter().enumerate() {
+        if let Bson::Array(_) = *value {
+            return Err(QueryError::new(format!(
+                "The {} field cannot be an array element, '{}' in doc with {} has an array field called '{}'",
+                role, path, id, parts[i]
+            )));
+        }
+        value = match child(value, part) {
+            Some(value) => value,
+            None => return Ok(())
+        };
+    }
+    Ok(())
+}
+
+/// Find the value at a concrete path, optionally creating it and any missing
+/// documents on the way, and padding arrays with nulls as the server does.
+fn locate<'a>(document: &'a mut Document, parts: &[String], create: bool) -> Result<Option<&'a mut Bson>, QueryError> {
+    locate_in_document(document, parts, 0, create)
+}
+
+fn locate_in_document<'a>(document: &'a mut Document, parts: &[String], index: usize, create: bool)
+        -> Result<Option<&'a mut Bson>, QueryError> {
+    let part = &parts[index];
+    let last = index + 1 == parts.len();
+    if create && !document.contains_key(part) {
+        document.insert(part.clone(), if last { Bson::Null } else { Bson::Document(Document::new()) });
+    }
+    match document.get_mut(part) {
+        Some(value) if last => Ok(Some(value)),
+        Some(value) => locate_in_value(value, parts, index + 1, create),
+        None => Ok(None)
+    }
+}
+
+fn locate_in_value<'a>(value: &'a mut Bson, parts: &[String], index: usize, create: bool)
+        -> Result<Option<&'a mut Bson>, QueryError> {
+    let last = index + 1 == parts.len();
+    let position = array_index(&parts[index]);
+    let traversable = match *value {
+        Bson::Document(_) => true,
+        Bson::Array(_) => position.is_some(),
+        _ => false
+    };
+    if !traversable {
+        return if create {
+            Err(QueryError::new(format!(
+                "Cannot create field '{}' in element {{{}: {}}}", parts[index], parts[index - 1], value
+            )))
+        } else {
+            Ok(None)
+        };
+    }
+    match *value {
+        Bson::Document(ref mut document) => locate_in_document(document, parts, index, create),
+        Bson::Array(ref mut elements) => {
+            let position = position.unwrap_or_default();
+            if create && position >= elements.len() {
+                if position - elements.len() > MAX_ARRAY_PADDING {
+                    return Err(QueryError::new(format!("can't backfill more than {} elements", MAX_ARRAY_PADDING)));
+                }
+                elements.resize(position, Bson::Null);
+                elements.push(if last { Bson::Null } else { Bson::Document(Document::new()) });
+            }
+            match elements.get_mut(position) {
+                Some(element) if last => Ok(Some(element)),
+                Some(element) => locate_in_value(element, parts, index + 1, create),
+                None => Ok(None)
+            }
+        },
+        _ => Ok(None)
+    }
+}
@@ -0,0 +1,498 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Error, ErrorKind, Result};
+use std::str;
+use document::Document;
+use document_deserializer::{document_length, invalid_data, parse_document, MAX_NESTING_DEPTH};
+
+/// What a `Visitor` wants to happen after one of its callbacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visit {
+    /// Carry on with the next element, or into the document or array entered.
+    Continue,
+    /// From `enter_document` or `enter_array`, jump over the document or array
+    /// without visiting its elements. From any other callback, jump over the
+    /// remaining elements of the enclosing document or array.
+    Skip,
+    /// Stop visiting altogether.
+    Stop
+}
+
+/// A visitor called for each element while walking the raw bytes of a
+/// document with `visit_bytes`. Every callback receives the element key,
+/// which is the index for elements of an array, and does nothing by default,
+/// so a visitor only implements the callbacks for the values it cares about.
+#[allow(unused_variables)]
+pub trait Visitor {
+
+    /// Called on reaching an embedded document, before its elements.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn enter_document(&mut self, key: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called after the elements of an entered embedded document.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    fn exit_document(&mut self, key: &str) {}
+
+    /// Called on reaching an array, before its elements.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn enter_array(&mut self, key: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called after the elements of an entered array.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    fn exit_array(&mut self, key: &str) {}
+
+    /// Called for a double.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The double value.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_double(&mut self, key: &str, value: f64) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a string.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The string, borrowed from the bytes.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_string(&mut self, key: &str, value: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for binary data.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `subtype` - The binary subtype.
+    /// - `bytes` - The data, borrowed from the bytes.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_binary(&mut self, key: &str, subtype: u8, bytes: &[u8]) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for the deprecated undefined value.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_undefined(&mut self, key: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for an object id.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `id` - The 12 bytes of the id.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_object_id(&mut self, key: &str, id: &[u8; 12]) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a boolean.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The boolean value.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_boolean(&mut self, key: &str, value: bool) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a UTC datetime.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `millis` - The milliseconds since the Unix epoch.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_datetime(&mut self, key: &str, millis: i64) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a null.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_null(&mut self, key: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a regular expression.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `pattern` - The pattern.
+    /// - `options` - The option flags, such as `i`.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_regexp(&mut self, key: &str, pattern: &str, options: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for the deprecated DBPointer.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `namespace` - The namespace pointed to.
+    /// - `id` - The 12 bytes of the object id.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_db_pointer(&mut self, key: &str, namespace: &str, id: &[u8; 12]) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for JavaScript code without a scope.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `code` - The code.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_code(&mut self, key: &str, code: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for code with scope, with the scope parsed.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `code` - The code.
+    /// - `scope` - The scope `Document`.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_code_with_scope(&mut self, key: &str, code: &str, scope: &Document) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for the deprecated symbol.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The symbol.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_symbol(&mut self, key: &str, value: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a 32 bit integer.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The integer value.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_i32(&mut self, key: &str, value: i32) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a timestamp.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The timestamp, with the seconds in the high 32 bits and the increment in the low 32 bits.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_timestamp(&mut self, key: &str, value: u64) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a 64 bit integer.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The integer value.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_i64(&mut self, key: &str, value: i64) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a 128 bit decimal.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    /// - `value` - The 16 bytes of the decimal, as read by `format_decimal128`.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_decimal128(&mut self, key: &str, value: &[u8; 16]) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for the min key.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_min_key(&mut self, key: &str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for the max key.
+    ///
+    /// # Parameters
+    /// - `key` - The element key.
+    ///
+    /// # Returns
+    /// The `Visit` saying how to carry on.
+    fn visit_max_key(&mut self, key: &str) -> Visit {
+        Visit::Continue
+    }
+}
+
+/// Walk the raw bytes of a single document, calling the visitor for each
+/// element in order. Values are read directly from the bytes without
+/// building a `Document`, and skipped documents and arrays are jumped over
+/// using their length prefixes without being checked. Documents nested
+/// deeper than the 200 levels the server allows are an `InvalidData` error.
+///
+/// # Parameters
+/// - `bytes` - The raw BSON bytes of the document.
+/// - `visitor` - The `Visitor` to call.
+///
+/// # Returns
+/// The `Result` with the `Visit` that ended the walk, `Stop` if the visitor
+/// stopped it and otherwise `Continue`.
+pub fn visit_bytes<V>(bytes: &[u8], visitor: &mut V) -> Result<Visit> where V: Visitor + ?Sized {
+    let mut walker = Walker { bytes, position: 0, depth: 1 };
+    let end = walker.document_end()?;
+    if end != bytes.len() {
+        return Err(invalid_data(format!("document length {} does not match the {} bytes given", end, bytes.len())));
+    }
+    walker.elements(visitor, end)
+}
+
+struct Walker<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    depth: usize
+}
+
+impl<'a> Walker<'a> {
+
+    /// Read a document length prefix, returning the offset where the document
+    /// ends.
+    fn document_end(&mut self) -> Result<usize> {
+        let start = self.position;
+        let length = document_length(self.i32()?)?;
+        match start.checked_add(length) {
+            Some(end) if end <= self.bytes.len() => Ok(end),
+            _ => Err(invalid_data(format!("document length {} exceeds available bytes", length)))
+        }
+    }
+
+    fn elements<V>(&mut self, visitor: &mut V, end: usize) -> Result<Visit> where V: Visitor + ?Sized {
+        loop {
+            let element_type = self.u8()?;
+            if element_type == 0 {
+                break;
+            }
+            let key = self.cstring()?;
+            let visit = match element_type {
+                0x03 | 0x04 => self.nested(visitor, key, element_type == 0x04)?,
+                _ => self.value(visitor, key, element_type)?
+            };
+            if self.position >= end {
+                return Err(invalid_data("document is missing its terminator".to_string()));
+            }
+            match visit {
+                Visit::Continue => {},
+                Visit::Skip => {
+                    self.position = end;
+                    return Ok(Visit::Continue);
+                },
+                Visit::Stop => return Ok(Visit::Stop)
+            }
+        }
+        if self.position != end {
+            return Err(invalid_data(format!("document ending at {} does not match its contents", end)));
+        }
+        Ok(Visit::Continue)
+    }
+
+    fn nested<V>(&mut self, visitor: &mut V, key: &str, is_array: bool) -> Result<Visit> where V: Visitor + ?Sized {
+        let end = self.document_end()?;
+        let enter = if is_array { visitor.enter_array(key) } else { visitor.enter_document(key) };
+        match enter {
+            Visit::Continue => {},
+            Visit::Skip => {
+                self.position = end;
+                return Ok(Visit::Continue);
+            },
+            Visit::Stop => return Ok(Visit::Stop)
+        }
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(invalid_data(format!("document nesting exceeds the maximum depth of {}", MAX_NESTING_DEPTH)));
+        }
+        self.depth += 1;
+        let visit = self.elements(visitor, end);
+        self.depth -= 1;
+        if visit? == Visit::Stop {
+            return Ok(Visit::Stop);
+        }
+        if is_array { visitor.exit_array(key) } else { visitor.exit_document(key) }
+        Ok(Visit::Continue)
+    }
+
+    fn value<V>(&mut self, visitor: &mut V, key: &str, element_type: u8) -> Result<Visit> where V: Visitor + ?Sized {
+        Ok(match element_type {
+            0x01 => visitor.visit_double(key, f64::from_bits(self.u64()?)),
+            0x02 => visitor.visit_string(key, self.string()?),
+            0x05 => {
+                let length = self.i32()?;
+                if length < 0 {
+                    return Err(invalid_data(format!("invalid binary length {}", length)));
+                }
+                let subtype = self.u8()?;
+                visitor.visit_binary(key, subtype, self.take(length as usize)?)
+            },
+            0x06 => visitor.visit_undefined(key),
+            0x07 => visitor.visit_object_id(key, &self.object_id()?),
+            0x08 => match self.u8()? {
+                0 => visitor.visit_boolean(key, false),
+                1 => visitor.visit_boolean(key, true),
+                other => return Err(invalid_data(format!("invalid boolean value {}", other)))
+            },
+            0x09 => visitor.visit_datetime(key, self.u64()? as i64),
+            0x0A => visitor.visit_null(key),
+            0x0B => {
+                let pattern = self.cstring()?;
+                visitor.visit_regexp(key, pattern, self.cstring()?)
+            },
+            0x0C => {
+                let namespace = self.string()?;
+                visitor.visit_db_pointer(key, namespace, &self.object_id()?)
+            },
+            0x0D => visitor.visit_code(key, self.string()?),
+            0x0E => visitor.visit_symbol(key, self.string()?),
+            0x0F => {
+                let start = self.position;
+                let length = self.i32()?;
+                let code = self.string()?;
+                let scope_start = self.position;
+                let scope_end = self.document_end()?;
+                self.position = scope_end;
+                if length < 0 || self.position - start != length as usize {
+                    return Err(invalid_data(format!("invalid code with scope length {}", length)));
+                }
+                let scope = parse_document(&self.bytes[scope_start..scope_end])?;
+                visitor.visit_code_with_scope(key, code, &scope)
+            },
+            0x10 => visitor.visit_i32(key, self.i32()?),
+            0x11 => visitor.visit_timestamp(key, self.u64()?),
+            0x12 => visitor.visit_i64(key, self.u64()? as i64),
+            0x13 => {
+                let mut value = [0u8; 16];
+                value.copy_from_slice(self.take(16)?);
+                visitor.visit_decimal128(key, &value)
+            },
+            0x7F => visitor.visit_max_key(key),
+            0xFF => visitor.visit_min_key(key),
+            other => return Err(invalid_data(format!("invalid element type 0x{:02X}", other)))
+        })
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        match self.position.checked_add(count) {
+            Some(end) if end <= self.bytes.len() => {
+                let slice = &self.bytes[self.position..end];
+                self.position = end;
+                Ok(slice)
+            },
+            _ => Err(Error::new(ErrorKind::UnexpectedEof, "unexpected end of document"))
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(LittleEndian::read_i32(self.take(4)?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(LittleEndian::read_u64(self.take(8)?))
+    }
+
+    fn object_id(&mut self) -> Result<[u8; 12]> {
+        let mut id = [0u8; 12];
+        id.copy_from_slice(self.take(12)?);
+        Ok(id)
+    }
+
+    fn cstring(&mut self) -> Result<&'a str> {
+        let bytes = self.bytes;
+        match bytes[self.position..].iter().position(|b| *b == 0) {
+            Some(length) => {
+                let value = utf8(&bytes[self.position..self.position + length])?;
+                self.position += length + 1;
+                Ok(value)
+            },
+            None => Err(invalid_data("unterminated cstring".to_string()))
+        }
+    }
+
+    fn string(&mut self) -> Result<&'a str> {
+        let length = self.i32()?;
+        if length < 1 {
+            return Err(invalid_data(format!("invalid string length {}", length)));
+        }
+        let bytes = self.take(length as usize)?;
+        if bytes[bytes.len() - 1] != 0 {
+            return Err(invalid_data("string is missing its terminator".to_string()));
+        }
+        utf8(&bytes[..bytes.len() - 1])
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str> {
+    str::from_utf8(bytes).map_err(|error| invalid_data(format!("invalid utf-8: {}", error)))
+}
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+use std::mem;
+use bson::Bson;
+use document::Document;
+
+/// What `Document::walk_mut` should do with the value it has just visited.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WalkAction {
+    /// Keep the value and walk its children.
+    Continue,
+    /// Keep the value without walking its children.
+    Skip,
+    /// Remove the value from its document or array.
+    Remove,
+    /// Replace the value. The replacement is not walked.
+    Replace(Bson),
+    /// Move the value to a new key in its document, keeping its position,
+    /// and walk its children. Ignored for elements of an array. The renamed
+    /// value takes precedence over any other value left with the new key,
+    /// earlier or later in the document, which is dropped.
+    Rename(String)
+}
+
+/// The implementation for walking a `Document`.
+impl Document {
+
+    /// Visit every value in the document depth first, each before its
+    /// children. Values are identified by their dotted path from the
+    /// document, with array elements identified by their index, such as
+    /// `"a.b.0"`.
+    ///
+    /// # Parameters
+    /// - `f` - The function called with each path and value, returning false
+    ///   to skip the children of the value.
+    pub fn walk<F>(&self, mut f: F) where F: FnMut(&str, &Bson) -> bool {
+        walk_document(self, &mut String::new(), &mut f);
+    }
+
+    /// Visit every value in the document depth first, each before its
+    /// children, allowing each to be replaced, removed or renamed. Paths are
+    /// as for `walk`, and use the original key of a renamed value.
+    ///
+    /// # Parameters
+    /// - `f` - The function called with each path and value, returning the
+    ///   `WalkAction` to take.
+    pub fn walk_mut<F>(&mut self, mut f: F) where F: FnMut(&str, &mut Bson) -> WalkAction {
+        walk_document_mut(self, &mut String::new(), &mut f);
+    }
+}
+
+/// The implementation for transforming a `Bson` value.
+impl Bson {
+
+    /// Transform every leaf value, that is every value that is not a document
+    /// or array, keeping the structure of documents and arrays around them.
+    ///
+    /// # Parameters
+    /// - `f` - The function transforming each leaf.
+    ///
+    /// # Returns
+    /// The transformed `Bson` value.
+    pub fn map_leaves<F>(self, mut f: F) -> Bson where F: FnMut(Bson) -> Bson {
+        map_leaves(self, &mut f)
+    }
+}
+
+fn map_leaves<F>(value: Bson, f: &mut F) -> Bson where F: FnMut(Bson) -> Bson {
+    match value {
+        Bson::Document(document) => {
+            let mut mapped = Document::new();
+            for (key, value) in document {
+                mapped.insert(key, map_leaves(value, f));
+            }
+            Bson::Document(mapped)
+        },
+        Bson::Array(values) => Bson::Array(values.into_iter().map(|value| map_leaves(value, f)).collect()),
+        leaf => f(leaf)
+    }
+}
+
+fn walk_document<F>(document: &Document, path: &mut String, f: &mut F) where F: FnMut(&str, &Bson) -> bool {
+    for (key, value) in document.iter() {
+        walk_value(key, value, path, f);
+    }
+}
+
+fn walk_value<F>(key: &str, value: &Bson, path: &mut String, f: &mut F) where F: FnMut(&str, &Bson) -> bool {
+    let parent = push_key(path, key);
+    if f(path, value) {
+        match *value {
+            Bson::Document(ref document) => walk_document(document, path, f),
+            Bson::Array(ref values) => {
+                for (index, value) in values.iter().enumerate() {
+                    walk_value(&index.to_string(), value, path, f);
+                }
+            },
+            _ => {}
+        }
+    }
+    path.truncate(parent);
+}
+
+fn walk_document_mut<F>(document: &mut Document, path: &mut String, f: &mut F)
+        where F: FnMut(&str, &mut Bson) -> WalkAction {
+    let mut renamed = HashSet::new();
+    for (key, mut value) in mem::replace(document, Document::new()) {
+        let parent = push_key(path, &key);
+        let action = walk_value_mut(&mut value, path, f);
+        path.truncate(parent);
+        match action {
+            WalkAction::Remove => {},
+            WalkAction::Rename(key) => {
+                renamed.insert(key.clone());
+                document.insert(key, value);
+            },
+            _ => {
+                if !renamed.contains(&key) {
+                    document.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+fn walk_array_mut<F>(values: &mut Vec<Bson>, path: &mut String, f: &mut F) where F: FnMut(&str, &mut Bson) -> WalkAction {
+    let mut index = 0;
+    values.retain_mut(|value| {
+        let parent = push_key(path, &index.to_string());
+        let action = walk_value_mut(value, path, f);
+        path.truncate(parent);
+        index += 1;
+        action != WalkAction::Remove
+    });
+}
+
+/// Call the function on a value and act on its result, walking the children
+/// of the value unless it was skipped, removed or replaced.
+///
+/// # Returns
+/// `Remove` if the value should be removed, `Rename` with the new key if it
+/// should be renamed, otherwise `Continue`.
+fn walk_value_mut<F>(value: &mut Bson, path: &mut String, f: &mut F) -> WalkAction
+        where F: FnMut(&str, &mut Bson) -> WalkAction {
+    match f(path, value) {
+        WalkAction::Replace(replacement) => {
+            *value = replacement;
+            WalkAction::Continue
+        },
+        WalkAction::Skip => WalkAction::Continue,
+        WalkAction::Remove => WalkAction::Remove,
+        action => {
+            match *value {
+                Bson::Document(ref mut document) => walk_document_mut(document, path, f),
+                Bson::Array(ref mut values) => walk_array_mut(values, path, f),
+                _ => {}
+            }
+            action
+        }
+    }
+}
+
+/// Append a key to a dotted path.
+///
+/// # Returns
+/// The length of the path before the key, to truncate back to.
+fn push_key(path: &mut String, key: &str) -> usize {
+    let parent = path.len();
+    if parent > 0 {
+        path.push('.');
+    }
+    path.push_str(key);
+    parent
+}
@@ -0,0 +1,669 @@
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use std::io::{Error, ErrorKind, Read, Result};
+use std::str;
+use bson::Bson;
+use compression::Compressor;
+use crc32c::checksum;
+use document::Document;
+use document_deserializer::{document_length, invalid_data, parse_document};
+use document_serializer::DocumentSerializer;
+use type_serializer::write_cstring;
+
+/// The op code of a legacy `OP_REPLY` message.
+pub const OP_REPLY: i32 = 1;
+
+/// The op code of a legacy `OP_QUERY` message.
+pub const OP_QUERY: i32 = 2004;
+
+/// The op code of an `OP_COMPRESSED` message.
+pub const OP_COMPRESSED: i32 = 2012;
+
+/// The op code of an `OP_MSG` message.
+pub const OP_MSG: i32 = 2013;
+
+/// The largest message accepted by `Message::read_from` when no maximum is
+/// given, matching the server's default `maxMessageSizeBytes`.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 48_000_000;
+
+/// The `MsgHeader` object, the 16 byte header that starts every message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MsgHeader {
+    /// The length of the whole message in bytes, including the header.
+    pub message_length: i32,
+    /// The identifier of the message.
+    pub request_id: i32,
+    /// The `request_id` of the message this one replies to, or 0.
+    pub response_to: i32,
+    /// The op code of the message.
+    pub op_code: i32
+}
+
+/// The `OpMsg` object, the body of an `OP_MSG` message.
+///
+/// The message has a single body document, sent as a kind 0 section, and
+/// any number of document sequences, sent as kind 1 sections. With the
+/// `CHECKSUM_PRESENT` flag set, a CRC-32C of the message is appended when
+/// encoding and verified when decoding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpMsg {
+    /// The flag bits.
+    pub flags: u32,
+    /// The body document.
+    pub body: Document,
+    /// The document sequences.
+    pub sequences: Vec<DocumentSequence>
+}
+
+/// The `DocumentSequence` object, a kind 1 section of an `OP_MSG`, such as
+/// the `documents` of an `insert` command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentSequence {
+    /// The name of the command field the documents belong to.
+    pub identifier: String,
+    /// The documents.
+    pub documents: Vec<Document>
+}
+
+/// The `OpQuery` object, the body of a legacy `OP_QUERY` message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpQuery {
+    /// The flag bits.
+    pub flags: u32,
+    /// The namespace, such as `admin.$cmd`.
+    pub full_collection_name: String,
+    /// The number of documents to skip.
+    pub number_to_skip: i32,
+    /// The number of documents to return.
+    pub number_to_return: i32,
+    /// The query document.
+    pub query: Document,
+    /// The optional projection document.
+    pub return_fields_selector: Option<Document>
+}
+
+/// The `OpReply` object, the body of a legacy `OP_REPLY` message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpReply {
+    /// The flag bits.
+    pub response_flags: u32,
+    /// The cursor id, or 0 if the cursor is exhausted.
+    pub cursor_id: i64,
+    /// The position of the first document in the cursor.
+    pub starting_from: i32,
+    /// The returned documents.
+    pub documents: Vec<Document>
+}
+
+/// The `OpCompressed` object, the body of an `OP_COMPRESSED` message that
+/// wraps the body of another message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpCompressed {
+    /// The op code of the wrapped message.
+    pub original_op_code: i32,
+    /// The length of the wrapped body before compression.
+    pub uncompressed_size: i32,
+    /// The identifier of the compressor.
+    pub compressor_id: u8,
+    /// The compressed body.
+    pub compressed_message: Vec<u8>
+}
+
+/// A message of any supported kind.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// An `OP_MSG` message.
+    Msg(OpMsg),
+    /// A legacy `OP_QUERY` message.
+    Query(OpQuery),
+    /// A legacy `OP_REPLY` message.
+    Reply(OpReply),
+    /// An `OP_COMPRESSED` message.
+    Compressed(OpCompressed)
+}
+
+/// Implementation for the `MsgHeader` object.
+impl MsgHeader {
+
+    /// The length of the header in bytes.
+    pub const LENGTH: usize = 16;
+
+    /// Parse a header from the start of a byte slice.
+    ///
+    /// # Parameters
+    /// - `bytes` - The bytes, at least `LENGTH` long.
+    ///
+    /// # Returns
+    /// The `Result` with the `MsgHeader`, or an `InvalidData` error if there
+    /// are too few bytes or the message length is shorter than the header.
+    pub fn parse(bytes: &[u8]) -> Result<MsgHeader> {
+        if bytes.len() < MsgHeader::LENGTH {
+            return Err(invalid_data(format!("message header needs {} bytes, got {}", MsgHeader::LENGTH, bytes.len())));
+        }
+        let header = MsgHeader {
+            message_length: LittleEndian::read_i32(&bytes[0..4]),
+            request_id: LittleEndian::read_i32(&bytes[4..8]),
+            response_to: LittleEndian::read_i32(&bytes[8..12]),
+            op_code: LittleEndian::read_i32(&bytes[12..16])
+        };
+        if header.message_length < MsgHeader::LENGTH as i32 {
+            return Err(invalid_data(format!("invalid message length {}", header.message_length)));
+        }
+        Ok(header)
+    }
+
+    /// Write the header to the end of a buffer.
+    ///
+    /// # Parameters
+    /// - `bytes` - The buffer.
+    pub fn write(&self, bytes: &mut Vec<u8>) {
+        for value in &[self.message_length, self.request_id, self.response_to, self.op_code] {
+            bytes.write_i32::<LittleEndian>(*value).expect("writing to a Vec");
+        }
+    }
+}
+
+/// Implementation for the `OpMsg` object.
+impl OpMsg {
+
+    /// The flag set when a checksum follows the sections.
+    pub const CHECKSUM_PRESENT: u32 = 1;
+
+    /// The flag set when the sender will send another message without
+    /// waiting for a reply.
+    pub const MORE_TO_COME: u32 = 1 << 1;
+
+    /// The flag set when the client accepts multiple replies to a request.
+    pub const EXHAUST_ALLOWED: u32 = 1 << 16;
+
+    /// Create a new `OpMsg` with no flags or document sequences.
+    ///
+    /// # Parameters
+    /// - `body` - The body `Document`.
+    ///
+    /// # Returns
+    /// The new `OpMsg`.
+    pub fn new(body: Document) -> OpMsg {
+        OpMsg { flags: 0, body, sequences: Vec::new() }
+    }
+
+    /// Add a document sequence.
+    ///
+    /// # Parameters
+    /// - `identifier` - The name of the command field.
+    /// - `documents` - The documents.
+    ///
+    /// # Returns
+    /// The `OpMsg`.
+    pub fn sequence(mut self, identifier: &str, documents: Vec<Document>) -> OpMsg {
+        self.sequences.push(DocumentSequence { identifier: identifier.to_string(), documents });
+        self
+    }
+
+    /// Set the flag bits.
+    ///
+    /// # Parameters
+    /// - `flags` - The flags.
+    ///
+    /// # Returns
+    /// The `OpMsg`.
+    pub fn flags(mut self, flags: u32) -> OpMsg {
+        self.flags = flags;
+        self
+    }
+
+    /// Get the body with each document sequence added as an array field,
+    /// the form in which the server sees the command.
+    ///
+    /// # Returns
+    /// The command `Document`.
+    pub fn command(&self) -> Document {
+        let mut command = self.body.clone();
+        for sequence in &self.sequences {
+            let documents = sequence.documents.iter().cloned().map(Bson::Document).collect();
+            command.insert(sequence.identifier.clone(), Bson::Array(documents));
+        }
+        command
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        bytes.write_u32::<LittleEndian>(self.flags)?;
+        bytes.push(0);
+        DocumentSerializer::new(bytes).serialize(&self.body)?;
+        for sequence in &self.sequences {
+            bytes.push(1);
+            let start = bytes.len();
+            bytes.write_i32::<LittleEndian>(0)?;
+            write_cstring(bytes, &sequence.identifier)?;
+            for document in &sequence.documents {
+                DocumentSerializer::new(bytes).serialize(document)?;
+            }
+            let length = (bytes.len() - start) as i32;
+            LittleEndian::write_i32(&mut bytes[start..start + 4], length);
+        }
+        Ok(())
+    }
+
+    fn parse(bytes: &[u8]) -> Result<OpMsg> {
+        let mut reader = Reader::new(bytes);
+        let flags = reader.u32()?;
+        let unknown = flags & 0xFFFF & !(OpMsg::CHECKSUM_PRESENT | OpMsg::MORE_TO_COME);
+        if unknown != 0 {
+            return Err(invalid_data(format!("unsupported required OP_MSG flag bits 0x{:X}", unknown)));
+        }
+        let end = if flags & OpMsg::CHECKSUM_PRESENT != 0 {
+            bytes.len().checked_sub(4).ok_or_else(|| invalid_data("OP_MSG missing its checksum".to_string()))?
+        } else {
+            bytes.len()
+        };
+        let mut body = None;
+        let mut sequences = Vec::new();
+        while reader.position < end {
+            match reader.u8()? {
+                0 => {
+                    if body.is_some() {
+                        return Err(invalid_data("OP_MSG has more than one body section".to_string()));
+                    }
+                    body = Some(reader.document()?);
+                },
+                1 => {
+                    let start = reader.position;
+                    let length = reader.i32()?;
+                    if length < 5 {
+                        return Err(invalid_data(format!("invalid document sequence length {}", length)));
+                    }
+                    let section_end = start + length as usize;
+                    if section_end > end {
+                        return Err(invalid_data("document sequence overruns the message".to_string()));
+                    }
+                    let identifier = reader.cstring()?;
+                    let mut documents = Vec::new();
+                    while reader.position < section_end {
+                        documents.push(reader.document()?);
+                    }
+                    if reader.position != section_end {
+                        return Err(invalid_data("document sequence length does not match its documents".to_string()));
+                    }
+                    sequences.push(DocumentSequence { identifier, documents });
+                },
+                kind => return Err(invalid_data(format!("unknown OP_MSG section kind {}", kind)))
+            }
+        }
+        if reader.position != end {
+            return Err(invalid_data("OP_MSG section overruns the message".to_string()));
+        }
+        match body {
+            Some(body) => Ok(OpMsg { flags, body, sequences }),
+            None => Err(invalid_data("OP_MSG has no body section".to_string()))
+        }
+    }
+}
+
+/// Implementation for the `OpQuery` object.
+impl OpQuery {
+
+    /// Create a new `OpQuery` for a command, such as the legacy `isMaster`
+    /// handshake, on the `$cmd` namespace of a database.
+    ///
+    /// # Parameters
+    /// - `database` - The database name.
+    /// - `command` - The command `Document`.
+    ///
+    /// # Returns
+    /// The new `OpQuery`.
+    pub fn command(database: &str, command: Document) -> OpQuery {
+        OpQuery {
+            flags: 0,
+            full_collection_name: format!("{}.$cmd", database),
+            number_to_skip: 0,
+            number_to_return: -1,
+            query: command,
+            return_fields_selector: None
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        bytes.write_u32::<LittleEndian>(self.flags)?;
+        write_cstring(bytes, &self.full_collection_name)?;
+        bytes.write_i32::<LittleEndian>(self.number_to_skip)?;
+        bytes.write_i32::<LittleEndian>(self.number_to_return)?;
+        DocumentSerializer::new(bytes).serialize(&self.query)?;
+        if let Some(ref selector) = self.return_fields_selector {
+            DocumentSerializer::new(bytes).serialize(selector)?;
+        }
+        Ok(())
+    }
+
+    fn parse(bytes: &[u8]) -> Result<OpQuery> {
+        let mut reader = Reader::new(bytes);
+        let query = OpQuery {
+            flags: reader.u32()?,
+            full_collection_name: reader.cstring()?,
+            number_to_skip: reader.i32()?,
+            number_to_return: reader.i32()?,
+            query: reader.document()?,
+            return_fields_selector: if reader.is_done() { None } else { Some(reader.document()?) }
+        };
+        reader.finish("OP_QUERY")?;
+        Ok(query)
+    }
+}
+
+/// Implementation for the `OpReply` object.
+impl OpReply {
+
+    /// Create a new `OpReply` with a single document and no cursor.
+    ///
+    /// # Parameters
+    /// - `document` - The reply `Document`.
+    ///
+    /// # Returns
+    /// The new `OpReply`.
+    pub fn new(document: Document) -> OpReply {
+        OpReply { response_flags: 0, cursor_id: 0, starting_from: 0, documents: vec![document] }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        bytes.write_u32::<LittleEndian>(self.response_flags)?;
+        bytes.write_i64::<LittleEndian>(self.cursor_id)?;
+        bytes.write_i32::<LittleEndian>(self.starting_from)?;
+        bytes.write_i32::<LittleEndian>(self.documents.len() as i32)?;
+        for document in &self.documents {
+            DocumentSerializer::new(bytes).serialize(document)?;
+        }
+        Ok(())
+    }
+
+    fn parse(bytes: &[u8]) -> Result<OpReply> {
+        let mut reader = Reader::new(bytes);
+        let response_flags = reader.u32()?;
+        let cursor_id = reader.i64()?;
+        let starting_from = reader.i32()?;
+        let number_returned = reader.i32()?;
+        if number_returned < 0 {
+            return Err(invalid_data(format!("invalid number of documents returned {}", number_returned)));
+        }
+        let mut documents = Vec::new();
+        for _ in 0..number_returned {
+            documents.push(reader.document()?);
+        }
+        reader.finish("OP_REPLY")?;
+        Ok(OpReply { response_flags, cursor_id, starting_from, documents })
+    }
+}
+
+/// Implementation for the `OpCompressed` object.
+impl OpCompressed {
+
+    /// Decompress the wrapped message.
+    ///
+    /// # Returns
+    /// The `Result` with the `Message`, or an error if the compressor is
+    /// unknown or not enabled, or the body is malformed.
+    pub fn decompress(&self) -> Result<Message> {
+        let compressor = Compressor::from_id(self.compressor_id)
+            .ok_or_else(|| invalid_data(format!("unknown compressor id {}", self.compressor_id)))?;
+        if self.original_op_code == OP_COMPRESSED {
+            return Err(invalid_data("OP_COMPRESSED message wraps another OP_COMPRESSED message".to_string()));
+        }
+        let size = self.uncompressed_size as usize;
+        if size > DEFAULT_MAX_MESSAGE_SIZE {
+            return Err(invalid_data(format!("uncompressed size {} is over the maximum of {}", size, DEFAULT_MAX_MESSAGE_SIZE)));
+        }
+        let body = compressor.decompress(&self.compressed_message, size)?;
+        Message::decode_body(self.original_op_code, &body)
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        bytes.write_i32::<LittleEndian>(self.original_op_code)?;
+        bytes.write_i32::<LittleEndian>(self.uncompressed_size)?;
+        bytes.push(self.compressor_id);
+        bytes.extend_from_slice(&self.compressed_message);
+        Ok(())
+    }
+
+    fn parse(bytes: &[u8]) -> Result<OpCompressed> {
+        let mut reader = Reader::new(bytes);
+        let original_op_code = reader.i32()?;
+        let uncompressed_size = reader.i32()?;
+        if uncompressed_size < 0 {
+            return Err(invalid_data(format!("invalid uncompressed size {}", uncompressed_size)));
+        }
+        let compressor_id = reader.u8()?;
+        Ok(OpCompressed {
+            original_op_code,
+            uncompressed_size,
+            compressor_id,
+            compressed_message: bytes[reader.position..].to_vec()
+        })
+    }
+}
+
+/// Implementation for the `Message` object.
+impl Message {
+
+    /// Get the op code of the message.
+    ///
+    /// # Returns
+    /// The op code.
+    pub fn op_code(&self) -> i32 {
+        match *self {
+            Message::Msg(_) => OP_MSG,
+            Message::Query(_) => OP_QUERY,
+            Message::Reply(_) => OP_REPLY,
+            Message::Compressed(_) => OP_COMPRESSED
+        }
+    }
+
+    /// Encode the message with its header.
+    ///
+    /// # Parameters
+    /// - `request_id` - The identifier of the message.
+    /// - `response_to` - The identifier of the message this one replies to,
+    ///   or 0.
+    ///
+    /// # Returns
+    /// The `Result` with the bytes of the message.
+    pub fn encode(&self, request_id: i32, response_to: i32) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; MsgHeader::LENGTH];
+        self.encode_body(&mut bytes)?;
+        let checksummed = match *self {
+            Message::Msg(ref msg) => msg.flags & OpMsg::CHECKSUM_PRESENT != 0,
+            _ => false
+        };
+        let length = bytes.len() + if checksummed { 4 } else { 0 };
+        if length > i32::MAX as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("message of {} bytes is too large", length)));
+        }
+        let header = MsgHeader { message_length: length as i32, request_id, response_to, op_code: self.op_code() };
+        let mut prefix = Vec::with_capacity(MsgHeader::LENGTH);
+        header.write(&mut prefix);
+        bytes[..MsgHeader::LENGTH].copy_from_slice(&prefix);
+        if checksummed {
+            let crc = checksum(&bytes);
+            bytes.write_u32::<LittleEndian>(crc)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Compress the message into an `OP_COMPRESSED` message. The `OP_MSG`
+    /// checksum flag is cleared, as checksums are not sent with compression.
+    ///
+    /// # Parameters
+    /// - `compressor` - The `Compressor`.
+    ///
+    /// # Returns
+    /// The `Result` with the compressed `Message`, or an error if the
+    /// message is already compressed or the compressor is not enabled.
+    pub fn compress(&self, compressor: Compressor) -> Result<Message> {
+        if let Message::Compressed(_) = *self {
+            return Err(Error::new(ErrorKind::InvalidInput, "message is already compressed"));
+        }
+        let mut body = Vec::new();
+        self.encode_body(&mut body)?;
+        if let Message::Msg(_) = *self {
+            body[0] &= !(OpMsg::CHECKSUM_PRESENT as u8);
+        }
+        Ok(Message::Compressed(OpCompressed {
+            original_op_code: self.op_code(),
+            uncompressed_size: body.len() as i32,
+            compressor_id: compressor.id(),
+            compressed_message: compressor.compress(&body)?
+        }))
+    }
+
+    /// Encode the message without its header, appending it to a buffer, as
+    /// wrapped by an `OP_COMPRESSED` message. No `OP_MSG` checksum is added.
+    ///
+    /// # Parameters
+    /// - `bytes` - The buffer.
+    ///
+    /// # Returns
+    /// The `Result`.
+    pub fn encode_body(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        match *self {
+            Message::Msg(ref msg) => msg.write(bytes),
+            Message::Query(ref query) => query.write(bytes),
+            Message::Reply(ref reply) => reply.write(bytes),
+            Message::Compressed(ref compressed) => compressed.write(bytes)
+        }
+    }
+
+    /// Decode a whole message, verifying the `OP_MSG` checksum if present.
+    ///
+    /// # Parameters
+    /// - `bytes` - The bytes of exactly one message, including the header.
+    ///
+    /// # Returns
+    /// The `Result` with the `MsgHeader` and the `Message`, or an
+    /// `InvalidData` error if the message is malformed.
+    pub fn decode(bytes: &[u8]) -> Result<(MsgHeader, Message)> {
+        let header = MsgHeader::parse(bytes)?;
+        if header.message_length as usize != bytes.len() {
+            return Err(invalid_data(format!(
+                "message length {} does not match the {} bytes given", header.message_length, bytes.len()
+            )));
+        }
+        if header.op_code == OP_MSG && bytes.len() >= MsgHeader::LENGTH + 8
+                && LittleEndian::read_u32(&bytes[MsgHeader::LENGTH..]) & OpMsg::CHECKSUM_PRESENT != 0 {
+            let (covered, crc) = bytes.split_at(bytes.len() - 4);
+            if checksum(covered) != LittleEndian::read_u32(crc) {
+                return Err(invalid_data("OP_MSG checksum mismatch".to_string()));
+            }
+        }
+        let message = Message::decode_body(header.op_code, &bytes[MsgHeader::LENGTH..])?;
+        Ok((header, message))
+    }
+
+    /// Decode the body of a message of the given op code. An `OP_MSG`
+    /// checksum, if flagged, is skipped but not verified.
+    ///
+    /// # Parameters
+    /// - `op_code` - The op code.
+    /// - `bytes` - The bytes of the body, after the header.
+    ///
+    /// # Returns
+    /// The `Result` with the `Message`.
+    pub fn decode_body(op_code: i32, bytes: &[u8]) -> Result<Message> {
+        match op_code {
+            OP_MSG => OpMsg::parse(bytes).map(Message::Msg),
+            OP_QUERY => OpQuery::parse(bytes).map(Message::Query),
+            OP_REPLY => OpReply::parse(bytes).map(Message::Reply),
+            OP_COMPRESSED => OpCompressed::parse(bytes).map(Message::Compressed),
+            _ => Err(invalid_data(format!("unsupported op code {}", op_code)))
+        }
+    }
+
+    /// Read and decode the next message from a stream.
+    ///
+    /// # Parameters
+    /// - `reader` - The reader.
+    /// - `max_size` - The largest message length accepted.
+    ///
+    /// # Returns
+    /// The `Result` with the `MsgHeader` and the `Message`. An
+    /// `UnexpectedEof` error is returned if the stream ends, including
+    /// cleanly before a new message.
+    pub fn read_from<R: Read>(reader: &mut R, max_size: usize) -> Result<(MsgHeader, Message)> {
+        let mut bytes = vec![0u8; MsgHeader::LENGTH];
+        reader.read_exact(&mut bytes)?;
+        let header = MsgHeader::parse(&bytes)?;
+        if header.message_length as usize > max_size {
+            return Err(invalid_data(format!(
+                "message length {} is over the maximum of {}", header.message_length, max_size
+            )));
+        }
+        bytes.resize(header.message_length as usize, 0);
+        reader.read_exact(&mut bytes[MsgHeader::LENGTH..])?;
+        Message::decode(&bytes)
+    }
+}
+
+// A cursor over the bytes of a message body.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    fn is_done(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn finish(&self, op: &str) -> Result<()> {
+        if self.is_done() {
+            Ok(())
+        } else {
+            Err(invalid_data(format!("trailing bytes after {}", op)))
+        }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() - self.position < length {
+            return Err(invalid_data("message body is truncated".to_string()));
+        }
+        let bytes = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(LittleEndian::read_i32(self.take(4)?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(LittleEndian::read_u32(self.take(4)?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(LittleEndian::read_i64(self.take(8)?))
+    }
+
+    fn cstring(&mut self) -> Result<String> {
+        let rest = &self.bytes[self.position..];
+        match rest.iter().position(|&byte| byte == 0) {
+            Some(end) => {
+                let value = str::from_utf8(&rest[..end]).map_err(|error| invalid_data(error.to_string()))?;
+                self.position += end + 1;
+                Ok(value.to_string())
+            },
+            None => Err(invalid_data("unterminated string in message body".to_string()))
+        }
+    }
+
+    fn document(&mut self) -> Result<Document> {
+        if self.bytes.len() - self.position < 4 {
+            return Err(invalid_data("message body is truncated".to_string()));
+        }
+        let length = document_length(LittleEndian::read_i32(&self.bytes[self.position..]))?;
+        parse_document(self.take(length)?)
+    }
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "async")]
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+extern crate futures_executor;
+
+use bson::{read_document_async, write_document_async, AsyncDocumentReader};
+use expectest::prelude::*;
+use futures_executor::{block_on, block_on_stream};
+use std::io::ErrorKind;
+
+describe! async_io_test {
+    before_each {
+        let first = vec![12, 0, 0, 0, 0x10, b'a', 0, 1, 0, 0, 0, 0];
+        let second = vec![12, 0, 0, 0, 0x10, b'a', 0, 2, 0, 0, 0, 0];
+        let mut bytes = first.clone();
+        bytes.extend_from_slice(&second);
+    }
+
+    describe! read_document_async {
+        it "reads exactly one document" {
+            let mut reader = &bytes[..];
+            expect!(block_on(read_document_async(&mut reader))).to(be_ok().value(document! { "a" => 1 }));
+            expect!(reader).to(be_equal_to(&second[..]));
+        }
+
+        it "returns an unexpected eof error for a truncated document" {
+            let mut reader = &first[..8];
+            let error = block_on(read_document_async(&mut reader)).unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::UnexpectedEof));
+        }
+
+        it "returns an unexpected eof error for a huge length prefix" {
+            let mut reader = &[0xffu8, 0xff, 0xff, 0x7f, 0, 0][..];
+            let error = block_on(read_document_async(&mut reader)).unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::UnexpectedEof));
+        }
+
+        it "reads a document spanning several reads" {
+            let document = document! { "s" => ("x".repeat(20000)) };
+            let mut bytes = Vec::new();
+            block_on(write_document_async(&mut bytes, &document)).unwrap();
+            let mut reader = &bytes[..];
+            expect!(block_on(read_document_async(&mut reader))).to(be_ok().value(document));
+        }
+
+        it "returns an invalid data error for an invalid length" {
+            let mut reader = &[2u8, 0, 0, 0, 0][..];
+            let error = block_on(read_document_async(&mut reader)).unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidData));
+        }
+    }
+
+    describe! write_document_async {
+        it "writes the serialized document" {
+            let mut writer = vec![];
+            expect!(block_on(write_document_async(&mut writer, &document! { "a" => 1 }))).to(be_ok());
+            expect!(writer).to(be_equal_to(first));
+        }
+    }
+
+    describe! async_document_reader {
+        it "yields each document in order" {
+            let documents: Vec<_> = block_on_stream(AsyncDocumentReader::new(&bytes[..])).map(Result::unwrap).collect();
+            expect!(documents).to(be_equal_to(vec![document! { "a" => 1 }, document! { "a" => 2 }]));
+        }
+
+        it "ends after an error" {
+            let mut stream = block_on_stream(AsyncDocumentReader::new(&bytes[..20]));
+            expect!(stream.next().unwrap()).to(be_ok());
+            expect!(stream.next().unwrap().unwrap_err().kind()).to(be_equal_to(ErrorKind::UnexpectedEof));
+            expect!(stream.next().is_none()).to(be_true());
+        }
+
+        it "returns an error for a document over the maximum size" {
+            let reader = AsyncDocumentReader::new(&bytes[..]).max_document_size(8);
+            let error = block_on_stream(reader).next().unwrap().unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidData));
+        }
+    }
+}
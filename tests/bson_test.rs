@@ -7,7 +7,97 @@ extern crate bson;
 
 use bson::*;
 use expectest::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::io::Cursor;
 
 describe! bson_test {
+    describe! from {
+        it "converts unsigned 8bit integers to int32" {
+            expect!(Bson::from(42u8)).to(be_equal_to(Bson::Int32(42)));
+        }
+
+        it "converts unsigned 16bit integers to int32" {
+            expect!(Bson::from(42u16)).to(be_equal_to(Bson::Int32(42)));
+        }
+
+        it "converts unsigned 32bit integers to int64" {
+            expect!(Bson::from(4294967295u32)).to(
+                be_equal_to(Bson::Int64(4294967295))
+            );
+        }
+
+        it "converts 32bit floats to doubles" {
+            expect!(Bson::from(24.5f32)).to(be_equal_to(Bson::Double(24.5)));
+        }
+
+        it "converts strings" {
+            expect!(Bson::from("value".to_string())).to(
+                be_equal_to(Bson::String("value".to_string()))
+            );
+        }
+
+        it "converts string references" {
+            let value = "value".to_string();
+            expect!(Bson::from(&value)).to(
+                be_equal_to(Bson::String("value".to_string()))
+            );
+        }
+
+        it "converts vectors to arrays" {
+            expect!(Bson::from(vec![1, 2])).to(
+                be_equal_to(Bson::Array(vec![Bson::Int32(1), Bson::Int32(2)]))
+            );
+        }
+
+        it "converts slices to arrays" {
+            let values: &[&str] = &["a", "b"];
+            expect!(Bson::from(values)).to(be_equal_to(Bson::Array(vec![
+                Bson::String("a".to_string()),
+                Bson::String("b".to_string())
+            ])));
+        }
+
+        it "converts some options to the value" {
+            expect!(Bson::from(Some(42))).to(be_equal_to(Bson::Int32(42)));
+        }
+
+        it "converts none options to null" {
+            expect!(Bson::from(None::<i32>)).to(be_equal_to(Bson::Null));
+        }
+
+        it "converts documents" {
+            expect!(Bson::from(Document::new())).to(
+                be_equal_to(Bson::Document(Document::new()))
+            );
+        }
+
+        it "converts hash maps to documents" {
+            let mut map = HashMap::new();
+            map.insert("test".to_string(), 42i64);
+            let mut expected = Document::new();
+            expected.insert("test".to_string(), Bson::Int64(42));
+            expect!(Document::from(map)).to(be_equal_to(expected));
+        }
+
+        it "converts btree maps to documents in key order" {
+            let mut map = BTreeMap::new();
+            map.insert("b".to_string(), true);
+            map.insert("a".to_string(), false);
+            let mut expected = Document::new();
+            expected.insert("a".to_string(), Bson::Boolean(false));
+            expected.insert("b".to_string(), Bson::Boolean(true));
+            expect!(Bson::from(map)).to(be_equal_to(Bson::Document(expected)));
+        }
+    }
+
+    describe! try_from {
+        it "converts usize values that fit to int64" {
+            expect!(Bson::try_from(42usize)).to(be_ok().value(Bson::Int64(42)));
+        }
+
+        it "rejects usize values that overflow" {
+            expect!(Bson::try_from(::std::usize::MAX)).to(be_err());
+        }
+    }
 }
@@ -0,0 +1,75 @@
+#![cfg(feature = "codec")]
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+extern crate bytes;
+extern crate tokio_util;
+
+use bson::{BsonCodec, RawDocumentBuf};
+use bytes::BytesMut;
+use expectest::prelude::*;
+use std::io::ErrorKind;
+use tokio_util::codec::{Decoder, Encoder};
+
+describe! codec_test {
+    before_each {
+        let frame = vec![12, 0, 0, 0, 0x10, b'a', 0, 1, 0, 0, 0, 0];
+        let mut codec = BsonCodec::new();
+    }
+
+    describe! decode {
+        it "returns none until the frame is complete" {
+            let mut src = BytesMut::from(&frame[..7]);
+            expect!(codec.decode(&mut src)).to(be_ok().value(None));
+            src.extend_from_slice(&frame[7..]);
+            expect!(codec.decode(&mut src)).to(be_ok().value(Some(document! { "a" => 1 })));
+            expect!(src.is_empty()).to(be_true());
+        }
+
+        it "consumes a malformed frame and continues with the next" {
+            let mut src = BytesMut::from(&[12, 0, 0, 0, 0x42, b'a', 0, 1, 0, 0, 0, 0][..]);
+            src.extend_from_slice(&frame);
+            expect!(codec.decode(&mut src).unwrap_err().kind()).to(be_equal_to(ErrorKind::InvalidData));
+            expect!(codec.decode(&mut src)).to(be_ok().value(Some(document! { "a" => 1 })));
+        }
+
+        it "returns an error for a frame over the maximum length" {
+            let mut codec = BsonCodec::new().max_frame_length(8);
+            let mut src = BytesMut::from(&frame[..]);
+            expect!(codec.decode(&mut src).unwrap_err().kind()).to(be_equal_to(ErrorKind::InvalidData));
+        }
+
+        it "decodes raw documents" {
+            let mut codec = BsonCodec::raw();
+            let mut src = BytesMut::from(&frame[..]);
+            expect!(codec.decode(&mut src)).to(be_ok().value(Some(RawDocumentBuf::new(frame.clone()).unwrap())));
+        }
+    }
+
+    describe! encode {
+        it "writes the serialized document" {
+            let mut dst = BytesMut::new();
+            codec.encode(&document! { "a" => 1 }, &mut dst).unwrap();
+            expect!(&dst[..]).to(be_equal_to(&frame[..]));
+        }
+
+        it "writes a raw document" {
+            let mut dst = BytesMut::new();
+            codec.encode(RawDocumentBuf::new(frame.clone()).unwrap(), &mut dst).unwrap();
+            expect!(&dst[..]).to(be_equal_to(&frame[..]));
+        }
+
+        it "returns an error and writes nothing for a frame over the maximum length" {
+            let mut codec = BsonCodec::new().max_frame_length(8);
+            let mut dst = BytesMut::new();
+            let error = codec.encode(document! { "a" => 1 }, &mut dst).unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidInput));
+            expect!(dst.is_empty()).to(be_true());
+        }
+    }
+}
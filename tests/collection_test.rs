@@ -0,0 +1,163 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use bson::{Bson, Collection, Document, FindOptions, UpdateOptions, UpdateResult};
+use common::doc;
+use expectest::prelude::*;
+
+fn collection(documents: &[&str]) -> Collection {
+    let mut collection = Collection::new("test.things");
+    for shell in documents {
+        collection.insert_one(doc(shell)).unwrap();
+    }
+    collection
+}
+
+fn ids(documents: Vec<Document>) -> Vec<i32> {
+    documents.iter().map(|document| match document.get("_id") {
+        Some(&Bson::Int32(id)) => id,
+        _ => panic!("expected an _id")
+    }).collect()
+}
+
+describe! collection_test {
+    describe! insert {
+        it "generates an ObjectId _id as the first field" {
+            let mut collection = Collection::new("test.things");
+            let id = collection.insert_one(doc("{a: 1}")).unwrap();
+            let found = collection.find_one(&doc("{a: 1}")).unwrap().unwrap();
+            expect!(found.iter().next().map(|(key, _)| key.as_str())).to(be_some().value("_id"));
+            expect!(found.get("_id")).to(be_some().value(&id));
+            let generated = match id { Bson::ObjectId(bytes) => bytes, _ => panic!("expected an ObjectId") };
+            expect!(generated == bson::new_object_id()).to(be_false());
+        }
+
+        it "parses ObjectId() without an argument as a new id" {
+            match doc("{_id: ObjectId()}").get("_id") {
+                Some(&Bson::ObjectId(_)) => {},
+                _ => panic!("expected an ObjectId")
+            }
+        }
+
+        it "rejects duplicate and array _ids" {
+            let mut collection = collection(&["{_id: 1}"]);
+            expect!(collection.insert_one(doc("{_id: 1.0}")).unwrap_err().message())
+                .to(be_equal_to("E11000 duplicate key error collection: test.things index: _id_ dup key: { \"_id\": 1.0 }"));
+            expect!(collection.insert_one(doc("{_id: [1]}")).unwrap_err().message())
+                .to(be_equal_to("The '_id' value cannot be of type array"));
+            expect!(collection.len()).to(be_equal_to(1));
+        }
+
+        it "stops inserting many at the first failure" {
+            let mut collection = Collection::new("test.things");
+            let result = collection.insert_many(vec![doc("{_id: 1}"), doc("{_id: 2}"), doc("{_id: 1}"), doc("{_id: 3}")]);
+            expect!(result.is_err()).to(be_true());
+            expect!(collection.len()).to(be_equal_to(2));
+        }
+    }
+
+    describe! find {
+        it "filters, sorts, skips, limits and projects" {
+            let collection = collection(&["{_id: 1, a: 3}", "{_id: 2, a: 1}", "{_id: 3, a: 2}", "{_id: 4, b: 1}"]);
+            let options = FindOptions::new().sort(doc("{a: -1}")).skip(1).limit(1).projection(doc("{a: 0}"));
+            expect!(collection.find(&doc("{a: {$gte: 1}}"), &options).unwrap()).to(be_equal_to(vec![doc("{_id: 3}")]));
+            expect!(collection.count(&doc("{a: {$exists: false}}")).unwrap()).to(be_equal_to(1));
+            expect!(ids(collection.find(&doc("{_id: 2}"), &FindOptions::new()).unwrap())).to(be_equal_to(vec![2]));
+        }
+
+        it "applies positional projections with the query" {
+            let collection = collection(&["{_id: 1, a: [1, 5, 9]}"]);
+            let options = FindOptions::new().projection(doc("{'a.$': 1}"));
+            expect!(collection.find(&doc("{a: {$gt: 4}}"), &options).unwrap()).to(be_equal_to(vec![doc("{_id: 1, a: [5]}")]));
+        }
+    }
+
+    describe! update {
+        it "updates one or many documents in place" {
+            let mut collection = collection(&["{_id: 1, a: 1}", "{_id: 2, a: 1}", "{_id: 3, a: 2}"]);
+            let result = collection.update_one(&doc("{a: 1}"), &doc("{$inc: {a: 10}}"), &UpdateOptions::new()).unwrap();
+            expect!(result).to(be_equal_to(UpdateResult { matched_count: 1, modified_count: 1, upserted_id: None }));
+            let result = collection.update_many(&doc("{}"), &doc("{$max: {a: 2}}"), &UpdateOptions::new()).unwrap();
+            expect!(result).to(be_equal_to(UpdateResult { matched_count: 3, modified_count: 1, upserted_id: None }));
+            expect!(collection.find(&doc("{}"), &FindOptions::new()).unwrap())
+                .to(be_equal_to(vec![doc("{_id: 1, a: 11}"), doc("{_id: 2, a: 2}"), doc("{_id: 3, a: 2}")]));
+        }
+
+        it "upserts from the filter's equality fields" {
+            let mut collection = Collection::new("test.things");
+            let options = UpdateOptions::new().upsert(true);
+            let result = collection.update_one(&doc("{_id: 7, a: {$eq: 1}, b: {$gt: 1}}"), &doc("{$set: {c: 2}}"), &options).unwrap();
+            expect!(result.upserted_id).to(be_some().value(Bson::Int32(7)));
+            expect!(collection.find_one(&doc("{_id: 7}")).unwrap()).to(be_some().value(doc("{_id: 7, a: 1, c: 2}")));
+        }
+
+        it "updates with array filters" {
+            let mut collection = collection(&["{_id: 1, a: [1, 5, 9]}"]);
+            let options = UpdateOptions::new().array_filters(vec![doc("{x: {$gt: 4}}")]);
+            collection.update_one(&doc("{_id: 1}"), &doc("{$set: {'a.$[x]': 0}}"), &options).unwrap();
+            expect!(collection.find_one(&doc("{_id: 1}")).unwrap()).to(be_some().value(doc("{_id: 1, a: [1, 0, 0]}")));
+        }
+    }
+
+    describe! delete {
+        it "deletes one or many documents" {
+            let mut collection = collection(&["{_id: 1, a: 1}", "{_id: 2, a: 1}", "{_id: 3, a: 2}"]);
+            expect!(collection.delete_one(&doc("{a: 1}")).unwrap()).to(be_equal_to(1));
+            expect!(collection.delete_many(&doc("{}")).unwrap()).to(be_equal_to(2));
+            expect!(collection.is_empty()).to(be_true());
+        }
+    }
+
+    describe! indexes {
+        it "rejects duplicate keys on unique compound indexes" {
+            let mut collection = collection(&["{_id: 1, a: 1, b: 1}"]);
+            expect!(collection.create_index(&doc("{a: 1, b: -1}"), true).unwrap()).to(be_equal_to("a_1_b_-1"));
+            expect!(collection.insert_one(doc("{_id: 2, a: 1, b: 2}")).is_ok()).to(be_true());
+            expect!(collection.insert_one(doc("{_id: 3, a: 1, b: 1}")).unwrap_err().message())
+                .to(be_equal_to("E11000 duplicate key error collection: test.things index: a_1_b_-1 dup key: { \"a\": 1, \"b\": 1 }"));
+            expect!(collection.update_one(&doc("{_id: 2}"), &doc("{$set: {b: 1}}"), &UpdateOptions::new()).is_err()).to(be_true());
+            expect!(collection.find_one(&doc("{_id: 2}")).unwrap()).to(be_some().value(doc("{_id: 2, a: 1, b: 2}")));
+        }
+
+        it "indexes array elements and frees keys of removed documents" {
+            let mut collection = collection(&["{_id: 1, tags: ['x', 'y']}"]);
+            collection.create_index(&doc("{tags: 1}"), true).unwrap();
+            expect!(collection.insert_one(doc("{_id: 2, tags: 'y'}")).is_err()).to(be_true());
+            collection.delete_one(&doc("{_id: 1}")).unwrap();
+            expect!(collection.insert_one(doc("{_id: 2, tags: 'y'}")).is_ok()).to(be_true());
+        }
+
+        it "indexes fields of subdocuments in arrays" {
+            let mut collection = collection(&["{_id: 1, tags: [{name: 'x'}]}"]);
+            collection.create_index(&doc("{'tags.name': 1}"), true).unwrap();
+            expect!(collection.insert_one(doc("{_id: 2, tags: [{name: 'y'}, {name: 'z'}]}")).is_ok()).to(be_true());
+            expect!(collection.insert_one(doc("{_id: 3, tags: [{name: 'w'}, {name: 'z'}]}")).unwrap_err().message())
+                .to(be_equal_to("E11000 duplicate key error collection: test.things index: tags.name_1 dup key: { \"tags.name\": \"z\" }"));
+        }
+
+        it "rejects compound keys over parallel arrays" {
+            let mut collection = collection(&[]);
+            collection.create_index(&doc("{a: 1, 'b.c': 1}"), false).unwrap();
+            expect!(collection.insert_one(doc("{_id: 1, a: [1, 2], b: {c: 3}}")).is_ok()).to(be_true());
+            expect!(collection.insert_one(doc("{_id: 2, a: [1, 2], b: [{c: 3}]}")).unwrap_err().message())
+                .to(be_equal_to("cannot index parallel arrays [b.c] [a]"));
+            expect!(collection.len()).to(be_equal_to(1));
+        }
+
+        it "fails to create a unique index over duplicate keys" {
+            let mut collection = collection(&["{_id: 1, a: 1}", "{_id: 2, a: 1}"]);
+            expect!(collection.create_index(&doc("{a: 1}"), true).is_err()).to(be_true());
+            expect!(collection.create_index(&doc("{a: 1}"), false).is_ok()).to(be_true());
+            expect!(collection.index_names()).to(be_equal_to(vec!["_id_".to_string(), "a_1".to_string()]));
+            expect!(collection.drop_index("a_1").is_ok()).to(be_true());
+            expect!(collection.drop_index("a_1").is_err()).to(be_true());
+        }
+    }
+}
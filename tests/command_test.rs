@@ -0,0 +1,151 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use std::io::Write;
+use std::net::TcpStream;
+use bson::{
+    AggregateCommand, Bson, Command, CreateIndexesCommand, CursorReply, DeleteCommand, Document, FindAndModifyCommand,
+    FindAndModifyReply, FindCommand, GetMoreCommand, IndexModel, InsertCommand, Message, MockServer, OpMsg, ReadConcern,
+    UpdateCommand, UpdateStatement, WriteConcern, WriteError, WriteReply, DEFAULT_MAX_MESSAGE_SIZE
+};
+use common::doc;
+use expectest::prelude::*;
+
+fn keys(document: &Document) -> Vec<String> {
+    document.iter().map(|(key, _)| key.clone()).collect()
+}
+
+fn run<C: Command>(stream: &mut TcpStream, command: &C) -> Document {
+    stream.write_all(&Message::Msg(OpMsg::new(command.to_document())).encode(1, 0).unwrap()).unwrap();
+    match Message::read_from(stream, DEFAULT_MAX_MESSAGE_SIZE).unwrap().1 {
+        Message::Msg(msg) => msg.body,
+        _ => panic!("expected OP_MSG")
+    }
+}
+
+describe! command_test {
+    describe! builders {
+        it "puts the command name first and the shared fields last" {
+            let command = FindCommand::new("test", "c")
+                .read_concern(ReadConcern::majority())
+                .txn_number(3)
+                .session(doc("{id: 1}"))
+                .limit(5)
+                .filter(doc("{a: 1}"));
+            expect!(keys(&command.to_document()))
+                .to(be_equal_to(vec!["find", "filter", "limit", "$db", "lsid", "txnNumber", "readConcern"]));
+            expect!(command.to_document().get("txnNumber")).to(be_some().value(&Bson::Int64(3)));
+        }
+
+        it "builds write commands with their statements" {
+            let insert = InsertCommand::new("test", "c", vec![doc("{_id: 1}")]).ordered(false)
+                .write_concern(WriteConcern::majority().journal(true).timeout(100));
+            expect!(insert.to_document()).to(be_equal_to(doc(
+                "{insert: 'c', documents: [{_id: 1}], ordered: false, $db: 'test', writeConcern: {w: 'majority', j: true, wtimeout: NumberLong(100)}}"
+            )));
+            let update = UpdateCommand::new("test", "c")
+                .statement(UpdateStatement::new(doc("{}"), doc("{$set: {'a.$[x]': 1}}")).multi(true).array_filters(vec![doc("{x: 0}")]));
+            expect!(update.to_document()).to(be_equal_to(doc(
+                "{update: 'c', updates: [{q: {}, u: {$set: {'a.$[x]': 1}}, multi: true, arrayFilters: [{x: 0}]}], $db: 'test'}"
+            )));
+            let delete = DeleteCommand::new("test", "c").delete_one(doc("{a: 1}")).delete_many(doc("{b: 1}"));
+            expect!(delete.to_document()).to(be_equal_to(doc(
+                "{delete: 'c', deletes: [{q: {a: 1}, limit: 1}, {q: {b: 1}, limit: 0}], $db: 'test'}"
+            )));
+        }
+
+        it "builds cursor commands" {
+            let aggregate = AggregateCommand::new("test", "c", vec![doc("{$match: {}}")]).batch_size(2);
+            expect!(aggregate.to_document()).to(be_equal_to(doc("{aggregate: 'c', pipeline: [{$match: {}}], cursor: {batchSize: 2}, $db: 'test'}")));
+            let current_op = AggregateCommand::database("admin", vec![doc("{$currentOp: {}}")]);
+            expect!(current_op.to_document().get("aggregate")).to(be_some().value(&Bson::Int32(1)));
+            expect!(current_op.to_document().get("cursor")).to(be_some().value(&Bson::Document(Document::new())));
+            let get_more = GetMoreCommand::new("test", "c", 42).batch_size(10).max_time_ms(500);
+            expect!(get_more.to_document()).to(be_equal_to(doc(
+                "{getMore: NumberLong(42), collection: 'c', batchSize: 10, maxTimeMS: NumberLong(500), $db: 'test'}"
+            )));
+        }
+
+        it "names indexes after their keys" {
+            let command = CreateIndexesCommand::new("test", "c")
+                .index(IndexModel::new(doc("{a: 1, b: -1}")).unique(true))
+                .index(IndexModel::new(doc("{loc: '2dsphere'}")))
+                .index(IndexModel::new(doc("{c: 1}")).name("by_c"));
+            expect!(command.to_document()).to(be_equal_to(doc(
+                "{createIndexes: 'c', indexes: [{key: {a: 1, b: -1}, name: 'a_1_b_-1', unique: true}, {key: {loc: '2dsphere'}, name: 'loc_2dsphere'}, {key: {c: 1}, name: 'by_c'}], $db: 'test'}"
+            )));
+        }
+
+        it "builds findAndModify to update or remove" {
+            let update = FindAndModifyCommand::update("test", "c", doc("{a: 1}"), doc("{$inc: {n: 1}}"))
+                .sort(doc("{_id: 1}")).return_new(true).upsert(true);
+            expect!(update.to_document()).to(be_equal_to(doc(
+                "{findAndModify: 'c', query: {a: 1}, sort: {_id: 1}, update: {$inc: {n: 1}}, new: true, upsert: true, $db: 'test'}"
+            )));
+            let remove = FindAndModifyCommand::remove("test", "c", doc("{a: 1}")).projection(doc("{_id: 0}"));
+            expect!(remove.to_document()).to(be_equal_to(doc("{findAndModify: 'c', query: {a: 1}, remove: true, fields: {_id: 0}, $db: 'test'}")));
+            expect!(keys(&remove.fields())).to(be_equal_to(vec!["findAndModify", "query", "remove", "fields"]));
+        }
+    }
+
+    describe! replies {
+        it "parses cursors from firstBatch and nextBatch" {
+            let first = CursorReply::parse(&doc("{cursor: {firstBatch: [{_id: 1}], id: NumberLong(7), ns: 'test.c'}, ok: 1.0}")).unwrap();
+            expect!(first.clone()).to(be_equal_to(CursorReply { id: 7, namespace: "test.c".to_string(), batch: vec![doc("{_id: 1}")] }));
+            expect!(first.has_more()).to(be_true());
+            let next = CursorReply::parse(&doc("{cursor: {nextBatch: [], id: NumberLong(0), ns: 'test.c'}, ok: 1}")).unwrap();
+            expect!(next.has_more()).to(be_false());
+            expect!(CursorReply::parse(&doc("{ok: 1}")).unwrap_err().code_name().to_string()).to(be_equal_to("InvalidReply"));
+        }
+
+        it "parses write replies with write errors and upserts" {
+            let reply = WriteReply::parse(&doc(
+                "{n: 1, nModified: 0, upserted: [{index: 2, _id: 9}], writeErrors: [{index: 1, code: 11000, errmsg: 'dup'}], ok: 1.0}"
+            )).unwrap();
+            expect!(reply.n).to(be_equal_to(1));
+            expect!(reply.n_modified).to(be_some().value(0));
+            expect!(reply.upserted.clone()).to(be_equal_to(vec![(2, Bson::Int32(9))]));
+            expect!(reply.write_errors.clone()).to(be_equal_to(vec![WriteError { index: 1, code: 11000, message: "dup".to_string() }]));
+            expect!(reply.is_ok()).to(be_false());
+            let concern = WriteReply::parse(&doc("{n: 1, writeConcernError: {code: 64, codeName: 'WriteConcernFailed', errmsg: 'timed out'}, ok: 1}")).unwrap();
+            expect!(concern.write_concern_error.map(|error| error.code())).to(be_some().value(64));
+        }
+
+        it "parses findAndModify replies and command failures" {
+            let reply = FindAndModifyReply::parse(&doc(
+                "{lastErrorObject: {n: 1, updatedExisting: false, upserted: 3}, value: {_id: 3, n: 1}, ok: 1.0}"
+            )).unwrap();
+            expect!(reply).to(be_equal_to(FindAndModifyReply {
+                value: Some(doc("{_id: 3, n: 1}")), n: 1, updated_existing: false, upserted: Some(Bson::Int32(3))
+            }));
+            expect!(FindAndModifyReply::parse(&doc("{lastErrorObject: {n: 0}, value: null, ok: 1}")).unwrap().value).to(be_none());
+            let error = WriteReply::parse(&doc("{ok: 0.0, errmsg: 'not primary', code: 10107, codeName: 'NotWritablePrimary'}")).unwrap_err();
+            expect!(error.to_string()).to(be_equal_to("not primary (NotWritablePrimary: 10107)"));
+        }
+
+        it "round trips built commands through the mock server" {
+            let server = MockServer::start().unwrap();
+            let mut stream = TcpStream::connect(server.address()).unwrap();
+            let documents = (0..3).map(|i| doc(&format!("{{_id: {}}}", i))).collect();
+            let inserted = WriteReply::parse(&run(&mut stream, &InsertCommand::new("test", "c", documents))).unwrap();
+            expect!(inserted.n).to(be_equal_to(3));
+            let update = UpdateCommand::new("test", "c").statement(UpdateStatement::new(doc("{_id: 5}"), doc("{$set: {a: 1}}")).upsert(true));
+            let updated = WriteReply::parse(&run(&mut stream, &update)).unwrap();
+            expect!(updated.upserted.clone()).to(be_equal_to(vec![(0, Bson::Int32(5))]));
+            let first = CursorReply::parse(&run(&mut stream, &FindCommand::new("test", "c").sort(doc("{_id: 1}")).batch_size(2))).unwrap();
+            expect!(first.batch.clone()).to(be_equal_to(vec![doc("{_id: 0}"), doc("{_id: 1}")]));
+            let next = CursorReply::parse(&run(&mut stream, &GetMoreCommand::new("test", "c", first.id))).unwrap();
+            expect!(next.batch.len()).to(be_equal_to(2));
+            expect!(next.has_more()).to(be_false());
+            let deleted = WriteReply::parse(&run(&mut stream, &DeleteCommand::new("test", "c").delete_many(doc("{}")))).unwrap();
+            expect!(deleted.n).to(be_equal_to(4));
+        }
+    }
+}
@@ -0,0 +1,20 @@
+#![allow(dead_code)]
+
+use bson::{Document, ShellParser};
+
+/// Parse a document written in shell syntax.
+pub fn doc(shell: &str) -> Document {
+    ShellParser::new(shell).parse_document().unwrap()
+}
+
+/// Build the raw bytes of a document nesting `depth` levels of documents.
+pub fn nested(depth: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for level in 0..depth - 1 {
+        bytes.extend_from_slice(&((5 + 8 * (depth - 1 - level)) as i32).to_le_bytes());
+        bytes.extend_from_slice(&[0x03, b'a', 0]);
+    }
+    bytes.extend_from_slice(&[5, 0, 0, 0, 0]);
+    bytes.extend(vec![0; depth - 1]);
+    bytes
+}
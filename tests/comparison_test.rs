@@ -0,0 +1,128 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+use bson::{Bson, bson_cmp, document_cmp, parse_decimal128};
+use expectest::prelude::*;
+use std::cmp::Ordering;
+use std::f64;
+
+fn decimal(value: &str) -> Bson {
+    Bson::Decimal128(parse_decimal128(value).unwrap())
+}
+
+describe! comparison_test {
+    describe! bson_cmp {
+        it "orders values by type bracket" {
+            let mut values = vec![
+                bson_maxkey!(),
+                Bson::Timestamp(1),
+                Bson::DateTime(1),
+                Bson::Boolean(false),
+                bson_binary!(0, vec![]),
+                bson!([]),
+                bson!({}),
+                bson!("a"),
+                bson!(1),
+                bson_null!(),
+                bson_minkey!()
+            ];
+            values.sort_by(bson_cmp);
+            expect!(values).to(be_equal_to(vec![
+                bson_minkey!(),
+                bson_null!(),
+                bson!(1),
+                bson!("a"),
+                bson!({}),
+                bson!([]),
+                bson_binary!(0, vec![]),
+                Bson::Boolean(false),
+                Bson::DateTime(1),
+                Bson::Timestamp(1),
+                bson_maxkey!()
+            ]));
+        }
+
+        it "compares numbers across types" {
+            expect!(bson_cmp(&Bson::Int32(1), &Bson::Double(1.0))).to(be_equal_to(Ordering::Equal));
+            expect!(bson_cmp(&Bson::Int64(2), &Bson::Double(1.5))).to(be_equal_to(Ordering::Greater));
+            expect!(bson_cmp(&Bson::Double(-0.5), &Bson::Int32(0))).to(be_equal_to(Ordering::Less));
+        }
+
+        it "compares large int64 values without losing precision" {
+            let large = Bson::Int64(9007199254740993);
+            let double = Bson::Double(9007199254740992.0);
+            expect!(bson_cmp(&large, &double)).to(be_equal_to(Ordering::Greater));
+        }
+
+        it "sorts nan before other numbers" {
+            expect!(bson_cmp(&Bson::Double(f64::NAN), &Bson::Int64(i64::min_value()))).to(
+                be_equal_to(Ordering::Less)
+            );
+            expect!(bson_cmp(&Bson::Double(f64::NAN), &Bson::Double(f64::NAN))).to(
+                be_equal_to(Ordering::Equal)
+            );
+        }
+
+        it "compares decimals with other numbers by value" {
+            expect!(bson_cmp(&decimal("1.50"), &decimal("1.5"))).to(be_equal_to(Ordering::Equal));
+            expect!(bson_cmp(&decimal("2"), &Bson::Int64(2))).to(be_equal_to(Ordering::Equal));
+            expect!(bson_cmp(&decimal("-0"), &Bson::Int32(0))).to(be_equal_to(Ordering::Equal));
+            expect!(bson_cmp(&decimal("9223372036854775808"), &Bson::Int64(i64::max_value()))).to(be_equal_to(Ordering::Greater));
+            expect!(bson_cmp(&decimal("0.5"), &Bson::Double(0.5))).to(be_equal_to(Ordering::Equal));
+            expect!(bson_cmp(&decimal("-1E+3"), &decimal("-999.9"))).to(be_equal_to(Ordering::Less));
+        }
+
+        it "rounds doubles to 34 digits to compare them with decimals" {
+            expect!(bson_cmp(&decimal("0.1"), &Bson::Double(0.1))).to(be_equal_to(Ordering::Less));
+            expect!(bson_cmp(&decimal("0.1000000000000000055511151231257827"), &Bson::Double(0.1))).to(be_equal_to(Ordering::Equal));
+        }
+
+        it "orders decimal infinities and nan around the other numbers" {
+            expect!(bson_cmp(&decimal("NaN"), &Bson::Double(f64::NAN))).to(be_equal_to(Ordering::Equal));
+            expect!(bson_cmp(&decimal("NaN"), &decimal("-Infinity"))).to(be_equal_to(Ordering::Less));
+            expect!(bson_cmp(&decimal("-Infinity"), &Bson::Int64(i64::min_value()))).to(be_equal_to(Ordering::Less));
+            expect!(bson_cmp(&decimal("Infinity"), &Bson::Double(f64::INFINITY))).to(be_equal_to(Ordering::Equal));
+        }
+
+        it "compares strings and symbols together" {
+            expect!(bson_cmp(&bson_symbol!("a"), &bson!("b"))).to(be_equal_to(Ordering::Less));
+        }
+
+        it "compares arrays element by element" {
+            expect!(bson_cmp(&bson!([1, 2]), &bson!([1, 3]))).to(be_equal_to(Ordering::Less));
+            expect!(bson_cmp(&bson!([1, 2]), &bson!([1]))).to(be_equal_to(Ordering::Greater));
+        }
+
+        it "compares binary by length before contents" {
+            expect!(bson_cmp(&bson_binary!(0, vec![9]), &bson_binary!(0, vec![1, 1]))).to(
+                be_equal_to(Ordering::Less)
+            );
+        }
+    }
+
+    describe! document_cmp {
+        it "compares by key when values share a type" {
+            let left = document! { "a" => 1 };
+            let right = document! { "b" => 0 };
+            expect!(document_cmp(&left, &right)).to(be_equal_to(Ordering::Less));
+        }
+
+        it "compares by type before key" {
+            let left = document! { "b" => 1 };
+            let right = document! { "a" => "x" };
+            expect!(document_cmp(&left, &right)).to(be_equal_to(Ordering::Less));
+        }
+
+        it "sorts prefixes first" {
+            let left = document! { "a" => 1 };
+            let right = document! { "a" => 1, "b" => 2 };
+            expect!(document_cmp(&left, &right)).to(be_equal_to(Ordering::Less));
+        }
+    }
+}
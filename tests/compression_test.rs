@@ -0,0 +1,99 @@
+#![cfg(all(feature = "snappy", feature = "zlib", feature = "zstd"))]
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use std::io::Write;
+use std::net::TcpStream;
+use bson::{negotiate_compressor, Bson, Compressor, Message, MockServer, OpMsg, DEFAULT_MAX_MESSAGE_SIZE, OP_MSG};
+use common::doc;
+use expectest::prelude::*;
+
+fn large_message() -> Message {
+    let documents = (0..100).map(|i| doc(&format!("{{_id: {}, name: 'a fairly repetitive value'}}", i))).collect();
+    Message::Msg(OpMsg::new(doc("{insert: 'c', $db: 'test'}")).sequence("documents", documents))
+}
+
+describe! compression_test {
+    it "maps compressors to their ids and names" {
+        expect!(Compressor::from_id(1)).to(be_some().value(Compressor::Snappy));
+        expect!(Compressor::from_name("zlib").map(Compressor::id)).to(be_some().value(2));
+        expect!(Compressor::Zstd.id()).to(be_equal_to(3));
+        expect!(Compressor::from_id(4)).to(be_none());
+    }
+
+    it "round trips messages through each compressor" {
+        let message = large_message();
+        for compressor in &[Compressor::Noop, Compressor::Snappy, Compressor::Zlib, Compressor::Zstd] {
+            let compressed = match message.compress(*compressor).unwrap() {
+                Message::Compressed(compressed) => compressed,
+                _ => panic!("expected OP_COMPRESSED")
+            };
+            expect!(compressed.original_op_code).to(be_equal_to(OP_MSG));
+            expect!(compressed.compressor_id).to(be_equal_to(compressor.id()));
+            if *compressor != Compressor::Noop {
+                expect!(compressed.compressed_message.len() < compressed.uncompressed_size as usize).to(be_true());
+            }
+            let bytes = Message::Compressed(compressed).encode(1, 0).unwrap();
+            let decoded = match Message::decode(&bytes).unwrap().1 {
+                Message::Compressed(compressed) => compressed.decompress().unwrap(),
+                _ => panic!("expected OP_COMPRESSED")
+            };
+            expect!(decoded).to(be_equal_to(message.clone()));
+        }
+    }
+
+    it "clears the checksum flag when compressing" {
+        let message = Message::Msg(OpMsg::new(doc("{ping: 1}")).flags(OpMsg::CHECKSUM_PRESENT));
+        let decompressed = match message.compress(Compressor::Zlib).unwrap() {
+            Message::Compressed(compressed) => compressed.decompress().unwrap(),
+            _ => panic!("expected OP_COMPRESSED")
+        };
+        expect!(decompressed).to(be_equal_to(Message::Msg(OpMsg::new(doc("{ping: 1}")))));
+    }
+
+    it "rejects bodies of the wrong size and unknown compressors" {
+        let mut compressed = match large_message().compress(Compressor::Snappy).unwrap() {
+            Message::Compressed(compressed) => compressed,
+            _ => panic!("expected OP_COMPRESSED")
+        };
+        compressed.uncompressed_size += 1;
+        expect!(compressed.decompress().is_err()).to(be_true());
+        compressed.compressor_id = 9;
+        expect!(compressed.decompress().unwrap_err().to_string()).to(be_equal_to("unknown compressor id 9"));
+        expect!(Compressor::Zstd.decompress(b"not zstd", 8).is_err()).to(be_true());
+    }
+
+    it "negotiates the first compressor in the server's list that was offered" {
+        let reply = doc("{ok: 1, compression: ['lz4', 'zstd', 'zlib']}");
+        expect!(negotiate_compressor(&reply, &[Compressor::Zlib, Compressor::Zstd])).to(be_some().value(Compressor::Zstd));
+        expect!(negotiate_compressor(&reply, &[Compressor::Snappy])).to(be_none());
+        expect!(negotiate_compressor(&doc("{ok: 1}"), &[Compressor::Zlib])).to(be_none());
+    }
+
+    it "negotiates and answers compressed messages with the mock server" {
+        let server = MockServer::start().unwrap();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        let hello = Message::Msg(OpMsg::new(doc("{hello: 1, compression: ['lz4', 'zstd'], $db: 'admin'}")));
+        stream.write_all(&hello.encode(1, 0).unwrap()).unwrap();
+        let reply = match Message::read_from(&mut stream, DEFAULT_MAX_MESSAGE_SIZE).unwrap().1 {
+            Message::Msg(msg) => msg.body,
+            _ => panic!("expected OP_MSG")
+        };
+        expect!(reply.get("compression")).to(be_some().value(&Bson::Array(vec![Bson::String("zstd".to_string())])));
+        let compressor = negotiate_compressor(&reply, &[Compressor::Zstd]).unwrap();
+        let ping = Message::Msg(OpMsg::new(doc("{ping: 1, $db: 'admin'}"))).compress(compressor).unwrap();
+        stream.write_all(&ping.encode(2, 0).unwrap()).unwrap();
+        let reply = match Message::read_from(&mut stream, DEFAULT_MAX_MESSAGE_SIZE).unwrap().1 {
+            Message::Compressed(compressed) => compressed.decompress().unwrap(),
+            _ => panic!("expected OP_COMPRESSED")
+        };
+        expect!(reply).to(be_equal_to(Message::Msg(OpMsg::new(doc("{ok: 1.0}")))));
+    }
+}
@@ -0,0 +1,72 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+use bson::{format_decimal128, parse_decimal128};
+use expectest::prelude::*;
+
+fn round_trip(value: &str) -> String {
+    format_decimal128(&parse_decimal128(value).unwrap())
+}
+
+describe! decimal128_test {
+    describe! parse_decimal128 {
+        it "encodes the coefficient and biased exponent" {
+            let mut one = [0u8; 16];
+            one[0] = 1;
+            one[14] = 0x40;
+            one[15] = 0x30;
+            expect!(parse_decimal128("1")).to(be_some().value(one));
+            let mut ten_tenths = one;
+            ten_tenths[0] = 10;
+            ten_tenths[14] = 0x3E;
+            expect!(parse_decimal128("+1.0E+0")).to(be_some().value(ten_tenths));
+        }
+
+        it "rejects malformed strings" {
+            for value in &["", ".", "1.2.3", "1e", "abc", "1 ", "--1", "0x10"] {
+                expect!(parse_decimal128(value)).to(be_none());
+            }
+        }
+
+        it "rejects values that would need rounding" {
+            expect!(parse_decimal128("1234567890123456789012345678901234.5")).to(be_none());
+            expect!(parse_decimal128("1E+6145")).to(be_none());
+            expect!(parse_decimal128("1E-6177")).to(be_none());
+        }
+
+        it "drops trailing zeros past 34 digits" {
+            expect!(round_trip("12345678901234567890123456789012340")).to(be_equal_to("1.234567890123456789012345678901234E+34"));
+        }
+    }
+
+    describe! format_decimal128 {
+        it "keeps the written precision" {
+            expect!(round_trip("1.50")).to(be_equal_to("1.50"));
+            expect!(round_trip("-0")).to(be_equal_to("-0"));
+            expect!(round_trip("0.000001")).to(be_equal_to("0.000001"));
+            expect!(round_trip("1000")).to(be_equal_to("1000"));
+        }
+
+        it "uses scientific notation for large and small exponents" {
+            expect!(round_trip("1E+3")).to(be_equal_to("1E+3"));
+            expect!(round_trip("0.0000001")).to(be_equal_to("1E-7"));
+            expect!(round_trip("-12.5E-10")).to(be_equal_to("-1.25E-9"));
+            expect!(round_trip("0E+10")).to(be_equal_to("0E+10"));
+        }
+
+        it "clamps exponents by padding the coefficient" {
+            expect!(round_trip("1E+6144")).to(be_equal_to("1.000000000000000000000000000000000E+6144"));
+        }
+
+        it "formats the special values" {
+            expect!(round_trip("inf")).to(be_equal_to("Infinity"));
+            expect!(round_trip("-Infinity")).to(be_equal_to("-Infinity"));
+            expect!(round_trip("NaN")).to(be_equal_to("NaN"));
+        }
+    }
+}
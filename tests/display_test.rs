@@ -0,0 +1,112 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+use bson::{parse_decimal128, Bson};
+use expectest::prelude::*;
+use std::f64;
+
+describe! display_test {
+    describe! bson {
+        it "displays doubles with a decimal point" {
+            expect!(Bson::Double(42.0).to_string()).to(be_equal_to("42.0"));
+        }
+
+        it "displays special doubles" {
+            expect!(Bson::Double(f64::NAN).to_string()).to(be_equal_to("NaN"));
+            expect!(Bson::Double(f64::NEG_INFINITY).to_string()).to(be_equal_to("-Infinity"));
+        }
+
+        it "displays escaped strings" {
+            expect!(Bson::from("a \"b\"\n").to_string()).to(be_equal_to("\"a \\\"b\\\"\\n\""));
+        }
+
+        it "displays int32 values as plain numbers" {
+            expect!(Bson::Int32(42).to_string()).to(be_equal_to("42"));
+        }
+
+        it "displays int64 values as number longs" {
+            expect!(Bson::Int64(42).to_string()).to(be_equal_to("NumberLong(42)"));
+        }
+
+        it "displays decimals as number decimals" {
+            expect!(Bson::Decimal128(parse_decimal128("-1.50").unwrap()).to_string()).to(
+                be_equal_to("NumberDecimal(\"-1.50\")")
+            );
+        }
+
+        it "displays binary as base64" {
+            expect!(bson_binary!(0, vec![1, 1, 1]).to_string()).to(
+                be_equal_to("BinData(0, \"AQEB\")")
+            );
+        }
+
+        it "displays uuid binary as uuids" {
+            let bytes = vec![0, 17, 34, 51, 68, 85, 102, 119, 136, 153, 170, 187, 204, 221, 238, 255];
+            expect!(bson_binary!(4, bytes).to_string()).to(
+                be_equal_to("UUID(\"00112233-4455-6677-8899-aabbccddeeff\")")
+            );
+        }
+
+        it "displays datetimes as iso dates" {
+            expect!(bson_datetime!(1486564200000).to_string()).to(
+                be_equal_to("ISODate(\"2017-02-08T14:30:00.000Z\")")
+            );
+        }
+
+        it "displays datetimes before the epoch" {
+            expect!(bson_datetime!(-1).to_string()).to(
+                be_equal_to("ISODate(\"1969-12-31T23:59:59.999Z\")")
+            );
+        }
+
+        it "displays regular expressions as literals" {
+            expect!(bson_regexp!("^a.*", "i").to_string()).to(be_equal_to("/^a.*/i"));
+        }
+
+        it "displays timestamps" {
+            expect!(Bson::Timestamp((1 << 32) + 2).to_string()).to(be_equal_to("Timestamp(1, 2)"));
+        }
+
+        it "displays min and max keys" {
+            expect!(bson_minkey!().to_string()).to(be_equal_to("MinKey()"));
+            expect!(bson_maxkey!().to_string()).to(be_equal_to("MaxKey()"));
+        }
+
+        it "displays arrays" {
+            expect!(bson!([1, "a"]).to_string()).to(be_equal_to("[ 1, \"a\" ]"));
+        }
+    }
+
+    describe! document {
+        before_each {
+            let document = document! {
+                "a" => 42i64,
+                "b" => { "c" => [1, 2], "d" => {} }
+            };
+        }
+
+        it "displays on one line" {
+            expect!(document.to_string()).to(be_equal_to(
+                "{ \"a\": NumberLong(42), \"b\": { \"c\": [ 1, 2 ], \"d\": {} } }"
+            ));
+        }
+
+        it "pretty prints with the alternate flag" {
+            expect!(format!("{:#}", document)).to(be_equal_to(
+                "{\n  \"a\": NumberLong(42),\n  \"b\": {\n    \"c\": [\n      1,\n      2\n    ],\n    \"d\": {}\n  }\n}"
+            ));
+        }
+
+        it "pretty prints with custom indentation" {
+            expect!(document! { "a" => 1 }.to_pretty_string(4)).to(
+                be_equal_to("{\n    \"a\": 1\n}")
+            );
+        }
+    }
+}
@@ -0,0 +1,143 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+mod common;
+
+use bson::{parse_decimal128, Bson, Document, DocumentDeserializer};
+use common::nested;
+use expectest::prelude::*;
+use std::io::Cursor;
+
+describe! document_deserializer_test {
+    describe! deserialize {
+        describe! success {
+            before_each {
+                let bytes = vec![
+                    49, 0, 0, 0,
+                    0x10, b'a', 0, 1, 0, 0, 0,
+                    0x02, b'b', 0, 3, 0, 0, 0, b'h', b'i', 0,
+                    0x03, b'd', 0, 12, 0, 0, 0, 0x10, b'a', 0, 1, 0, 0, 0, 0,
+                    0x04, b'r', 0, 9, 0, 0, 0, 0x08, b'0', 0, 1, 0,
+                    0
+                ];
+                let mut reader = Cursor::new(bytes);
+                let result = DocumentDeserializer::new(&mut reader).deserialize();
+            }
+
+            it "returns the document" {
+                expect!(result).to(be_ok().value(document! {
+                    "a" => 1,
+                    "b" => "hi",
+                    "d" => { "a" => 1 },
+                    "r" => [ true ]
+                }));
+            }
+
+            it "reads only the document bytes" {
+                expect!(reader.position()).to(be_equal_to(49));
+            }
+        }
+
+        describe! with_an_empty_document {
+            it "returns an empty document" {
+                let mut reader = Cursor::new(vec![5, 0, 0, 0, 0]);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(
+                    be_ok().value(Document::new())
+                );
+            }
+        }
+
+        describe! with_every_fixed_size_type {
+            before_each {
+                let bytes = vec![
+                    81, 0, 0, 0,
+                    0x01, b'a', 0, 0, 0, 0, 0, 0, 0, 0xF8, 0x3F,
+                    0x07, b'b', 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+                    0x09, b'c', 0, 0xE8, 0x03, 0, 0, 0, 0, 0, 0,
+                    0x0A, b'd', 0,
+                    0x12, b'e', 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                    0xFF, b'f', 0,
+                    0x7F, b'g', 0,
+                    0x13, b'h', 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x40, 0x30,
+                    0
+                ];
+                let mut reader = Cursor::new(bytes);
+                let document = DocumentDeserializer::new(&mut reader).deserialize().unwrap();
+            }
+
+            it "reads doubles" {
+                expect!(document.get("a")).to(be_equal_to(Some(&Bson::Double(1.5))));
+            }
+
+            it "reads object ids" {
+                expect!(document.get("b")).to(be_equal_to(Some(&bson_objectid!([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]))));
+            }
+
+            it "reads datetimes" {
+                expect!(document.get("c")).to(be_equal_to(Some(&bson_datetime!(1000))));
+            }
+
+            it "reads nulls" {
+                expect!(document.get("d")).to(be_equal_to(Some(&bson_null!())));
+            }
+
+            it "reads 64bit integers" {
+                expect!(document.get("e")).to(be_equal_to(Some(&Bson::Int64(-1))));
+            }
+
+            it "reads min and max keys" {
+                expect!(document.get("f")).to(be_equal_to(Some(&bson_minkey!())));
+                expect!(document.get("g")).to(be_equal_to(Some(&bson_maxkey!())));
+            }
+
+            it "reads decimals" {
+                expect!(document.get("h")).to(be_equal_to(Some(&Bson::Decimal128(parse_decimal128("1").unwrap()))));
+            }
+        }
+
+        describe! failure {
+            it "errors on an invalid length" {
+                let mut reader = Cursor::new(vec![4, 0, 0, 0]);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(be_err());
+            }
+
+            it "errors on an unknown element type" {
+                let mut reader = Cursor::new(vec![8, 0, 0, 0, 0x42, b'a', 0, 0]);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(be_err());
+            }
+
+            it "errors on a missing terminator" {
+                let mut reader = Cursor::new(vec![7, 0, 0, 0, 0x0A, b'a', 0]);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(be_err());
+            }
+
+            it "errors on invalid utf-8" {
+                let mut reader = Cursor::new(vec![14, 0, 0, 0, 0x02, b'a', 0, 2, 0, 0, 0, 0xFF, 0, 0]);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(be_err());
+            }
+
+            it "errors on a truncated stream" {
+                let mut reader = Cursor::new(vec![12, 0, 0, 0, 0x10, b'a', 0]);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(be_err());
+            }
+
+            it "errors on a length prefix beyond the stream" {
+                let mut reader = Cursor::new(vec![0xFF, 0xFF, 0xFF, 0x7F, 0]);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(be_err());
+            }
+
+            it "errors on documents nested deeper than the limit" {
+                expect!(DocumentDeserializer::new(&mut Cursor::new(nested(200))).deserialize()).to(be_ok());
+                let error = DocumentDeserializer::new(&mut Cursor::new(nested(201))).deserialize().unwrap_err();
+                expect!(error.to_string()).to(be_equal_to("document nesting exceeds the maximum depth of 200"));
+                expect!(DocumentDeserializer::new(&mut Cursor::new(nested(100_000))).deserialize()).to(be_err());
+            }
+        }
+    }
+}
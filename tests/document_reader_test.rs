@@ -0,0 +1,188 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+use bson::{DocumentReader, RawDocumentBuf};
+use expectest::prelude::*;
+use std::io::{Cursor, ErrorKind};
+
+describe! document_reader_test {
+    before_each {
+        let first = vec![12, 0, 0, 0, 0x10, b'a', 0, 1, 0, 0, 0, 0];
+        let second = vec![12, 0, 0, 0, 0x10, b'a', 0, 2, 0, 0, 0, 0];
+        let mut bytes = first.clone();
+        bytes.extend_from_slice(&second);
+    }
+
+    describe! documents {
+        describe! with_concatenated_documents {
+            before_each {
+                let mut reader = DocumentReader::new(Cursor::new(bytes));
+            }
+
+            it "yields each document in order" {
+                expect!(reader.next().unwrap()).to(be_ok().value(document! { "a" => 1 }));
+                expect!(reader.next().unwrap()).to(be_ok().value(document! { "a" => 2 }));
+                expect!(reader.next().is_none()).to(be_true());
+            }
+
+            it "tracks the offset of each document" {
+                reader.next();
+                expect!(reader.document_offset()).to(be_equal_to(0));
+                reader.next();
+                expect!(reader.document_offset()).to(be_equal_to(12));
+                expect!(reader.position()).to(be_equal_to(24));
+            }
+        }
+
+        describe! with_an_empty_stream {
+            it "yields nothing" {
+                let mut reader = DocumentReader::new(Cursor::new(vec![]));
+                expect!(reader.next().is_none()).to(be_true());
+            }
+        }
+
+        describe! with_a_truncated_document {
+            before_each {
+                bytes.truncate(20);
+                let mut reader = DocumentReader::new(Cursor::new(bytes));
+                reader.next();
+                let error = reader.next().unwrap().unwrap_err();
+            }
+
+            it "returns an unexpected eof error" {
+                expect!(error.kind()).to(be_equal_to(ErrorKind::UnexpectedEof));
+            }
+
+            it "stops reading" {
+                expect!(reader.next().is_none()).to(be_true());
+            }
+        }
+
+        describe! with_a_huge_length_prefix {
+            it "returns an unexpected eof error once the stream ends" {
+                let mut reader = DocumentReader::new(Cursor::new(vec![0xff, 0xff, 0xff, 0x7f, 0, 0]));
+                expect!(reader.next().unwrap().unwrap_err().kind()).to(be_equal_to(ErrorKind::UnexpectedEof));
+            }
+        }
+
+        describe! with_malformed_contents {
+            before_each {
+                bytes[4] = 0x42;
+                let mut reader = DocumentReader::new(Cursor::new(bytes));
+            }
+
+            it "returns an error and continues with the next document" {
+                expect!(reader.next().unwrap()).to(be_err());
+                expect!(reader.next().unwrap()).to(be_ok().value(document! { "a" => 2 }));
+            }
+        }
+
+        describe! with_a_maximum_document_size {
+            it "rejects larger documents" {
+                let mut reader = DocumentReader::new(Cursor::new(bytes)).max_document_size(8);
+                let error = reader.next().unwrap().unwrap_err();
+                expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidData));
+            }
+        }
+    }
+
+    describe! recover {
+        describe! with_garbage_between_documents {
+            before_each {
+                let mut corrupt = first.clone();
+                corrupt.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+                corrupt.extend_from_slice(&second);
+                let mut reader = DocumentReader::new(Cursor::new(corrupt)).recover();
+                let documents: Vec<_> = reader.by_ref().collect();
+            }
+
+            it "yields the documents around the garbage" {
+                expect!(documents.len()).to(be_equal_to(2));
+                expect!(documents[1].as_ref().ok()).to(be_equal_to(Some(&document! { "a" => 2 })));
+            }
+
+            it "reports the skipped range" {
+                expect!(reader.skipped_ranges().to_vec()).to(be_equal_to(vec![12..19]));
+            }
+
+            it "reports the offset of the recovered document" {
+                expect!(reader.document_offset()).to(be_equal_to(19));
+            }
+        }
+
+        describe! with_a_torn_write {
+            before_each {
+                let mut corrupt = first.clone();
+                corrupt.extend_from_slice(&second[..6]);
+                corrupt.extend_from_slice(&second);
+                let mut reader = DocumentReader::new(Cursor::new(corrupt)).recover();
+                let documents: Vec<_> = reader.by_ref().map(|result| result.unwrap()).collect();
+            }
+
+            it "skips the partial document" {
+                expect!(documents).to(be_equal_to(vec![document! { "a" => 1 }, document! { "a" => 2 }]));
+                expect!(reader.skipped_ranges().to_vec()).to(be_equal_to(vec![12..18]));
+            }
+        }
+
+        describe! with_malformed_contents {
+            before_each {
+                bytes[4] = 0x42;
+                let mut reader = DocumentReader::new(Cursor::new(bytes)).recover();
+                let documents: Vec<_> = reader.by_ref().map(|result| result.unwrap()).collect();
+            }
+
+            it "skips the malformed document" {
+                expect!(documents).to(be_equal_to(vec![document! { "a" => 2 }]));
+                expect!(reader.skipped_ranges().to_vec()).to(be_equal_to(vec![0..12]));
+            }
+        }
+
+        describe! with_trailing_garbage {
+            before_each {
+                let mut corrupt = first.clone();
+                corrupt.extend_from_slice(&[1, 2, 3]);
+                let mut reader = DocumentReader::new(Cursor::new(corrupt)).recover().raw();
+                let documents: Vec<_> = reader.by_ref().map(|result| result.unwrap()).collect();
+            }
+
+            it "skips to the end of the stream" {
+                expect!(documents).to(be_equal_to(vec![RawDocumentBuf::new(first).unwrap()]));
+                expect!(reader.skipped_ranges().to_vec()).to(be_equal_to(vec![12..15]));
+            }
+        }
+    }
+
+    describe! raw {
+        before_each {
+            let mut reader = DocumentReader::new(Cursor::new(bytes)).raw();
+        }
+
+        it "yields raw documents" {
+            expect!(reader.next().unwrap()).to(be_ok().value(RawDocumentBuf::new(first).unwrap()));
+            expect!(reader.next().unwrap()).to(be_ok().value(RawDocumentBuf::new(second).unwrap()));
+            expect!(reader.next().is_none()).to(be_true());
+        }
+
+        it "converts raw documents to documents" {
+            let raw = reader.next().unwrap().unwrap();
+            expect!(raw.to_document()).to(be_ok().value(document! { "a" => 1 }));
+        }
+    }
+
+    describe! raw_document_buf {
+        it "rejects mismatched lengths" {
+            expect!(RawDocumentBuf::new(vec![6, 0, 0, 0, 0])).to(be_err());
+        }
+
+        it "rejects missing terminators" {
+            expect!(RawDocumentBuf::new(vec![5, 0, 0, 0, 1])).to(be_err());
+        }
+    }
+}
@@ -3,11 +3,12 @@
 #[macro_use(expect)]
 
 extern crate expectest;
+#[macro_use]
 extern crate bson;
 
-use bson::{Document, DocumentSerializer};
+use bson::{parse_decimal128, Bson, Document, DocumentDeserializer, DocumentSerializer};
 use expectest::prelude::*;
-use std::io::Cursor;
+use std::io::{Cursor, ErrorKind};
 
 describe! document_serializer_test {
     describe! serialize {
@@ -25,5 +26,48 @@ describe! document_serializer_test {
                 }
             }
         }
+
+        describe! bytes {
+            it "writes the length prefix, elements and terminator" {
+                let mut bytes = vec![];
+                DocumentSerializer::new(&mut bytes).serialize(&document! { "a" => 1 }).unwrap();
+                expect!(bytes).to(be_equal_to(vec![12, 0, 0, 0, 0x10, b'a', 0, 1, 0, 0, 0, 0]));
+            }
+
+            it "writes an empty document as five bytes" {
+                let mut bytes = vec![];
+                DocumentSerializer::new(&mut bytes).serialize(&Document::new()).unwrap();
+                expect!(bytes).to(be_equal_to(vec![5, 0, 0, 0, 0]));
+            }
+        }
+
+        describe! round_trip {
+            it "deserializes to the same document" {
+                let document = document! {
+                    "double" => 1.5,
+                    "string" => "value",
+                    "nested" => (document! { "array" => (vec![Bson::Int32(1), Bson::Null]) }),
+                    "binary" => (Bson::Binary(0, vec![1, 2, 3])),
+                    "id" => (Bson::ObjectId([1; 12])),
+                    "regex" => (Bson::RegExp("^a".to_string(), "i".to_string())),
+                    "code" => (Bson::Code("x".to_string(), document! { "y" => 1 })),
+                    "long" => (Bson::Int64(-7)),
+                    "decimal" => (Bson::Decimal128(parse_decimal128("-0.001").unwrap())),
+                    "min" => (Bson::MinKey)
+                };
+                let mut bytes = vec![];
+                DocumentSerializer::new(&mut bytes).serialize(&document).unwrap();
+                let mut reader = Cursor::new(bytes);
+                expect!(DocumentDeserializer::new(&mut reader).deserialize()).to(be_ok().value(document));
+            }
+        }
+
+        describe! invalid_key {
+            it "returns an invalid input error" {
+                let mut bytes = vec![];
+                let result = DocumentSerializer::new(&mut bytes).serialize(&document! { "a\0b" => 1 });
+                expect!(result.unwrap_err().kind()).to(be_equal_to(ErrorKind::InvalidInput));
+            }
+        }
     }
 }
@@ -27,6 +27,7 @@ describe! document_test {
                 "array_with_one" => [ 1 ],
                 "binary" => (bson_binary!(1, vec![1, 1, 1])),
                 "undefined" => (bson_undefined!()),
+                "objectid" => (bson_objectid!([ 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1 ])),
                 "true" => true,
                 "false" => false,
                 "datetime" => (bson_datetime!(1486564200000)),
@@ -103,6 +104,12 @@ describe! document_test {
             );
         }
 
+        it "handles object ids" {
+            expect!(document.get("objectid")).to(be_equal_to(
+                Some(&Bson::ObjectId([ 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1 ]))
+            ));
+        }
+
         it "handles boolean true" {
             expect!(document.get("true")).to(
                 be_equal_to(Some(&Bson::Boolean(true)))
@@ -0,0 +1,82 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+use bson::{Bson, DocumentReader, DocumentWriter};
+use expectest::prelude::*;
+use std::io::{Cursor, ErrorKind};
+
+describe! document_writer_test {
+    before_each {
+        let mut writer = DocumentWriter::new(Cursor::new(vec![]));
+    }
+
+    describe! write_document {
+        it "writes a sequence readable by the document reader" {
+            writer.write_document(&document! { "a" => 1 }).unwrap();
+            writer.write_document(&document! { "a" => 2 }).unwrap();
+            expect!(writer.bytes_written()).to(be_equal_to(24));
+            let bytes = writer.into_inner().into_inner();
+            let documents: Vec<_> = DocumentReader::new(Cursor::new(bytes)).map(Result::unwrap).collect();
+            expect!(documents).to(be_equal_to(vec![document! { "a" => 1 }, document! { "a" => 2 }]));
+        }
+
+        it "returns an error while a document is open" {
+            writer.start_document().unwrap();
+            let error = writer.write_document(&document! { "a" => 1 }).unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidInput));
+        }
+    }
+
+    describe! streaming {
+        it "patches the lengths of nested values on close" {
+            writer.start_document().unwrap();
+            writer.write_element("name", &Bson::from("rows")).unwrap();
+            writer.start_array("rows").unwrap();
+            writer.push(&Bson::Int32(1)).unwrap();
+            writer.start_document_element("").unwrap();
+            writer.write_element("b", &Bson::Boolean(true)).unwrap();
+            writer.end().unwrap();
+            writer.push(&Bson::Int32(3)).unwrap();
+            writer.end().unwrap();
+            writer.end().unwrap();
+            writer.write_document(&document! { "after" => 1 }).unwrap();
+            expect!(writer.depth()).to(be_equal_to(0));
+            let bytes = writer.into_inner().into_inner();
+            let documents: Vec<_> = DocumentReader::new(Cursor::new(bytes)).map(Result::unwrap).collect();
+            let rows = vec![Bson::Int32(1), Bson::Document(document! { "b" => true }), Bson::Int32(3)];
+            expect!(documents).to(be_equal_to(vec![
+                document! { "name" => "rows", "rows" => rows },
+                document! { "after" => 1 }
+            ]));
+        }
+
+        it "tracks the bytes written" {
+            writer.start_document().unwrap();
+            writer.end().unwrap();
+            expect!(writer.bytes_written()).to(be_equal_to(5));
+            expect!(writer.into_inner().into_inner()).to(be_equal_to(vec![5, 0, 0, 0, 0]));
+        }
+
+        it "returns an error when pushing outside an array" {
+            writer.start_document().unwrap();
+            expect!(writer.push(&Bson::Null).unwrap_err().kind()).to(be_equal_to(ErrorKind::InvalidInput));
+        }
+
+        it "returns an error when writing a keyed element into an array" {
+            writer.start_document().unwrap();
+            writer.start_array("a").unwrap();
+            let error = writer.write_element("b", &Bson::Null).unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidInput));
+        }
+
+        it "returns an error when ending with nothing open" {
+            expect!(writer.end().unwrap_err().kind()).to(be_equal_to(ErrorKind::InvalidInput));
+        }
+    }
+}
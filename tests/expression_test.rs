@@ -0,0 +1,131 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use bson::{parse_decimal128, Bson, Expression};
+use common::doc;
+use expectest::prelude::*;
+
+fn evaluate(expression: &str, document: &str) -> Option<Bson> {
+    let expression = doc(&format!("{{e: {}}}", expression)).get("e").cloned().unwrap();
+    Expression::parse(&expression).unwrap().evaluate(&doc(document)).unwrap()
+}
+
+fn decimal(value: &str) -> Option<Bson> {
+    Some(Bson::Decimal128(parse_decimal128(value).unwrap()))
+}
+
+fn error(expression: &str, document: &str) -> String {
+    let expression = doc(&format!("{{e: {}}}", expression)).get("e").cloned().unwrap();
+    match Expression::parse(&expression) {
+        Ok(expression) => expression.evaluate(&doc(document)).unwrap_err().message().to_string(),
+        Err(error) => error.message().to_string()
+    }
+}
+
+describe! expression_test {
+    describe! references {
+        it "resolves fields and variables" {
+            expect!(evaluate("'$a.b'", "{a: {b: 1}}")).to(be_equal_to(Some(Bson::Int32(1))));
+            expect!(evaluate("'$a.b'", "{a: [{b: 1}, {c: 2}, {b: 3}]}")).to(be_equal_to(Some(Bson::Array(vec![Bson::Int32(1), Bson::Int32(3)]))));
+            expect!(evaluate("'$missing'", "{a: 1}")).to(be_none());
+            expect!(evaluate("'$$ROOT'", "{a: 1}")).to(be_equal_to(Some(Bson::Document(doc("{a: 1}")))));
+            expect!(evaluate("{x: '$a', y: '$missing'}", "{a: 1}")).to(be_equal_to(Some(Bson::Document(doc("{x: 1}")))));
+        }
+
+        it "binds variables with $let" {
+            expect!(evaluate("{$let: {vars: {x: '$a'}, in: {$multiply: ['$$x', 2]}}}", "{a: 4}")).to(be_equal_to(Some(Bson::Int32(8))));
+            expect!(error("'$$x'", "{}")).to(be_equal_to("Use of undefined variable: x"));
+        }
+    }
+
+    describe! arithmetic {
+        it "adds, subtracts, multiplies and divides" {
+            expect!(evaluate("{$add: ['$a', 2, 0.5]}", "{a: 1}")).to(be_equal_to(Some(Bson::Double(3.5))));
+            expect!(evaluate("{$add: [2147483647, 1]}", "{}")).to(be_equal_to(Some(Bson::Int64(2147483648))));
+            expect!(evaluate("{$subtract: [10, '$a']}", "{a: 3}")).to(be_equal_to(Some(Bson::Int32(7))));
+            expect!(evaluate("{$divide: [7, 2]}", "{}")).to(be_equal_to(Some(Bson::Double(3.5))));
+            expect!(evaluate("{$mod: [7, 3]}", "{}")).to(be_equal_to(Some(Bson::Int32(1))));
+            expect!(evaluate("{$add: [1, '$missing']}", "{}")).to(be_equal_to(Some(Bson::Null)));
+        }
+
+        it "computes with decimals" {
+            expect!(evaluate("{$add: [NumberDecimal('0.1'), NumberDecimal('0.2')]}", "{}")).to(be_equal_to(decimal("0.3")));
+            expect!(evaluate("{$add: ['$a', 0.1]}", "{a: NumberDecimal('1')}")).to(be_equal_to(decimal("1.100000000000000")));
+            expect!(evaluate("{$subtract: [NumberDecimal('1'), 3]}", "{}")).to(be_equal_to(decimal("-2")));
+            expect!(evaluate("{$multiply: [NumberDecimal('1.5'), NumberLong(2)]}", "{}")).to(be_equal_to(decimal("3.0")));
+            expect!(evaluate("{$divide: [NumberDecimal('1'), 3]}", "{}")).to(be_equal_to(decimal("0.3333333333333333333333333333333333")));
+            expect!(evaluate("{$divide: [NumberDecimal('6'), 2]}", "{}")).to(be_equal_to(decimal("3")));
+            expect!(evaluate("{$divide: [1, NumberDecimal('4')]}", "{}")).to(be_equal_to(decimal("0.25")));
+            expect!(evaluate("{$mod: [NumberDecimal('-7.5'), 2]}", "{}")).to(be_equal_to(decimal("-1.5")));
+        }
+
+        it "rejects division by zero" {
+            expect!(error("{$divide: [1, 0]}", "{}")).to(be_equal_to("can't $divide by zero"));
+            expect!(error("{$divide: [1, NumberDecimal('0.00')]}", "{}")).to(be_equal_to("can't $divide by zero"));
+            expect!(error("{$subtract: [1]}", "{}")).to(be_equal_to("Expression $subtract takes exactly 2 arguments. 1 were passed in."));
+        }
+    }
+
+    describe! strings {
+        it "concatenates, slices and changes case" {
+            expect!(evaluate("{$concat: ['$a', '-', 'b']}", "{a: 'x'}")).to(be_equal_to(Some(Bson::String("x-b".to_string()))));
+            expect!(evaluate("{$substrCP: ['héllo', 1, 3]}", "{}")).to(be_equal_to(Some(Bson::String("éll".to_string()))));
+            expect!(evaluate("{$toUpper: '$a'}", "{a: 'abc'}")).to(be_equal_to(Some(Bson::String("ABC".to_string()))));
+            expect!(error("{$concat: ['a', 1]}", "{}")).to(be_equal_to("$concat only supports strings, not int"));
+        }
+    }
+
+    describe! conditionals {
+        it "compares and chooses values" {
+            expect!(evaluate("{$gt: ['$a', 1]}", "{a: 2}")).to(be_equal_to(Some(Bson::Boolean(true))));
+            expect!(evaluate("{$cmp: ['a', 'b']}", "{}")).to(be_equal_to(Some(Bson::Int32(-1))));
+            expect!(evaluate("{$cond: {if: {$eq: ['$a', 1]}, then: 'one', else: 'other'}}", "{a: 1}"))
+                .to(be_equal_to(Some(Bson::String("one".to_string()))));
+            expect!(evaluate("{$cond: [false, 1, 2]}", "{}")).to(be_equal_to(Some(Bson::Int32(2))));
+            expect!(evaluate("{$ifNull: ['$a', '$b', 0]}", "{b: null}")).to(be_equal_to(Some(Bson::Int32(0))));
+        }
+
+        it "switches between branches" {
+            let switch = "{$switch: {branches: [{case: {$lt: ['$a', 0]}, then: 'negative'}, {case: {$gt: ['$a', 0]}, then: 'positive'}], default: 'zero'}}";
+            expect!(evaluate(switch, "{a: 5}")).to(be_equal_to(Some(Bson::String("positive".to_string()))));
+            expect!(evaluate(switch, "{a: 0}")).to(be_equal_to(Some(Bson::String("zero".to_string()))));
+        }
+    }
+
+    describe! arrays {
+        it "maps, filters and reduces arrays" {
+            expect!(evaluate("{$map: {input: '$a', as: 'x', in: {$add: ['$$x', 1]}}}", "{a: [1, 2]}"))
+                .to(be_equal_to(Some(Bson::Array(vec![Bson::Int32(2), Bson::Int32(3)]))));
+            expect!(evaluate("{$filter: {input: '$a', cond: {$gte: ['$$this', 2]}}}", "{a: [1, 2, 3]}"))
+                .to(be_equal_to(Some(Bson::Array(vec![Bson::Int32(2), Bson::Int32(3)]))));
+            expect!(evaluate("{$reduce: {input: '$a', initialValue: 0, in: {$add: ['$$value', '$$this']}}}", "{a: [1, 2, 3]}"))
+                .to(be_equal_to(Some(Bson::Int32(6))));
+            expect!(evaluate("{$size: '$a'}", "{a: [1, 2]}")).to(be_equal_to(Some(Bson::Int32(2))));
+            expect!(error("{$size: '$a'}", "{a: 1}")).to(be_equal_to("The argument to $size must be an array, but was of type: int"));
+        }
+    }
+
+    describe! conversions {
+        it "converts between types" {
+            expect!(evaluate("{$toString: '$a'}", "{a: 1.5}")).to(be_equal_to(Some(Bson::String("1.5".to_string()))));
+            expect!(evaluate("{$toInt: '$a'}", "{a: '42'}")).to(be_equal_to(Some(Bson::Int32(42))));
+            expect!(evaluate("{$toInt: 2.9}", "{}")).to(be_equal_to(Some(Bson::Int32(2))));
+            expect!(evaluate("{$convert: {input: 'x', to: 'int', onError: -1}}", "{}")).to(be_equal_to(Some(Bson::Int32(-1))));
+            expect!(evaluate("{$convert: {input: '$missing', to: 'int', onNull: 0}}", "{}")).to(be_equal_to(Some(Bson::Int32(0))));
+            expect!(evaluate("{$convert: {input: 1, to: 'bool'}}", "{}")).to(be_equal_to(Some(Bson::Boolean(true))));
+            expect!(evaluate("{$toDecimal: 2.5}", "{}")).to(be_equal_to(decimal("2.50000000000000")));
+            expect!(evaluate("{$toDecimal: ' 1.50'}", "{}")).to(be_equal_to(decimal("1.50")));
+            expect!(evaluate("{$convert: {input: NumberLong(7), to: 19}}", "{}")).to(be_equal_to(decimal("7")));
+            expect!(evaluate("{$toString: NumberDecimal('1E+40')}", "{}")).to(be_equal_to(Some(Bson::String("1E+40".to_string()))));
+            expect!(evaluate("{$toInt: NumberDecimal('-2.9')}", "{}")).to(be_equal_to(Some(Bson::Int32(-2))));
+            expect!(evaluate("{$toDouble: NumberDecimal('0.5')}", "{}")).to(be_equal_to(Some(Bson::Double(0.5))));
+        }
+    }
+}
@@ -0,0 +1,123 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use bson::{Filter};
+use common::doc;
+use expectest::prelude::*;
+
+fn matches(filter: &str, document: &str) -> bool {
+    Filter::parse(&doc(filter)).unwrap().matches(&doc(document))
+}
+
+describe! filter_test {
+    describe! equality {
+        it "matches equal values across numeric types" {
+            expect!(matches("{a: 1}", "{a: 1.0}")).to(be_true());
+            expect!(matches("{a: 1}", "{a: NumberLong(2)}")).to(be_false());
+            expect!(matches("{a: NumberDecimal('1.0')}", "{a: 1}")).to(be_true());
+            expect!(matches("{a: 0.1}", "{a: NumberDecimal('0.1')}")).to(be_false());
+        }
+
+        it "matches an element of an array field" {
+            expect!(matches("{a: 2}", "{a: [1, 2, 3]}")).to(be_true());
+            expect!(matches("{a: [1, 2]}", "{a: [1, 2]}")).to(be_true());
+        }
+
+        it "matches null against missing fields" {
+            expect!(matches("{b: null}", "{a: 1}")).to(be_true());
+            expect!(matches("{a: {$ne: null}}", "{a: 1}")).to(be_true());
+        }
+
+        it "follows dotted paths through arrays of documents" {
+            expect!(matches("{'a.b': 2}", "{a: [{b: 1}, {b: 2}]}")).to(be_true());
+            expect!(matches("{'a.1': 5}", "{a: [4, 5]}")).to(be_true());
+            expect!(matches("{'a.b.c': 3}", "{a: {b: {c: 3}}}")).to(be_true());
+        }
+    }
+
+    describe! comparison {
+        it "only compares values in the same type bracket" {
+            expect!(matches("{a: {$gt: 1}}", "{a: 2}")).to(be_true());
+            expect!(matches("{a: {$gt: 1}}", "{a: 'x'}")).to(be_false());
+            expect!(matches("{a: {$gte: 1, $lt: 3}}", "{a: [0, 2]}")).to(be_true());
+            expect!(matches("{a: {$lte: 'm'}}", "{a: 'a'}")).to(be_true());
+        }
+
+        it "supports $in and $nin" {
+            expect!(matches("{a: {$in: [1, /^x/]}}", "{a: 'xyz'}")).to(be_true());
+            expect!(matches("{a: {$nin: [1, 2]}}", "{a: 3}")).to(be_true());
+            expect!(matches("{a: {$nin: [1, 2]}}", "{a: [3, 2]}")).to(be_false());
+        }
+    }
+
+    describe! logical {
+        it "supports $and, $or, $nor and $not" {
+            expect!(matches("{$and: [{a: 1}, {b: 2}]}", "{a: 1, b: 2}")).to(be_true());
+            expect!(matches("{$or: [{a: 2}, {b: 2}]}", "{a: 1, b: 2}")).to(be_true());
+            expect!(matches("{$nor: [{a: 1}]}", "{a: 1}")).to(be_false());
+            expect!(matches("{a: {$not: {$gt: 5}}}", "{a: 1}")).to(be_true());
+            expect!(matches("{a: {$not: /^x/}}", "{a: 'xy'}")).to(be_false());
+        }
+    }
+
+    describe! element {
+        it "supports $exists and $type" {
+            expect!(matches("{a: {$exists: true}}", "{a: null}")).to(be_true());
+            expect!(matches("{b: {$exists: false}}", "{a: 1}")).to(be_true());
+            expect!(matches("{a: {$type: 'string'}}", "{a: 'x'}")).to(be_true());
+            expect!(matches("{a: {$type: ['number']}}", "{a: NumberLong(1)}")).to(be_true());
+            expect!(matches("{a: {$type: 'array'}}", "{a: []}")).to(be_true());
+            expect!(matches("{a: {$type: 2}}", "{a: 1}")).to(be_false());
+            expect!(matches("{a: {$type: 'decimal'}}", "{a: NumberDecimal('1')}")).to(be_true());
+            expect!(matches("{a: {$type: 19}}", "{a: 1.5}")).to(be_false());
+        }
+    }
+
+    describe! evaluation {
+        it "supports $regex with $options" {
+            expect!(matches("{a: {$regex: '^AB', $options: 'i'}}", "{a: 'abc'}")).to(be_true());
+            expect!(matches("{a: /c$/}", "{a: 'abc'}")).to(be_true());
+        }
+
+        it "supports $mod" {
+            expect!(matches("{a: {$mod: [4, 1]}}", "{a: 9}")).to(be_true());
+            expect!(matches("{a: {$mod: [4, 1]}}", "{a: 10.5}")).to(be_false());
+            expect!(matches("{a: {$mod: [NumberDecimal('4'), 1]}}", "{a: NumberDecimal('-7.9')}")).to(be_false());
+            expect!(matches("{a: {$mod: [4, 1]}}", "{a: NumberDecimal('9.9')}")).to(be_true());
+        }
+    }
+
+    describe! array {
+        it "supports $size, $all and $elemMatch" {
+            expect!(matches("{a: {$size: 2}}", "{a: [1, 2]}")).to(be_true());
+            expect!(matches("{a: {$size: NumberDecimal('2.00')}}", "{a: [1, 2]}")).to(be_true());
+            expect!(matches("{a: {$all: [1, 3]}}", "{a: [1, 2, 3]}")).to(be_true());
+            expect!(matches("{a: {$all: []}}", "{a: [1]}")).to(be_false());
+            expect!(matches("{a: {$elemMatch: {$gt: 1, $lt: 3}}}", "{a: [0, 2]}")).to(be_true());
+            expect!(matches("{a: {$elemMatch: {$gt: 1, $lt: 2}}}", "{a: [0, 3]}")).to(be_false());
+            expect!(matches("{a: {$elemMatch: {b: 1, c: 2}}}", "{a: [{b: 1, c: 1}, {b: 2, c: 2}]}")).to(be_false());
+            expect!(matches("{a: {$elemMatch: {b: 1, c: 2}}}", "{a: [{b: 1, c: 2}]}")).to(be_true());
+        }
+    }
+
+    describe! parse {
+        it "returns an error for unknown operators" {
+            expect!(Filter::parse(&doc("{a: {$near: 1}}"))).to(be_err());
+            expect!(Filter::parse(&doc("{$where: 'x'}"))).to(be_err());
+        }
+
+        it "returns an error for invalid operands" {
+            expect!(Filter::parse(&doc("{$or: []}"))).to(be_err());
+            expect!(Filter::parse(&doc("{a: {$mod: [0, 1]}}"))).to(be_err());
+            expect!(Filter::parse(&doc("{a: {$regex: '('}}"))).to(be_err());
+            expect!(Filter::parse(&doc("{a: {$size: -1}}"))).to(be_err());
+        }
+    }
+}
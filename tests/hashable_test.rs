@@ -0,0 +1,103 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+use bson::{parse_decimal128, Bson, HashableBson, HashableDocument};
+use expectest::prelude::*;
+use std::collections::HashSet;
+use std::f64;
+
+fn decimal(value: &str) -> Bson {
+    Bson::Decimal128(parse_decimal128(value).unwrap())
+}
+
+describe! hashable_test {
+    describe! hashable_bson {
+        describe! when_comparing_strictly {
+            before_each {
+                let mut set = HashSet::new();
+                set.insert(HashableBson::new(Bson::Double(f64::NAN)));
+                set.insert(HashableBson::new(Bson::Double(-0.0)));
+                set.insert(HashableBson::new(Bson::Int32(1)));
+            }
+
+            it "treats all nans as equal" {
+                expect!(set.contains(&HashableBson::new(Bson::Double(f64::NAN)))).to(be_true());
+            }
+
+            it "treats negative zero as zero" {
+                expect!(set.contains(&HashableBson::new(Bson::Double(0.0)))).to(be_true());
+            }
+
+            it "distinguishes numeric types" {
+                expect!(set.contains(&HashableBson::new(Bson::Int64(1)))).to(be_false());
+            }
+
+            it "treats decimals with trailing zeros as equal" {
+                set.insert(HashableBson::new(decimal("1.50")));
+                expect!(set.contains(&HashableBson::new(decimal("1.5")))).to(be_true());
+                expect!(set.contains(&HashableBson::new(decimal("15E-1")))).to(be_true());
+                expect!(set.contains(&HashableBson::new(Bson::Double(1.5)))).to(be_false());
+            }
+        }
+
+        describe! when_comparing_numerically {
+            before_each {
+                let mut set = HashSet::new();
+                set.insert(HashableBson::numeric(Bson::Int32(1)));
+            }
+
+            it "matches equal int64 values" {
+                expect!(set.contains(&HashableBson::numeric(Bson::Int64(1)))).to(be_true());
+            }
+
+            it "matches equal double values" {
+                expect!(set.contains(&HashableBson::numeric(Bson::Double(1.0)))).to(be_true());
+            }
+
+            it "matches equal decimal values" {
+                set.insert(HashableBson::numeric(Bson::Double(0.5)));
+                set.insert(HashableBson::numeric(Bson::Int64(9223372036854775807)));
+                expect!(set.contains(&HashableBson::numeric(decimal("1.000")))).to(be_true());
+                expect!(set.contains(&HashableBson::numeric(decimal("0.50")))).to(be_true());
+                expect!(set.contains(&HashableBson::numeric(decimal("9223372036854775807")))).to(be_true());
+                expect!(set.contains(&HashableBson::numeric(decimal("0.1")))).to(be_false());
+            }
+
+            it "does not match different values" {
+                expect!(set.contains(&HashableBson::numeric(Bson::Double(1.5)))).to(be_false());
+            }
+
+            it "does not match strict wrappers" {
+                expect!(set.contains(&HashableBson::new(Bson::Int32(1)))).to(be_false());
+            }
+        }
+    }
+
+    describe! hashable_document {
+        it "dedupes equal documents" {
+            let mut set = HashSet::new();
+            set.insert(HashableDocument::new(document! { "a" => 1, "b" => [ 2.5 ] }));
+            set.insert(HashableDocument::new(document! { "a" => 1, "b" => [ 2.5 ] }));
+            expect!(set.len()).to(be_equal_to(1));
+        }
+
+        it "distinguishes key order" {
+            let mut set = HashSet::new();
+            set.insert(HashableDocument::new(document! { "a" => 1, "b" => 2 }));
+            set.insert(HashableDocument::new(document! { "b" => 2, "a" => 1 }));
+            expect!(set.len()).to(be_equal_to(2));
+        }
+
+        it "compares nested numbers numerically when requested" {
+            let left = HashableDocument::numeric(document! { "a" => { "b" => 1 } });
+            let right = HashableDocument::numeric(document! { "a" => { "b" => 1.0 } });
+            expect!(left == right).to(be_true());
+        }
+    }
+}
@@ -0,0 +1,131 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use std::io::Write;
+use std::net::TcpStream;
+use bson::{Bson, Document, Message, MockServer, OpMsg, OpQuery, DEFAULT_MAX_MESSAGE_SIZE};
+use common::doc;
+use expectest::prelude::*;
+
+fn send(stream: &mut TcpStream, message: Message) -> Document {
+    stream.write_all(&message.encode(1, 0).unwrap()).unwrap();
+    let (header, reply) = Message::read_from(stream, DEFAULT_MAX_MESSAGE_SIZE).unwrap();
+    expect!(header.response_to).to(be_equal_to(1));
+    match reply {
+        Message::Msg(msg) => msg.body,
+        Message::Reply(mut reply) => reply.documents.remove(0),
+        _ => panic!("unexpected reply")
+    }
+}
+
+fn run(stream: &mut TcpStream, command: &str) -> Document {
+    send(stream, Message::Msg(OpMsg::new(doc(command))))
+}
+
+fn connect(server: &MockServer) -> TcpStream {
+    TcpStream::connect(server.address()).unwrap()
+}
+
+fn batch(reply: &Document, name: &str) -> (Vec<Bson>, Bson) {
+    match reply.get("cursor") {
+        Some(&Bson::Document(ref cursor)) => match (cursor.get(name), cursor.get("id")) {
+            (Some(&Bson::Array(ref documents)), Some(id)) => (documents.clone(), id.clone()),
+            _ => panic!("expected a batch")
+        },
+        _ => panic!("expected a cursor")
+    }
+}
+
+describe! mock_server_test {
+    describe! handshake {
+        it "answers hello, isMaster over OP_QUERY, ping and buildInfo" {
+            let server = MockServer::start().unwrap();
+            let mut stream = connect(&server);
+            let hello = run(&mut stream, "{hello: 1, $db: 'admin'}");
+            expect!(hello.get("isWritablePrimary")).to(be_some().value(&Bson::Boolean(true)));
+            expect!(hello.get("maxWireVersion")).to(be_some().value(&Bson::Int32(17)));
+            let legacy = send(&mut stream, Message::Query(OpQuery::command("admin", doc("{isMaster: 1}"))));
+            expect!(legacy.get("ismaster")).to(be_some().value(&Bson::Boolean(true)));
+            expect!(run(&mut stream, "{ping: 1, $db: 'admin'}")).to(be_equal_to(doc("{ok: 1.0}")));
+            expect!(run(&mut stream, "{buildInfo: 1, $db: 'admin'}").get("version")).to(be_some().value(&Bson::String("6.0.0".to_string())));
+        }
+
+        it "replies with command errors" {
+            let server = MockServer::start().unwrap();
+            let mut stream = connect(&server);
+            expect!(run(&mut stream, "{foo: 1, $db: 'test'}"))
+                .to(be_equal_to(doc("{ok: 0.0, errmsg: \"no such command: 'foo'\", code: 59, codeName: 'CommandNotFound'}")));
+            expect!(run(&mut stream, "{find: 'c', filter: 1, $db: 'test'}").get("codeName"))
+                .to(be_some().value(&Bson::String("TypeMismatch".to_string())));
+        }
+    }
+
+    describe! commands {
+        it "finds with cursors across getMore and killCursors" {
+            let server = MockServer::start().unwrap();
+            server.collection("test", "c", |collection| {
+                for i in 0..5 {
+                    collection.insert_one(doc(&format!("{{_id: {}}}", i))).unwrap();
+                }
+            });
+            let mut stream = connect(&server);
+            let (first, id) = batch(&run(&mut stream, "{find: 'c', sort: {_id: -1}, batchSize: 2, $db: 'test'}"), "firstBatch");
+            expect!(first).to(be_equal_to(vec![Bson::Document(doc("{_id: 4}")), Bson::Document(doc("{_id: 3}"))]));
+            let get_more = format!("{{getMore: {}, collection: 'c', batchSize: 2, $db: 'test'}}", id);
+            let (next, _) = batch(&run(&mut stream, &get_more), "nextBatch");
+            expect!(next.len()).to(be_equal_to(2));
+            let kill = format!("{{killCursors: 'c', cursors: [{}], $db: 'test'}}", id);
+            expect!(run(&mut stream, &kill).get("cursorsKilled")).to(be_some().value(&Bson::Array(vec![id.clone()])));
+            expect!(run(&mut stream, &get_more).get("code")).to(be_some().value(&Bson::Int32(43)));
+        }
+
+        it "inserts, updates and deletes with write errors" {
+            let server = MockServer::start().unwrap();
+            let mut stream = connect(&server);
+            let insert = OpMsg::new(doc("{insert: 'c', $db: 'test'}")).sequence("documents", vec![doc("{_id: 1}"), doc("{_id: 1}"), doc("{_id: 2}")]);
+            let reply = send(&mut stream, Message::Msg(insert));
+            expect!(reply.get("n")).to(be_some().value(&Bson::Int32(1)));
+            match reply.get("writeErrors") {
+                Some(&Bson::Array(ref errors)) => expect!(errors.len()).to(be_equal_to(1)),
+                _ => panic!("expected write errors")
+            };
+            let reply = run(&mut stream, "{update: 'c', updates: [{q: {_id: 1}, u: {$set: {a: 1}}}, {q: {_id: 9}, u: {$set: {a: 2}}, upsert: true}], $db: 'test'}");
+            expect!(reply).to(be_equal_to(doc("{n: 2, nModified: 1, upserted: [{index: 1, _id: 9}], ok: 1.0}")));
+            expect!(run(&mut stream, "{delete: 'c', deletes: [{q: {}, limit: 0}], $db: 'test'}")).to(be_equal_to(doc("{n: 2, ok: 1.0}")));
+            expect!(server.collection("test", "c", |collection| collection.len())).to(be_equal_to(0));
+        }
+
+        it "aggregates into a cursor" {
+            let server = MockServer::start().unwrap();
+            server.collection("test", "c", |collection| {
+                collection.insert_many(vec![doc("{k: 'a', v: 1}"), doc("{k: 'a', v: 2}"), doc("{k: 'b', v: 5}")]).unwrap();
+            });
+            let mut stream = connect(&server);
+            let reply = run(&mut stream, "{aggregate: 'c', pipeline: [{$group: {_id: '$k', total: {$sum: '$v'}}}, {$sort: {_id: 1}}], cursor: {}, $db: 'test'}");
+            let (first, id) = batch(&reply, "firstBatch");
+            expect!(first).to(be_equal_to(vec![Bson::Document(doc("{_id: 'a', total: 3}")), Bson::Document(doc("{_id: 'b', total: 5}"))]));
+            expect!(id).to(be_equal_to(Bson::Int64(0)));
+            expect!(run(&mut stream, "{aggregate: 'c', pipeline: [], $db: 'test'}").get("code")).to(be_some().value(&Bson::Int32(9)));
+        }
+    }
+
+    describe! scripting {
+        it "returns canned replies in turn before the built-in handling" {
+            let server = MockServer::start().unwrap();
+            server.respond("ping", doc("{ok: 1.0, canned: true}"));
+            server.fail("ping", 91, "ShutdownInProgress", "shutting down");
+            let mut stream = connect(&server);
+            expect!(run(&mut stream, "{ping: 1, $db: 'admin'}")).to(be_equal_to(doc("{ok: 1.0, canned: true}")));
+            expect!(run(&mut stream, "{ping: 1, $db: 'admin'}").get("code")).to(be_some().value(&Bson::Int32(91)));
+            expect!(run(&mut stream, "{ping: 1, $db: 'admin'}")).to(be_equal_to(doc("{ok: 1.0}")));
+            expect!(server.received().len()).to(be_equal_to(3));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+use bson::{Bson, Document, Pipeline, ShellParser};
+use expectest::prelude::*;
+
+fn docs(shell: &str) -> Vec<Document> {
+    match ShellParser::new(&format!("{{d: {}}}", shell)).parse_document().unwrap().get("d") {
+        Some(&Bson::Array(ref values)) => values.iter().map(|value| match *value {
+            Bson::Document(ref document) => document.clone(),
+            _ => panic!("expected documents")
+        }).collect(),
+        _ => panic!("expected an array")
+    }
+}
+
+fn run(stages: &str, input: &str) -> Vec<Document> {
+    let pipeline = Pipeline::parse(&docs(stages)).unwrap();
+    let output: Result<Vec<Document>, _> = pipeline.run(docs(input)).collect();
+    output.unwrap()
+}
+
+fn error(stages: &str) -> String {
+    Pipeline::parse(&docs(stages)).unwrap_err().message().to_string()
+}
+
+describe! pipeline_test {
+    describe! stages {
+        it "matches, sorts, skips and limits" {
+            let input = "[{a: 3}, {a: 1}, {a: 4}, {a: 2}, {b: 1}]";
+            expect!(run("[{$match: {a: {$exists: true}}}, {$sort: {a: -1}}, {$skip: 1}, {$limit: 2}]", input))
+                .to(be_equal_to(docs("[{a: 3}, {a: 2}]")));
+        }
+
+        it "projects and adds computed fields" {
+            expect!(run("[{$project: {_id: 0, a: 1, total: {$add: ['$a', '$b']}}}]", "[{_id: 1, a: 1, b: 2, c: 3}]"))
+                .to(be_equal_to(docs("[{a: 1, total: 3}]")));
+            expect!(run("[{$addFields: {'c.d': '$a', a: 5}}]", "[{a: 1, b: 2}]")).to(be_equal_to(docs("[{a: 5, b: 2, c: {d: 1}}]")));
+            expect!(run("[{$unset: ['b', 'c']}]", "[{a: 1, b: 2, c: 3}]")).to(be_equal_to(docs("[{a: 1}]")));
+        }
+
+        it "unwinds arrays" {
+            expect!(run("[{$unwind: '$a'}]", "[{a: [1, 2]}, {a: []}, {b: 1}]")).to(be_equal_to(docs("[{a: 1}, {a: 2}]")));
+            expect!(run("[{$unwind: {path: '$a', includeArrayIndex: 'i', preserveNullAndEmptyArrays: true}}]", "[{a: [5]}, {b: 1}]"))
+                .to(be_equal_to(docs("[{a: 5, i: NumberLong(0)}, {b: 1, i: null}]")));
+        }
+
+        it "counts and replaces the root" {
+            expect!(run("[{$count: 'n'}]", "[{}, {}, {}]")).to(be_equal_to(docs("[{n: 3}]")));
+            expect!(run("[{$count: 'n'}]", "[]")).to(be_equal_to(Vec::new()));
+            expect!(run("[{$replaceRoot: {newRoot: '$a'}}]", "[{a: {b: 1}}]")).to(be_equal_to(docs("[{b: 1}]")));
+        }
+    }
+
+    describe! group {
+        it "groups with accumulators in order of first appearance" {
+            let input = "[{k: 'x', v: 1}, {k: 'y', v: 4}, {k: 'x', v: 3}, {k: 'x', v: 3}]";
+            let stages = "[{$group: {_id: '$k', sum: {$sum: '$v'}, avg: {$avg: '$v'}, min: {$min: '$v'}, max: {$max: '$v'}, \
+                all: {$push: '$v'}, set: {$addToSet: '$v'}, first: {$first: '$v'}, last: {$last: '$v'}, n: {$sum: 1}}}]";
+            expect!(run(stages, input)).to(be_equal_to(docs("[\
+                {_id: 'x', sum: 7, avg: 2.3333333333333335, min: 1, max: 3, all: [1, 3, 3], set: [1, 3], first: 1, last: 3, n: 3}, \
+                {_id: 'y', sum: 4, avg: 4.0, min: 4, max: 4, all: [4], set: [4], first: 4, last: 4, n: 1}]")));
+        }
+
+        it "groups everything under a null _id" {
+            expect!(run("[{$group: {_id: null, total: {$sum: '$v'}}}]", "[{v: 1}, {v: 2.5}, {v: 'x'}]"))
+                .to(be_equal_to(docs("[{_id: null, total: 3.5}]")));
+        }
+
+        it "sums past the NumberLong range as a double" {
+            expect!(run("[{$group: {_id: null, total: {$sum: '$v'}}}]", "[{v: NumberLong('9223372036854775807')}, {v: NumberLong(1)}]"))
+                .to(be_equal_to(docs("[{_id: null, total: 9223372036854775808.0}]")));
+        }
+
+        it "sums and averages decimals as decimals" {
+            let stages = "[{$group: {_id: null, total: {$sum: '$v'}, avg: {$avg: '$v'}}}]";
+            expect!(run(stages, "[{v: 1}, {v: NumberDecimal('0.1')}, {v: NumberLong(1)}]"))
+                .to(be_equal_to(docs("[{_id: null, total: NumberDecimal('2.1'), avg: NumberDecimal('0.7')}]")));
+        }
+    }
+
+    describe! errors {
+        it "rejects invalid stages" {
+            expect!(error("[{$foo: {}}]")).to(be_equal_to("Unrecognized pipeline stage name: '$foo'"));
+            expect!(error("[{$group: {total: {$sum: 1}}}]")).to(be_equal_to("a group specification must specify an _id"));
+            expect!(error("[{$project: {a: 0, b: '$c'}}]"))
+                .to(be_equal_to("Invalid $project :: caused by :: Cannot use expression other than $meta in exclusion projection"));
+        }
+
+        it "yields evaluation errors" {
+            let pipeline = Pipeline::parse(&docs("[{$project: {x: {$divide: [1, '$a']}}}]")).unwrap();
+            let output: Vec<_> = pipeline.run(docs("[{a: 1}, {a: 0}]")).collect();
+            expect!(output[0].is_ok()).to(be_true());
+            expect!(output[1].as_ref().unwrap_err().message()).to(be_equal_to("can't $divide by zero"));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use bson::{project, Document, Filter, Projection};
+use common::doc;
+use expectest::prelude::*;
+
+fn projected(document: &str, spec: &str) -> Document {
+    project(&doc(document), &doc(spec)).unwrap()
+}
+
+fn error(spec: &str) -> String {
+    Projection::parse(&doc(spec)).unwrap_err().message().to_string()
+}
+
+describe! projection_test {
+    describe! inclusion {
+        it "includes fields in source order with the _id" {
+            expect!(projected("{_id: 1, a: 1, b: 2, c: 3}", "{c: 1, a: 1}")).to(be_equal_to(doc("{_id: 1, a: 1, c: 3}")));
+            expect!(projected("{_id: 1, a: 1, b: 2}", "{a: 1, _id: 0}")).to(be_equal_to(doc("{a: 1}")));
+            expect!(projected("{_id: 1, a: 1}", "{_id: 1}")).to(be_equal_to(doc("{_id: 1}")));
+        }
+
+        it "includes dotted paths within documents and arrays" {
+            expect!(projected("{a: {b: 1, c: 2}, d: 3}", "{'a.b': 1, _id: 0}")).to(be_equal_to(doc("{a: {b: 1}}")));
+            expect!(projected("{a: [{b: 1, c: 2}, 5, {c: 3}]}", "{'a.b': 1, _id: 0}")).to(be_equal_to(doc("{a: [{b: 1}, {}]}")));
+        }
+    }
+
+    describe! exclusion {
+        it "excludes fields and dotted paths" {
+            expect!(projected("{_id: 1, a: 1, b: 2}", "{a: 0}")).to(be_equal_to(doc("{_id: 1, b: 2}")));
+            expect!(projected("{_id: 1, a: 1}", "{_id: 0}")).to(be_equal_to(doc("{a: 1}")));
+            expect!(projected("{_id: 1, a: 1}", "{_id: NumberDecimal('0.0')}")).to(be_equal_to(doc("{a: 1}")));
+            expect!(projected("{a: [{b: 1, c: 2}, 5]}", "{'a.b': 0}")).to(be_equal_to(doc("{a: [{c: 2}, 5]}")));
+        }
+
+        it "returns every field for an empty projection" {
+            expect!(projected("{_id: 1, a: 1}", "{}")).to(be_equal_to(doc("{_id: 1, a: 1}")));
+        }
+    }
+
+    describe! operators {
+        it "slices arrays" {
+            expect!(projected("{a: [1, 2, 3, 4], b: 1}", "{a: {$slice: 2}}")).to(be_equal_to(doc("{a: [1, 2], b: 1}")));
+            expect!(projected("{a: [1, 2, 3, 4]}", "{a: {$slice: -3}}")).to(be_equal_to(doc("{a: [2, 3, 4]}")));
+            expect!(projected("{a: [1, 2, 3, 4]}", "{a: {$slice: [1, 2]}}")).to(be_equal_to(doc("{a: [2, 3]}")));
+            expect!(projected("{a: [1, 2]}", "{a: {$slice: NumberLong('-9223372036854775808')}}")).to(be_equal_to(doc("{a: [1, 2]}")));
+            expect!(projected("{_id: 1, a: [1, 2], b: 1}", "{a: {$slice: 1}, b: 1}")).to(be_equal_to(doc("{_id: 1, a: [1], b: 1}")));
+        }
+
+        it "keeps the first element matching $elemMatch" {
+            expect!(projected("{_id: 1, a: [{x: 1}, {x: 2}, {x: 2, y: 1}], b: 1}", "{a: {$elemMatch: {x: 2}}}"))
+                .to(be_equal_to(doc("{_id: 1, a: [{x: 2}]}")));
+            expect!(projected("{_id: 1, a: [{x: 1}]}", "{a: {$elemMatch: {x: 2}}}")).to(be_equal_to(doc("{_id: 1}")));
+        }
+
+        it "keeps the element matched by the query for a positional projection" {
+            let projection = Projection::parse(&doc("{'a.$': 1}")).unwrap().query(Filter::parse(&doc("{a: {$gt: 2}}")).unwrap());
+            expect!(projection.apply(&doc("{_id: 1, a: [1, 3, 5], b: 2}")).unwrap()).to(be_equal_to(doc("{_id: 1, a: [3]}")));
+            expect!(projection.apply(&doc("{_id: 1, a: [1]}")).is_err()).to(be_true());
+        }
+    }
+
+    describe! errors {
+        it "rejects mixed and colliding projections" {
+            expect!(error("{a: 1, b: 0}")).to(be_equal_to("Cannot do exclusion on field b in inclusion projection"));
+            expect!(error("{a: 0, b: 1}")).to(be_equal_to("Cannot do inclusion on field b in exclusion projection"));
+            expect!(error("{a: 1, 'a.b': 1}")).to(be_equal_to("Path collision at a.b"));
+            expect!(error("{'a.$': 1, 'b.$': 1}")).to(be_equal_to("Cannot specify more than one positional projection per query."));
+        }
+    }
+}
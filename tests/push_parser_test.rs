@@ -0,0 +1,117 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+mod common;
+
+use bson::{parse_decimal128, Bson, DocumentSerializer, ParseEvent, PushParser};
+use common::nested;
+use expectest::prelude::*;
+use std::io::ErrorKind;
+
+describe! push_parser_test {
+    before_each {
+        let document = document! {
+            "a" => 1,
+            "b" => (document! { "c" => "d" }),
+            "e" => (vec![Bson::Int32(2), Bson::Boolean(true)])
+        };
+        let mut bytes = vec![];
+        DocumentSerializer::new(&mut bytes).serialize(&document).unwrap();
+        let mut parser = PushParser::new();
+    }
+
+    describe! next_event {
+        it "emits events for nested documents and arrays" {
+            parser.feed(&bytes);
+            let events: Vec<_> = ::std::iter::from_fn(|| parser.next_event()).map(Result::unwrap).collect();
+            expect!(events).to(be_equal_to(vec![
+                ParseEvent::StartDocument,
+                ParseEvent::Key("a".to_string()),
+                ParseEvent::Value(Bson::Int32(1)),
+                ParseEvent::Key("b".to_string()),
+                ParseEvent::StartDocument,
+                ParseEvent::Key("c".to_string()),
+                ParseEvent::Value(Bson::from("d")),
+                ParseEvent::EndDocument,
+                ParseEvent::Key("e".to_string()),
+                ParseEvent::StartArray,
+                ParseEvent::Value(Bson::Int32(2)),
+                ParseEvent::Value(Bson::Boolean(true)),
+                ParseEvent::EndArray,
+                ParseEvent::EndDocument
+            ]));
+        }
+
+        it "waits for the rest of a partial value" {
+            parser.feed(&bytes[..9]);
+            expect!(parser.next_event().unwrap()).to(be_ok().value(ParseEvent::StartDocument));
+            expect!(parser.next_event().unwrap()).to(be_ok().value(ParseEvent::Key("a".to_string())));
+            expect!(parser.next_event().is_none()).to(be_true());
+            expect!(parser.buffered()).to(be_equal_to(2));
+            parser.feed(&bytes[9..]);
+            expect!(parser.next_event().unwrap()).to(be_ok().value(ParseEvent::Value(Bson::Int32(1))));
+            expect!(parser.position()).to(be_equal_to(11));
+        }
+
+        it "returns an error for an element past the end of its document" {
+            bytes[0] = 10;
+            parser.feed(&bytes);
+            parser.next_event();
+            parser.next_event();
+            let error = parser.next_event().unwrap().unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidData));
+            expect!(parser.next_event().is_none()).to(be_true());
+        }
+    }
+
+    describe! next_document {
+        it "emits each document once fed one byte at a time" {
+            let mut stream = bytes.clone();
+            stream.extend_from_slice(&bytes);
+            let mut documents = vec![];
+            for byte in &stream {
+                parser.feed(&[*byte]);
+                while let Some(result) = parser.next_document() {
+                    documents.push(result.unwrap());
+                }
+            }
+            expect!(documents).to(be_equal_to(vec![document.clone(), document.clone()]));
+            expect!(parser.depth()).to(be_equal_to(0));
+        }
+
+        it "emits decimals once all of their bytes arrive" {
+            let document = document! { "d" => (Bson::Decimal128(parse_decimal128("1.25").unwrap())) };
+            let mut bytes = vec![];
+            DocumentSerializer::new(&mut bytes).serialize(&document).unwrap();
+            parser.feed(&bytes[..20]);
+            expect!(parser.next_document().is_none()).to(be_true());
+            parser.feed(&bytes[20..]);
+            expect!(parser.next_document().unwrap()).to(be_ok().value(document));
+        }
+
+        it "accepts nesting up to the maximum depth" {
+            parser.feed(&nested(200));
+            expect!(parser.next_document().unwrap()).to(be_ok());
+        }
+
+        it "returns an error for nesting past the maximum depth" {
+            parser.feed(&nested(201));
+            let error = parser.next_document().unwrap().unwrap_err();
+            expect!(error.to_string()).to(be_equal_to("document nesting exceeds the maximum depth of 200"));
+        }
+
+        it "returns an error for a document over the maximum size" {
+            let mut parser = PushParser::new().max_document_size(10);
+            parser.feed(&bytes[..4]);
+            let error = parser.next_document().unwrap().unwrap_err();
+            expect!(error.kind()).to(be_equal_to(ErrorKind::InvalidData));
+            expect!(parser.next_document().is_none()).to(be_true());
+        }
+    }
+}
@@ -0,0 +1,175 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+use bson::{parse_decimal128, Bson, ShellParser};
+use expectest::prelude::*;
+
+describe! shell_parser_test {
+    describe! parse_document {
+        describe! with_shell_syntax {
+            before_each {
+                let input = r#"{
+                    _id: ObjectId("5899ab8d6b2c4a1d3c000001"),
+                    ts: ISODate("2017-02-08T14:30:00Z"),
+                    n: NumberLong(5),
+                    'single': 'quoted',
+                    re: /^a\/b/i,
+                    bin: BinData(0, "AQEB"),
+                    uuid: UUID("00112233-4455-6677-8899-aabbccddeeff"),
+                    time: Timestamp(1, 2),
+                    small: NumberInt("7"),
+                    dec: NumberDecimal("1.50"),
+                    tenth: NumberDecimal(0.1),
+                    nested: { list: [ 1, 2.5, -3, ], },
+                    min: MinKey,
+                    max: MaxKey(),
+                }"#;
+                let document = ShellParser::new(input).parse_document().unwrap();
+            }
+
+            it "parses object ids" {
+                expect!(document.get("_id")).to(be_equal_to(Some(&bson_objectid!(
+                    [0x58, 0x99, 0xab, 0x8d, 0x6b, 0x2c, 0x4a, 0x1d, 0x3c, 0x00, 0x00, 0x01]
+                ))));
+            }
+
+            it "parses iso dates" {
+                expect!(document.get("ts")).to(be_equal_to(Some(&bson_datetime!(1486564200000))));
+            }
+
+            it "parses number longs" {
+                expect!(document.get("n")).to(be_equal_to(Some(&Bson::Int64(5))));
+            }
+
+            it "parses single quoted keys and strings" {
+                expect!(document.get("single")).to(be_equal_to(Some(&Bson::String("quoted".to_string()))));
+            }
+
+            it "parses regular expression literals" {
+                expect!(document.get("re")).to(be_equal_to(Some(&bson_regexp!("^a\\/b", "i"))));
+            }
+
+            it "parses binary data" {
+                expect!(document.get("bin")).to(be_equal_to(Some(&bson_binary!(0, vec![1, 1, 1]))));
+            }
+
+            it "parses uuids" {
+                expect!(document.get("uuid")).to(be_equal_to(Some(&bson_binary!(4, vec![
+                    0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+                    0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff
+                ]))));
+            }
+
+            it "parses timestamps" {
+                expect!(document.get("time")).to(be_equal_to(Some(&Bson::Timestamp((1 << 32) + 2))));
+            }
+
+            it "parses number ints from strings" {
+                expect!(document.get("small")).to(be_equal_to(Some(&Bson::Int32(7))));
+            }
+
+            it "parses number decimals from strings and numbers" {
+                expect!(document.get("dec")).to(be_equal_to(Some(&Bson::Decimal128(parse_decimal128("1.50").unwrap()))));
+                expect!(document.get("tenth")).to(be_equal_to(Some(&Bson::Decimal128(parse_decimal128("0.1").unwrap()))));
+            }
+
+            it "parses nested values with trailing commas" {
+                expect!(document.get("nested")).to(be_equal_to(Some(&bson!({
+                    "list" => [ 1, 2.5, (-3) ]
+                }))));
+            }
+
+            it "parses min and max keys" {
+                expect!(document.get("min")).to(be_equal_to(Some(&bson_minkey!())));
+                expect!(document.get("max")).to(be_equal_to(Some(&bson_maxkey!())));
+            }
+        }
+
+        describe! with_a_displayed_document {
+            before_each {
+                let document = document! {
+                    "double" => 24.5,
+                    "whole" => 3.0,
+                    "string" => "va\"lue\n",
+                    "int64" => 42i64,
+                    "decimal" => (Bson::Decimal128(parse_decimal128("-2.50E+10").unwrap())),
+                    "array" => [ true, (bson_null!()) ],
+                    "regexp" => (bson_regexp!("a/b", "")),
+                    "code" => (bson_code!("x", document! { "x" => 1 })),
+                    "datetime" => (bson_datetime!(-86400001))
+                };
+                let parsed = ShellParser::new(&document.to_string()).parse_document();
+            }
+
+            it "round trips the document" {
+                expect!(parsed).to(be_ok().value(document));
+            }
+        }
+
+        describe! with_invalid_input {
+            it "errors on unknown constructors" {
+                let result = ShellParser::new("{ a: Foo(1) }").parse_document();
+                expect!(result.unwrap_err().message()).to(be_equal_to("Unknown constructor 'Foo'"));
+            }
+
+            it "errors on malformed object ids" {
+                expect!(ShellParser::new("{ a: ObjectId('xyz') }").parse_document()).to(be_err());
+            }
+
+            it "errors on unterminated strings" {
+                let result = ShellParser::new("{ a: 'abc }").parse_document();
+                expect!(result.unwrap_err().position()).to(be_equal_to(5));
+            }
+
+            it "errors on trailing input" {
+                expect!(ShellParser::new("{} {}").parse_document()).to(be_err());
+            }
+
+            it "errors on nesting past the maximum depth" {
+                let result = ShellParser::new(&"[".repeat(100_000)).parse_value();
+                expect!(result.unwrap_err().message()).to(be_equal_to("Nesting exceeds the maximum depth of 200"));
+                let nested = format!("{}{{}}{}", "{a: ".repeat(199), "}".repeat(199));
+                expect!(ShellParser::new(&nested).parse_document()).to(be_ok());
+                let deeper = format!("{}{{}}{}", "{a: ".repeat(200), "}".repeat(200));
+                expect!(ShellParser::new(&deeper).parse_document()).to(be_err());
+            }
+
+            it "errors on malformed decimals" {
+                expect!(ShellParser::new("{ a: NumberDecimal('1.5x') }").parse_document()).to(be_err());
+                expect!(ShellParser::new("{ a: NumberDecimal('1E+7000') }").parse_document()).to(be_err());
+            }
+        }
+    }
+
+    describe! parse_value {
+        it "parses integers that overflow int32 as int64" {
+            expect!(ShellParser::new("4294967296").parse_value()).to(be_ok().value(Bson::Int64(4294967296)));
+        }
+
+        it "parses exponents as doubles" {
+            expect!(ShellParser::new("1e3").parse_value()).to(be_ok().value(Bson::Double(1000.0)));
+        }
+
+        it "parses new dates from milliseconds" {
+            expect!(ShellParser::new("new Date(1000)").parse_value()).to(be_ok().value(bson_datetime!(1000)));
+        }
+
+        it "parses dates with offsets" {
+            expect!(ShellParser::new("ISODate('2017-02-08T15:30:00+01:00')").parse_value()).to(
+                be_ok().value(bson_datetime!(1486564200000))
+            );
+        }
+
+        it "skips comments" {
+            expect!(ShellParser::new("/* a */ [ 1 // one\n ]").parse_value()).to(
+                be_ok().value(bson!([ 1 ]))
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use bson::{Bson, Document, SortSpec};
+use common::doc;
+use expectest::prelude::*;
+
+fn sorted(spec: &str, documents: &[&str]) -> Vec<i32> {
+    let spec = SortSpec::parse(&doc(spec)).unwrap();
+    let mut documents: Vec<Document> = documents.iter().map(|shell| doc(shell)).collect();
+    documents.sort_by(|left, right| spec.compare(left, right));
+    documents.iter().map(|document| match document.get("_id") {
+        Some(&Bson::Int32(id)) => id,
+        _ => panic!("expected an _id")
+    }).collect()
+}
+
+describe! sort_spec_test {
+    it "sorts by compound keys" {
+        let documents = ["{_id: 1, a: 2, b: {c: 1}}", "{_id: 2, a: 1, b: {c: 1}}", "{_id: 3, a: 2, b: {c: 5}}"];
+        expect!(sorted("{a: 1, 'b.c': -1}", &documents)).to(be_equal_to(vec![2, 3, 1]));
+    }
+
+    it "sorts missing fields as null and empty arrays first" {
+        let documents = ["{_id: 1, a: 1}", "{_id: 2, a: null}", "{_id: 3}", "{_id: 4, a: []}"];
+        expect!(sorted("{a: 1}", &documents)).to(be_equal_to(vec![4, 2, 3, 1]));
+    }
+
+    it "uses the smallest array element ascending and the largest descending" {
+        let documents = ["{_id: 1, a: [1, 9]}", "{_id: 2, a: 5}", "{_id: 3, a: [3, 4]}"];
+        expect!(sorted("{a: 1}", &documents)).to(be_equal_to(vec![1, 3, 2]));
+        expect!(sorted("{a: -1}", &documents)).to(be_equal_to(vec![1, 2, 3]));
+    }
+
+    it "collects keys from documents in arrays" {
+        let documents = ["{_id: 1, a: [{b: 7}, {b: 2}]}", "{_id: 2, a: [{b: 3}]}", "{_id: 3, a: [{c: 1}]}"];
+        expect!(sorted("{'a.b': 1}", &documents)).to(be_equal_to(vec![3, 1, 2]));
+    }
+
+    it "orders values of different types by type bracket" {
+        let documents = ["{_id: 1, a: 'x'}", "{_id: 2, a: true}", "{_id: 3, a: 10}", "{_id: 4, a: {b: 1}}"];
+        expect!(sorted("{a: 1}", &documents)).to(be_equal_to(vec![3, 1, 4, 2]));
+    }
+
+    it "rejects invalid directions" {
+        let error = SortSpec::parse(&doc("{a: 2}")).unwrap_err();
+        expect!(error.message()).to(be_equal_to("$sort key ordering must be 1 (for ascending) or -1 (for descending)"));
+    }
+}
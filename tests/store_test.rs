@@ -0,0 +1,154 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+use bson::{Bson, Document, Store};
+use common::doc;
+use expectest::prelude::*;
+
+fn log_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("bson-store-{}-{}.log", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn scan(store: &Store) -> Vec<Document> {
+    store.scan().map(Result::unwrap).collect()
+}
+
+describe! store_test {
+    it "puts, gets and deletes documents" {
+        let mut store = Store::open(log_path("basic")).unwrap();
+        store.put(&doc("{_id: 1, a: 1}")).unwrap();
+        store.put(&doc("{_id: 2, a: 2}")).unwrap();
+        store.put(&doc("{_id: 1, a: 3}")).unwrap();
+        expect!(store.get(&Bson::Int64(1)).unwrap()).to(be_some().value(doc("{_id: 1, a: 3}")));
+        expect!(store.delete(&Bson::Int32(2)).unwrap()).to(be_true());
+        expect!(store.delete(&Bson::Int32(2)).unwrap()).to(be_false());
+        expect!(store.get(&Bson::Int32(2)).unwrap()).to(be_none());
+        expect!(scan(&store)).to(be_equal_to(vec![doc("{_id: 1, a: 3}")]));
+        expect!(store.dead_records()).to(be_equal_to(3));
+    }
+
+    it "rejects documents without an _id" {
+        let mut store = Store::open(log_path("no-id")).unwrap();
+        expect!(store.put(&doc("{a: 1}")).is_err()).to(be_true());
+        expect!(store.is_empty()).to(be_true());
+    }
+
+    it "rebuilds the index on open" {
+        let path = log_path("reopen");
+        {
+            let mut store = Store::open(&path).unwrap();
+            store.put(&doc("{_id: 'a', v: 1}")).unwrap();
+            store.put(&doc("{_id: 'b', v: 2}")).unwrap();
+            store.delete(&Bson::String("a".to_string())).unwrap();
+            store.sync().unwrap();
+        }
+        let store = Store::open(&path).unwrap();
+        expect!(store.len()).to(be_equal_to(1));
+        expect!(scan(&store)).to(be_equal_to(vec![doc("{_id: 'b', v: 2}")]));
+    }
+
+    it "truncates a torn record at the end of the log" {
+        let path = log_path("torn");
+        {
+            let mut store = Store::open(&path).unwrap();
+            store.put(&doc("{_id: 1}")).unwrap();
+        }
+        let length = fs::metadata(&path).unwrap().len();
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&[40, 0, 0, 0, 3]).unwrap();
+        let mut store = Store::open(&path).unwrap();
+        expect!(fs::metadata(&path).unwrap().len()).to(be_equal_to(length));
+        store.put(&doc("{_id: 2}")).unwrap();
+        let store = Store::open(&path).unwrap();
+        expect!(scan(&store)).to(be_equal_to(vec![doc("{_id: 1}"), doc("{_id: 2}")]));
+    }
+
+    it "truncates a garbage record length without allocating it" {
+        let path = log_path("garbage");
+        {
+            let mut store = Store::open(&path).unwrap();
+            store.put(&doc("{_id: 1}")).unwrap();
+        }
+        let length = fs::metadata(&path).unwrap().len();
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&[0xFF, 0xFF, 0xFF, 0x7F, 3]).unwrap();
+        let store = Store::open(&path).unwrap();
+        expect!(fs::metadata(&path).unwrap().len()).to(be_equal_to(length));
+        expect!(scan(&store)).to(be_equal_to(vec![doc("{_id: 1}")]));
+    }
+
+    it "stops at a record with a bad checksum" {
+        let path = log_path("checksum");
+        {
+            let mut store = Store::open(&path).unwrap();
+            store.put(&doc("{_id: 1}")).unwrap();
+            store.put(&doc("{_id: 2}")).unwrap();
+        }
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        fs::write(&path, &bytes).unwrap();
+        let store = Store::open(&path).unwrap();
+        expect!(scan(&store)).to(be_equal_to(vec![doc("{_id: 1}")]));
+    }
+
+    it "fails on a corrupt record before the end without changing the log" {
+        let path = log_path("corrupt");
+        {
+            let mut store = Store::open(&path).unwrap();
+            store.put(&doc("{_id: 1}")).unwrap();
+            store.put(&doc("{_id: 2}")).unwrap();
+        }
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[6] ^= 0xFF;
+        fs::write(&path, &bytes).unwrap();
+        expect!(Store::open(&path).is_err()).to(be_true());
+        expect!(fs::read(&path).unwrap()).to(be_equal_to(bytes));
+    }
+
+    it "gets documents from several threads at once" {
+        let mut store = Store::open(log_path("threads")).unwrap();
+        for i in 0..20 {
+            store.put(&doc(&format!("{{_id: {}, v: {}}}", i, i))).unwrap();
+        }
+        let store = &store;
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(move || {
+                    for i in 0..20 {
+                        expect!(store.get(&Bson::Int32(i)).unwrap()).to(be_some().value(doc(&format!("{{_id: {}, v: {}}}", i, i))));
+                    }
+                });
+            }
+        });
+    }
+
+    it "compacts the log to the live documents" {
+        let path = log_path("compact");
+        let mut store = Store::open(&path).unwrap();
+        for i in 0..10 {
+            store.put(&doc(&format!("{{_id: {}, v: {}}}", i % 3, i))).unwrap();
+        }
+        store.delete(&Bson::Int32(0)).unwrap();
+        let before = fs::metadata(&path).unwrap().len();
+        store.compact().unwrap();
+        expect!(fs::metadata(&path).unwrap().len() < before).to(be_true());
+        expect!(store.dead_records()).to(be_equal_to(0));
+        store.put(&doc("{_id: 5}")).unwrap();
+        let expected = vec![doc("{_id: 1, v: 7}"), doc("{_id: 2, v: 8}"), doc("{_id: 5}")];
+        expect!(scan(&store)).to(be_equal_to(expected.clone()));
+        expect!(scan(&Store::open(&path).unwrap())).to(be_equal_to(expected));
+    }
+}
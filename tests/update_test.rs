@@ -0,0 +1,163 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use bson::{apply_update, Bson, Document, Filter, Update};
+use common::doc;
+use expectest::prelude::*;
+
+fn updated(document: &str, update: &str) -> Document {
+    let mut document = doc(document);
+    apply_update(&mut document, &doc(update)).unwrap();
+    document
+}
+
+fn error(document: &str, update: &str) -> String {
+    let mut document = doc(document);
+    apply_update(&mut document, &doc(update)).unwrap_err().message().to_string()
+}
+
+describe! update_test {
+    describe! fields {
+        it "sets fields, creating documents and padding arrays" {
+            expect!(updated("{_id: 1, a: 1}", "{$set: {a: 2, 'b.c': 3}}")).to(be_equal_to(doc("{_id: 1, a: 2, b: {c: 3}}")));
+            expect!(updated("{a: [1]}", "{$set: {'a.3': 4}}")).to(be_equal_to(doc("{a: [1, null, null, 4]}")));
+            expect!(error("{a: 1}", "{$set: {'a.b': 2}}")).to(be_equal_to("Cannot create field 'b' in element {a: 1}"));
+        }
+
+        it "limits the padding of arrays" {
+            expect!(updated("{a: []}", "{$set: {'a.1500000': 1}}").get("a").map(|a| match a {
+                Bson::Array(elements) => elements.len(),
+                _ => 0
+            })).to(be_some().value(1_500_001));
+            expect!(error("{a: []}", "{$set: {'a.1500001': 1}}")).to(be_equal_to("can't backfill more than 1500000 elements"));
+            expect!(error("{a: []}", "{$set: {'a.18446744073709551615': 1}}")).to(be_equal_to("can't backfill more than 1500000 elements"));
+        }
+
+        it "unsets fields and nulls array elements" {
+            expect!(updated("{a: 1, b: {c: 2, d: 3}}", "{$unset: {a: '', 'b.c': ''}}")).to(be_equal_to(doc("{b: {d: 3}}")));
+            expect!(updated("{a: [1, 2]}", "{$unset: {'a.0': ''}}")).to(be_equal_to(doc("{a: [null, 2]}")));
+        }
+
+        it "renames fields" {
+            expect!(updated("{a: 1, b: 2}", "{$rename: {a: 'c.d'}}")).to(be_equal_to(doc("{b: 2, c: {d: 1}}")));
+            expect!(updated("{b: 2}", "{$rename: {a: 'c'}}")).to(be_equal_to(doc("{b: 2}")));
+            expect!(error("{a: 1}", "{$rename: {a: 'a'}}")).to(be_equal_to("The source and target field for $rename must differ: a: \"a\""));
+        }
+
+        it "applies $min, $max and $currentDate" {
+            expect!(updated("{a: 5, b: 5}", "{$min: {a: 3}, $max: {b: 3}}")).to(be_equal_to(doc("{a: 3, b: 5}")));
+            let document = updated("{}", "{$currentDate: {a: true, b: {$type: 'timestamp'}}}");
+            expect!(document.get("a").map(Bson::type_name)).to(be_equal_to(Some("date")));
+            expect!(document.get("b").map(Bson::type_name)).to(be_equal_to(Some("timestamp")));
+        }
+
+        it "replaces the document, keeping the _id" {
+            expect!(updated("{_id: 1, a: 1}", "{b: 2}")).to(be_equal_to(doc("{_id: 1, b: 2}")));
+            let replaced = updated("{_id: 1, a: 1}", "{b: 2, _id: 1}");
+            expect!(replaced.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>()).to(be_equal_to(vec!["_id".to_string(), "b".to_string()]));
+            expect!(error("{_id: 1}", "{_id: 2}")).to(be_equal_to("Performing an update on the path '_id' would modify the immutable field '_id'"));
+        }
+    }
+
+    describe! arithmetic {
+        it "increments and multiplies across numeric types" {
+            expect!(updated("{a: 1, b: 2}", "{$inc: {a: 2, c: 1}, $mul: {b: 1.5, d: NumberLong(3)}}"))
+                .to(be_equal_to(doc("{a: 3, b: 3.0, c: 1, d: NumberLong(0)}")));
+            expect!(updated("{a: 2147483647}", "{$inc: {a: 1}}")).to(be_equal_to(doc("{a: NumberLong(2147483648)}")));
+        }
+
+        it "increments and multiplies decimals" {
+            expect!(updated("{a: NumberDecimal('1.5'), b: NumberDecimal('0.1')}", "{$inc: {a: 0.1}, $mul: {b: 3, c: NumberDecimal('2')}}"))
+                .to(be_equal_to(doc("{a: NumberDecimal('1.600000000000000'), b: NumberDecimal('0.3'), c: NumberDecimal('0')}")));
+            expect!(updated("{a: 1}", "{$inc: {a: NumberDecimal('9999999999999999999999999999999999')}}"))
+                .to(be_equal_to(doc("{a: NumberDecimal('1.000000000000000000000000000000000E+34')}")));
+        }
+
+        it "rounds decimal results to even" {
+            expect!(updated("{a: NumberDecimal('1')}", "{$inc: {a: NumberDecimal('5E-34')}}"))
+                .to(be_equal_to(doc("{a: NumberDecimal('1.000000000000000000000000000000000')}")));
+            expect!(updated("{a: NumberDecimal('1E+100')}", "{$inc: {a: NumberDecimal('-1E-100')}}"))
+                .to(be_equal_to(doc("{a: NumberDecimal('1.000000000000000000000000000000000E+100')}")));
+        }
+
+        it "rejects non-numeric values" {
+            expect!(error("{_id: 1}", "{$inc: {a: 'x'}}")).to(be_equal_to("Cannot increment with non-numeric argument: {a: \"x\"}"));
+            expect!(error("{_id: 1, a: 'x'}", "{$inc: {a: 1}}"))
+                .to(be_equal_to("Cannot apply $inc to a value of non-numeric type. {_id: 1} has the field 'a' of non-numeric type string"));
+        }
+    }
+
+    describe! arrays {
+        it "pushes with modifiers" {
+            expect!(updated("{a: [1]}", "{$push: {a: 2, b: 1}}")).to(be_equal_to(doc("{a: [1, 2], b: [1]}")));
+            expect!(updated("{a: [5, 1]}", "{$push: {a: {$each: [3, 4], $sort: -1, $slice: 3}}}")).to(be_equal_to(doc("{a: [5, 4, 3]}")));
+            expect!(updated("{a: [1, 2]}", "{$push: {a: {$each: [9], $position: -1}}}")).to(be_equal_to(doc("{a: [1, 9, 2]}")));
+            expect!(updated("{a: [{s: 2}, {s: 1}]}", "{$push: {a: {$each: [], $sort: {s: 1}}}}")).to(be_equal_to(doc("{a: [{s: 1}, {s: 2}]}")));
+            expect!(error("{_id: 1, a: 1}", "{$push: {a: 2}}")).to(be_equal_to("The field 'a' must be an array but is of type int in document {_id: 1}"));
+        }
+
+        it "adds to sets, pulls and pops" {
+            expect!(updated("{a: [1, 2]}", "{$addToSet: {a: {$each: [2, 3, 3]}}}")).to(be_equal_to(doc("{a: [1, 2, 3]}")));
+            expect!(updated("{a: [1, 5, 8], b: [{x: 1}, {x: 2}]}", "{$pull: {a: {$gte: 5}, b: {x: 2}}}")).to(be_equal_to(doc("{a: [1], b: [{x: 1}]}")));
+            expect!(updated("{a: [[5, 6], 5, 7]}", "{$pull: {a: 5}}")).to(be_equal_to(doc("{a: [[5, 6], 7]}")));
+            expect!(updated("{a: [1, 2, 1, 3]}", "{$pullAll: {a: [1, 3]}}")).to(be_equal_to(doc("{a: [2]}")));
+            expect!(updated("{a: [1, 2, 3], b: [1, 2]}", "{$pop: {a: -1, b: 1}}")).to(be_equal_to(doc("{a: [2, 3], b: [1]}")));
+            expect!(error("{a: [1]}", "{$pop: {a: 2}}")).to(be_equal_to("$pop expects 1 or -1, found: 2"));
+        }
+    }
+
+    describe! positional {
+        it "updates the element matched by the query" {
+            let mut document = doc("{a: [1, 2, 3]}");
+            let update = Update::parse(&doc("{$set: {'a.$': 9}}")).unwrap().query(Filter::parse(&doc("{a: 2}")).unwrap());
+            update.apply(&mut document).unwrap();
+            expect!(document).to(be_equal_to(doc("{a: [1, 9, 3]}")));
+            expect!(error("{a: [1]}", "{$set: {'a.$': 9}}")).to(be_equal_to("The positional operator did not find the match needed from the query."));
+        }
+
+        it "updates all elements and filtered elements" {
+            expect!(updated("{a: [{b: 1}, {b: 2}]}", "{$inc: {'a.$[].b': 10}}")).to(be_equal_to(doc("{a: [{b: 11}, {b: 12}]}")));
+            let mut document = doc("{a: [1, 5, 8]}");
+            let update = Update::parse(&doc("{$set: {'a.$[x]': 0}}")).unwrap().array_filters(&[doc("{x: {$gt: 4}}")]).unwrap();
+            update.apply(&mut document).unwrap();
+            expect!(document).to(be_equal_to(doc("{a: [1, 0, 0]}")));
+            expect!(error("{b: 1}", "{$set: {'a.$[]': 1}}")).to(be_equal_to("The path 'a' must exist in the document in order to apply array updates."));
+        }
+
+        it "rejects unused array filters" {
+            let result = Update::parse(&doc("{$set: {a: 1}}")).unwrap().array_filters(&[doc("{x: 1}")]);
+            expect!(result.unwrap_err().message()).to(be_equal_to("The array filter for identifier 'x' was not used in the update"));
+        }
+    }
+
+    describe! validation {
+        it "rejects unknown operators, conflicts and empty field names" {
+            expect!(error("{}", "{$foo: {a: 1}}"))
+                .to(be_equal_to("Unknown modifier: $foo. Expected a valid update modifier or pipeline-style update specified as an array"));
+            expect!(error("{}", "{$set: {a: 1}, $inc: {'a.b': 1}}")).to(be_equal_to("Updating the path 'a.b' would create a conflict at 'a'"));
+            expect!(error("{}", "{$set: {'a..b': 1}}")).to(be_equal_to("The update path 'a..b' contains an empty field name, which is not allowed."));
+        }
+
+        it "leaves the document unchanged on error" {
+            let mut document = doc("{a: 1, b: 'x'}");
+            expect!(apply_update(&mut document, &doc("{$set: {a: 2}, $inc: {b: 1}}")).is_err()).to(be_true());
+            expect!(document).to(be_equal_to(doc("{a: 1, b: 'x'}")));
+        }
+
+        it "only applies $setOnInsert on insert" {
+            let update = Update::parse(&doc("{$set: {a: 1}, $setOnInsert: {b: 2}}")).unwrap();
+            let mut document = doc("{}");
+            update.apply(&mut document).unwrap();
+            expect!(document.clone()).to(be_equal_to(doc("{a: 1}")));
+            update.apply_insert(&mut document).unwrap();
+            expect!(document).to(be_equal_to(doc("{a: 1, b: 2}")));
+        }
+    }
+}
@@ -0,0 +1,122 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+mod common;
+
+use bson::{format_decimal128, parse_decimal128, visit_bytes, Bson, DocumentSerializer, RawDocumentBuf, Visit, Visitor};
+use common::nested;
+use expectest::prelude::*;
+
+struct Recorder {
+    calls: Vec<String>,
+    skip: &'static str,
+    stop: &'static str
+}
+
+impl Visitor for Recorder {
+    fn enter_document(&mut self, key: &str) -> Visit {
+        self.calls.push(format!("enter {}", key));
+        if key == self.skip { Visit::Skip } else { Visit::Continue }
+    }
+
+    fn exit_document(&mut self, key: &str) {
+        self.calls.push(format!("exit {}", key));
+    }
+
+    fn enter_array(&mut self, key: &str) -> Visit {
+        self.calls.push(format!("enter array {}", key));
+        Visit::Continue
+    }
+
+    fn exit_array(&mut self, key: &str) {
+        self.calls.push(format!("exit array {}", key));
+    }
+
+    fn visit_i32(&mut self, key: &str, value: i32) -> Visit {
+        self.calls.push(format!("{} = {}", key, value));
+        if key == self.stop { Visit::Stop } else { Visit::Continue }
+    }
+
+    fn visit_string(&mut self, key: &str, value: &str) -> Visit {
+        self.calls.push(format!("{} = {:?}", key, value));
+        if key == self.skip { Visit::Skip } else { Visit::Continue }
+    }
+
+    fn visit_decimal128(&mut self, key: &str, value: &[u8; 16]) -> Visit {
+        self.calls.push(format!("{} = {}", key, format_decimal128(value)));
+        Visit::Continue
+    }
+}
+
+describe! visitor_test {
+    before_each {
+        let document = document! {
+            "a" => 1,
+            "b" => (document! { "c" => 2, "d" => 3 }),
+            "e" => (vec![Bson::Int32(4), Bson::from("five"), Bson::Int32(6)]),
+            "f" => 7
+        };
+        let mut bytes = vec![];
+        DocumentSerializer::new(&mut bytes).serialize(&document).unwrap();
+        let mut recorder = Recorder { calls: vec![], skip: "", stop: "" };
+    }
+
+    it "calls the visitor for each element in order" {
+        expect!(visit_bytes(&bytes, &mut recorder)).to(be_ok().value(Visit::Continue));
+        expect!(recorder.calls).to(be_equal_to(vec![
+            "a = 1", "enter b", "c = 2", "d = 3", "exit b", "enter array e",
+            "0 = 4", "1 = \"five\"", "2 = 6", "exit array e", "f = 7"
+        ].into_iter().map(String::from).collect::<Vec<_>>()));
+    }
+
+    it "jumps over a skipped document" {
+        recorder.skip = "b";
+        visit_bytes(&bytes, &mut recorder).unwrap();
+        expect!(recorder.calls[..3].to_vec()).to(be_equal_to(vec!["a = 1".to_string(), "enter b".to_string(), "enter array e".to_string()]));
+    }
+
+    it "jumps over the rest of the enclosing array" {
+        recorder.skip = "1";
+        visit_bytes(&bytes, &mut recorder).unwrap();
+        expect!(recorder.calls[7..].to_vec()).to(be_equal_to(vec![
+            "1 = \"five\"".to_string(), "exit array e".to_string(), "f = 7".to_string()
+        ]));
+    }
+
+    it "stops when asked" {
+        recorder.stop = "c";
+        expect!(visit_bytes(&bytes, &mut recorder)).to(be_ok().value(Visit::Stop));
+        expect!(recorder.calls.len()).to(be_equal_to(3));
+    }
+
+    it "visits decimals" {
+        let document = document! { "g" => (Bson::Decimal128(parse_decimal128("0.10").unwrap())) };
+        let mut bytes = vec![];
+        DocumentSerializer::new(&mut bytes).serialize(&document).unwrap();
+        visit_bytes(&bytes, &mut recorder).unwrap();
+        expect!(recorder.calls).to(be_equal_to(vec!["g = 0.10".to_string()]));
+    }
+
+    it "visits a raw document" {
+        let raw = RawDocumentBuf::new(bytes.clone()).unwrap();
+        expect!(raw.visit(&mut recorder)).to(be_ok().value(Visit::Continue));
+        expect!(recorder.calls.len()).to(be_equal_to(11));
+    }
+
+    it "returns an error for truncated bytes" {
+        expect!(visit_bytes(&bytes[..bytes.len() - 1], &mut recorder)).to(be_err());
+    }
+
+    it "returns an error for documents nested deeper than the limit" {
+        expect!(visit_bytes(&nested(200), &mut recorder)).to(be_ok().value(Visit::Continue));
+        let error = visit_bytes(&nested(201), &mut recorder).unwrap_err();
+        expect!(error.to_string()).to(be_equal_to("document nesting exceeds the maximum depth of 200"));
+        expect!(visit_bytes(&nested(100_000), &mut recorder)).to(be_err());
+    }
+}
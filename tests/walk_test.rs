@@ -0,0 +1,87 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+#[macro_use]
+extern crate bson;
+
+use bson::{Bson, WalkAction};
+use expectest::prelude::*;
+
+describe! walk_test {
+    before_each {
+        let mut document = document! {
+            "a" => 1,
+            "b" => (document! { "password" => "secret", "c" => 2 }),
+            "d" => (vec![Bson::Int32(3), Bson::Null, Bson::Int32(4)])
+        };
+    }
+
+    describe! walk {
+        it "visits every path depth first" {
+            let mut paths = vec![];
+            document.walk(|path, _| {
+                paths.push(path.to_string());
+                true
+            });
+            expect!(paths).to(be_equal_to(vec![
+                "a", "b", "b.password", "b.c", "d", "d.0", "d.1", "d.2"
+            ].into_iter().map(String::from).collect::<Vec<_>>()));
+        }
+
+        it "skips children when asked" {
+            let mut paths = vec![];
+            document.walk(|path, _| {
+                paths.push(path.to_string());
+                path != "b"
+            });
+            expect!(paths.len()).to(be_equal_to(6));
+        }
+    }
+
+    describe! walk_mut {
+        it "replaces, removes and renames values" {
+            document.walk_mut(|path, value| match (path, value) {
+                ("b.password", _) => WalkAction::Replace(Bson::from("***")),
+                ("b", _) => WalkAction::Rename("e".to_string()),
+                (_, &mut Bson::Null) => WalkAction::Remove,
+                (_, &mut Bson::Int32(ref mut n)) => {
+                    *n *= 10;
+                    WalkAction::Continue
+                },
+                _ => WalkAction::Continue
+            });
+            expect!(document).to(be_equal_to(document! {
+                "a" => 10,
+                "e" => (document! { "password" => "***", "c" => 20 }),
+                "d" => (vec![Bson::Int32(30), Bson::Int32(40)])
+            }));
+        }
+
+        it "gives a renamed value precedence over siblings with the new key" {
+            let mut later = document! { "a" => 1, "b" => 2, "c" => 3 };
+            later.walk_mut(|path, _| if path == "a" { WalkAction::Rename("b".to_string()) } else { WalkAction::Continue });
+            expect!(later).to(be_equal_to(document! { "b" => 1, "c" => 3 }));
+            let mut earlier = document! { "b" => 2, "c" => 3, "a" => 1 };
+            earlier.walk_mut(|path, _| if path == "a" { WalkAction::Rename("b".to_string()) } else { WalkAction::Continue });
+            expect!(earlier.iter().map(|(key, value)| (key.clone(), value.clone())).collect::<Vec<_>>())
+                .to(be_equal_to(vec![("c".to_string(), Bson::Int32(3)), ("b".to_string(), Bson::Int32(1))]));
+        }
+    }
+
+    describe! map_leaves {
+        it "transforms every leaf" {
+            let mapped = Bson::Document(document).map_leaves(|value| match value {
+                Bson::Int32(n) => Bson::Int64(n as i64),
+                other => other
+            });
+            expect!(mapped).to(be_equal_to(Bson::Document(document! {
+                "a" => (Bson::Int64(1)),
+                "b" => (document! { "password" => "secret", "c" => (Bson::Int64(2)) }),
+                "d" => (vec![Bson::Int64(3), Bson::Null, Bson::Int64(4)])
+            })));
+        }
+    }
+}
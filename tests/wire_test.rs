@@ -0,0 +1,92 @@
+#![feature(plugin)]
+#![cfg_attr(test, plugin(stainless))]
+
+#[macro_use(expect)]
+extern crate expectest;
+
+extern crate bson;
+
+mod common;
+
+use std::io::{Cursor, ErrorKind};
+use bson::{Message, MsgHeader, OpCompressed, OpMsg, OpQuery, OpReply, OP_MSG, OP_QUERY};
+use common::doc;
+use expectest::prelude::*;
+
+fn round_trip(message: Message) -> Message {
+    let bytes = message.encode(7, 3).unwrap();
+    let (header, decoded) = Message::decode(&bytes).unwrap();
+    expect!(header).to(be_equal_to(MsgHeader { message_length: bytes.len() as i32, request_id: 7, response_to: 3, op_code: message.op_code() }));
+    decoded
+}
+
+describe! wire_test {
+    describe! op_msg {
+        it "encodes a body section after the header and flags" {
+            let bytes = Message::Msg(OpMsg::new(doc("{ping: 1}"))).encode(1, 0).unwrap();
+            expect!(bytes.len()).to(be_equal_to(16 + 4 + 1 + 15));
+            expect!(&bytes[12..16]).to(be_equal_to(&[0xDD, 0x07, 0, 0][..]));
+            expect!(bytes[20]).to(be_equal_to(0));
+        }
+
+        it "round trips document sequences" {
+            let message = Message::Msg(OpMsg::new(doc("{insert: 'c', $db: 'test'}")).sequence("documents", vec![doc("{a: 1}"), doc("{a: 2}")]));
+            let decoded = round_trip(message.clone());
+            expect!(decoded.clone()).to(be_equal_to(message));
+            let command = match decoded {
+                Message::Msg(msg) => msg.command(),
+                _ => panic!("expected an OP_MSG")
+            };
+            expect!(command).to(be_equal_to(doc("{insert: 'c', $db: 'test', documents: [{a: 1}, {a: 2}]}")));
+        }
+
+        it "appends and verifies a checksum" {
+            let message = Message::Msg(OpMsg::new(doc("{ping: 1}")).flags(OpMsg::CHECKSUM_PRESENT));
+            let mut bytes = message.encode(1, 0).unwrap();
+            expect!(Message::decode(&bytes).unwrap().1).to(be_equal_to(message));
+            bytes[22] ^= 1;
+            expect!(Message::decode(&bytes).unwrap_err().to_string()).to(be_equal_to("OP_MSG checksum mismatch"));
+        }
+
+        it "rejects unknown required flags and missing bodies" {
+            let mut bytes = Message::Msg(OpMsg::new(doc("{ping: 1}"))).encode(1, 0).unwrap();
+            bytes[16] = 4;
+            expect!(Message::decode(&bytes).is_err()).to(be_true());
+            let mut header = Vec::new();
+            MsgHeader { message_length: 20, request_id: 1, response_to: 0, op_code: OP_MSG }.write(&mut header);
+            header.extend_from_slice(&[0, 0, 0, 0]);
+            expect!(Message::decode(&header).unwrap_err().to_string()).to(be_equal_to("OP_MSG has no body section"));
+        }
+    }
+
+    describe! legacy {
+        it "round trips OP_QUERY and OP_REPLY" {
+            let query = OpQuery::command("admin", doc("{isMaster: 1}"));
+            expect!(query.full_collection_name.clone()).to(be_equal_to("admin.$cmd"));
+            expect!(round_trip(Message::Query(query.clone()))).to(be_equal_to(Message::Query(query)));
+            let reply = OpReply { response_flags: 8, cursor_id: 42, starting_from: 0, documents: vec![doc("{a: 1}"), doc("{a: 2}")] };
+            expect!(round_trip(Message::Reply(reply.clone()))).to(be_equal_to(Message::Reply(reply)));
+        }
+
+        it "round trips OP_COMPRESSED" {
+            let compressed = OpCompressed { original_op_code: OP_QUERY, uncompressed_size: 5, compressor_id: 0, compressed_message: vec![1, 2, 3, 4, 5] };
+            expect!(round_trip(Message::Compressed(compressed.clone()))).to(be_equal_to(Message::Compressed(compressed)));
+        }
+    }
+
+    describe! streams {
+        it "reads consecutive messages" {
+            let mut bytes = Message::Msg(OpMsg::new(doc("{a: 1}"))).encode(1, 0).unwrap();
+            bytes.extend(Message::Reply(OpReply::new(doc("{ok: 1}"))).encode(2, 1).unwrap());
+            let mut cursor = Cursor::new(bytes);
+            expect!(Message::read_from(&mut cursor, 1024).unwrap().0.request_id).to(be_equal_to(1));
+            expect!(Message::read_from(&mut cursor, 1024).unwrap().1).to(be_equal_to(Message::Reply(OpReply::new(doc("{ok: 1}")))));
+            expect!(Message::read_from(&mut cursor, 1024).unwrap_err().kind()).to(be_equal_to(ErrorKind::UnexpectedEof));
+        }
+
+        it "rejects messages over the maximum size" {
+            let bytes = Message::Msg(OpMsg::new(doc("{a: 1}"))).encode(1, 0).unwrap();
+            expect!(Message::read_from(&mut Cursor::new(bytes), 20).unwrap_err().kind()).to(be_equal_to(ErrorKind::InvalidData));
+        }
+    }
+}